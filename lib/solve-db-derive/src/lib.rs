@@ -80,7 +80,7 @@ pub fn derive_from_value(input: TokenStream) -> TokenStream {
             return TokenStream::from(quote! {
                 impl solve_db::FromValue for #ident {
                     fn from_value(value: &solve_db::Value) -> Result<Self, solve_db::Error> {
-                        Ok(match value.parse()? { #(#from_tokens),* })
+                        Ok(match value.parse::<i64>()? { #(#from_tokens),* })
                     }
                 }
             });
@@ -107,7 +107,7 @@ pub fn derive_into_value(input: TokenStream) -> TokenStream {
             return TokenStream::from(quote! {
                 impl solve_db::IntoValue for #ident {
                     fn into_value(self) -> solve_db::Value {
-                        solve_db::Value::from(match self { #(#into_tokens),* })
+                        solve_db::Value::from::<i64>(match self { #(#into_tokens),* })
                     }
                 }
             });
@@ -1,4 +1,8 @@
-use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use solve_db::{Error, FromValue, IntoValue, Value};
 
@@ -6,21 +10,108 @@ use solve_db::{Error, FromValue, IntoValue, Value};
 pub struct Instant(DateTime<Utc>);
 
 impl Instant {
+    pub const MIN: Self = Self(DateTime::<Utc>::MIN_UTC);
+    pub const MAX: Self = Self(DateTime::<Utc>::MAX_UTC);
+
+    /// Returns current time truncated to milliseconds.
     pub fn now() -> Self {
-        Utc::now().into()
+        let now = Utc::now();
+        Self(DateTime::from_timestamp_millis(now.timestamp_millis()).unwrap_or(now))
+    }
+
+    /// Creates instant from seconds since epoch saturating at [`Instant::MIN`]
+    /// and [`Instant::MAX`].
+    pub fn from_unix(secs: i64) -> Self {
+        match DateTime::from_timestamp(secs, 0) {
+            Some(v) => Self(v),
+            None if secs < 0 => Self::MIN,
+            None => Self::MAX,
+        }
+    }
+
+    /// Returns amount of seconds since epoch.
+    pub fn unix(&self) -> i64 {
+        self.0.timestamp()
     }
 }
 
+impl std::fmt::Display for Instant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+}
+
+/// Parses RFC 3339 strings and integer amount of seconds since epoch.
+impl std::str::FromStr for Instant {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(secs) = s.parse::<i64>() {
+            let dt = DateTime::from_timestamp(secs, 0).ok_or("invalid timestamp")?;
+            return Ok(Self(dt));
+        }
+        let dt = DateTime::parse_from_rfc3339(s)?;
+        Ok(Self(dt.with_timezone(&Utc)))
+    }
+}
+
+/// Values with smaller magnitude are treated as legacy timestamps in seconds.
+///
+/// In milliseconds this bound corresponds to 1973-03-03, in seconds to 5138-11-16.
+const LEGACY_SECONDS_BOUND: i64 = 100_000_000_000;
+
 impl FromValue for Instant {
     fn from_value(value: &Value) -> Result<Self, Error> {
-        let dt = DateTime::from_timestamp(value.parse()?, 0);
+        let value: i64 = value.parse()?;
+        let dt = if value.abs() < LEGACY_SECONDS_BOUND {
+            DateTime::from_timestamp(value, 0)
+        } else {
+            DateTime::from_timestamp_millis(value)
+        };
         Ok(Self(dt.ok_or("cannot parse timestamp")?))
     }
 }
 
 impl IntoValue for Instant {
     fn into_value(self) -> Value {
-        self.0.timestamp().into_value()
+        self.0.timestamp_millis().into_value()
+    }
+}
+
+impl Serialize for Instant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Instant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(InstantVisitor)
+    }
+}
+
+/// Accepts RFC 3339 strings and integer amount of seconds since epoch.
+struct InstantVisitor;
+
+impl<'de> serde::de::Visitor<'de> for InstantVisitor {
+    type Value = Instant;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("RFC 3339 string or integer timestamp")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        let dt = DateTime::from_timestamp(v, 0).ok_or_else(|| E::custom("invalid timestamp"))?;
+        Ok(Instant(dt))
+    }
+
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        let v = i64::try_from(v).map_err(|_| E::custom("invalid timestamp"))?;
+        self.visit_i64(v)
     }
 }
 
@@ -58,6 +149,112 @@ where
     }
 }
 
+/// Duration stored as amount of milliseconds.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DurationMs(Duration);
+
+impl DurationMs {
+    pub const ZERO: Self = Self(Duration::ZERO);
+
+    pub fn from_millis(millis: u64) -> Self {
+        Self(Duration::from_millis(millis))
+    }
+
+    /// Returns amount of milliseconds saturating at `u64::MAX`.
+    pub fn as_millis(&self) -> u64 {
+        self.0.as_millis().try_into().unwrap_or(u64::MAX)
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    fn to_time_delta(self) -> TimeDelta {
+        TimeDelta::from_std(self.0).unwrap_or(TimeDelta::max_value())
+    }
+}
+
+impl FromValue for DurationMs {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        let millis: i64 = value.parse()?;
+        let millis: u64 = millis.try_into().map_err(|_| "negative duration")?;
+        Ok(Self::from_millis(millis))
+    }
+}
+
+impl IntoValue for DurationMs {
+    fn into_value(self) -> Value {
+        i64::try_from(self.as_millis())
+            .unwrap_or(i64::MAX)
+            .into_value()
+    }
+}
+
+impl Serialize for DurationMs {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_millis())
+    }
+}
+
+impl<'de> Deserialize<'de> for DurationMs {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_millis(u64::deserialize(deserializer)?))
+    }
+}
+
+impl From<Duration> for DurationMs {
+    fn from(value: Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl From<DurationMs> for Duration {
+    fn from(value: DurationMs) -> Self {
+        value.0
+    }
+}
+
+impl std::ops::Add<DurationMs> for DateTime<Utc> {
+    type Output = DateTime<Utc>;
+
+    fn add(self, rhs: DurationMs) -> Self::Output {
+        self.checked_add_signed(rhs.to_time_delta())
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+impl std::ops::Sub<DurationMs> for DateTime<Utc> {
+    type Output = DateTime<Utc>;
+
+    fn sub(self, rhs: DurationMs) -> Self::Output {
+        self.checked_sub_signed(rhs.to_time_delta())
+            .unwrap_or(DateTime::<Utc>::MIN_UTC)
+    }
+}
+
+/// Serializes [`Duration`] fields as amount of milliseconds.
+///
+/// Should be used as `#[serde(with = "solve_db_types::duration_ms")]`.
+pub mod duration_ms {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::DurationMs;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        DurationMs::from(*value).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Ok(DurationMs::deserialize(deserializer)?.into())
+    }
+}
+
 #[derive(Clone, Default, Debug, PartialEq)]
 pub struct JSON(serde_json::Value);
 
@@ -77,6 +274,74 @@ impl IntoValue for JSON {
     }
 }
 
+impl JSON {
+    pub fn from_serialize<T: Serialize>(value: T) -> Result<Self, Error> {
+        Ok(Self(serde_json::to_value(value)?))
+    }
+
+    pub fn parse_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(T::deserialize(&self.0)?)
+    }
+
+    pub fn get<I: serde_json::value::Index>(&self, index: I) -> Option<&serde_json::Value> {
+        self.0.get(index)
+    }
+
+    /// Looks up value by JSON pointer (RFC 6901), e.g. `/tests/0/verdict`.
+    pub fn pointer(&self, path: &str) -> Option<&serde_json::Value> {
+        self.0.pointer(path)
+    }
+
+    /// Applies JSON merge patch (RFC 7396), null values remove keys.
+    pub fn merge(&mut self, other: &JSON) {
+        merge_patch(&mut self.0, &other.0);
+    }
+}
+
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let patch = match patch {
+        serde_json::Value::Object(v) => v,
+        v => {
+            *target = v.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(
+                target.entry(key.clone()).or_insert(serde_json::Value::Null),
+                value,
+            );
+        }
+    }
+}
+
+impl std::ops::Deref for JSON {
+    type Target = serde_json::Value;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Serialize for JSON {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for JSON {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self(serde_json::Value::deserialize(deserializer)?))
+    }
+}
+
 impl From<serde_json::Value> for JSON {
     fn from(value: serde_json::Value) -> Self {
         Self(value)
@@ -88,3 +353,620 @@ impl From<JSON> for serde_json::Value {
         value.0
     }
 }
+
+/// Universally unique identifier stored in canonical hyphenated form.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid(uuid::Uuid);
+
+impl Uuid {
+    pub const fn nil() -> Self {
+        Self(uuid::Uuid::nil())
+    }
+
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(uuid::Uuid::from_bytes(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        self.0.as_bytes()
+    }
+
+    /// Generates random UUID (version 4).
+    #[cfg(feature = "rand")]
+    pub fn new_v4() -> Self {
+        Self(uuid::Uuid::new_v4())
+    }
+}
+
+impl From<uuid::Uuid> for Uuid {
+    fn from(value: uuid::Uuid) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Uuid> for uuid::Uuid {
+    fn from(value: Uuid) -> Self {
+        value.0
+    }
+}
+
+impl std::fmt::Display for Uuid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl std::str::FromStr for Uuid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match uuid::Uuid::try_parse(s) {
+            Ok(v) => Ok(Self(v)),
+            Err(_) => Err(format!("invalid uuid: {s}").into()),
+        }
+    }
+}
+
+impl FromValue for Uuid {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Text(v) => v.parse(),
+            Value::Blob(v) => match uuid::Uuid::from_slice(v) {
+                Ok(v) => Ok(Self(v)),
+                Err(_) => Err("invalid uuid length".into()),
+            },
+            _ => Err("cannot parse uuid".into()),
+        }
+    }
+}
+
+impl IntoValue for Uuid {
+    fn into_value(self) -> Value {
+        self.to_string().into_value()
+    }
+}
+
+impl Serialize for Uuid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IpAddr(std::net::IpAddr);
+
+impl Default for IpAddr {
+    fn default() -> Self {
+        Self(std::net::Ipv4Addr::UNSPECIFIED.into())
+    }
+}
+
+impl std::fmt::Display for IpAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::str::FromStr for IpAddr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.parse().map_err(|_| format!("invalid ip address: {s}"))?,
+        ))
+    }
+}
+
+impl FromValue for IpAddr {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Text(v) => v.parse(),
+            _ => Err("cannot parse ip address".into()),
+        }
+    }
+}
+
+impl IntoValue for IpAddr {
+    fn into_value(self) -> Value {
+        self.to_string().into_value()
+    }
+}
+
+impl From<std::net::IpAddr> for IpAddr {
+    fn from(value: std::net::IpAddr) -> Self {
+        Self(value)
+    }
+}
+
+impl From<IpAddr> for std::net::IpAddr {
+    fn from(value: IpAddr) -> Self {
+        value.0
+    }
+}
+
+/// IP network in CIDR notation, e.g. `10.0.0.0/8`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct IpNet {
+    addr: std::net::IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    pub fn new<T: Into<std::net::IpAddr>>(addr: T, prefix_len: u8) -> Result<Self, Error> {
+        let addr = addr.into();
+        let max_len = match addr {
+            std::net::IpAddr::V4(_) => 32,
+            std::net::IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_len {
+            return Err(format!("invalid prefix length: {prefix_len}").into());
+        }
+        Ok(Self { addr, prefix_len })
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        IpAddr(self.addr)
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    pub fn contains<T: Into<std::net::IpAddr>>(&self, addr: T) -> bool {
+        match (self.addr, addr.into()) {
+            (std::net::IpAddr::V4(net), std::net::IpAddr::V4(addr)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (std::net::IpAddr::V6(net), std::net::IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for IpNet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl std::str::FromStr for IpNet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s
+            .split_once('/')
+            .ok_or_else(|| format!("invalid ip network: {s}"))?;
+        let addr: IpAddr = addr.parse()?;
+        let prefix_len = prefix_len
+            .parse()
+            .map_err(|_| format!("invalid ip network: {s}"))?;
+        Self::new(addr.0, prefix_len)
+    }
+}
+
+impl FromValue for IpNet {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::Text(v) => v.parse(),
+            _ => Err("cannot parse ip network".into()),
+        }
+    }
+}
+
+impl IntoValue for IpNet {
+    fn into_value(self) -> Value {
+        self.to_string().into_value()
+    }
+}
+
+/// Set of bits with indices in range `0..64` stored as BIGINT.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq, Hash)]
+pub struct BitSet64(u64);
+
+impl BitSet64 {
+    pub const fn new() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn set(&mut self, bit: u8) {
+        assert!(bit < 64, "bit index out of range: {bit}");
+        self.0 |= 1 << bit;
+    }
+
+    pub fn clear(&mut self, bit: u8) {
+        assert!(bit < 64, "bit index out of range: {bit}");
+        self.0 &= !(1 << bit);
+    }
+
+    pub fn contains(&self, bit: u8) -> bool {
+        bit < 64 && self.0 & (1 << bit) != 0
+    }
+
+    pub fn iter_bits(&self) -> impl Iterator<Item = u8> {
+        let bits = self.0;
+        (0..64).filter(move |i| bits & (1 << i) != 0)
+    }
+}
+
+impl FromIterator<u8> for BitSet64 {
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        let mut value = Self::new();
+        for bit in iter {
+            value.set(bit);
+        }
+        value
+    }
+}
+
+impl FromValue for BitSet64 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        let bits: i64 = value.parse()?;
+        Ok(Self(bits as u64))
+    }
+}
+
+impl IntoValue for BitSet64 {
+    fn into_value(self) -> Value {
+        (self.0 as i64).into_value()
+    }
+}
+
+impl Serialize for BitSet64 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter_bits())
+    }
+}
+
+impl<'de> Deserialize<'de> for BitSet64 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bits = Vec::<u8>::deserialize(deserializer)?;
+        if let Some(bit) = bits.iter().find(|v| **v >= 64) {
+            return Err(serde::de::Error::custom(format!(
+                "bit index out of range: {bit}"
+            )));
+        }
+        Ok(bits.into_iter().collect())
+    }
+}
+
+impl std::ops::BitOr for BitSet64 {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitAnd for BitSet64 {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl std::ops::BitXor for BitSet64 {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl std::ops::Not for BitSet64 {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl std::ops::BitOrAssign for BitSet64 {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl std::ops::BitAndAssign for BitSet64 {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl std::ops::BitXorAssign for BitSet64 {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+/// Fixed-point score with precision of hundredths.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Score(i64);
+
+impl Score {
+    pub const ZERO: Self = Self(0);
+    pub const MIN: Self = Self(i64::MIN);
+    pub const MAX: Self = Self(i64::MAX);
+
+    const SCALE: i64 = 100;
+
+    pub const fn from_hundredths(value: i64) -> Self {
+        Self(value)
+    }
+
+    pub const fn hundredths(&self) -> i64 {
+        self.0
+    }
+
+    pub fn from_int(value: i64) -> Self {
+        Self(value.saturating_mul(Self::SCALE))
+    }
+
+    /// Converts float rounding it to the nearest hundredth.
+    pub fn from_f64(value: f64) -> Self {
+        Self((value * Self::SCALE as f64).round() as i64)
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.0 as f64 / Self::SCALE as f64
+    }
+
+    pub fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        let scale = Self::SCALE as u64;
+        write!(f, "{sign}{}.{:02}", value / scale, value % scale)
+    }
+}
+
+impl std::str::FromStr for Score {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || -> Error { format!("invalid score: {s}").into() };
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(v) => (true, v),
+            None => (false, s),
+        };
+        let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+        if int.is_empty() || !int.bytes().chain(frac.bytes()).all(|v| v.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let mut value: i64 = int.parse::<i64>().map_err(|_| invalid())?;
+        let mut frac_bytes = frac.bytes().map(|v| (v - b'0') as i64);
+        for _ in 0..2 {
+            let digit = frac_bytes.next().unwrap_or(0);
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add(digit))
+                .ok_or_else(invalid)?;
+        }
+        // Round half away from zero by the first dropped digit.
+        if frac_bytes.next().unwrap_or(0) >= 5 {
+            value = value.checked_add(1).ok_or_else(invalid)?;
+        }
+        Ok(Self(if negative { -value } else { value }))
+    }
+}
+
+impl FromValue for Score {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        Ok(Self(i64::from_value(value)?))
+    }
+}
+
+impl IntoValue for Score {
+    fn into_value(self) -> Value {
+        self.0.into_value()
+    }
+}
+
+impl Serialize for Score {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Score {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::ops::Add for Score {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl std::ops::Sub for Score {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl std::ops::Mul<i64> for Score {
+    type Output = Self;
+
+    fn mul(self, rhs: i64) -> Self::Output {
+        Self(self.0 * rhs)
+    }
+}
+
+impl std::ops::Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl std::ops::AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl std::ops::SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl std::iter::Sum for Score {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, v| acc + v)
+    }
+}
+
+/// Position of event consumer.
+///
+/// Event ids are allocated in increasing order, but they become visible in
+/// commit order, so ids below the last seen id can still appear later or
+/// never appear at all (rolled back transactions). Such ids are tracked as
+/// gaps until they are observed or expired.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EventRange {
+    begin: i64,
+    gaps: std::collections::BTreeMap<i64, Instant>,
+    /// Transaction horizons of gaps, see [`EventRange::set_horizon`].
+    #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+    horizons: std::collections::BTreeMap<i64, i64>,
+}
+
+impl EventRange {
+    /// Maximal amount of ids before seen id that are tracked as gaps.
+    ///
+    /// Older missing ids are considered lost, so a large jump of ids (for
+    /// example after pruning of events) does not allocate gap for every id.
+    pub const MAX_GAP_SPAN: i64 = 1000;
+
+    pub fn new(begin: i64) -> Self {
+        Self {
+            begin,
+            gaps: Default::default(),
+            horizons: Default::default(),
+        }
+    }
+
+    /// Returns id following the last seen id.
+    pub fn end(&self) -> i64 {
+        self.begin
+    }
+
+    /// Returns smallest id that can still appear.
+    pub fn next_begin(&self) -> i64 {
+        match self.gaps.first_key_value() {
+            Some((id, _)) => *id,
+            None => self.begin,
+        }
+    }
+
+    pub fn gaps(&self) -> impl Iterator<Item = i64> + '_ {
+        self.gaps.keys().cloned()
+    }
+
+    /// Marks event id as seen and returns false if it was already seen.
+    pub fn add(&mut self, id: i64) -> bool {
+        self.add_at(id, Instant::now())
+    }
+
+    /// Same as [`EventRange::add`] with explicit time of observation.
+    pub fn add_at(&mut self, id: i64, now: Instant) -> bool {
+        if id < self.begin {
+            self.horizons.remove(&id);
+            return self.gaps.remove(&id).is_some();
+        }
+        for gap in self.begin.max(id - Self::MAX_GAP_SPAN)..id {
+            self.gaps.insert(gap, now);
+        }
+        self.begin = id + 1;
+        true
+    }
+
+    /// Removes gaps observed before `now - window`.
+    pub fn expire_gaps(&mut self, now: Instant, window: Duration) {
+        let deadline = now - DurationMs::from(window);
+        self.gaps.retain(|_, time| *time >= deadline);
+        let gaps = &self.gaps;
+        self.horizons.retain(|id, _| gaps.contains_key(id));
+    }
+
+    /// Assigns transaction horizon to already observed gaps without one.
+    ///
+    /// Horizon should be greater than ids of all transactions started so far,
+    /// so it is greater than id of transaction that allocated gap.
+    pub fn set_horizon(&mut self, xmax: i64) {
+        for id in self.gaps.keys() {
+            self.horizons.entry(*id).or_insert(xmax);
+        }
+    }
+
+    /// Removes gaps whose writers are finished.
+    ///
+    /// All transactions with ids less than `xmin` should be finished before
+    /// last poll of gaps, so committed gaps are already observed.
+    pub fn expire_finished_gaps(&mut self, xmin: i64) {
+        let horizons = &self.horizons;
+        self.gaps
+            .retain(|id, _| horizons.get(id).map_or(true, |xmax| *xmax > xmin));
+        let gaps = &self.gaps;
+        self.horizons.retain(|id, _| gaps.contains_key(id));
+    }
+}
+
+impl FromValue for EventRange {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        JSON::from_value(value)?.parse_as()
+    }
+}
+
+impl IntoValue for EventRange {
+    fn into_value(self) -> Value {
+        JSON::from_serialize(self)
+            .expect("event range is always serializable")
+            .into_value()
+    }
+}
@@ -0,0 +1,72 @@
+use solve_db::{FromRow, FromValue, IntoRow, IntoValue, Row, Value};
+use solve_db_types::BitSet64;
+
+#[derive(Debug, FromRow, IntoRow)]
+struct TestRoleRow {
+    id: i64,
+    permissions: BitSet64,
+}
+
+#[test]
+fn test_bitset_ops() {
+    let mut value = BitSet64::new();
+    assert!(value.is_empty());
+    value.set(0);
+    value.set(5);
+    value.set(5);
+    assert!(value.contains(0));
+    assert!(value.contains(5));
+    assert!(!value.contains(1));
+    assert!(!value.contains(64));
+    assert_eq!(value.iter_bits().collect::<Vec<_>>(), vec![0, 5]);
+    value.clear(0);
+    value.clear(1);
+    assert_eq!(value.bits(), 1 << 5);
+    let other: BitSet64 = [1, 5].into_iter().collect();
+    assert_eq!((value | other).iter_bits().collect::<Vec<_>>(), vec![1, 5]);
+    assert_eq!((value & other).iter_bits().collect::<Vec<_>>(), vec![5]);
+    assert_eq!((value ^ other).iter_bits().collect::<Vec<_>>(), vec![1]);
+    assert_eq!((!value).iter_bits().count(), 63);
+    let mut value = other;
+    value |= BitSet64::from_bits(1);
+    value &= BitSet64::from_bits(0b11);
+    value ^= BitSet64::from_bits(0b100);
+    assert_eq!(value.bits(), 0b111);
+}
+
+#[test]
+#[should_panic]
+fn test_bitset_out_of_range() {
+    BitSet64::new().set(64);
+}
+
+#[test]
+fn test_bitset_sign_bit() {
+    let value: BitSet64 = [0, 63].into_iter().collect();
+    assert_eq!(value.into_value(), Value::BigInt(i64::MIN + 1));
+    assert_eq!(BitSet64::from_value(&value.into_value()).unwrap(), value);
+    let value = BitSet64::from_bits(u64::MAX);
+    assert_eq!(value.into_value(), Value::BigInt(-1));
+    assert_eq!(BitSet64::from_value(&Value::BigInt(-1)).unwrap(), value);
+    let row = TestRoleRow {
+        id: 1,
+        permissions: value,
+    };
+    let row = TestRoleRow::from_row(&Row::from_iter(row.into_row().into_iter())).unwrap();
+    assert_eq!(row.id, 1);
+    assert_eq!(row.permissions, value);
+}
+
+#[test]
+fn test_bitset_serde() {
+    let value: BitSet64 = [2, 63, 7].into_iter().collect();
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, "[2,7,63]");
+    assert_eq!(serde_json::from_str::<BitSet64>(&json).unwrap(), value);
+    assert_eq!(
+        serde_json::from_str::<BitSet64>("[]").unwrap(),
+        BitSet64::new()
+    );
+    assert!(serde_json::from_str::<BitSet64>("[64]").is_err());
+    assert!(serde_json::from_str::<BitSet64>("[-1]").is_err());
+}
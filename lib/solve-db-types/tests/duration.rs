@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use solve_db::{FromValue, IntoValue, Value};
+use solve_db_types::{DurationMs, Instant};
+
+#[test]
+fn test_duration_zero() {
+    let value = DurationMs::ZERO.into_value();
+    assert_eq!(value, Value::BigInt(0));
+    assert_eq!(DurationMs::from_value(&value).unwrap(), DurationMs::ZERO);
+    assert_eq!(serde_json::to_string(&DurationMs::ZERO).unwrap(), "0");
+}
+
+#[test]
+fn test_duration_truncation() {
+    let duration = DurationMs::from(Duration::from_micros(1_999));
+    assert_eq!(duration.as_millis(), 1);
+    assert_eq!(duration.into_value(), Value::BigInt(1));
+    assert_eq!(serde_json::to_string(&duration).unwrap(), "1");
+    let duration: DurationMs = serde_json::from_str("1500").unwrap();
+    assert_eq!(Duration::from(duration), Duration::from_millis(1500));
+    assert!(DurationMs::from_value(&Value::BigInt(-1)).is_err());
+}
+
+#[test]
+fn test_duration_large() {
+    let duration = DurationMs::from(Duration::MAX);
+    assert_eq!(duration.as_millis(), u64::MAX);
+    assert_eq!(duration.into_value(), Value::BigInt(i64::MAX));
+    assert_eq!(
+        duration.saturating_add(DurationMs::from_millis(1)),
+        DurationMs::from(Duration::MAX)
+    );
+    assert_eq!(
+        DurationMs::ZERO.saturating_sub(DurationMs::from_millis(1)),
+        DurationMs::ZERO
+    );
+    let instant = Instant::now() + duration;
+    assert_eq!(DateTime::<Utc>::from(instant), DateTime::<Utc>::MAX_UTC);
+}
+
+#[test]
+fn test_duration_instant() {
+    let dt = DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+    let instant = Instant::from(dt) + DurationMs::from_millis(1_500);
+    assert_eq!(
+        DateTime::<Utc>::from(instant),
+        DateTime::from_timestamp_millis(1_700_000_001_500).unwrap()
+    );
+    let instant = instant - DurationMs::from_millis(500);
+    assert_eq!(
+        DateTime::<Utc>::from(instant),
+        DateTime::from_timestamp_millis(1_700_000_001_000).unwrap()
+    );
+}
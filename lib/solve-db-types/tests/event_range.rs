@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use solve_db::{FromValue, IntoValue};
+use solve_db_types::{EventRange, Instant};
+
+fn gaps(range: &EventRange) -> Vec<i64> {
+    range.gaps().collect()
+}
+
+#[test]
+fn test_event_range_sequential() {
+    let mut range = EventRange::new(1);
+    assert_eq!(range.next_begin(), 1);
+    for id in 1..=5 {
+        assert!(range.add(id));
+    }
+    assert_eq!(range.end(), 6);
+    assert_eq!(range.next_begin(), 6);
+    assert!(gaps(&range).is_empty());
+    assert!(!range.add(3));
+    assert!(!range.add(0));
+    assert_eq!(range.next_begin(), 6);
+}
+
+#[test]
+fn test_event_range_out_of_order() {
+    let mut range = EventRange::new(1);
+    assert!(range.add(1));
+    assert!(range.add(4));
+    assert_eq!(gaps(&range), vec![2, 3]);
+    assert_eq!(range.next_begin(), 2);
+    assert_eq!(range.end(), 5);
+    assert!(range.add(3));
+    assert!(!range.add(3));
+    assert_eq!(gaps(&range), vec![2]);
+    assert!(range.add(7));
+    assert_eq!(gaps(&range), vec![2, 5, 6]);
+    assert!(range.add(2));
+    assert_eq!(range.next_begin(), 5);
+    assert!(range.add(6));
+    assert!(range.add(5));
+    assert!(gaps(&range).is_empty());
+    assert_eq!(range.next_begin(), 8);
+}
+
+#[test]
+fn test_event_range_large_jump() {
+    let mut range = EventRange::new(1);
+    assert!(range.add(10_000_001));
+    assert_eq!(range.end(), 10_000_002);
+    assert_eq!(gaps(&range).len() as i64, EventRange::MAX_GAP_SPAN);
+    assert_eq!(range.next_begin(), 10_000_001 - EventRange::MAX_GAP_SPAN);
+    // Ids before tracked span are considered lost.
+    assert!(!range.add(5));
+    assert!(range.add(10_000_000));
+    assert_eq!(gaps(&range).len() as i64, EventRange::MAX_GAP_SPAN - 1);
+}
+
+#[test]
+fn test_event_range_expire_gaps() {
+    let now = Instant::from_unix(1_700_000_000);
+    let mut range = EventRange::new(1);
+    range.add_at(3, now);
+    range.add_at(6, now + Duration::from_secs(10));
+    assert_eq!(gaps(&range), vec![1, 2, 4, 5]);
+    range.expire_gaps(now + Duration::from_secs(5), Duration::from_secs(5));
+    assert_eq!(gaps(&range), vec![1, 2, 4, 5]);
+    range.expire_gaps(now + Duration::from_secs(6), Duration::from_secs(5));
+    assert_eq!(gaps(&range), vec![4, 5]);
+    assert_eq!(range.next_begin(), 4);
+    assert!(!range.add_at(1, now + Duration::from_secs(6)));
+    range.expire_gaps(now + Duration::from_secs(20), Duration::from_secs(5));
+    assert!(gaps(&range).is_empty());
+    assert_eq!(range.next_begin(), 7);
+}
+
+#[test]
+fn test_event_range_value() {
+    let now = Instant::from_unix(1_700_000_000);
+    let mut range = EventRange::new(10);
+    range.add_at(12, now);
+    let value = range.clone().into_value();
+    assert_eq!(EventRange::from_value(&value).unwrap(), range);
+    assert_eq!(
+        EventRange::from_value(&EventRange::default().into_value()).unwrap(),
+        EventRange::default()
+    );
+}
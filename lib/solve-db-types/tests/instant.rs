@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use solve_db::{FromValue, IntoValue, Value};
+use solve_db_types::Instant;
+
+#[test]
+fn test_instant_millis() {
+    let dt = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+    let value = Instant::from(dt).into_value();
+    assert_eq!(value, Value::BigInt(1_700_000_000_123));
+    let instant = Instant::from_value(&value).unwrap();
+    assert_eq!(DateTime::<Utc>::from(instant), dt);
+}
+
+#[test]
+fn test_instant_legacy_seconds() {
+    let instant = Instant::from_value(&Value::BigInt(1_700_000_000)).unwrap();
+    assert_eq!(
+        DateTime::<Utc>::from(instant),
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    );
+    assert_eq!(instant.into_value(), Value::BigInt(1_700_000_000_000));
+    let instant = Instant::from_value(&Value::BigInt(0)).unwrap();
+    assert_eq!(DateTime::<Utc>::from(instant), DateTime::UNIX_EPOCH);
+    assert!(Instant::from_value(&Value::Text("abc".into())).is_err());
+}
+
+#[test]
+fn test_instant_now() {
+    let mut last = Instant::now();
+    for _ in 0..1000 {
+        let now = Instant::now();
+        assert!(now >= last);
+        assert_eq!(Instant::from_value(&now.into_value()).unwrap(), now);
+        last = now;
+    }
+}
+
+#[test]
+fn test_instant_format() {
+    let instant: Instant = "2024-05-01T10:00:00Z".parse().unwrap();
+    assert_eq!(instant.unix(), 1_714_557_600);
+    assert_eq!(instant.to_string(), "2024-05-01T10:00:00Z");
+    assert_eq!(instant, Instant::from_unix(1_714_557_600));
+    let instant: Instant = "2024-05-01T13:00:00.250+03:00".parse().unwrap();
+    assert_eq!(instant.to_string(), "2024-05-01T10:00:00.250Z");
+    assert_eq!(instant.to_string().parse::<Instant>().unwrap(), instant);
+    let instant: Instant = "1714557600".parse().unwrap();
+    assert_eq!(instant.to_string(), "2024-05-01T10:00:00Z");
+    let instant: Instant = "-1".parse().unwrap();
+    assert_eq!(instant.to_string(), "1969-12-31T23:59:59Z");
+    assert!("".parse::<Instant>().is_err());
+    assert!("2024-05-01".parse::<Instant>().is_err());
+    assert!("2024-05-01T10:00:00".parse::<Instant>().is_err());
+    assert!("2024-13-01T10:00:00Z".parse::<Instant>().is_err());
+    assert!(i64::MAX.to_string().parse::<Instant>().is_err());
+}
+
+#[test]
+fn test_instant_bounds() {
+    assert!(Instant::MIN < Instant::now());
+    assert!(Instant::now() < Instant::MAX);
+    assert_eq!(Instant::from_unix(i64::MIN), Instant::MIN);
+    assert_eq!(Instant::from_unix(i64::MAX), Instant::MAX);
+    assert_eq!(Instant::from_unix(0).unix(), 0);
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use solve_db_types::JSON;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TestConfig {
+    problem_id: i64,
+    compiler: Option<String>,
+}
+
+#[test]
+fn test_json_typed() {
+    let config = TestConfig {
+        problem_id: 1,
+        compiler: Some("cpp".into()),
+    };
+    let value = JSON::from_serialize(&config).unwrap();
+    assert_eq!(value, json!({"problem_id": 1, "compiler": "cpp"}).into());
+    assert_eq!(value.parse_as::<TestConfig>().unwrap(), config);
+    assert!(value.parse_as::<Vec<i64>>().is_err());
+}
+
+#[test]
+fn test_json_pointer() {
+    let value = JSON::from(json!({"tests": [{"verdict": "accepted"}]}));
+    assert_eq!(value.pointer("/tests/0/verdict").unwrap(), "accepted");
+    assert!(value.pointer("/tests/1/verdict").is_none());
+    assert_eq!(
+        value.pointer("").unwrap(),
+        &json!({"tests": [{"verdict": "accepted"}]})
+    );
+}
+
+#[test]
+fn test_json_merge() {
+    let mut value = JSON::from(json!({
+        "title": "Goodbye!",
+        "author": {"givenName": "John", "familyName": "Doe"},
+        "tags": ["example", "sample"],
+        "content": "This will be unchanged",
+    }));
+    value.merge(&JSON::from(json!({
+        "title": "Hello!",
+        "phoneNumber": "+01-123-456-7890",
+        "author": {"familyName": null},
+        "tags": ["example"],
+    })));
+    assert_eq!(
+        value,
+        JSON::from(json!({
+            "title": "Hello!",
+            "author": {"givenName": "John"},
+            "tags": ["example"],
+            "content": "This will be unchanged",
+            "phoneNumber": "+01-123-456-7890",
+        }))
+    );
+    let mut value = JSON::from(json!({"a": "b"}));
+    value.merge(&JSON::from(json!({"a": null, "b": {"c": null}})));
+    assert_eq!(value, JSON::from(json!({"b": {}})));
+    let mut value = JSON::from(json!(["a"]));
+    value.merge(&JSON::from(json!({"a": "b"})));
+    assert_eq!(value, JSON::from(json!({"a": "b"})));
+    value.merge(&JSON::from(json!(null)));
+    assert_eq!(value, JSON::from(json!(null)));
+}
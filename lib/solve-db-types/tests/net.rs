@@ -0,0 +1,54 @@
+use solve_db::{FromRow, FromValue, IntoRow, IntoValue, Row, Value};
+use solve_db_types::{IpAddr, IpNet};
+
+#[derive(Debug, FromRow, IntoRow)]
+struct TestAuditRow {
+    id: i64,
+    addr: IpAddr,
+    net: Option<IpNet>,
+}
+
+#[test]
+fn test_ip_addr() {
+    let addr: IpAddr = "192.168.0.1".parse().unwrap();
+    assert_eq!(addr.into_value(), Value::Text("192.168.0.1".into()));
+    assert_eq!(IpAddr::from_value(&addr.into_value()).unwrap(), addr);
+    let addr: IpAddr = "2001:0DB8:0000::0001".parse().unwrap();
+    assert_eq!(addr.into_value(), Value::Text("2001:db8::1".into()));
+    assert_eq!(IpAddr::from_value(&addr.into_value()).unwrap(), addr);
+    assert!("256.0.0.1".parse::<IpAddr>().is_err());
+    assert!("2001:db8:::1".parse::<IpAddr>().is_err());
+    assert!(IpAddr::from_value(&Value::Text("localhost".into())).is_err());
+    assert!(IpAddr::from_value(&Value::BigInt(1)).is_err());
+}
+
+#[test]
+fn test_ip_net() {
+    let net: IpNet = "10.1.0.0/16".parse().unwrap();
+    assert_eq!(net.prefix_len(), 16);
+    assert!(net.contains("10.1.2.3".parse::<std::net::IpAddr>().unwrap()));
+    assert!(!net.contains("10.2.0.1".parse::<std::net::IpAddr>().unwrap()));
+    assert!(!net.contains("::1".parse::<std::net::IpAddr>().unwrap()));
+    assert_eq!(IpNet::from_value(&net.into_value()).unwrap(), net);
+    let net: IpNet = "2001:db8::/32".parse().unwrap();
+    assert!(net.contains("2001:db8:1::1".parse::<std::net::IpAddr>().unwrap()));
+    assert!(!net.contains("2001:db9::1".parse::<std::net::IpAddr>().unwrap()));
+    let net: IpNet = "0.0.0.0/0".parse().unwrap();
+    assert!(net.contains("8.8.8.8".parse::<std::net::IpAddr>().unwrap()));
+    assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+    assert!("10.0.0.0".parse::<IpNet>().is_err());
+    assert!("10.0.0.0/abc".parse::<IpNet>().is_err());
+}
+
+#[test]
+fn test_ip_row() {
+    let row = TestAuditRow {
+        id: 1,
+        addr: "::1".parse().unwrap(),
+        net: Some("127.0.0.0/8".parse().unwrap()),
+    };
+    let row = TestAuditRow::from_row(&Row::from_iter(row.into_row().into_iter())).unwrap();
+    assert_eq!(row.id, 1);
+    assert_eq!(row.addr, "::1".parse().unwrap());
+    assert_eq!(row.net, Some("127.0.0.0/8".parse().unwrap()));
+}
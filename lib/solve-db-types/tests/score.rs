@@ -0,0 +1,75 @@
+use solve_db::{FromValue, IntoValue, Value};
+use solve_db_types::Score;
+
+#[test]
+fn test_score_rounding() {
+    let a: Score = "0.1".parse().unwrap();
+    let b: Score = "0.2".parse().unwrap();
+    assert_eq!(a + b, "0.3".parse().unwrap());
+    assert_eq!(
+        Score::from_f64(0.1) + Score::from_f64(0.2),
+        Score::from_f64(0.3)
+    );
+    let total: Score = (0..1000).map(|_| Score::from_f64(0.1)).sum();
+    assert_eq!(total, Score::from_int(100));
+    assert_eq!(
+        "0.125".parse::<Score>().unwrap(),
+        Score::from_hundredths(13)
+    );
+    assert_eq!(
+        "0.124".parse::<Score>().unwrap(),
+        Score::from_hundredths(12)
+    );
+    assert_eq!(
+        "-0.125".parse::<Score>().unwrap(),
+        Score::from_hundredths(-13)
+    );
+    assert_eq!(Score::from_f64(2.675).as_f64(), 2.68);
+}
+
+#[test]
+fn test_score_format() {
+    assert_eq!(Score::ZERO.to_string(), "0.00");
+    assert_eq!(Score::from_hundredths(150).to_string(), "1.50");
+    assert_eq!(Score::from_hundredths(-5).to_string(), "-0.05");
+    assert_eq!("7".parse::<Score>().unwrap(), Score::from_int(7));
+    assert_eq!(
+        "-1.5".parse::<Score>().unwrap(),
+        Score::from_hundredths(-150)
+    );
+    for value in ["", "-", ".5", "1.a", "+1", "1.2.3", "1e2"] {
+        assert!(value.parse::<Score>().is_err(), "{value}");
+    }
+}
+
+#[test]
+fn test_score_ops() {
+    let mut score = Score::from_int(3);
+    score += Score::from_hundredths(25);
+    assert_eq!(score, Score::from_hundredths(325));
+    score -= Score::from_int(1);
+    assert_eq!(score, Score::from_hundredths(225));
+    assert_eq!(score * 2, Score::from_hundredths(450));
+    assert_eq!(-score, Score::from_hundredths(-225));
+    assert_eq!(Score::MAX.saturating_add(score), Score::MAX);
+    assert_eq!(Score::MIN.saturating_sub(score), Score::MIN);
+}
+
+#[test]
+fn test_score_value() {
+    let score = Score::from_hundredths(12345);
+    assert_eq!(score.into_value(), Value::BigInt(12345));
+    assert_eq!(
+        Score::from_value(&Value::BigInt(-7)).unwrap(),
+        Score::from_hundredths(-7)
+    );
+    assert!(Score::from_value(&Value::Text("1.00".into())).is_err());
+}
+
+#[test]
+fn test_score_serde() {
+    let score = Score::from_hundredths(1005);
+    assert_eq!(serde_json::to_string(&score).unwrap(), "\"10.05\"");
+    assert_eq!(serde_json::from_str::<Score>("\"10.05\"").unwrap(), score);
+    assert!(serde_json::from_str::<Score>("10.05").is_err());
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use solve_db_types::{Instant, JSON};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TestReport {
+    time: Instant,
+    data: JSON,
+}
+
+#[test]
+fn test_serde_round_trip() {
+    let report = TestReport {
+        time: DateTime::from_timestamp_millis(1_700_000_000_123)
+            .unwrap()
+            .into(),
+        data: serde_json::json!({"verdict": "accepted", "tests": [1, 2]}).into(),
+    };
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "time": "2023-11-14T22:13:20.123Z",
+            "data": {"verdict": "accepted", "tests": [1, 2]},
+        })
+    );
+    let parsed: TestReport = serde_json::from_value(value).unwrap();
+    assert_eq!(parsed, report);
+}
+
+#[test]
+fn test_serde_instant_seconds() {
+    let report: TestReport = serde_json::from_str(r#"{"time": 1700000000, "data": null}"#).unwrap();
+    assert_eq!(
+        DateTime::<Utc>::from(report.time),
+        DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+    );
+    assert_eq!(report.data, serde_json::Value::Null.into());
+    assert!(serde_json::from_str::<Instant>(r#""invalid""#).is_err());
+    assert!(serde_json::from_str::<Instant>("1.5").is_err());
+}
+
+#[test]
+fn test_json_access() {
+    let data = JSON::from(serde_json::json!({"verdict": "accepted", "points": 5}));
+    assert_eq!(data.get("verdict").unwrap(), "accepted");
+    assert!(data.get("unknown").is_none());
+    assert!(data.is_object());
+    assert_eq!(data["points"], 5);
+}
@@ -0,0 +1,62 @@
+use solve_db::{FromValue, IntoValue, Value};
+use solve_db_types::Uuid;
+
+#[test]
+fn test_uuid_string() {
+    let uuid: Uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8".parse().unwrap();
+    assert_eq!(uuid.as_bytes()[0], 0x67);
+    assert_eq!(uuid.to_string(), "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    assert_eq!(
+        "67E5504410B1426F9247BB680E5FE0C8".parse::<Uuid>().unwrap(),
+        uuid
+    );
+    assert_eq!(
+        Uuid::nil().to_string(),
+        "00000000-0000-0000-0000-000000000000"
+    );
+    assert!("67e55044-10b1-426f-9247-bb680e5fe0c"
+        .parse::<Uuid>()
+        .is_err());
+    assert!("67e5504410b1-426f-9247-bb680e5fe0c8-"
+        .parse::<Uuid>()
+        .is_err());
+    assert!("67e55044-10b1-426f-9247-bb680e5fe0cx"
+        .parse::<Uuid>()
+        .is_err());
+    assert!("+7e55044-10b1-426f-9247-bb680e5fe0c8"
+        .parse::<Uuid>()
+        .is_err());
+}
+
+#[test]
+fn test_uuid_value() {
+    let uuid: Uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8".parse().unwrap();
+    let value = uuid.into_value();
+    assert_eq!(
+        value,
+        Value::Text("67e55044-10b1-426f-9247-bb680e5fe0c8".into())
+    );
+    assert_eq!(Uuid::from_value(&value).unwrap(), uuid);
+    let value = Value::Blob(uuid.as_bytes().to_vec());
+    assert_eq!(Uuid::from_value(&value).unwrap(), uuid);
+    assert!(Uuid::from_value(&Value::Blob(vec![1, 2, 3])).is_err());
+    assert!(Uuid::from_value(&Value::BigInt(1)).is_err());
+}
+
+#[test]
+fn test_uuid_serde() {
+    let uuid: Uuid = "67e55044-10b1-426f-9247-bb680e5fe0c8".parse().unwrap();
+    let json = serde_json::to_string(&uuid).unwrap();
+    assert_eq!(json, r#""67e55044-10b1-426f-9247-bb680e5fe0c8""#);
+    assert_eq!(serde_json::from_str::<Uuid>(&json).unwrap(), uuid);
+    assert!(serde_json::from_str::<Uuid>(r#""invalid""#).is_err());
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_uuid_new_v4() {
+    let uuid = Uuid::new_v4();
+    assert_ne!(uuid, Uuid::new_v4());
+    assert_eq!(uuid.as_bytes()[6] >> 4, 4);
+    assert_eq!(uuid.as_bytes()[8] >> 6, 2);
+}
@@ -41,14 +41,28 @@ impl<'a, T: driver::Rows<'a> + 'a> From<T> for Rows<'a> {
     }
 }
 
+type CommitHook<'a> = Box<dyn FnOnce() + Send + Sync + 'a>;
+
 pub struct Transaction<'a> {
     inner: Box<dyn driver::Transaction<'a> + 'a>,
+    commit_hooks: Vec<CommitHook<'a>>,
 }
 
 impl<'a> Transaction<'a> {
     pub fn new<T: driver::Transaction<'a> + 'a>(tx: T) -> Self {
         let inner = Box::new(tx);
-        Self { inner }
+        Self {
+            inner,
+            commit_hooks: Vec::new(),
+        }
+    }
+
+    /// Registers function that is called after successful commit.
+    ///
+    /// Functions are called in order of registration and are discarded
+    /// when transaction is rolled back.
+    pub fn on_commit<F: FnOnce() + Send + Sync + 'a>(&mut self, hook: F) {
+        self.commit_hooks.push(Box::new(hook));
     }
 
     pub fn builder(&self) -> QueryBuilder {
@@ -56,7 +70,11 @@ impl<'a> Transaction<'a> {
     }
 
     pub async fn commit(self) -> Result<(), Error> {
-        self.inner.commit().await
+        self.inner.commit().await?;
+        for hook in self.commit_hooks {
+            hook();
+        }
+        Ok(())
     }
 
     pub async fn rollback(self) -> Result<(), Error> {
@@ -64,12 +82,12 @@ impl<'a> Transaction<'a> {
     }
 
     pub async fn execute<Q: IntoQuery<T>, T: Query>(&mut self, query: Q) -> Result<Status, Error> {
-        let query = query.into_query(self.builder());
+        let query = query.try_into_query(self.builder())?;
         self.inner.execute(query.query(), query.values()).await
     }
 
     pub async fn query<Q: IntoQuery<T>, T: Query>(&mut self, query: Q) -> Result<Rows, Error> {
-        let query = query.into_query(self.builder());
+        let query = query.try_into_query(self.builder())?;
         self.inner.query(query.query(), query.values()).await
     }
 }
@@ -117,12 +135,12 @@ impl Connection {
     }
 
     pub async fn execute<Q: IntoQuery<T>, T: Query>(&mut self, query: Q) -> Result<Status, Error> {
-        let query = query.into_query(self.builder());
+        let query = query.try_into_query(self.builder())?;
         self.inner.execute(query.query(), query.values()).await
     }
 
     pub async fn query<Q: IntoQuery<T>, T: Query>(&mut self, query: Q) -> Result<Rows, Error> {
-        let query = query.into_query(self.builder());
+        let query = query.try_into_query(self.builder())?;
         self.inner.query(query.query(), query.values()).await
     }
 }
@@ -133,11 +151,27 @@ impl<T: driver::Connection + 'static> From<T> for Connection {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct ConnectionOptions {
     pub read_only: bool,
 }
 
+/// Status of connection pool.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStatus {
+    pub max_size: usize,
+    pub size: usize,
+    pub available: usize,
+    pub waiting: usize,
+}
+
+impl PoolStatus {
+    /// Returns amount of connections that are taken from pool.
+    pub fn in_use(&self) -> usize {
+        self.size - self.available
+    }
+}
+
 pub struct Database {
     inner: Box<dyn driver::Database>,
 }
@@ -156,6 +190,10 @@ impl Database {
         self.inner.connection(options).await
     }
 
+    pub fn pool_status(&self) -> PoolStatus {
+        self.inner.pool_status()
+    }
+
     pub async fn transaction(&self, options: TransactionOptions) -> Result<Transaction, Error> {
         let conn_options = ConnectionOptions {
             read_only: options.read_only,
@@ -173,10 +211,19 @@ impl Database {
     }
 
     pub async fn query<Q: IntoQuery<T>, T: Query>(&self, query: Q) -> Result<Rows, Error> {
-        let conn = self.connection(Default::default()).await?;
+        self.query_with_options(Default::default(), query).await
+    }
+
+    /// Executes query using connection with specified options.
+    pub async fn query_with_options<Q: IntoQuery<T>, T: Query>(
+        &self,
+        options: ConnectionOptions,
+        query: Q,
+    ) -> Result<Rows, Error> {
+        let query = query.try_into_query(self.builder())?;
+        let conn = self.connection(options).await?;
         let conn = Box::leak(conn.inner);
         let mut rows = OwnedRows { conn, rows: None };
-        let query = query.into_query(self.builder());
         rows.rows = Some(conn.query(query.query(), query.values()).await?.inner);
         Ok(Rows::new(rows))
     }
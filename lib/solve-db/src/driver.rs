@@ -1,4 +1,4 @@
-use crate::{ConnectionOptions, Error, RawQuery, Row, TransactionOptions, Value};
+use crate::{ConnectionOptions, Dialect, Error, RawQuery, Row, TransactionOptions, Value};
 
 pub trait QueryBuilder: Send + Sync {
     fn push(&mut self, ch: char);
@@ -10,6 +10,21 @@ pub trait QueryBuilder: Send + Sync {
     fn push_value(&mut self, value: Value);
 
     fn build(self: Box<Self>) -> RawQuery;
+
+    /// Reports whether `SELECT ... FOR UPDATE/SHARE` clauses are supported.
+    fn supports_locking(&self) -> bool {
+        false
+    }
+
+    /// Reports whether case-insensitive `ILIKE` operator is supported.
+    fn supports_ilike(&self) -> bool {
+        false
+    }
+
+    /// Reports SQL dialect used for rendering of dialect-specific statements.
+    fn dialect(&self) -> Dialect {
+        Dialect::Generic
+    }
 }
 
 #[async_trait::async_trait]
@@ -51,4 +66,8 @@ pub trait Database: Send + Sync {
     fn builder(&self) -> crate::QueryBuilder;
 
     async fn connection(&self, options: ConnectionOptions) -> Result<crate::Connection, Error>;
+
+    fn pool_status(&self) -> crate::PoolStatus {
+        Default::default()
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{driver, Value};
+use crate::{driver, Error, Value};
 
 pub trait Query: Send + Sync {
     fn query(&self) -> &str;
@@ -56,6 +56,14 @@ impl Query for RawQuery {
 
 pub trait IntoQuery<T: Query>: Send + Sync {
     fn into_query(self, builer: QueryBuilder) -> T;
+
+    /// Builds query or returns error if query is not allowed to be built.
+    fn try_into_query(self, builder: QueryBuilder) -> Result<T, Error>
+    where
+        Self: Sized,
+    {
+        Ok(self.into_query(builder))
+    }
 }
 
 impl<T: Query> IntoQuery<T> for T {
@@ -64,14 +72,22 @@ impl<T: Query> IntoQuery<T> for T {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dialect {
+    Generic,
+    SQLite,
+    Postgres,
+}
+
 pub struct QueryBuilder {
     inner: Box<dyn driver::QueryBuilder>,
+    error: Option<Error>,
 }
 
 impl QueryBuilder {
     pub fn new<T: driver::QueryBuilder + 'static>(builder: T) -> Self {
         let inner = Box::new(builder);
-        Self { inner }
+        Self { inner, error: None }
     }
 
     pub fn push(&mut self, ch: char) {
@@ -90,7 +106,39 @@ impl QueryBuilder {
         self.inner.push_value(value.into());
     }
 
+    /// Marks query as invalid, so it cannot be built.
+    ///
+    /// Only first error is kept.
+    pub fn push_error<E: Into<Error>>(&mut self, err: E) {
+        if self.error.is_none() {
+            self.error = Some(err.into());
+        }
+    }
+
     pub fn build(self) -> RawQuery {
-        self.inner.build()
+        match self.try_build() {
+            Ok(v) => v,
+            Err(err) => panic!("Cannot build query: {err}"),
+        }
+    }
+
+    /// Builds query or returns first error pushed into builder.
+    pub fn try_build(self) -> Result<RawQuery, Error> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.inner.build()),
+        }
+    }
+
+    pub fn supports_locking(&self) -> bool {
+        self.inner.supports_locking()
+    }
+
+    pub fn supports_ilike(&self) -> bool {
+        self.inner.supports_ilike()
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.inner.dialect()
     }
 }
@@ -88,6 +88,8 @@ impl FromValue for bool {
     fn from_value(value: &Value) -> Result<Self, Error> {
         match value {
             Value::Bool(v) => Ok(*v),
+            // SQLite stores booleans as integers.
+            Value::BigInt(v) => Ok(*v != 0),
             _ => Err("cannot parse bool".into()),
         }
     }
@@ -114,6 +116,21 @@ impl IntoValue for i64 {
     }
 }
 
+impl FromValue for i32 {
+    fn from_value(value: &Value) -> Result<Self, Error> {
+        match value {
+            Value::BigInt(v) => Ok(i32::try_from(*v)?),
+            _ => Err("cannot parse i32".into()),
+        }
+    }
+}
+
+impl IntoValue for i32 {
+    fn into_value(self) -> Value {
+        Value::BigInt(self.into())
+    }
+}
+
 impl FromValue for f64 {
     fn from_value(value: &Value) -> Result<Self, Error> {
         match value {
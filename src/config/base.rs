@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::env::var;
 use std::path::{Path, PathBuf};
 
@@ -5,6 +6,8 @@ use gtmpl::{Context, FuncError, Value};
 use serde::{Deserialize, Serialize};
 
 use crate::core::Error;
+use crate::managers::tasks::DEFAULT_MAX_ATTEMPTS;
+use crate::models::{FileKind, TaskKind};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -22,6 +25,12 @@ pub struct Config {
     #[serde(default)]
     pub smtp: Option<SMTP>,
     #[serde(default)]
+    pub events: Option<Events>,
+    #[serde(default)]
+    pub problems: Option<Problems>,
+    #[serde(default)]
+    pub files: Option<Files>,
+    #[serde(default)]
     pub log_level: String,
 }
 
@@ -43,6 +52,28 @@ pub struct Invoker {
     pub temp_dir: PathBuf,
     #[serde(default)]
     pub safeexec: Option<Safeexec>,
+    /// Kinds of tasks that are taken by workers with corresponding index.
+    ///
+    /// Workers without entry or with empty entry take tasks of any kind.
+    #[serde(default)]
+    pub worker_kinds: Vec<Vec<TaskKind>>,
+    /// Maximal amount of concurrently running tasks of specific kinds.
+    #[serde(default)]
+    pub kind_limits: HashMap<TaskKind, usize>,
+    /// Interval in seconds between recoveries of abandoned tasks.
+    #[serde(default = "default_recover_interval")]
+    pub recover_interval: u64,
+    /// Maximal amount of attempts to run task before it fails.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: i32,
+}
+
+fn default_recover_interval() -> u64 {
+    60
+}
+
+fn default_max_attempts() -> i32 {
+    DEFAULT_MAX_ATTEMPTS
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -94,13 +125,37 @@ pub enum StorageConfig {
     S3(S3StorageConfig),
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+impl StorageConfig {
+    pub fn limits(&self) -> &FileLimits {
+        match self {
+            StorageConfig::Local(config) => &config.limits,
+            StorageConfig::S3(config) => &config.limits,
+        }
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FileLimits {
+    /// Maximal size of uploaded file in bytes.
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Overrides of maximal size of uploaded file for specific kinds.
+    #[serde(default)]
+    pub kind_max_file_size: HashMap<FileKind, u64>,
+    /// Maximal total size of available files uploaded by one account.
+    #[serde(default)]
+    pub account_quota: Option<u64>,
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct LocalStorageConfig {
     #[serde(default)]
     pub files_dir: PathBuf,
+    #[serde(flatten)]
+    pub limits: FileLimits,
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct S3StorageConfig {
     #[serde(default)]
     pub region: String,
@@ -116,12 +171,20 @@ pub struct S3StorageConfig {
     pub path_prefix: String,
     #[serde(default)]
     pub use_path_style: bool,
+    #[serde(flatten)]
+    pub limits: FileLimits,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Security {
     #[serde(default)]
     pub password_salt: String,
+    /// Secret key used for signing page cursors.
+    ///
+    /// Random key is generated on start if secret is empty, so cursors
+    /// are not accepted by other instances and after restart.
+    #[serde(default)]
+    pub cursor_secret: String,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -138,6 +201,104 @@ pub struct SMTP {
     pub name: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Events {
+    /// Events older than this amount of days are pruned.
+    pub retention_days: u32,
+    #[serde(default = "default_prune_batch_size")]
+    pub prune_batch_size: usize,
+}
+
+fn default_prune_batch_size() -> usize {
+    1000
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Problems {
+    /// Delete files of problem resources when resources are deleted.
+    #[serde(default)]
+    pub delete_resource_files: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Files {
+    /// Interval in seconds between cleanups of expired pending files.
+    #[serde(default = "default_cleanup_interval")]
+    pub cleanup_interval: u64,
+    #[serde(default = "default_cleanup_batch_size")]
+    pub cleanup_batch_size: usize,
+    /// Verification of loaded files against their meta.
+    #[serde(default)]
+    pub verify: VerifyMode,
+    #[serde(default)]
+    pub chunked_upload: ChunkedUpload,
+}
+
+impl Default for Files {
+    fn default() -> Self {
+        Self {
+            cleanup_interval: default_cleanup_interval(),
+            cleanup_batch_size: default_cleanup_batch_size(),
+            verify: Default::default(),
+            chunked_upload: Default::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkedUpload {
+    /// Files larger than this amount of bytes are uploaded in parts.
+    #[serde(default = "default_chunked_upload_threshold")]
+    pub threshold: u64,
+    /// Size of one part in bytes.
+    #[serde(default = "default_chunked_upload_part_size")]
+    pub part_size: u64,
+    /// Maximal amount of parts that are written concurrently.
+    #[serde(default = "default_chunked_upload_concurrency")]
+    pub concurrency: usize,
+}
+
+impl Default for ChunkedUpload {
+    fn default() -> Self {
+        Self {
+            threshold: default_chunked_upload_threshold(),
+            part_size: default_chunked_upload_part_size(),
+            concurrency: default_chunked_upload_concurrency(),
+        }
+    }
+}
+
+fn default_chunked_upload_threshold() -> u64 {
+    64 * 1024 * 1024
+}
+
+fn default_chunked_upload_part_size() -> u64 {
+    8 * 1024 * 1024
+}
+
+fn default_chunked_upload_concurrency() -> usize {
+    4
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyMode {
+    /// Size and hash of file are verified.
+    Always,
+    /// Only size of file is verified.
+    SizeOnly,
+    #[default]
+    Never,
+}
+
+fn default_cleanup_interval() -> u64 {
+    300
+}
+
+fn default_cleanup_batch_size() -> usize {
+    100
+}
+
 pub fn parse_str(data: &str) -> Result<Config, Error> {
     let mut tmpl = gtmpl::Template::default();
     tmpl.add_func("env", tmpl_env);
@@ -1,28 +1,44 @@
 use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures_util::future::BoxFuture;
 use slog::Drain;
 use solve_db::Database;
+use solve_db_types::Instant;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
-use crate::config::Config;
+use crate::config::{Config, Events};
 use crate::db::new_database;
-use crate::managers::files::{new_storage, FileManager};
-use crate::managers::tasks::TaskManager;
-use crate::models::{FileStore, ProblemStore, SolutionStore, TaskStore};
+use crate::managers::files::{new_storage, FileManager, StorageHealth};
+use crate::managers::tasks::{RecoverPolicy, TaskManager, DEFAULT_MAX_ATTEMPTS};
+use crate::models::{
+    read_tx_options, run_in_tx, write_tx_options, CompilerStore, Context, FileStore,
+    MemoryStoreMetrics, PeriodicTaskStore, ProblemResourceStore, ProblemStore, SettingStore,
+    SolutionStore, TaskStore,
+};
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 
 pub struct Core {
     logger: slog::Logger,
     db: Arc<Database>,
+    store_metrics: Arc<MemoryStoreMetrics>,
     // Stores.
     task_store: Arc<TaskStore>,
+    periodic_task_store: Arc<PeriodicTaskStore>,
     file_store: Arc<FileStore>,
     problem_store: Arc<ProblemStore>,
+    problem_resource_store: Arc<ProblemResourceStore>,
     solution_store: Arc<SolutionStore>,
+    compiler_store: Arc<CompilerStore>,
+    setting_store: Arc<SettingStore>,
     // Managers.
     task_manager: Option<Arc<TaskManager>>,
     file_manager: Option<Arc<FileManager>>,
+    // Cancels background jobs when core is dropped.
+    shutdown: CancellationToken,
 }
 
 impl Core {
@@ -40,19 +56,71 @@ impl Core {
             .fuse();
         let drain = drain.filter_level(get_log_level(&config.log_level)).fuse();
         let logger = slog::Logger::root(drain, slog::o!());
-        let task_store = Arc::new(TaskStore::new(db.clone()));
-        let file_store = Arc::new(FileStore::new(db.clone()));
-        let problem_store = Arc::new(ProblemStore::new(db.clone()));
-        let solution_store = Arc::new(SolutionStore::new(db.clone()));
+        let cursor_key = match &config.security {
+            Some(security) if !security.cursor_secret.is_empty() => {
+                security.cursor_secret.clone().into_bytes()
+            }
+            _ => {
+                slog::warn!(logger, "Cursor secret is not configured, using random key");
+                rand::random::<[u8; 32]>().to_vec()
+            }
+        };
+        let store_metrics = Arc::new(MemoryStoreMetrics::new());
+        let task_store = Arc::new(
+            TaskStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let periodic_task_store = Arc::new(
+            PeriodicTaskStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let file_store = Arc::new(
+            FileStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let problem_store = Arc::new(
+            ProblemStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let problem_resource_store = Arc::new(
+            ProblemResourceStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let solution_store = Arc::new(
+            SolutionStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let compiler_store = Arc::new(
+            CompilerStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
+        let setting_store = Arc::new(
+            SettingStore::new(db.clone())
+                .with_metrics(store_metrics.clone())
+                .with_cursor_key(cursor_key.clone()),
+        );
         Ok(Self {
             logger,
             db,
+            store_metrics,
             task_store,
+            periodic_task_store,
             file_store,
             problem_store,
+            problem_resource_store,
             solution_store,
+            compiler_store,
+            setting_store,
             task_manager: None,
             file_manager: None,
+            shutdown: CancellationToken::new(),
         })
     }
 
@@ -64,10 +132,37 @@ impl Core {
         &self.db
     }
 
+    /// Runs function in write transaction spanning any stores of core.
+    ///
+    /// Transaction is committed only if function succeeds.
+    pub async fn with_tx<F, T>(&self, f: F) -> Result<T, Error>
+    where
+        F: for<'a, 'b> FnOnce(Context<'a, 'b>) -> BoxFuture<'a, Result<T, Error>>,
+    {
+        run_in_tx(&self.db, write_tx_options(), f).await
+    }
+
+    /// Runs function in read-only transaction.
+    pub async fn with_read_tx<F, T>(&self, f: F) -> Result<T, Error>
+    where
+        F: for<'a, 'b> FnOnce(Context<'a, 'b>) -> BoxFuture<'a, Result<T, Error>>,
+    {
+        run_in_tx(&self.db, read_tx_options(), f).await
+    }
+
+    /// Returns metrics of operations of stores labeled by table name.
+    pub fn store_metrics(&self) -> &MemoryStoreMetrics {
+        &self.store_metrics
+    }
+
     pub fn task_store(&self) -> &TaskStore {
         &self.task_store
     }
 
+    pub fn periodic_task_store(&self) -> &PeriodicTaskStore {
+        &self.periodic_task_store
+    }
+
     pub fn file_store(&self) -> &FileStore {
         &self.file_store
     }
@@ -76,10 +171,22 @@ impl Core {
         &self.problem_store
     }
 
+    pub fn problem_resource_store(&self) -> &ProblemResourceStore {
+        &self.problem_resource_store
+    }
+
     pub fn solution_store(&self) -> &SolutionStore {
         &self.solution_store
     }
 
+    pub fn compiler_store(&self) -> &CompilerStore {
+        &self.compiler_store
+    }
+
+    pub fn settings(&self) -> &SettingStore {
+        &self.setting_store
+    }
+
     pub fn task_manager(&self) -> &TaskManager {
         self.task_manager
             .as_ref()
@@ -92,35 +199,210 @@ impl Core {
             .expect("File manager is not initialized")
     }
 
-    pub async fn init_server(&mut self, _config: &Config) -> Result<(), Error> {
+    pub async fn init_server(&mut self, config: &Config) -> Result<(), Error> {
+        if config.storage.is_some() {
+            self.init_file_manager(config)?;
+            self.check_storage().await?;
+        }
         Ok(())
     }
 
     pub async fn init_invoker(&mut self, config: &Config) -> Result<(), Error> {
-        self.init_task_manager()?;
+        self.init_task_manager(config)?;
+        let recover_interval = match &config.invoker {
+            Some(v) => v.recover_interval,
+            None => 60,
+        };
+        if let Some(task_manager) = &self.task_manager {
+            task_manager.spawn_recover_expired(
+                self.logger.clone(),
+                Duration::from_secs(recover_interval.max(1)),
+                RecoverPolicy::Requeue,
+                self.shutdown.clone(),
+            );
+            self.spawn_scheduler(task_manager.clone());
+        }
         self.init_file_manager(config)?;
+        self.check_storage().await?;
         Ok(())
     }
 
-    fn init_task_manager(&mut self) -> Result<(), Error> {
-        self.task_manager = Some(Arc::new(TaskManager::new(self.task_store.clone())));
+    /// Returns health of components of core.
+    pub async fn health(&self) -> Health {
+        let storage = match &self.file_manager {
+            Some(v) => Some(v.check_storage().await.map_err(|v| v.to_string())),
+            None => None,
+        };
+        Health { storage }
+    }
+
+    /// Spawns job that periodically prunes events older than retention period.
+    pub fn spawn_event_pruner(
+        &self,
+        config: &Events,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
+        const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+        let retention = Duration::from_secs(u64::from(config.retention_days) * 24 * 3600);
+        let batch_size = config.prune_batch_size.max(1);
+        let logger = self.logger.clone();
+        let task_store = self.task_store.clone();
+        let periodic_task_store = self.periodic_task_store.clone();
+        let file_store = self.file_store.clone();
+        let problem_store = self.problem_store.clone();
+        let solution_store = self.solution_store.clone();
+        let setting_store = self.setting_store.clone();
+        tokio::spawn(async move {
+            loop {
+                let before = Instant::now() - retention;
+                let results = [
+                    (
+                        "task",
+                        task_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                    (
+                        "periodic_task",
+                        periodic_task_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                    (
+                        "file",
+                        file_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                    (
+                        "problem",
+                        problem_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                    (
+                        "solution",
+                        solution_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                    (
+                        "setting",
+                        setting_store
+                            .prune_events(Context::new(), before, batch_size)
+                            .await,
+                    ),
+                ];
+                for (store, result) in results {
+                    match result {
+                        Ok(count) => {
+                            slog::debug!(logger, "Pruned events"; "store" => store, "count" => count)
+                        }
+                        Err(err) => {
+                            slog::warn!(logger, "Cannot prune events"; "store" => store, "error" => err.to_string())
+                        }
+                    }
+                }
+                let sleep = tokio::time::timeout(PRUNE_INTERVAL, shutdown.cancelled());
+                if let Ok(()) = sleep.await {
+                    return;
+                }
+            }
+        })
+    }
+
+    async fn check_storage(&self) -> Result<(), Error> {
+        match self.file_manager().check_storage().await {
+            Ok(StorageHealth::Probed { .. }) => {}
+            Ok(StorageHealth::NotProbed { reason }) => {
+                slog::warn!(self.logger, "Storage is not checked"; "reason" => reason)
+            }
+            Err(err) => Err(format!("Storage is not available: {err}"))?,
+        }
+        Ok(())
+    }
+
+    fn init_task_manager(&mut self, config: &Config) -> Result<(), Error> {
+        let max_attempts = match &config.invoker {
+            Some(v) => v.max_attempts,
+            None => DEFAULT_MAX_ATTEMPTS,
+        };
+        self.task_manager = Some(Arc::new(
+            TaskManager::new(self.task_store.clone())
+                .with_max_attempts(max_attempts)
+                .with_periodic_tasks(self.periodic_task_store.clone()),
+        ));
         Ok(())
     }
 
+    /// Spawns scheduler of periodic tasks that is restarted on errors.
+    fn spawn_scheduler(&self, task_manager: Arc<TaskManager>) -> JoinHandle<()> {
+        const RESTART_DELAY: Duration = Duration::from_secs(5);
+        let logger = self.logger.clone();
+        let shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                match task_manager.run_scheduler(shutdown.clone()).await {
+                    Ok(()) => return,
+                    Err(err) => {
+                        slog::warn!(logger, "Cannot schedule periodic tasks"; "error" => err.to_string())
+                    }
+                }
+                let sleep = tokio::time::timeout(RESTART_DELAY, shutdown.cancelled());
+                if let Ok(()) = sleep.await {
+                    return;
+                }
+            }
+        })
+    }
+
     fn init_file_manager(&mut self, config: &Config) -> Result<(), Error> {
-        let config = config
+        let storage = config
             .storage
             .as_ref()
             .expect("Storage config is not provided");
-        let file_manager = Arc::new(FileManager::new(
-            new_storage(config)?,
-            self.file_store.clone(),
-        ));
+        let files = config.files.clone().unwrap_or_default();
+        let file_manager = Arc::new(
+            FileManager::new(new_storage(storage)?, self.file_store.clone())
+                .with_limits(storage.limits().clone())
+                .with_verify_mode(files.verify)
+                .with_chunked_upload(files.chunked_upload.clone()),
+        );
+        if let Some(problems) = &config.problems {
+            if problems.delete_resource_files {
+                file_manager.watch_problem_resources(&self.problem_resource_store);
+            }
+        }
+        file_manager.spawn_cleanup(
+            self.logger.clone(),
+            Duration::from_secs(files.cleanup_interval.max(1)),
+            files.cleanup_batch_size.max(1),
+            self.shutdown.clone(),
+        );
         self.file_manager = Some(file_manager);
         Ok(())
     }
 }
 
+/// Health of components of core.
+#[derive(Clone, Debug)]
+pub struct Health {
+    /// Result of check of storage if file manager is initialized.
+    pub storage: Option<Result<StorageHealth, String>>,
+}
+
+impl Health {
+    pub fn is_healthy(&self) -> bool {
+        !matches!(self.storage, Some(Err(_)))
+    }
+}
+
+impl Drop for Core {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
 /// Awaits future from a blocking function.
 pub fn blocking_await<F: Future>(future: F) -> F::Output {
     tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
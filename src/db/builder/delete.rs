@@ -1,4 +1,4 @@
-use solve_db::{IntoQuery, QueryBuilder, RawQuery};
+use solve_db::{Error, IntoQuery, QueryBuilder, RawQuery};
 
 use super::Predicate;
 
@@ -6,6 +6,8 @@ use super::Predicate;
 pub struct Delete {
     table: String,
     predicate: Predicate,
+    allow_full_scan: bool,
+    returning: Vec<String>,
 }
 
 impl Delete {
@@ -13,6 +15,8 @@ impl Delete {
         Self {
             table: Default::default(),
             predicate: Predicate::Bool(false),
+            allow_full_scan: false,
+            returning: Default::default(),
         }
     }
 
@@ -25,6 +29,17 @@ impl Delete {
         self.predicate = predicate.into();
         self
     }
+
+    /// Allows query to affect all rows of table.
+    pub fn allow_full_scan(mut self) -> Self {
+        self.allow_full_scan = true;
+        self
+    }
+
+    pub fn with_returning(mut self, columns: Vec<String>) -> Self {
+        self.returning = columns;
+        self
+    }
 }
 
 impl Default for Delete {
@@ -34,11 +49,91 @@ impl Default for Delete {
 }
 
 impl IntoQuery<RawQuery> for Delete {
-    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
+    fn into_query(self, builder: QueryBuilder) -> RawQuery {
+        self.try_into_query(builder).unwrap()
+    }
+
+    fn try_into_query(self, mut builder: QueryBuilder) -> Result<RawQuery, Error> {
+        let predicate = self.predicate.simplify();
+        if !self.allow_full_scan && predicate.is_trivially_true() {
+            return Err("Full scan is not allowed".into());
+        }
         builder.push_str("DELETE FROM ");
         builder.push_name(&self.table);
         builder.push_str(" WHERE ");
-        self.predicate.push_into(&mut builder);
-        builder.build()
+        predicate.push_into(&mut builder);
+        if !self.returning.is_empty() {
+            builder.push_str(" RETURNING ");
+            for (i, name) in self.returning.into_iter().enumerate() {
+                if i > 0 {
+                    builder.push_str(", ");
+                }
+                builder.push_name(&name);
+            }
+        }
+        builder.try_build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solve_db::{IntoQuery, IntoValue, Query};
+
+    use super::{
+        super::{column, testing::TestBuilder, Predicate},
+        Delete,
+    };
+
+    #[test]
+    fn delete_query() {
+        let query = Delete::new()
+            .with_table("tbl")
+            .with_where(column("id").equal(1))
+            .into_query(TestBuilder::builder());
+        assert_eq!(query.query(), "DELETE FROM \"tbl\" WHERE \"id\" = $1");
+        assert_eq!(query.values(), vec![1.into_value()]);
+    }
+
+    #[test]
+    fn delete_returning_query() {
+        let query = Delete::new()
+            .with_table("tbl")
+            .with_where(column("id").equal(1))
+            .with_returning(vec!["id".to_string(), "col".to_string()])
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "DELETE FROM \"tbl\" WHERE \"id\" = $1 RETURNING \"id\", \"col\""
+        );
+        assert_eq!(query.values(), vec![1.into_value()]);
+    }
+
+    #[test]
+    fn delete_full_scan_query() {
+        let delete = Delete::new()
+            .with_table("tbl")
+            .with_where(column("id").equal(1).or(Predicate::Bool(false).not()));
+        assert!(delete
+            .clone()
+            .try_into_query(TestBuilder::builder())
+            .is_err());
+        let query = delete
+            .allow_full_scan()
+            .try_into_query(TestBuilder::builder())
+            .unwrap();
+        assert_eq!(query.query(), "DELETE FROM \"tbl\" WHERE true");
+        assert!(query.values().is_empty());
+        let query = Delete::new()
+            .with_table("tbl")
+            .with_where(
+                column("id")
+                    .equal(1)
+                    .or(true.into())
+                    .and(column("id").less(2)),
+            )
+            .try_into_query(TestBuilder::builder())
+            .unwrap();
+        assert_eq!(query.query(), "DELETE FROM \"tbl\" WHERE \"id\" < $1");
+        assert_eq!(query.values(), vec![2.into_value()]);
     }
 }
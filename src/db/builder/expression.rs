@@ -1,5 +1,7 @@
 use solve_db::{IntoValue, QueryBuilder, Value};
 
+use super::Select;
+
 #[derive(Debug, Clone)]
 pub struct BinaryExpression {
     left: Box<Expression>,
@@ -12,16 +14,41 @@ impl BinaryExpression {
         builder.push_str(delim);
         self.right.write_to(builder);
     }
+
+    fn write_arithmetic_to(self, builder: &mut QueryBuilder, delim: &str) {
+        self.left.write_to(builder);
+        builder.push_str(delim);
+        let wrap = matches!(self.right.as_ref(), Expression::Add(_) | Expression::Sub(_));
+        if wrap {
+            builder.push_str("(");
+        }
+        self.right.write_to(builder);
+        if wrap {
+            builder.push_str(")");
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Expression {
     Value(Value),
     Column(String),
+    Aggregate(Aggregate),
+    Add(BinaryExpression),
+    Sub(BinaryExpression),
     Raw(String),
+    RawWithValues(String, Vec<Value>),
 }
 
 impl Expression {
+    /// Creates raw SQL fragment with `?` placeholders for specified values.
+    ///
+    /// Literal `?`, for example jsonb operator, is written as `??`. Query
+    /// with invalid amount of values fails to build.
+    pub fn raw_with_values<T: Into<String>>(sql: T, values: Vec<Value>) -> Expression {
+        Expression::RawWithValues(sql.into(), values)
+    }
+
     pub fn equal<T: Into<Expression>>(self, rhs: T) -> Predicate {
         match rhs.into() {
             Expression::Value(Value::Null) => Predicate::IsNull(Box::new(self)),
@@ -70,11 +97,72 @@ impl Expression {
         })
     }
 
-    fn write_to(self, builder: &mut QueryBuilder) {
+    /// Matches expression with pattern ignoring case.
+    ///
+    /// Use [`like_escape`] for patterns containing user input.
+    pub fn ilike<T: Into<Expression>>(self, pattern: T) -> Predicate {
+        Predicate::ILike(BinaryExpression {
+            left: Box::new(self),
+            right: Box::new(pattern.into()),
+        })
+    }
+
+    /// Matches expression with any of values, empty list matches nothing.
+    pub fn in_values<T: IntoValue>(self, values: Vec<T>) -> Predicate {
+        let values = values.into_iter().map(IntoValue::into_value).collect();
+        Predicate::InValues(Box::new(self), values)
+    }
+
+    pub fn in_select(self, select: Select) -> Predicate {
+        Predicate::InSelect(Box::new(self), Box::new(select))
+    }
+
+    pub fn plus<T: Into<Expression>>(self, rhs: T) -> Expression {
+        Expression::Add(BinaryExpression {
+            left: Box::new(self),
+            right: Box::new(rhs.into()),
+        })
+    }
+
+    pub fn minus<T: Into<Expression>>(self, rhs: T) -> Expression {
+        Expression::Sub(BinaryExpression {
+            left: Box::new(self),
+            right: Box::new(rhs.into()),
+        })
+    }
+
+    pub(super) fn write_to(self, builder: &mut QueryBuilder) {
         match self {
             Expression::Value(v) => builder.push_value(v),
             Expression::Column(v) => builder.push_name(&v),
+            Expression::Aggregate(v) => v.write_to(builder),
+            Expression::Add(v) => v.write_arithmetic_to(builder, " + "),
+            Expression::Sub(v) => v.write_arithmetic_to(builder, " - "),
             Expression::Raw(v) => builder.push_str(&v),
+            Expression::RawWithValues(v, values) => {
+                let mut values = values.into_iter();
+                let mut chars = v.chars().peekable();
+                let mut part = String::new();
+                while let Some(ch) = chars.next() {
+                    if ch != '?' {
+                        part.push(ch);
+                    } else if chars.next_if_eq(&'?').is_some() {
+                        part.push('?');
+                    } else {
+                        builder.push_str(&part);
+                        part.clear();
+                        match values.next() {
+                            Some(value) => builder.push_value(value),
+                            None => builder
+                                .push_error(format!("Not enough values for raw expression: {v}")),
+                        }
+                    }
+                }
+                builder.push_str(&part);
+                if values.next().is_some() {
+                    builder.push_error(format!("Too many values for raw expression: {v}"));
+                }
+            }
         }
     }
 }
@@ -85,10 +173,57 @@ impl<T: IntoValue> From<T> for Expression {
     }
 }
 
+impl From<Aggregate> for Expression {
+    fn from(value: Aggregate) -> Self {
+        Self::Aggregate(value)
+    }
+}
+
 pub fn column<T: Into<String>>(column: T) -> Expression {
     Expression::Column(column.into())
 }
 
+/// Escapes `%`, `_` and `\` characters for usage in LIKE patterns.
+pub fn like_escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+#[derive(Debug, Clone)]
+pub enum Aggregate {
+    Count,
+    Min(String),
+    Max(String),
+    Sum(String),
+}
+
+impl Aggregate {
+    pub fn expr(self) -> Expression {
+        Expression::Aggregate(self)
+    }
+
+    pub(super) fn write_to(self, builder: &mut QueryBuilder) {
+        let (func, column) = match self {
+            Aggregate::Count => {
+                builder.push_str("COUNT(*)");
+                return;
+            }
+            Aggregate::Min(v) => ("MIN(", v),
+            Aggregate::Max(v) => ("MAX(", v),
+            Aggregate::Sum(v) => ("SUM(", v),
+        };
+        builder.push_str(func);
+        builder.push_name(&column);
+        builder.push_str(")");
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct BinaryPredicate {
     left: Box<Predicate>,
@@ -121,8 +256,13 @@ pub enum Predicate {
     LessEqual(BinaryExpression),
     Greater(BinaryExpression),
     GreaterEqual(BinaryExpression),
+    ILike(BinaryExpression),
     IsNull(Box<Expression>),
     IsNotNull(Box<Expression>),
+    InValues(Box<Expression>, Vec<Value>),
+    InSelect(Box<Expression>, Box<Select>),
+    Exists(Box<Select>),
+    Not(Box<Predicate>),
 }
 
 impl Predicate {
@@ -140,6 +280,58 @@ impl Predicate {
         })
     }
 
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Predicate {
+        match self {
+            Predicate::Bool(v) => Predicate::Bool(!v),
+            Predicate::Equal(v) => Predicate::NotEqual(v),
+            Predicate::NotEqual(v) => Predicate::Equal(v),
+            Predicate::IsNull(v) => Predicate::IsNotNull(v),
+            Predicate::IsNotNull(v) => Predicate::IsNull(v),
+            Predicate::Not(v) => *v,
+            v => Predicate::Not(Box::new(v)),
+        }
+    }
+
+    /// Folds constant parts of predicate.
+    pub fn simplify(self) -> Predicate {
+        match self {
+            Predicate::And(v) => match (v.left.simplify(), v.right.simplify()) {
+                (Predicate::Bool(false), _) | (_, Predicate::Bool(false)) => Predicate::Bool(false),
+                (Predicate::Bool(true), v) | (v, Predicate::Bool(true)) => v,
+                (left, right) => left.and(right),
+            },
+            Predicate::Or(v) => match (v.left.simplify(), v.right.simplify()) {
+                (Predicate::Bool(true), _) | (_, Predicate::Bool(true)) => Predicate::Bool(true),
+                (Predicate::Bool(false), v) | (v, Predicate::Bool(false)) => v,
+                (left, right) => left.or(right),
+            },
+            Predicate::Not(v) => v.simplify().not(),
+            v => v,
+        }
+    }
+
+    /// Reports whether predicate is true for every row.
+    pub fn is_trivially_true(&self) -> bool {
+        match self {
+            Predicate::Bool(v) => *v,
+            Predicate::And(v) => v.left.is_trivially_true() && v.right.is_trivially_true(),
+            Predicate::Or(v) => v.left.is_trivially_true() || v.right.is_trivially_true(),
+            Predicate::Not(v) => v.is_trivially_false(),
+            _ => false,
+        }
+    }
+
+    fn is_trivially_false(&self) -> bool {
+        match self {
+            Predicate::Bool(v) => !*v,
+            Predicate::And(v) => v.left.is_trivially_false() || v.right.is_trivially_false(),
+            Predicate::Or(v) => v.left.is_trivially_false() && v.right.is_trivially_false(),
+            Predicate::Not(v) => v.is_trivially_true(),
+            _ => false,
+        }
+    }
+
     pub fn push_into(self, builder: &mut QueryBuilder) {
         let disc = std::mem::discriminant(&self);
         match self {
@@ -152,6 +344,18 @@ impl Predicate {
             Predicate::LessEqual(v) => v.write_to(builder, " <= "),
             Predicate::Greater(v) => v.write_to(builder, " > "),
             Predicate::GreaterEqual(v) => v.write_to(builder, " >= "),
+            Predicate::ILike(v) => {
+                if builder.supports_ilike() {
+                    v.write_to(builder, " ILIKE ");
+                } else {
+                    builder.push_str("LOWER(");
+                    v.left.write_to(builder);
+                    builder.push_str(") LIKE LOWER(");
+                    v.right.write_to(builder);
+                    builder.push_str(")");
+                }
+                builder.push_str(" ESCAPE '\\'");
+            }
             Predicate::IsNull(v) => {
                 v.write_to(builder);
                 builder.push_str(" IS NULL");
@@ -160,6 +364,43 @@ impl Predicate {
                 v.write_to(builder);
                 builder.push_str(" IS NOT NULL");
             }
+            Predicate::InValues(_, values) if values.is_empty() => builder.push_str("false"),
+            Predicate::InValues(v, values) => {
+                v.write_to(builder);
+                builder.push_str(" IN (");
+                for (i, value) in values.into_iter().enumerate() {
+                    if i > 0 {
+                        builder.push_str(", ");
+                    }
+                    builder.push_value(value);
+                }
+                builder.push_str(")");
+            }
+            Predicate::InSelect(v, select) => {
+                v.write_to(builder);
+                builder.push_str(" IN (");
+                select.push_into(builder);
+                builder.push_str(")");
+            }
+            Predicate::Exists(select) => {
+                builder.push_str("EXISTS (");
+                select.push_into(builder);
+                builder.push_str(")");
+            }
+            Predicate::Not(v) => {
+                builder.push_str("NOT ");
+                let wrap = !matches!(
+                    v.as_ref(),
+                    Predicate::Bool(_) | Predicate::Exists(_) | Predicate::Not(_)
+                );
+                if wrap {
+                    builder.push_str("(");
+                }
+                v.push_into(builder);
+                if wrap {
+                    builder.push_str(")");
+                }
+            }
         }
     }
 
@@ -178,6 +419,18 @@ impl Predicate {
     }
 }
 
+pub fn exists(select: Select) -> Predicate {
+    Predicate::Exists(Box::new(select))
+}
+
+impl std::ops::Not for Predicate {
+    type Output = Predicate;
+
+    fn not(self) -> Self::Output {
+        Predicate::not(self)
+    }
+}
+
 impl From<bool> for Predicate {
     fn from(value: bool) -> Self {
         Self::Bool(value)
@@ -1,10 +1,13 @@
-use solve_db::{IntoQuery, IntoRow, QueryBuilder, RawQuery, Value};
+use solve_db::{Error, IntoQuery, IntoRow, QueryBuilder, RawQuery, Value};
+
+use super::Predicate;
 
 #[derive(Clone, Debug)]
 pub struct Insert {
     table: String,
     columns: Vec<String>,
-    values: Vec<Value>,
+    rows: Vec<Vec<Value>>,
+    predicate: Option<Predicate>,
     returning: Vec<String>,
 }
 
@@ -13,7 +16,8 @@ impl Insert {
         Self {
             table: Default::default(),
             columns: Default::default(),
-            values: Default::default(),
+            rows: Default::default(),
+            predicate: None,
             returning: Default::default(),
         }
     }
@@ -29,7 +33,19 @@ impl Insert {
     }
 
     pub fn with_values(mut self, values: Vec<Value>) -> Self {
-        self.values = values;
+        self.rows = vec![values];
+        self
+    }
+
+    /// Sets values of several rows inserted by single statement.
+    pub fn with_rows_values(mut self, rows: Vec<Vec<Value>>) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    /// Inserts row only if predicate is satisfied.
+    pub fn with_where<T: Into<Predicate>>(mut self, predicate: T) -> Self {
+        self.predicate = Some(predicate.into());
         self
     }
 
@@ -42,6 +58,22 @@ impl Insert {
         let (columns, values) = row.into_row().into_iter().unzip();
         self.with_columns(columns).with_values(values)
     }
+
+    /// Sets rows with identical columns.
+    pub fn with_rows<T: IntoRow>(self, rows: Vec<T>) -> Self {
+        let mut columns = Vec::new();
+        let mut values = Vec::with_capacity(rows.len());
+        for (i, row) in rows.into_iter().enumerate() {
+            let (row_columns, row_values): (Vec<_>, _) = row.into_row().into_iter().unzip();
+            if i == 0 {
+                columns = row_columns;
+            } else {
+                assert_eq!(columns, row_columns, "rows should have same columns");
+            }
+            values.push(row_values);
+        }
+        self.with_columns(columns).with_rows_values(values)
+    }
 }
 
 impl Default for Insert {
@@ -51,25 +83,51 @@ impl Default for Insert {
 }
 
 impl IntoQuery<RawQuery> for Insert {
-    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
-        assert_eq!(self.columns.len(), self.values.len());
+    fn into_query(self, builder: QueryBuilder) -> RawQuery {
+        self.try_into_query(builder).unwrap()
+    }
+
+    fn try_into_query(self, mut builder: QueryBuilder) -> Result<RawQuery, Error> {
+        assert!(!self.rows.is_empty());
+        assert!(
+            self.predicate.is_none() || self.rows.len() == 1,
+            "predicate requires single row"
+        );
         builder.push_str("INSERT INTO ");
         builder.push_name(&self.table);
         builder.push_str(" (");
-        for (i, column) in self.columns.into_iter().enumerate() {
+        for (i, column) in self.columns.iter().enumerate() {
             if i > 0 {
                 builder.push_str(", ");
             }
-            builder.push_name(&column);
+            builder.push_name(column);
         }
-        builder.push_str(") VALUES (");
-        for (i, value) in self.values.into_iter().enumerate() {
+        builder.push_str(match self.predicate {
+            Some(_) => ") SELECT ",
+            None => ") VALUES ",
+        });
+        for (i, values) in self.rows.into_iter().enumerate() {
+            assert_eq!(self.columns.len(), values.len());
             if i > 0 {
                 builder.push_str(", ");
             }
-            builder.push_value(value);
+            if self.predicate.is_none() {
+                builder.push('(');
+            }
+            for (j, value) in values.into_iter().enumerate() {
+                if j > 0 {
+                    builder.push_str(", ");
+                }
+                builder.push_value(value);
+            }
+            if self.predicate.is_none() {
+                builder.push(')');
+            }
+        }
+        if let Some(predicate) = self.predicate {
+            builder.push_str(" WHERE ");
+            predicate.simplify().push_into(&mut builder);
         }
-        builder.push_str(")");
         if !self.returning.is_empty() {
             builder.push_str(" RETURNING ");
             for (i, name) in self.returning.into_iter().enumerate() {
@@ -79,6 +137,80 @@ impl IntoQuery<RawQuery> for Insert {
                 builder.push_name(&name);
             }
         }
-        builder.build()
+        builder.try_build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solve_db::{IntoQuery, IntoValue, Query};
+
+    use super::{
+        super::{column, exists, testing::TestBuilder, Select},
+        Insert,
+    };
+
+    #[test]
+    fn insert_query() {
+        let query = Insert::new()
+            .with_table("tbl")
+            .with_columns(vec!["a".to_owned(), "b".to_owned()])
+            .with_values(vec![1.into_value(), "test".into_value()])
+            .with_returning(vec!["id".to_owned()])
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            r#"INSERT INTO "tbl" ("a", "b") VALUES ($1, $2) RETURNING "id""#
+        );
+        assert_eq!(query.values(), vec![1.into_value(), "test".into_value()]);
+    }
+
+    #[test]
+    fn insert_where_query() {
+        let query = Insert::new()
+            .with_table("tbl")
+            .with_columns(vec!["a".to_owned(), "b".to_owned()])
+            .with_values(vec![1.into_value(), "test".into_value()])
+            .with_where(!exists(
+                Select::new()
+                    .with_table("tbl")
+                    .with_columns(vec!["a".to_owned()])
+                    .with_where(column("b").equal("test")),
+            ))
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            r#"INSERT INTO "tbl" ("a", "b") SELECT $1, $2 WHERE NOT EXISTS (SELECT "a" FROM "tbl" WHERE "b" = $3)"#
+        );
+        assert_eq!(
+            query.values(),
+            vec![1.into_value(), "test".into_value(), "test".into_value()]
+        );
+    }
+
+    #[test]
+    fn insert_rows_query() {
+        let query = Insert::new()
+            .with_table("tbl")
+            .with_columns(vec!["a".to_owned(), "b".to_owned()])
+            .with_rows_values(vec![
+                vec![1.into_value(), "x".into_value()],
+                vec![2.into_value(), "y".into_value()],
+            ])
+            .with_returning(vec!["id".to_owned()])
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            r#"INSERT INTO "tbl" ("a", "b") VALUES ($1, $2), ($3, $4) RETURNING "id""#
+        );
+        assert_eq!(
+            query.values(),
+            vec![
+                1.into_value(),
+                "x".into_value(),
+                2.into_value(),
+                "y".into_value()
+            ]
+        );
     }
 }
@@ -1,11 +1,16 @@
 mod delete;
 mod expression;
 mod insert;
+mod schema;
 mod select;
 mod update;
 
+#[cfg(test)]
+mod testing;
+
 pub use delete::*;
 pub use expression::*;
 pub use insert::*;
+pub use schema::*;
 pub use select::*;
 pub use update::*;
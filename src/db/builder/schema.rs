@@ -0,0 +1,295 @@
+use solve_db::{Dialect, IntoQuery, QueryBuilder, RawQuery};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    Bool,
+    BigInt,
+    Double,
+    Text,
+    Blob,
+}
+
+#[derive(Clone, Debug)]
+pub struct Column {
+    name: String,
+    kind: ColumnType,
+    nullable: bool,
+    primary_key: bool,
+    auto_increment: bool,
+}
+
+impl Column {
+    pub fn new<T: Into<String>>(name: T, kind: ColumnType) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            nullable: false,
+            primary_key: false,
+            auto_increment: false,
+        }
+    }
+
+    pub fn bool<T: Into<String>>(name: T) -> Self {
+        Self::new(name, ColumnType::Bool)
+    }
+
+    pub fn big_int<T: Into<String>>(name: T) -> Self {
+        Self::new(name, ColumnType::BigInt)
+    }
+
+    pub fn double<T: Into<String>>(name: T) -> Self {
+        Self::new(name, ColumnType::Double)
+    }
+
+    pub fn text<T: Into<String>>(name: T) -> Self {
+        Self::new(name, ColumnType::Text)
+    }
+
+    pub fn blob<T: Into<String>>(name: T) -> Self {
+        Self::new(name, ColumnType::Blob)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> ColumnType {
+        self.kind
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    pub fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    pub fn primary_key(mut self) -> Self {
+        self.primary_key = true;
+        self
+    }
+
+    pub fn auto_increment(mut self) -> Self {
+        self.auto_increment = true;
+        self
+    }
+
+    fn push_into(self, builder: &mut QueryBuilder) {
+        assert!(
+            !self.auto_increment || (self.primary_key && self.kind == ColumnType::BigInt),
+            "Auto increment requires BIGINT primary key"
+        );
+        let dialect = builder.dialect();
+        builder.push_name(&self.name);
+        if self.auto_increment {
+            builder.push_str(match dialect {
+                // Only "INTEGER PRIMARY KEY" is an alias for ROWID.
+                Dialect::SQLite => " INTEGER PRIMARY KEY",
+                Dialect::Postgres => " bigserial PRIMARY KEY",
+                Dialect::Generic => " BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY",
+            });
+            return;
+        }
+        builder.push_str(match (dialect, self.kind) {
+            (Dialect::SQLite, ColumnType::Bool) => " BOOLEAN",
+            (Dialect::SQLite, ColumnType::BigInt) => " INTEGER",
+            (Dialect::SQLite, ColumnType::Double) => " REAL",
+            (Dialect::SQLite, ColumnType::Text) => " TEXT",
+            (Dialect::SQLite, ColumnType::Blob) => " BLOB",
+            (Dialect::Postgres, ColumnType::Blob) => " bytea",
+            (_, ColumnType::Bool) => " boolean",
+            (_, ColumnType::BigInt) => " bigint",
+            (_, ColumnType::Double) => " double precision",
+            (_, ColumnType::Text) => " text",
+            (Dialect::Generic, ColumnType::Blob) => " blob",
+        });
+        if self.primary_key {
+            builder.push_str(" PRIMARY KEY");
+        } else if !self.nullable {
+            builder.push_str(" NOT NULL");
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateTable {
+    table: String,
+    columns: Vec<Column>,
+    if_not_exists: bool,
+}
+
+impl CreateTable {
+    pub fn new<T: Into<String>>(table: T) -> Self {
+        Self {
+            table: table.into(),
+            columns: Default::default(),
+            if_not_exists: false,
+        }
+    }
+
+    pub fn add_column(mut self, column: Column) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<Column>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+}
+
+impl IntoQuery<RawQuery> for CreateTable {
+    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
+        assert!(!self.columns.is_empty());
+        builder.push_str("CREATE TABLE ");
+        if self.if_not_exists {
+            builder.push_str("IF NOT EXISTS ");
+        }
+        builder.push_name(&self.table);
+        builder.push_str(" (");
+        for (i, column) in self.columns.into_iter().enumerate() {
+            if i > 0 {
+                builder.push_str(", ");
+            }
+            column.push_into(&mut builder);
+        }
+        builder.push_str(")");
+        builder.build()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CreateIndex {
+    name: String,
+    table: String,
+    columns: Vec<String>,
+    unique: bool,
+    if_not_exists: bool,
+}
+
+impl CreateIndex {
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self {
+            name: name.into(),
+            table: Default::default(),
+            columns: Default::default(),
+            unique: false,
+            if_not_exists: false,
+        }
+    }
+
+    pub fn with_table<T: Into<String>>(mut self, table: T) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    pub fn with_unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    pub fn with_if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+}
+
+impl IntoQuery<RawQuery> for CreateIndex {
+    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
+        assert!(!self.columns.is_empty());
+        builder.push_str(if self.unique {
+            "CREATE UNIQUE INDEX "
+        } else {
+            "CREATE INDEX "
+        });
+        if self.if_not_exists {
+            builder.push_str("IF NOT EXISTS ");
+        }
+        builder.push_name(&self.name);
+        builder.push_str(" ON ");
+        builder.push_name(&self.table);
+        builder.push_str(" (");
+        for (i, name) in self.columns.into_iter().enumerate() {
+            if i > 0 {
+                builder.push_str(", ");
+            }
+            builder.push_name(&name);
+        }
+        builder.push_str(")");
+        builder.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solve_db::{Dialect, IntoQuery, Query};
+
+    use super::{super::testing::TestBuilder, Column, CreateIndex, CreateTable};
+
+    fn file_table() -> CreateTable {
+        CreateTable::new("solve_file")
+            .add_column(Column::big_int("id").primary_key().auto_increment())
+            .add_column(Column::big_int("status"))
+            .add_column(Column::big_int("expire_time").nullable())
+            .add_column(Column::text("path"))
+            .add_column(Column::blob("meta"))
+    }
+
+    #[test]
+    fn create_table_query() {
+        let query = file_table().into_query(TestBuilder::dialect_builder(Dialect::SQLite));
+        assert_eq!(
+            query.query(),
+            "CREATE TABLE \"solve_file\" (\"id\" INTEGER PRIMARY KEY, \"status\" INTEGER NOT NULL, \"expire_time\" INTEGER, \"path\" TEXT NOT NULL, \"meta\" BLOB NOT NULL)"
+        );
+        let query = file_table()
+            .with_if_not_exists()
+            .into_query(TestBuilder::dialect_builder(Dialect::Postgres));
+        assert_eq!(
+            query.query(),
+            "CREATE TABLE IF NOT EXISTS \"solve_file\" (\"id\" bigserial PRIMARY KEY, \"status\" bigint NOT NULL, \"expire_time\" bigint, \"path\" text NOT NULL, \"meta\" bytea NOT NULL)"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn create_table_invalid_auto_increment() {
+        CreateTable::new("tbl")
+            .add_column(Column::text("id").primary_key().auto_increment())
+            .into_query(TestBuilder::builder());
+    }
+
+    #[test]
+    fn create_index_query() {
+        let query = CreateIndex::new("solve_file_event_id_idx")
+            .with_table("solve_file_event")
+            .with_columns(vec!["id".to_string(), "event_id".to_string()])
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "CREATE INDEX \"solve_file_event_id_idx\" ON \"solve_file_event\" (\"id\", \"event_id\")"
+        );
+        let query = CreateIndex::new("solve_file_path_idx")
+            .with_table("solve_file")
+            .with_columns(vec!["path".to_string()])
+            .with_unique()
+            .with_if_not_exists()
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "CREATE UNIQUE INDEX IF NOT EXISTS \"solve_file_path_idx\" ON \"solve_file\" (\"path\")"
+        );
+    }
+}
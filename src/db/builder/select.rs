@@ -1,14 +1,101 @@
-use solve_db::{IntoQuery, QueryBuilder, RawQuery};
+use solve_db::{Error, IntoQuery, QueryBuilder, RawQuery};
 
-use super::Predicate;
+use super::{Aggregate, Expression, Predicate};
+
+#[derive(Clone, Debug)]
+pub enum SelectColumn {
+    Name(String),
+    Qualified(String, String),
+    Expression(Expression, String),
+}
+
+impl SelectColumn {
+    pub fn name<T: Into<String>>(name: T) -> Self {
+        Self::Name(name.into())
+    }
+
+    pub fn qualified<T: Into<String>, C: Into<String>>(table: T, name: C) -> Self {
+        Self::Qualified(table.into(), name.into())
+    }
+
+    pub fn expr<T: Into<Expression>, A: Into<String>>(expr: T, alias: A) -> Self {
+        Self::Expression(expr.into(), alias.into())
+    }
+
+    fn push_into(self, builder: &mut QueryBuilder) {
+        match self {
+            SelectColumn::Name(v) => builder.push_name(&v),
+            SelectColumn::Qualified(table, v) => {
+                builder.push_name(&table);
+                builder.push('.');
+                builder.push_name(&v);
+            }
+            SelectColumn::Expression(expr, alias) => {
+                expr.write_to(builder);
+                builder.push_str(" AS ");
+                builder.push_name(&alias);
+            }
+        }
+    }
+}
+
+impl From<String> for SelectColumn {
+    fn from(value: String) -> Self {
+        Self::Name(value)
+    }
+}
+
+impl From<&str> for SelectColumn {
+    fn from(value: &str) -> Self {
+        Self::Name(value.to_owned())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Order {
+    Asc(String),
+    Desc(String),
+}
+
+impl Order {
+    pub fn asc<T: Into<String>>(column: T) -> Self {
+        Self::Asc(column.into())
+    }
+
+    pub fn desc<T: Into<String>>(column: T) -> Self {
+        Self::Desc(column.into())
+    }
+
+    pub fn column(&self) -> &str {
+        match self {
+            Order::Asc(v) | Order::Desc(v) => v,
+        }
+    }
+
+    pub fn is_desc(&self) -> bool {
+        matches!(self, Order::Desc(_))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Locking {
+    ForUpdate { skip_locked: bool },
+    ForShare,
+}
 
 #[derive(Clone, Debug)]
 pub struct Select {
     table: String,
-    columns: Vec<String>,
-    predicate: Predicate,
-    order_by: Vec<String>,
+    columns: Vec<SelectColumn>,
+    aggregate: Option<Aggregate>,
+    // Unfiltered select is represented as None.
+    predicate: Option<Predicate>,
+    group_by: Vec<String>,
+    having: Option<Predicate>,
+    order_by: Vec<Order>,
     limit: usize,
+    locking: Option<Locking>,
+    include_deleted: bool,
 }
 
 impl Select {
@@ -16,36 +103,105 @@ impl Select {
         Self {
             table: Default::default(),
             columns: Default::default(),
-            predicate: Predicate::Bool(false),
+            aggregate: None,
+            predicate: None,
+            group_by: Default::default(),
+            having: None,
             order_by: Default::default(),
             limit: 0,
+            locking: None,
+            include_deleted: false,
         }
     }
 
+    /// Creates select that matches no rows.
+    pub fn none() -> Self {
+        Self::new().with_where(false)
+    }
+
     pub fn with_table<T: Into<String>>(mut self, table: T) -> Self {
         self.table = table.into();
         self
     }
 
-    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+    pub fn with_columns(self, columns: Vec<String>) -> Self {
+        self.with_select_columns(columns.into_iter().map(SelectColumn::Name).collect())
+    }
+
+    pub fn with_select_columns(mut self, columns: Vec<SelectColumn>) -> Self {
         self.columns = columns;
         self
     }
 
+    pub fn with_aggregate(mut self, aggregate: Aggregate) -> Self {
+        self.aggregate = Some(aggregate);
+        self
+    }
+
     pub fn with_where<T: Into<Predicate>>(mut self, predicate: T) -> Self {
-        self.predicate = predicate.into();
+        self.predicate = Some(predicate.into());
+        self
+    }
+
+    /// Adds predicate to existing one using AND.
+    pub fn and_where<T: Into<Predicate>>(mut self, predicate: T) -> Self {
+        self.predicate = Some(match self.predicate {
+            Some(v) => v.and(predicate.into()),
+            None => predicate.into(),
+        });
         self
     }
 
-    pub fn with_order_by(mut self, columns: Vec<String>) -> Self {
-        self.order_by = columns;
+    /// Disables hiding of soft deleted objects by stores.
+    pub fn include_deleted(mut self) -> Self {
+        self.include_deleted = true;
         self
     }
 
+    pub fn includes_deleted(&self) -> bool {
+        self.include_deleted
+    }
+
+    pub fn with_group_by(mut self, columns: Vec<String>) -> Self {
+        self.group_by = columns;
+        self
+    }
+
+    pub fn with_having<T: Into<Predicate>>(mut self, predicate: T) -> Self {
+        self.having = Some(predicate.into());
+        self
+    }
+
+    pub fn with_order_by(self, columns: Vec<String>) -> Self {
+        self.with_order(columns.into_iter().map(Order::Asc).collect())
+    }
+
+    pub fn with_order(mut self, order: Vec<Order>) -> Self {
+        self.order_by = order;
+        self
+    }
+
+    pub fn has_order(&self) -> bool {
+        !self.order_by.is_empty()
+    }
+
+    pub fn has_limit(&self) -> bool {
+        self.limit > 0
+    }
+
     pub fn with_limit(mut self, limit: usize) -> Self {
         self.limit = limit;
         self
     }
+
+    /// Sets row locking clause.
+    ///
+    /// Locking is silently ignored for drivers without its support, so
+    /// callers should check [`QueryBuilder::supports_locking`] and warn.
+    pub fn with_locking(mut self, locking: Locking) -> Self {
+        self.locking = Some(locking);
+        self
+    }
 }
 
 impl Default for Select {
@@ -54,141 +210,403 @@ impl Default for Select {
     }
 }
 
-impl IntoQuery<RawQuery> for Select {
-    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
-        assert!(!self.columns.is_empty());
+impl Select {
+    pub fn push_into(self, builder: &mut QueryBuilder) {
+        if self.having.is_some() && self.group_by.is_empty() {
+            builder.push_error("HAVING requires GROUP BY");
+        }
         builder.push_str("SELECT ");
-        for (i, column) in self.columns.into_iter().enumerate() {
+        // Aggregated rows contain only grouping columns and aggregate.
+        let columns = match self.aggregate {
+            Some(_) => self
+                .group_by
+                .iter()
+                .cloned()
+                .map(SelectColumn::Name)
+                .collect(),
+            None => self.columns,
+        };
+        assert!(!columns.is_empty() || self.aggregate.is_some());
+        for (i, column) in columns.into_iter().enumerate() {
             if i > 0 {
                 builder.push_str(", ");
             }
-            builder.push_name(&column);
+            column.push_into(builder);
+        }
+        // Ordering of a single aggregated row is pointless.
+        let skip_order_by = self.aggregate.is_some() && self.group_by.is_empty();
+        if let Some(aggregate) = self.aggregate {
+            if !self.group_by.is_empty() {
+                builder.push_str(", ");
+            }
+            aggregate.write_to(builder);
         }
         builder.push_str(" FROM ");
         builder.push_name(&self.table);
-        builder.push_str(" WHERE ");
-        self.predicate.push_into(&mut builder);
-        if !self.order_by.is_empty() {
-            builder.push_str(" ORDER BY ");
-            for (i, name) in self.order_by.into_iter().enumerate() {
+        if let Some(predicate) = self.predicate {
+            builder.push_str(" WHERE ");
+            predicate.simplify().push_into(builder);
+        }
+        if !self.group_by.is_empty() {
+            builder.push_str(" GROUP BY ");
+            for (i, name) in self.group_by.into_iter().enumerate() {
                 if i > 0 {
                     builder.push_str(", ");
                 }
                 builder.push_name(&name);
             }
         }
+        if let Some(having) = self.having {
+            builder.push_str(" HAVING ");
+            having.simplify().push_into(builder);
+        }
+        if !skip_order_by && !self.order_by.is_empty() {
+            builder.push_str(" ORDER BY ");
+            for (i, order) in self.order_by.into_iter().enumerate() {
+                if i > 0 {
+                    builder.push_str(", ");
+                }
+                builder.push_name(order.column());
+                if order.is_desc() {
+                    builder.push_str(" DESC");
+                }
+            }
+        }
         if self.limit > 0 {
             builder.push_str(" LIMIT ");
             builder.push_str(&self.limit.to_string())
         }
-        builder.build()
+        if let Some(locking) = self.locking {
+            if builder.supports_locking() {
+                builder.push_str(match locking {
+                    Locking::ForUpdate { skip_locked: false } => " FOR UPDATE",
+                    Locking::ForUpdate { skip_locked: true } => " FOR UPDATE SKIP LOCKED",
+                    Locking::ForShare => " FOR SHARE",
+                });
+            }
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use solve_db::{driver, IntoQuery, IntoValue, Query, QueryBuilder, RawQuery, Value};
-
-    use super::{super::column, Predicate, Select};
-
-    struct TestBuilder {
-        query: String,
-        values: Vec<Value>,
+impl IntoQuery<RawQuery> for Select {
+    fn into_query(self, builder: QueryBuilder) -> RawQuery {
+        self.try_into_query(builder).unwrap()
     }
 
-    impl TestBuilder {
-        pub fn new() -> QueryBuilder {
-            QueryBuilder::new(Self {
-                query: Default::default(),
-                values: Default::default(),
-            })
-        }
+    fn try_into_query(self, mut builder: QueryBuilder) -> Result<RawQuery, Error> {
+        self.push_into(&mut builder);
+        builder.try_build()
     }
+}
 
-    impl driver::QueryBuilder for TestBuilder {
-        fn push(&mut self, ch: char) {
-            self.query.push(ch);
-        }
-
-        fn push_str(&mut self, part: &str) {
-            self.query.push_str(part);
-        }
-
-        fn push_name(&mut self, name: &str) {
-            assert!(name.find(|c| c == '"' || c == '\\').is_none());
-            self.push('"');
-            self.push_str(name);
-            self.push('"');
-        }
-
-        fn push_value(&mut self, value: Value) {
-            self.values.push(value);
-            self.push_str(format!("${}", self.values.len()).as_str())
-        }
+#[cfg(test)]
+mod tests {
+    use solve_db::{IntoQuery, IntoValue, Query, Value};
 
-        fn build(self: Box<Self>) -> RawQuery {
-            RawQuery::new(self.query, self.values)
-        }
-    }
+    use super::{
+        super::{column, exists, like_escape, testing::TestBuilder, Expression},
+        Aggregate, Locking, Order, Predicate, Select, SelectColumn,
+    };
 
     #[test]
     fn bool_expression() {
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             Predicate::Bool(true).push_into(&mut builder);
             assert_eq!(builder.build().query(), "true");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             Predicate::Bool(false).push_into(&mut builder);
             assert_eq!(builder.build().query(), "false");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             Predicate::Bool(true)
                 .and(Predicate::Bool(false))
                 .push_into(&mut builder);
             assert_eq!(builder.build().query(), "true AND false");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").equal("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" = $1");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").not_equal("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" <> $1");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").less("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" < $1");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").greater("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" > $1");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").less_equal("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" <= $1");
         }
         {
-            let mut builder = TestBuilder::new();
+            let mut builder = TestBuilder::builder();
             column("col").greater_equal("42").push_into(&mut builder);
             assert_eq!(builder.build().query(), "\"col\" >= $1");
         }
     }
 
+    #[test]
+    fn not_expression() {
+        {
+            let mut builder = TestBuilder::builder();
+            column("col").equal("42").not().push_into(&mut builder);
+            assert_eq!(builder.build().query(), "\"col\" <> $1");
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("col")
+                .equal(Value::Null)
+                .not()
+                .push_into(&mut builder);
+            assert_eq!(builder.build().query(), "\"col\" IS NOT NULL");
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("col").less("42").not().push_into(&mut builder);
+            assert_eq!(builder.build().query(), "NOT (\"col\" < $1)");
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("col1")
+                .equal(1)
+                .and(column("col2").less(2))
+                .not()
+                .push_into(&mut builder);
+            assert_eq!(
+                builder.build().query(),
+                "NOT (\"col1\" = $1 AND \"col2\" < $2)"
+            );
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("col1")
+                .equal(1)
+                .and(column("col2").less(2).not())
+                .or(column("col3").greater(3))
+                .not()
+                .push_into(&mut builder);
+            assert_eq!(
+                builder.build().query(),
+                "NOT ((\"col1\" = $1 AND NOT (\"col2\" < $2)) OR \"col3\" > $3)"
+            );
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("col").less("42").not().not().push_into(&mut builder);
+            assert_eq!(builder.build().query(), "\"col\" < $1");
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            exists(
+                Select::new()
+                    .with_table("tbl")
+                    .with_columns(vec!["col".to_string()]),
+            )
+            .not()
+            .push_into(&mut builder);
+            assert_eq!(
+                builder.build().query(),
+                "NOT EXISTS (SELECT \"col\" FROM \"tbl\")"
+            );
+        }
+    }
+
+    #[test]
+    fn raw_expression() {
+        {
+            let mut builder = TestBuilder::builder();
+            column("col1")
+                .equal(1)
+                .and(
+                    Expression::raw_with_values(
+                        "json_extract(\"meta\", ?)",
+                        vec!["$.kind".into_value()],
+                    )
+                    .equal(2),
+                )
+                .push_into(&mut builder);
+            let query = builder.build();
+            assert_eq!(
+                query.query(),
+                "\"col1\" = $1 AND json_extract(\"meta\", $2) = $3"
+            );
+            assert_eq!(
+                query.values(),
+                vec![1.into_value(), "$.kind".into_value(), 2.into_value()]
+            );
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            Expression::raw_with_values("? + ?", vec![1.into_value(), 2.into_value()])
+                .equal(3)
+                .push_into(&mut builder);
+            assert_eq!(builder.build().query(), "$1 + $2 = $3");
+        }
+    }
+
+    #[test]
+    fn raw_expression_invalid_values() {
+        let select = |values| {
+            Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col".to_string()])
+                .with_where(Expression::raw_with_values("? + ?", values).equal(3))
+        };
+        for values in [vec![1.into_value()], vec![1.into_value(); 3]] {
+            let result = select(values).try_into_query(TestBuilder::builder());
+            assert!(result.is_err());
+        }
+        assert!(select(vec![1.into_value(); 2])
+            .try_into_query(TestBuilder::builder())
+            .is_ok());
+    }
+
+    #[test]
+    fn raw_expression_escaped_placeholder() {
+        let mut builder = TestBuilder::builder();
+        Expression::raw_with_values("\"meta\" ?? ? AND '??'", vec!["kind".into_value()])
+            .equal(true)
+            .push_into(&mut builder);
+        let query = builder.build();
+        assert_eq!(query.query(), "\"meta\" ? $1 AND '?' = $2");
+        assert_eq!(query.values(), vec!["kind".into_value(), true.into_value()]);
+    }
+
+    #[test]
+    fn select_simplify_query() {
+        let select = || {
+            Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col".to_string()])
+        };
+        {
+            let query = select()
+                .with_where(
+                    Predicate::Bool(true)
+                        .and(column("status").equal(1))
+                        .and(true.into()),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col\" FROM \"tbl\" WHERE \"status\" = $1"
+            );
+            assert_eq!(query.values(), vec![1.into_value()]);
+        }
+        {
+            let query = select()
+                .with_where(
+                    Predicate::Bool(false)
+                        .and(column("status").equal(1))
+                        .or(column("kind").equal(2)),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col\" FROM \"tbl\" WHERE \"kind\" = $1"
+            );
+            assert_eq!(query.values(), vec![2.into_value()]);
+        }
+        {
+            let query = select()
+                .with_where(
+                    column("status")
+                        .less(1)
+                        .not()
+                        .not()
+                        .and(Predicate::Bool(false).not()),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col\" FROM \"tbl\" WHERE \"status\" < $1"
+            );
+            assert_eq!(query.values(), vec![1.into_value()]);
+        }
+        {
+            let query = select()
+                .with_where(
+                    Predicate::Bool(true)
+                        .and(false.into())
+                        .or(Predicate::Not(Box::new(true.into())))
+                        .or(column("status").equal(1).and(false.into())),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(query.query(), "SELECT \"col\" FROM \"tbl\" WHERE false");
+            assert!(query.values().is_empty());
+        }
+    }
+
+    #[test]
+    fn ilike_expression() {
+        {
+            let mut builder = TestBuilder::builder();
+            column("name").ilike("%abc%").push_into(&mut builder);
+            assert_eq!(
+                builder.build().query(),
+                "LOWER(\"name\") LIKE LOWER($1) ESCAPE '\\'"
+            );
+        }
+        {
+            let mut builder = TestBuilder::ilike_builder();
+            column("name").ilike("%abc%").push_into(&mut builder);
+            assert_eq!(builder.build().query(), "\"name\" ILIKE $1 ESCAPE '\\'");
+        }
+        assert_eq!(like_escape("a%b_c\\d"), "a\\%b\\_c\\\\d");
+    }
+
+    #[test]
+    fn in_values_expression() {
+        {
+            let mut builder = TestBuilder::builder();
+            column("id")
+                .in_values(vec![1, 2, 3])
+                .push_into(&mut builder);
+            let query = builder.build();
+            assert_eq!(query.query(), "\"id\" IN ($1, $2, $3)");
+            assert_eq!(
+                query.values(),
+                vec![1.into_value(), 2.into_value(), 3.into_value()]
+            );
+        }
+        {
+            let mut builder = TestBuilder::builder();
+            column("id")
+                .in_values(Vec::<i64>::new())
+                .push_into(&mut builder);
+            assert_eq!(builder.build().query(), "false");
+        }
+    }
+
     #[test]
     fn select_query() {
         {
             let query = Select::new()
                 .with_table("tbl")
                 .with_columns(vec!["col1".to_string(), "col2".to_string()])
-                .into_query(TestBuilder::new());
+                .into_query(TestBuilder::builder());
+            assert_eq!(query.query(), "SELECT \"col1\", \"col2\" FROM \"tbl\"");
+            assert!(query.values().is_empty());
+        }
+        {
+            let query = Select::none()
+                .with_table("tbl")
+                .with_columns(vec!["col1".to_string(), "col2".to_string()])
+                .into_query(TestBuilder::builder());
             assert_eq!(
                 query.query(),
                 "SELECT \"col1\", \"col2\" FROM \"tbl\" WHERE false"
@@ -200,7 +618,7 @@ mod tests {
                 .with_table("tbl")
                 .with_columns(vec!["col1".to_string(), "col2".to_string()])
                 .with_where(false)
-                .into_query(TestBuilder::new());
+                .into_query(TestBuilder::builder());
             assert_eq!(
                 query.query(),
                 "SELECT \"col1\", \"col2\" FROM \"tbl\" WHERE false"
@@ -212,7 +630,7 @@ mod tests {
                 .with_table("tbl")
                 .with_columns(vec!["col1".to_string(), "col2".to_string()])
                 .with_where(true)
-                .into_query(TestBuilder::new());
+                .into_query(TestBuilder::builder());
             assert_eq!(
                 query.query(),
                 "SELECT \"col1\", \"col2\" FROM \"tbl\" WHERE true"
@@ -224,7 +642,7 @@ mod tests {
                 .with_table("tbl")
                 .with_columns(vec!["col1".to_string(), "col2".to_string()])
                 .with_where(column("col1").greater(5).and(column("col2").equal("abc")))
-                .into_query(TestBuilder::new());
+                .into_query(TestBuilder::builder());
             assert_eq!(
                 query.query(),
                 "SELECT \"col1\", \"col2\" FROM \"tbl\" WHERE \"col1\" > $1 AND \"col2\" = $2"
@@ -232,4 +650,251 @@ mod tests {
             assert_eq!(query.values(), vec![5.into_value(), "abc".into_value()],);
         }
     }
+
+    #[test]
+    fn select_columns_query() {
+        let query = Select::new()
+            .with_table("tbl")
+            .with_select_columns(vec![
+                "col1".into(),
+                SelectColumn::qualified("tbl", "col2"),
+                SelectColumn::expr(Expression::Raw("LENGTH(\"col3\")".into()), "col3_len"),
+                SelectColumn::expr(column("col4").plus(1), "col4_next"),
+            ])
+            .with_where(column("col1").equal(1))
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "SELECT \"col1\", \"tbl\".\"col2\", LENGTH(\"col3\") AS \"col3_len\", \"col4\" + $1 AS \"col4_next\" FROM \"tbl\" WHERE \"col1\" = $2"
+        );
+        assert_eq!(query.values(), vec![1.into_value(), 1.into_value()]);
+    }
+
+    #[test]
+    fn select_aggregate_query() {
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_aggregate(Aggregate::Count)
+                .with_where(column("col1").equal(5))
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT COUNT(*) FROM \"tbl\" WHERE \"col1\" = $1"
+            );
+            assert_eq!(query.values(), vec![5.into_value()]);
+        }
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col1".to_string()])
+                .with_aggregate(Aggregate::Max("col1".into()))
+                .with_where(true)
+                .with_order_by(vec!["col1".to_string()])
+                .with_limit(1)
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT MAX(\"col1\") FROM \"tbl\" WHERE true LIMIT 1"
+            );
+        }
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_aggregate(Aggregate::Min("col1".into()))
+                .into_query(TestBuilder::builder());
+            assert_eq!(query.query(), "SELECT MIN(\"col1\") FROM \"tbl\"");
+        }
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_aggregate(Aggregate::Sum("col1".into()))
+                .into_query(TestBuilder::builder());
+            assert_eq!(query.query(), "SELECT SUM(\"col1\") FROM \"tbl\"");
+        }
+    }
+
+    #[test]
+    fn select_and_where_query() {
+        let query = Select::new()
+            .with_table("tbl")
+            .with_columns(vec!["col1".to_string()])
+            .and_where(column("col1").equal(1))
+            .and_where(column("col2").equal(2))
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "SELECT \"col1\" FROM \"tbl\" WHERE \"col1\" = $1 AND \"col2\" = $2"
+        );
+    }
+
+    #[test]
+    fn select_order_query() {
+        assert!(!Select::new().has_order());
+        let select = Select::new()
+            .with_table("tbl")
+            .with_columns(vec!["col1".to_string()])
+            .with_order(vec![Order::desc("col1"), Order::asc("id")])
+            .with_limit(10);
+        assert!(select.has_order());
+        let query = select.into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "SELECT \"col1\" FROM \"tbl\" ORDER BY \"col1\" DESC, \"id\" LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn select_group_by_query() {
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col1".to_string()])
+                .with_where(true)
+                .with_group_by(vec!["col1".to_string()])
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE true GROUP BY \"col1\""
+            );
+        }
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_aggregate(Aggregate::Count)
+                .with_where(column("col2").not_equal(1))
+                .with_group_by(vec!["col1".to_string()])
+                .with_having(Aggregate::Count.expr().greater(2))
+                .with_order_by(vec!["col1".to_string()])
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\", COUNT(*) FROM \"tbl\" WHERE \"col2\" <> $1 GROUP BY \"col1\" HAVING COUNT(*) > $2 ORDER BY \"col1\""
+            );
+            assert_eq!(query.values(), vec![1.into_value(), 2.into_value()]);
+        }
+    }
+
+    #[test]
+    fn select_having_without_group_by() {
+        let result = Select::new()
+            .with_table("tbl")
+            .with_aggregate(Aggregate::Count)
+            .with_where(true)
+            .with_having(Aggregate::Count.expr().greater(2))
+            .try_into_query(TestBuilder::builder());
+        match result {
+            Ok(_) => panic!("query should not be built"),
+            Err(err) => assert_eq!(err.to_string(), "HAVING requires GROUP BY"),
+        }
+        // Subqueries are checked too.
+        let result = Select::new()
+            .with_table("tbl")
+            .with_columns(vec!["col".to_string()])
+            .with_where(exists(
+                Select::new()
+                    .with_table("tbl2")
+                    .with_aggregate(Aggregate::Count)
+                    .with_where(true)
+                    .with_having(Aggregate::Count.expr().greater(2)),
+            ))
+            .try_into_query(TestBuilder::builder());
+        match result {
+            Ok(_) => panic!("query should not be built"),
+            Err(err) => assert_eq!(err.to_string(), "HAVING requires GROUP BY"),
+        }
+    }
+
+    #[test]
+    fn select_subquery() {
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col1".to_string()])
+                .with_where(
+                    column("col2").equal(1).and(
+                        column("col1").in_select(
+                            Select::new()
+                                .with_table("tbl2")
+                                .with_columns(vec!["col3".to_string()])
+                                .with_where(column("col4").greater(2)),
+                        ),
+                    ),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE \"col2\" = $1 AND \"col1\" IN (SELECT \"col3\" FROM \"tbl2\" WHERE \"col4\" > $2)"
+            );
+            assert_eq!(query.values(), vec![1.into_value(), 2.into_value()]);
+        }
+        {
+            let query = Select::new()
+                .with_table("tbl")
+                .with_columns(vec!["col1".to_string()])
+                .with_where(
+                    exists(
+                        Select::new()
+                            .with_table("tbl2")
+                            .with_columns(vec!["col3".to_string()])
+                            .with_where(column("col3").equal(1)),
+                    )
+                    .or(column("col2").equal(2)),
+                )
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE EXISTS (SELECT \"col3\" FROM \"tbl2\" WHERE \"col3\" = $1) OR \"col2\" = $2"
+            );
+            assert_eq!(query.values(), vec![1.into_value(), 2.into_value()]);
+        }
+    }
+
+    #[test]
+    fn select_locking() {
+        let select = Select::new()
+            .with_table("tbl")
+            .with_columns(vec!["col1".to_string()])
+            .with_where(column("col1").equal(1))
+            .with_limit(5);
+        {
+            let query = select
+                .clone()
+                .with_locking(Locking::ForUpdate { skip_locked: true })
+                .into_query(TestBuilder::locking_builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE \"col1\" = $1 LIMIT 5 FOR UPDATE SKIP LOCKED"
+            );
+        }
+        {
+            let query = select
+                .clone()
+                .with_locking(Locking::ForUpdate { skip_locked: false })
+                .into_query(TestBuilder::locking_builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE \"col1\" = $1 LIMIT 5 FOR UPDATE"
+            );
+        }
+        {
+            let query = select
+                .clone()
+                .with_locking(Locking::ForShare)
+                .into_query(TestBuilder::locking_builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE \"col1\" = $1 LIMIT 5 FOR SHARE"
+            );
+        }
+        {
+            let query = select
+                .with_locking(Locking::ForShare)
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "SELECT \"col1\" FROM \"tbl\" WHERE \"col1\" = $1 LIMIT 5"
+            );
+        }
+    }
 }
@@ -0,0 +1,89 @@
+use solve_db::{driver, Dialect, QueryBuilder, RawQuery, Value};
+
+pub(super) struct TestBuilder {
+    query: String,
+    values: Vec<Value>,
+    supports_locking: bool,
+    supports_ilike: bool,
+    dialect: Dialect,
+}
+
+impl TestBuilder {
+    pub fn builder() -> QueryBuilder {
+        QueryBuilder::new(Self {
+            query: Default::default(),
+            values: Default::default(),
+            supports_locking: false,
+            supports_ilike: false,
+            dialect: Dialect::Generic,
+        })
+    }
+
+    pub fn locking_builder() -> QueryBuilder {
+        QueryBuilder::new(Self {
+            query: Default::default(),
+            values: Default::default(),
+            supports_locking: true,
+            supports_ilike: false,
+            dialect: Dialect::Generic,
+        })
+    }
+
+    pub fn ilike_builder() -> QueryBuilder {
+        QueryBuilder::new(Self {
+            query: Default::default(),
+            values: Default::default(),
+            supports_locking: false,
+            supports_ilike: true,
+            dialect: Dialect::Generic,
+        })
+    }
+
+    pub fn dialect_builder(dialect: Dialect) -> QueryBuilder {
+        QueryBuilder::new(Self {
+            query: Default::default(),
+            values: Default::default(),
+            supports_locking: false,
+            supports_ilike: false,
+            dialect,
+        })
+    }
+}
+
+impl driver::QueryBuilder for TestBuilder {
+    fn push(&mut self, ch: char) {
+        self.query.push(ch);
+    }
+
+    fn push_str(&mut self, part: &str) {
+        self.query.push_str(part);
+    }
+
+    fn push_name(&mut self, name: &str) {
+        assert!(name.find(['"', '\\']).is_none());
+        self.push('"');
+        self.push_str(name);
+        self.push('"');
+    }
+
+    fn push_value(&mut self, value: Value) {
+        self.values.push(value);
+        self.push_str(format!("${}", self.values.len()).as_str())
+    }
+
+    fn build(self: Box<Self>) -> RawQuery {
+        RawQuery::new(self.query, self.values)
+    }
+
+    fn supports_locking(&self) -> bool {
+        self.supports_locking
+    }
+
+    fn supports_ilike(&self) -> bool {
+        self.supports_ilike
+    }
+
+    fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+}
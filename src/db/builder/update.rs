@@ -1,12 +1,13 @@
-use solve_db::{IntoQuery, IntoRow, QueryBuilder, RawQuery, Value};
+use solve_db::{Error, IntoQuery, IntoRow, QueryBuilder, RawQuery};
 
-use super::Predicate;
+use super::{Expression, Predicate};
 
 #[derive(Clone, Debug)]
 pub struct Update {
     table: String,
-    update: Vec<(String, Value)>,
+    update: Vec<(String, Expression)>,
     predicate: Predicate,
+    allow_full_scan: bool,
     returning: Vec<String>,
 }
 
@@ -16,6 +17,7 @@ impl Update {
             table: Default::default(),
             update: Default::default(),
             predicate: Predicate::Bool(false),
+            allow_full_scan: false,
             returning: Default::default(),
         }
     }
@@ -25,8 +27,13 @@ impl Update {
         self
     }
 
-    pub fn with_update(mut self, update: Vec<(String, Value)>) -> Self {
-        self.update = update;
+    pub fn with_update<T: Into<Expression>>(mut self, update: Vec<(String, T)>) -> Self {
+        self.update = update.into_iter().map(|(k, v)| (k, v.into())).collect();
+        self
+    }
+
+    pub fn with_set<C: Into<String>, T: Into<Expression>>(mut self, column: C, value: T) -> Self {
+        self.update.push((column.into(), value.into()));
         self
     }
 
@@ -35,6 +42,12 @@ impl Update {
         self
     }
 
+    /// Allows query to affect all rows of table.
+    pub fn allow_full_scan(mut self) -> Self {
+        self.allow_full_scan = true;
+        self
+    }
+
     pub fn with_returning(mut self, columns: Vec<String>) -> Self {
         self.returning = columns;
         self
@@ -52,7 +65,15 @@ impl Default for Update {
 }
 
 impl IntoQuery<RawQuery> for Update {
-    fn into_query(self, mut builder: QueryBuilder) -> RawQuery {
+    fn into_query(self, builder: QueryBuilder) -> RawQuery {
+        self.try_into_query(builder).unwrap()
+    }
+
+    fn try_into_query(self, mut builder: QueryBuilder) -> Result<RawQuery, Error> {
+        let predicate = self.predicate.simplify();
+        if !self.allow_full_scan && predicate.is_trivially_true() {
+            return Err("Full scan is not allowed".into());
+        }
         assert!(!self.update.is_empty());
         builder.push_str("UPDATE ");
         builder.push_name(&self.table);
@@ -63,10 +84,10 @@ impl IntoQuery<RawQuery> for Update {
             }
             builder.push_name(&column);
             builder.push_str(" = ");
-            builder.push_value(value);
+            value.write_to(&mut builder);
         }
         builder.push_str(" WHERE ");
-        self.predicate.push_into(&mut builder);
+        predicate.push_into(&mut builder);
         if !self.returning.is_empty() {
             builder.push_str(" RETURNING ");
             for (i, name) in self.returning.into_iter().enumerate() {
@@ -76,6 +97,95 @@ impl IntoQuery<RawQuery> for Update {
                 builder.push_name(&name);
             }
         }
-        builder.build()
+        builder.try_build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solve_db::{IntoQuery, IntoValue, Query, Value};
+
+    use super::{
+        super::{column, testing::TestBuilder, Predicate},
+        Update,
+    };
+
+    #[test]
+    fn update_query() {
+        let query = Update::new()
+            .with_table("tbl")
+            .with_update(vec![("col1".to_string(), 5), ("col2".to_string(), 6)])
+            .with_where(column("id").equal(1))
+            .with_returning(vec!["id".to_string()])
+            .into_query(TestBuilder::builder());
+        assert_eq!(
+            query.query(),
+            "UPDATE \"tbl\" SET \"col1\" = $1, \"col2\" = $2 WHERE \"id\" = $3 RETURNING \"id\""
+        );
+        assert_eq!(
+            query.values(),
+            vec![5.into_value(), 6.into_value(), 1.into_value()]
+        );
+    }
+
+    #[test]
+    fn update_set_query() {
+        {
+            let query = Update::new()
+                .with_table("tbl")
+                .with_set("attempts", column("attempts").plus(1))
+                .with_set("expire_time", Value::Null)
+                .with_where(column("id").equal(1))
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "UPDATE \"tbl\" SET \"attempts\" = \"attempts\" + $1, \"expire_time\" = $2 WHERE \"id\" = $3"
+            );
+            assert_eq!(
+                query.values(),
+                vec![1.into_value(), Value::Null, 1.into_value()]
+            );
+        }
+        {
+            let query = Update::new()
+                .with_table("tbl")
+                .with_set("col1", column("col2"))
+                .with_where(column("col1").less(column("col2")))
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "UPDATE \"tbl\" SET \"col1\" = \"col2\" WHERE \"col1\" < \"col2\""
+            );
+            assert!(query.values().is_empty());
+        }
+        {
+            let query = Update::new()
+                .with_table("tbl")
+                .with_set("col1", column("col1").minus(column("col2").plus(1)))
+                .with_where(true)
+                .allow_full_scan()
+                .into_query(TestBuilder::builder());
+            assert_eq!(
+                query.query(),
+                "UPDATE \"tbl\" SET \"col1\" = \"col1\" - (\"col2\" + $1) WHERE true"
+            );
+        }
+    }
+
+    #[test]
+    fn update_full_scan_query() {
+        let update = Update::new()
+            .with_table("tbl")
+            .with_set("col1", 1)
+            .with_where(Predicate::Bool(true).and(true.into()));
+        assert!(update
+            .clone()
+            .try_into_query(TestBuilder::builder())
+            .is_err());
+        let query = update
+            .allow_full_scan()
+            .try_into_query(TestBuilder::builder())
+            .unwrap();
+        assert_eq!(query.query(), "UPDATE \"tbl\" SET \"col1\" = $1 WHERE true");
     }
 }
@@ -6,15 +6,52 @@ use deadpool_postgres::tokio_postgres;
 use deadpool_postgres::tokio_postgres::types::{to_sql_checked, FromSql, IsNull, ToSql, Type};
 use futures_util::stream::StreamExt;
 use solve_db::{
-    driver, ColumnIndex, Connection, ConnectionOptions, IsolationLevel, QueryBuilder, Row, Rows,
-    Status, Transaction, TransactionOptions, Value,
+    driver, ColumnIndex, Connection, ConnectionOptions, Dialect, IsolationLevel, PoolStatus,
+    QueryBuilder, RawQuery, Row, Rows, Status, Transaction, TransactionOptions, Value,
 };
 use tokio_util::bytes::BufMut;
 
 use crate::config::PostgresConfig;
 use crate::core::Error;
 
-use super::sqlite::WrapQueryBuilder;
+use super::sqlite;
+
+#[derive(Default)]
+struct WrapQueryBuilder(sqlite::WrapQueryBuilder);
+
+impl driver::QueryBuilder for WrapQueryBuilder {
+    fn push(&mut self, ch: char) {
+        self.0.push(ch);
+    }
+
+    fn push_str(&mut self, part: &str) {
+        self.0.push_str(part);
+    }
+
+    fn push_name(&mut self, name: &str) {
+        self.0.push_name(name);
+    }
+
+    fn push_value(&mut self, value: Value) {
+        self.0.push_value(value);
+    }
+
+    fn build(self: Box<Self>) -> RawQuery {
+        Box::new(self.0).build()
+    }
+
+    fn supports_locking(&self) -> bool {
+        true
+    }
+
+    fn supports_ilike(&self) -> bool {
+        true
+    }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+}
 
 #[derive(Debug)]
 struct WrapValue(Value);
@@ -195,7 +232,7 @@ impl<'a> driver::Transaction<'a> for WrapTransaction<'a> {
         })
     }
 
-    async fn query(&mut self, query: &str, values: &[Value]) -> Result<Rows, Error> {
+    async fn query<'b>(&'b mut self, query: &str, values: &[Value]) -> Result<Rows<'b>, Error> {
         let statement = self.0.client().prepare(query).await?;
         let rows = self
             .0
@@ -213,7 +250,10 @@ impl driver::Connection for WrapConnection {
         QueryBuilder::new(WrapQueryBuilder::default())
     }
 
-    async fn transaction(&mut self, options: TransactionOptions) -> Result<Transaction, Error> {
+    async fn transaction<'b>(
+        &'b mut self,
+        options: TransactionOptions,
+    ) -> Result<Transaction<'b>, Error> {
         let tx_builder = self
             .0
             .build_transaction()
@@ -233,7 +273,7 @@ impl driver::Connection for WrapConnection {
         })
     }
 
-    async fn query(&mut self, query: &str, values: &[Value]) -> Result<Rows, Error> {
+    async fn query<'b>(&'b mut self, query: &str, values: &[Value]) -> Result<Rows<'b>, Error> {
         let statement = self.0.prepare(query).await?;
         let rows = self
             .0
@@ -299,4 +339,15 @@ impl driver::Database for WrapDatabase {
         }?;
         Ok(Connection::new(WrapConnection(conn)))
     }
+
+    fn pool_status(&self) -> PoolStatus {
+        let mut result = PoolStatus::default();
+        for status in [self.read_only.status(), self.writable.status()] {
+            result.max_size += status.max_size;
+            result.size += status.size;
+            result.available += status.available;
+            result.waiting += status.waiting;
+        }
+        result
+    }
 }
@@ -1,6 +1,6 @@
 use solve_db::{
-    driver, ColumnIndex, Connection, ConnectionOptions, FromValue, IntoValue, QueryBuilder,
-    RawQuery, Row, Rows, Status, Transaction, TransactionOptions, Value,
+    driver, ColumnIndex, Connection, ConnectionOptions, Dialect, FromValue, IntoValue, PoolStatus,
+    QueryBuilder, RawQuery, Row, Rows, Status, Transaction, TransactionOptions, Value,
 };
 
 use crate::core::Error;
@@ -100,7 +100,7 @@ impl driver::QueryBuilder for WrapQueryBuilder {
     }
 
     fn push_name(&mut self, name: &str) {
-        assert!(name.find(|c| c == '"' || c == '\\').is_none());
+        assert!(name.find(['"', '\\']).is_none());
         self.push('"');
         self.push_str(name);
         self.push('"');
@@ -114,6 +114,10 @@ impl driver::QueryBuilder for WrapQueryBuilder {
     fn build(self: Box<Self>) -> RawQuery {
         RawQuery::new(self.query, self.values)
     }
+
+    fn dialect(&self) -> Dialect {
+        Dialect::SQLite
+    }
 }
 
 pub(super) struct Manager {
@@ -167,7 +171,7 @@ impl<'a> driver::Transaction<'a> for WrapTransaction<'a> {
         })
     }
 
-    async fn query(&mut self, query: &str, values: &[Value]) -> Result<Rows, Error> {
+    async fn query<'b>(&'b mut self, query: &str, values: &[Value]) -> Result<Rows<'b>, Error> {
         let values: Vec<_> = values
             .iter()
             .cloned()
@@ -187,7 +191,10 @@ impl driver::Connection for WrapConnection {
         QueryBuilder::new(WrapQueryBuilder::default())
     }
 
-    async fn transaction(&mut self, _options: TransactionOptions) -> Result<Transaction, Error> {
+    async fn transaction<'b>(
+        &'b mut self,
+        _options: TransactionOptions,
+    ) -> Result<Transaction<'b>, Error> {
         let tx = self.0.transaction().await?;
         Ok(WrapTransaction(tx).into())
     }
@@ -205,7 +212,7 @@ impl driver::Connection for WrapConnection {
         })
     }
 
-    async fn query(&mut self, query: &str, values: &[Value]) -> Result<Rows, Error> {
+    async fn query<'b>(&'b mut self, query: &str, values: &[Value]) -> Result<Rows<'b>, Error> {
         let values: Vec<_> = values
             .iter()
             .cloned()
@@ -242,4 +249,14 @@ impl driver::Database for WrapDatabase {
         };
         Ok(WrapConnection(conn).into())
     }
+
+    fn pool_status(&self) -> PoolStatus {
+        let status = self.0.status();
+        PoolStatus {
+            max_size: status.max_size,
+            size: status.size,
+            available: status.available,
+            waiting: status.waiting,
+        }
+    }
 }
@@ -0,0 +1,165 @@
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::core::Error;
+
+/// Limits of extracted archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExtractLimits {
+    /// Maximal amount of entries in archive.
+    pub max_entries: usize,
+    /// Maximal uncompressed size of single file.
+    pub max_file_size: u64,
+    /// Maximal uncompressed size of all files.
+    pub max_total_size: u64,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_entries: 10000,
+            max_file_size: 256 * 1024 * 1024,
+            max_total_size: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Extracts zip archive into directory and returns relative paths of files.
+///
+/// Entries with absolute paths or paths leading outside of directory are
+/// rejected. Only stored and deflated entries are supported.
+pub fn extract_zip(
+    archive: &Path,
+    dir: &Path,
+    limits: &ExtractLimits,
+) -> Result<Vec<String>, Error> {
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(archive)?)?;
+    if zip.len() > limits.max_entries {
+        return Err(format!("Archive has more than {} entries", limits.max_entries).into());
+    }
+    let mut total_size = 0;
+    let mut files = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip.by_index(index)?;
+        let name = entry.name().to_owned();
+        let relative_path = match safe_path(&name) {
+            Some(v) => v,
+            None => return Err(format!("Invalid path in archive: {name:?}").into()),
+        };
+        let path = dir.join(&relative_path);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        // Declared size is not trusted, so amount of read bytes is limited.
+        let mut data = Vec::new();
+        entry
+            .take(limits.max_file_size + 1)
+            .read_to_end(&mut data)?;
+        if data.len() as u64 > limits.max_file_size {
+            return Err(format!("File {name:?} is too large").into());
+        }
+        total_size += data.len() as u64;
+        if total_size > limits.max_total_size {
+            return Err("Archive is too large".into());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, data)?;
+        files.push(relative_path.to_string_lossy().into_owned());
+    }
+    Ok(files)
+}
+
+/// Returns relative path if it does not leave directory of archive.
+fn safe_path(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains('\\') || name.contains('\0') {
+        return None;
+    }
+    let path = Path::new(name);
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(v) => result.push(v),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    match result.as_os_str().is_empty() {
+        true => None,
+        false => Some(result),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn build_zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            let options =
+                SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(content).unwrap();
+            }
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn temp_dir() -> PathBuf {
+        let path = std::env::temp_dir().join(format!("solve-test-{}", rand::random::<u64>()));
+        std::fs::create_dir(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = temp_dir();
+        let archive = dir.join("archive.zip");
+        let entries: &[(&str, &[u8])] = &[
+            ("problem.json", b"{}"),
+            ("tests/", b""),
+            ("tests/1.in", b"1 2\n"),
+            ("./tests/1.ans", b"3\n"),
+        ];
+        std::fs::write(&archive, build_zip(entries)).unwrap();
+        let output = dir.join("output");
+        let files = extract_zip(&archive, &output, &ExtractLimits::default()).unwrap();
+        assert_eq!(files, ["problem.json", "tests/1.in", "tests/1.ans"]);
+        assert_eq!(std::fs::read(output.join("tests/1.in")).unwrap(), b"1 2\n");
+        assert_eq!(std::fs::read(output.join("tests/1.ans")).unwrap(), b"3\n");
+        let limits = ExtractLimits {
+            max_total_size: 6,
+            ..Default::default()
+        };
+        assert!(extract_zip(&archive, &dir.join("small"), &limits).is_err());
+        let limits = ExtractLimits {
+            max_entries: 3,
+            ..Default::default()
+        };
+        assert!(extract_zip(&archive, &dir.join("small"), &limits).is_err());
+        let limits = ExtractLimits {
+            max_file_size: 3,
+            ..Default::default()
+        };
+        assert!(extract_zip(&archive, &dir.join("small"), &limits).is_err());
+        for name in ["../evil", "tests/../../evil", "/tmp/evil", "a\\..\\evil"] {
+            std::fs::write(&archive, build_zip(&[(name, b"evil")])).unwrap();
+            let err = extract_zip(&archive, &dir.join("evil"), &ExtractLimits::default());
+            assert!(err.is_err(), "{name}");
+        }
+        assert!(!dir.join("evil").exists());
+        std::fs::write(&archive, b"not a zip").unwrap();
+        assert!(extract_zip(&archive, &output, &ExtractLimits::default()).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -3,24 +3,32 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
 use crate::config;
 use crate::core::{blocking_await, Core, Error};
 use crate::managers::files::FileManager;
 use crate::managers::tasks::Task;
-use crate::models::{ProblemStore, SolutionStore, TaskKind, TaskStatus};
+use crate::models::{
+    CompilerStore, ProblemResourceStore, ProblemStore, SolutionStore, StoreError, TakeOptions,
+    TakeOutcome, TaskKind, TaskStatus,
+};
 
+use super::limits::KindLimits;
 use super::safeexec;
 use super::tasks::{JudgeSolutionTask, TaskProcess, UpdateProblemPackageTask};
 
 pub struct Invoker {
     core: Arc<Core>,
-    #[allow(unused)]
-    safeexec: Option<safeexec::Manager>,
+    pub(super) safeexec: Option<safeexec::Manager>,
     workers: u32,
-    temp_dir: PathBuf,
+    worker_kinds: Vec<Vec<TaskKind>>,
+    kind_limits: KindLimits,
+    pub(super) temp_dir: PathBuf,
     counter: AtomicUsize,
+    /// Serializes unpacking of compiler images.
+    pub(super) layer_lock: Mutex<()>,
 }
 
 impl Invoker {
@@ -38,8 +46,11 @@ impl Invoker {
             core,
             safeexec,
             workers: config.workers,
+            worker_kinds: config.worker_kinds.clone(),
+            kind_limits: KindLimits::new(&config.kind_limits),
             temp_dir: config.temp_dir.clone(),
             counter: AtomicUsize::default(),
+            layer_lock: Mutex::new(()),
         })
     }
 
@@ -59,21 +70,36 @@ impl Invoker {
         self.core.problem_store()
     }
 
+    pub fn problem_resource_store(&self) -> &ProblemResourceStore {
+        self.core.problem_resource_store()
+    }
+
     pub fn solution_store(&self) -> &SolutionStore {
         self.core.solution_store()
     }
 
+    pub fn compiler_store(&self) -> &CompilerStore {
+        self.core.compiler_store()
+    }
+
     pub fn file_manager(&self) -> &FileManager {
         self.core.file_manager()
     }
 
     pub async fn run(self, shutdown: CancellationToken) -> Result<(), Error> {
         let this = Arc::new(self);
+        if !this.core.db().builder().supports_locking() {
+            slog::warn!(
+                this.core.logger(),
+                "Database does not support row locking, tasks are taken optimistically"
+            );
+        }
         let mut join_set = tokio::task::JoinSet::new();
         for i in 0..this.workers {
             let this = this.clone();
             let logger = this.core.logger().new(slog::o!("worker" => i + 1));
-            join_set.spawn(this.run_worker(shutdown.clone(), logger));
+            let options = this.take_options(i as usize);
+            join_set.spawn(this.run_worker(shutdown.clone(), logger, options));
         }
         while let Some(res) = join_set.join_next().await {
             res??;
@@ -85,18 +111,25 @@ impl Invoker {
         self: Arc<Self>,
         shutdown: CancellationToken,
         logger: slog::Logger,
+        options: TakeOptions,
     ) -> Result<(), Error> {
         slog::info!(logger, "Running invoker");
         let task_manager = self.core.task_manager();
         loop {
+            let (take_options, permits) = self.kind_limits.acquire(&options);
             tokio::select! {
                 _ = shutdown.cancelled() => {
                     break;
                 }
-                task = task_manager.take_task() => {
-                    let task = match task {
-                        Ok(Some(task)) => task,
-                        Ok(None) => {
+                outcome = task_manager.claim_task(&take_options) => {
+                    let task = match outcome {
+                        Ok(TakeOutcome::Claimed(task)) => task,
+                        Ok(TakeOutcome::Contended { conflicts }) => {
+                            // Queue is not empty, so retry without delay.
+                            slog::debug!(logger, "Task was taken concurrently"; "conflicts" => conflicts);
+                            continue;
+                        }
+                        Ok(TakeOutcome::Empty) => {
                             slog::debug!(logger, "Task queue is empty");
                             let delay = Duration::from_millis((800 + rand::random::<u16>() % 400) as u64);
                             let sleep = tokio::time::timeout(delay, shutdown.cancelled());
@@ -119,8 +152,16 @@ impl Invoker {
                     let task_kind = task.get_kind().await;
                     let logger = logger
                         .new(slog::o!("task_id" => task_id, "kind" => task_kind.to_string()));
-                    if let Err(err) = self.clone().run_task(task, logger.clone()).await {
-                        slog::error!(logger, "Task failed"; "error" => err.to_string());
+                    // Permit is released when task finishes or worker panics.
+                    let _permit = permits.into_permit(task_kind);
+                    if let Err(err) = self.clone().run_task(task.clone(), logger.clone()).await {
+                        if StoreError::is_conflict(&err) {
+                            slog::warn!(logger, "Task was expired or modified concurrently");
+                        } else {
+                            slog::error!(logger, "Task failed"; "error" => err.to_string());
+                        }
+                    } else if task.is_cancelled() {
+                        slog::info!(logger, "Task was cancelled");
                     } else {
                         slog::info!(logger, "Task succeeded");
                     }
@@ -131,40 +172,30 @@ impl Invoker {
         Ok(())
     }
 
+    /// Returns options of tasks that are taken by worker.
+    fn take_options(&self, worker: usize) -> TakeOptions {
+        match self.worker_kinds.get(worker) {
+            Some(kinds) if !kinds.is_empty() => TakeOptions::default().with_kinds(kinds.clone()),
+            _ => TakeOptions::default(),
+        }
+    }
+
     async fn run_task(self: Arc<Invoker>, task: Task, logger: slog::Logger) -> Result<(), Error> {
         slog::info!(logger, "Executing task");
         let task_kind = task.get_kind().await;
         let task_impl = match self.new_task_process(task_kind).await {
             Ok(v) => v,
             Err(err) => {
+                if let Err(err) = task.set_error(&err).await {
+                    log_status_error(&logger, "Unable to save task error", &err);
+                }
                 if let Err(err) = task.set_status(TaskStatus::Failed).await {
-                    slog::error!(logger, "Unable to set failed task status"; "error" => err.to_string());
+                    log_status_error(&logger, "Unable to set failed task status", &err);
                 }
                 return Err(err);
             }
         };
-        let shutdown = CancellationToken::new();
-        let pinger_task = task.spawn_pinger(shutdown.clone(), logger.clone());
-        let result = task_impl
-            .run(task.clone(), logger.clone(), shutdown.clone())
-            .await;
-        shutdown.cancel();
-        pinger_task.await.unwrap();
-        match result {
-            Ok(()) => {
-                if let Err(err) = task.set_status(TaskStatus::Succeeded).await {
-                    slog::error!(logger, "Unable to set succeeded task status"; "error" => err.to_string());
-                    return Err(err);
-                }
-                Ok(())
-            }
-            Err(err) => {
-                if let Err(err) = task.set_status(TaskStatus::Failed).await {
-                    slog::error!(logger, "Unable to set failed task status"; "error" => err.to_string());
-                }
-                Err(err)
-            }
-        }
+        run_task_process(task, task_impl, logger).await
     }
 
     async fn new_task_process(
@@ -179,6 +210,94 @@ impl Invoker {
     }
 }
 
+/// Runs process of task and saves its outcome.
+async fn run_task_process(
+    task: Task,
+    task_impl: Box<dyn TaskProcess>,
+    logger: slog::Logger,
+) -> Result<(), Error> {
+    let start = std::time::Instant::now();
+    let shutdown = CancellationToken::new();
+    let pinger_task = task.spawn_pinger(shutdown.clone(), logger.clone());
+    let result = task_impl
+        .run(task.clone(), logger.clone(), shutdown.clone())
+        .await;
+    shutdown.cancel();
+    pinger_task.await.unwrap();
+    let queue_latency = task.get_queue_latency().await;
+    slog::info!(
+        logger,
+        "Task process finished";
+        "queue_latency" => ?queue_latency,
+        "execution_time" => ?start.elapsed(),
+    );
+    // Status of cancelled task should not be overwritten.
+    if task.is_cancelled() {
+        return Ok(());
+    }
+    match result {
+        Ok(()) => {
+            if let Err(err) = task.set_status(TaskStatus::Succeeded).await {
+                if task.is_cancelled() {
+                    return Ok(());
+                }
+                log_status_error(&logger, "Unable to set succeeded task status", &err);
+                return Err(err);
+            }
+            Ok(())
+        }
+        Err(err) => {
+            // Error is saved on every attempt to keep history visible.
+            if let Err(err) = task.set_error(&err).await {
+                if task.is_cancelled() {
+                    return Ok(());
+                }
+                log_status_error(&logger, "Unable to save task error", &err);
+            }
+            if task.can_retry().await {
+                let backoff = retry_backoff(task.get_attempts().await);
+                match task.retry_later(backoff).await {
+                    Ok(()) => {
+                        slog::warn!(logger, "Task will be retried"; "backoff" => ?backoff)
+                    }
+                    Err(err) => log_status_error(&logger, "Unable to requeue task", &err),
+                }
+                return Err(err);
+            }
+            if let Err(err) = task.set_status(TaskStatus::Failed).await {
+                if task.is_cancelled() {
+                    return Ok(());
+                }
+                log_status_error(&logger, "Unable to set failed task status", &err);
+            }
+            Err(err)
+        }
+    }
+}
+
+/// Returns delay before next attempt of failed task.
+///
+/// Delay grows exponentially with random jitter up to a quarter of it.
+fn retry_backoff(attempts: i32) -> Duration {
+    const BASE_DELAY: Duration = Duration::from_secs(10);
+    const MAX_DELAY: Duration = Duration::from_secs(3600);
+    let delay = BASE_DELAY
+        .saturating_mul(1 << attempts.clamp(0, 16))
+        .min(MAX_DELAY);
+    delay + delay.mul_f64(rand::random::<f64>() / 4.0)
+}
+
+/// Logs failed update of task status.
+///
+/// Conflicts are expected when task expires and is taken by another worker.
+fn log_status_error(logger: &slog::Logger, message: &str, err: &Error) {
+    if StoreError::is_conflict(err) {
+        slog::warn!(logger, "{}", message; "error" => err.to_string());
+    } else {
+        slog::error!(logger, "{}", message; "error" => err.to_string());
+    }
+}
+
 pub struct TempDir(PathBuf);
 
 impl TempDir {
@@ -196,3 +315,143 @@ impl Drop for TempDir {
         drop(blocking_await(tokio::fs::remove_dir_all(&self.0)));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use tokio_util::sync::CancellationToken;
+
+    use crate::config::{DatabaseConfig, SQLiteConfig};
+    use crate::core::Error;
+    use crate::db::new_database;
+    use crate::invoker::tasks::{Task, TaskProcess};
+    use crate::managers::tasks::{TaskManager, DEFAULT_MAX_ATTEMPTS};
+    use crate::models::{
+        Context, Event, ObjectStore, TakeOptions, Task as TaskModel, TaskError, TaskStatus,
+        TaskStore,
+    };
+
+    use super::{retry_backoff, run_task_process};
+
+    struct FailingTask;
+
+    #[async_trait::async_trait]
+    impl TaskProcess for FailingTask {
+        async fn run(
+            self: Box<Self>,
+            _task: Task,
+            _logger: slog::Logger,
+            _shutdown: CancellationToken,
+        ) -> Result<(), Error> {
+            Err("Test failure".into())
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_task_process_saves_error() {
+        let path =
+            std::env::temp_dir().join(format!("solve-test-{}.sqlite", rand::random::<u64>()));
+        let config = DatabaseConfig::SQLite(SQLiteConfig {
+            path: path.to_str().unwrap().to_owned(),
+        });
+        let store = Arc::new(TaskStore::new(Arc::new(new_database(&config).unwrap())));
+        store.create_tables().await.unwrap();
+        let manager = TaskManager::new(store.clone());
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        let task = TaskModel {
+            state: serde_json::json!({"test": 3}).into(),
+            max_attempts: 2,
+            ..Default::default()
+        };
+        let id = store
+            .create(Context::new(), task)
+            .await
+            .unwrap()
+            .object()
+            .id;
+        // Error of retried attempt is saved with state of task.
+        let task = manager
+            .take_task(&TakeOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let err = run_task_process(task, Box::new(FailingTask), logger.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Test failure");
+        let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(stored.status, TaskStatus::Queued);
+        assert_eq!(stored.state["test"], 3);
+        let error: TaskError = serde_json::from_value(stored.state["error"].clone()).unwrap();
+        assert_eq!(error.message, "Test failure");
+        assert_eq!(error.kind, "internal");
+        // Error of last attempt is saved before task fails.
+        store
+            .update(
+                Context::new(),
+                TaskModel {
+                    not_before: None,
+                    ..stored
+                },
+            )
+            .await
+            .unwrap();
+        let task = manager
+            .take_task(&TakeOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        run_task_process(task, Box::new(FailingTask), logger)
+            .await
+            .unwrap_err();
+        let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(stored.status, TaskStatus::Failed);
+        assert_eq!(stored.state["test"], 3);
+        assert_eq!(stored.state["error"]["message"], "Test failure");
+        drop(store);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn run_task_process_retries_enqueued_task() {
+        let path =
+            std::env::temp_dir().join(format!("solve-test-{}.sqlite", rand::random::<u64>()));
+        let config = DatabaseConfig::SQLite(SQLiteConfig {
+            path: path.to_str().unwrap().to_owned(),
+        });
+        let store = Arc::new(TaskStore::new(Arc::new(new_database(&config).unwrap())));
+        store.create_tables().await.unwrap();
+        let manager = TaskManager::new(store.clone());
+        let logger = slog::Logger::root(slog::Discard, slog::o!());
+        // Task enqueued by manager gets default amount of attempts.
+        let id = manager
+            .enqueue_judge_solution(Context::new(), Default::default())
+            .await
+            .unwrap();
+        let task = manager
+            .take_task(&TakeOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        run_task_process(task, Box::new(FailingTask), logger)
+            .await
+            .unwrap_err();
+        let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(stored.status, TaskStatus::Queued);
+        assert_eq!(stored.attempts, 1);
+        assert_eq!(stored.max_attempts, DEFAULT_MAX_ATTEMPTS);
+        drop(store);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn retry_backoff_grows() {
+        for (attempts, delay) in [(0, 10), (1, 20), (3, 80), (10, 3600), (100, 3600)] {
+            let delay = Duration::from_secs(delay);
+            let backoff = retry_backoff(attempts);
+            assert!(backoff >= delay && backoff <= delay + delay / 4);
+        }
+    }
+}
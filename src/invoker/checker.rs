@@ -0,0 +1,228 @@
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::time::Duration;
+
+use solve_db_types::Score;
+use tokio::task::block_in_place;
+
+use crate::core::Error;
+use crate::models::{Compiler, Verdict};
+
+use super::compiler::{read_log, BINARY_NAME};
+use super::safeexec::ProcessConfig;
+use super::Invoker;
+
+const CHECK_TIME_LIMIT: Duration = Duration::from_secs(10);
+const CHECK_REAL_TIME_LIMIT: Duration = Duration::from_secs(20);
+const CHECK_MEMORY_LIMIT: u64 = 256 * 1024 * 1024;
+/// Maximal size of checker log that is kept.
+const CHECK_LOG_LIMIT: u64 = 16 * 1024;
+
+/// Directory inside of sandbox where checker is run.
+const CHECK_DIR: &str = "sandbox";
+const CHECK_LOG_NAME: &str = "check.log";
+/// File where checker can write points of partially accepted test.
+const CHECK_POINTS_NAME: &str = "points";
+
+/// Result of check of test.
+#[derive(Clone, Debug)]
+pub struct CheckReport {
+    pub verdict: Verdict,
+    pub points: Option<Score>,
+    /// Stderr of checker or reason of failure of checker.
+    pub log: String,
+}
+
+impl Invoker {
+    /// Runs checker as `checker input output answer`.
+    ///
+    /// Checker is run with command of specified compiler. Crashes of checker
+    /// and exceeded limits result in [`Verdict::Failed`].
+    pub async fn run_checker(
+        &self,
+        compiler: &Compiler,
+        binary: &Path,
+        input: &Path,
+        output: &Path,
+        answer: &Path,
+    ) -> Result<CheckReport, Error> {
+        let config = compiler.parse_config()?;
+        let safeexec = self.safeexec.as_ref().ok_or("Safeexec is not configured")?;
+        let image = self.compiler_layer(compiler).await?;
+        let temp_dir = self.create_temp_dir()?;
+        let layer = temp_dir.join("layer");
+        tokio::fs::create_dir_all(layer.join(CHECK_DIR)).await?;
+        for (source, name) in [
+            (binary, BINARY_NAME),
+            (input, "input"),
+            (output, "output"),
+            (answer, "answer"),
+        ] {
+            tokio::fs::copy(source, layer.join(CHECK_DIR).join(name)).await?;
+        }
+        let process_config = ProcessConfig {
+            command: vec![
+                "/bin/sh".into(),
+                "-c".into(),
+                format!(
+                    "exec {} input output answer 2>{CHECK_LOG_NAME}",
+                    config.run_command
+                ),
+            ],
+            environ: config.environ.clone(),
+            layers: vec![layer, image],
+            work_dir: Path::new("/").join(CHECK_DIR),
+            time_limit: CHECK_TIME_LIMIT,
+            real_time_limit: CHECK_REAL_TIME_LIMIT,
+            memory_limit: CHECK_MEMORY_LIMIT,
+        };
+        let mut process = block_in_place(|| safeexec.create_process(process_config))?;
+        process.start().await?;
+        let report = process.wait().await?;
+        let output_dir = process.upper_dir().join(CHECK_DIR);
+        let log = block_in_place(|| read_log(&output_dir.join(CHECK_LOG_NAME), CHECK_LOG_LIMIT))?;
+        if report.time > CHECK_TIME_LIMIT
+            || report.real_time > CHECK_REAL_TIME_LIMIT
+            || report.memory > CHECK_MEMORY_LIMIT
+        {
+            return Ok(CheckReport {
+                verdict: Verdict::Failed,
+                points: None,
+                log: format!("Checker exceeded limits\n{log}"),
+            });
+        }
+        let points =
+            block_in_place(|| read_log(&output_dir.join(CHECK_POINTS_NAME), CHECK_LOG_LIMIT))?;
+        Ok(check_report(report.exit_code, &points, log))
+    }
+}
+
+/// Maps exit code of testlib-style checker to verdict.
+///
+/// Points of partially accepted test are read from `points` file or from
+/// first token of stderr.
+fn check_report(exit_code: i32, points: &str, log: String) -> CheckReport {
+    let verdict = match exit_code {
+        0 => Verdict::Accepted,
+        1 => Verdict::WrongAnswer,
+        2 => Verdict::PresentationError,
+        3 => Verdict::Failed,
+        7 => Verdict::PartiallyAccepted,
+        _ => {
+            return CheckReport {
+                verdict: Verdict::Failed,
+                points: None,
+                log: format!("Checker exited with code {exit_code}\n{log}"),
+            }
+        }
+    };
+    if verdict != Verdict::PartiallyAccepted {
+        return CheckReport {
+            verdict,
+            points: None,
+            log,
+        };
+    }
+    let points = match points.split_whitespace().next() {
+        Some(v) => v,
+        None => log.split_whitespace().next().unwrap_or_default(),
+    };
+    match points.parse::<f64>() {
+        Ok(v) if v.is_finite() => CheckReport {
+            verdict,
+            points: Some(Score::from_f64(v)),
+            log,
+        },
+        _ => CheckReport {
+            verdict: Verdict::Failed,
+            points: None,
+            log: format!("Checker reported invalid points: {points:?}\n{log}"),
+        },
+    }
+}
+
+/// Compares output with answer token by token.
+///
+/// Tokens are separated by any ASCII whitespace, so differences in spaces
+/// and line endings are ignored.
+pub fn compare_tokens(output: &Path, answer: &Path) -> Result<bool, Error> {
+    let mut output = BufReader::new(std::fs::File::open(output)?);
+    let mut answer = BufReader::new(std::fs::File::open(answer)?);
+    let mut output_token = Vec::new();
+    let mut answer_token = Vec::new();
+    loop {
+        read_token(&mut output, &mut output_token)?;
+        read_token(&mut answer, &mut answer_token)?;
+        if output_token != answer_token {
+            return Ok(false);
+        }
+        if output_token.is_empty() {
+            return Ok(true);
+        }
+    }
+}
+
+/// Reads next token into buffer, empty buffer means end of input.
+fn read_token(reader: &mut impl BufRead, token: &mut Vec<u8>) -> Result<(), Error> {
+    token.clear();
+    for byte in reader.bytes() {
+        let byte = byte?;
+        if !byte.is_ascii_whitespace() {
+            token.push(byte);
+        } else if !token.is_empty() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_tokens() {
+        let dir = std::env::temp_dir().join(format!("solve-test-{}", rand::random::<u64>()));
+        std::fs::create_dir(&dir).unwrap();
+        let compare = |output: &str, answer: &str| {
+            std::fs::write(dir.join("output"), output).unwrap();
+            std::fs::write(dir.join("answer"), answer).unwrap();
+            compare_tokens(&dir.join("output"), &dir.join("answer")).unwrap()
+        };
+        assert!(compare("1 2\n3\n", "1 2\n3\n"));
+        assert!(compare("1   2\r\n3", "1\n2\n3\n"));
+        assert!(compare("", "\n"));
+        assert!(!compare("1 2", "1 2 3"));
+        assert!(!compare("1 2 3", "1 2"));
+        assert!(!compare("12", "1 2"));
+        assert!(!compare("1 2\n", "1 3\n"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_report() {
+        let check = |exit_code, points: &str, log: &str| {
+            let report = check_report(exit_code, points, log.into());
+            (report.verdict, report.points)
+        };
+        assert_eq!(check(0, "", "ok"), (Verdict::Accepted, None));
+        assert_eq!(check(1, "", "wrong"), (Verdict::WrongAnswer, None));
+        assert_eq!(check(2, "", ""), (Verdict::PresentationError, None));
+        assert_eq!(check(3, "", ""), (Verdict::Failed, None));
+        assert_eq!(
+            check(7, "0.5\n", ""),
+            (Verdict::PartiallyAccepted, Some(Score::from_hundredths(50)))
+        );
+        assert_eq!(
+            check(7, "", "2.25 points"),
+            (
+                Verdict::PartiallyAccepted,
+                Some(Score::from_hundredths(225))
+            )
+        );
+        assert_eq!(check(7, "", "no points"), (Verdict::Failed, None));
+        assert_eq!(check(9, "", ""), (Verdict::Failed, None));
+        let report = check_report(139, "", "crash".into());
+        assert_eq!(report.log, "Checker exited with code 139\ncrash");
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use tokio::task::block_in_place;
+
+use crate::core::Error;
+use crate::models::Compiler;
+
+use super::safeexec::ProcessConfig;
+use super::Invoker;
+
+const COMPILE_TIME_LIMIT: Duration = Duration::from_secs(20);
+const COMPILE_REAL_TIME_LIMIT: Duration = Duration::from_secs(30);
+const COMPILE_MEMORY_LIMIT: u64 = 1024 * 1024 * 1024;
+/// Maximal size of compilation log that is kept.
+const COMPILE_LOG_LIMIT: u64 = 64 * 1024;
+
+/// Directory inside of sandbox where compilation is run.
+const COMPILE_DIR: &str = "sandbox";
+const COMPILE_LOG_NAME: &str = "compile.log";
+pub(super) const BINARY_NAME: &str = "solution";
+
+/// Result of compilation.
+#[derive(Clone, Debug)]
+pub struct CompileReport {
+    pub exit_code: i32,
+    /// Combined stdout and stderr of compiler.
+    pub log: String,
+}
+
+impl CompileReport {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
+}
+
+impl Invoker {
+    /// Compiles source into binary using specified compiler.
+    ///
+    /// Command of compiler can refer to source and binary as `{source}` and
+    /// `{binary}`. Compiler without command just copies source into binary.
+    /// Binary is written only if compilation succeeds.
+    pub async fn compile(
+        &self,
+        compiler: &Compiler,
+        source: &Path,
+        binary: &Path,
+    ) -> Result<CompileReport, Error> {
+        let config = compiler.parse_config()?;
+        let Some(command) = &config.compile_command else {
+            tokio::fs::copy(source, binary).await?;
+            return Ok(CompileReport {
+                exit_code: 0,
+                log: String::new(),
+            });
+        };
+        let safeexec = self.safeexec.as_ref().ok_or("Safeexec is not configured")?;
+        let image = self.compiler_layer(compiler).await?;
+        let source_name = match config.extensions.first() {
+            Some(v) => format!("{BINARY_NAME}.{v}"),
+            None => format!("{BINARY_NAME}.src"),
+        };
+        let temp_dir = self.create_temp_dir()?;
+        let layer = temp_dir.join("layer");
+        tokio::fs::create_dir_all(layer.join(COMPILE_DIR)).await?;
+        tokio::fs::copy(source, layer.join(COMPILE_DIR).join(&source_name)).await?;
+        let command = command
+            .replace("{source}", &source_name)
+            .replace("{binary}", BINARY_NAME);
+        let process_config = ProcessConfig {
+            command: vec![
+                "/bin/sh".into(),
+                "-c".into(),
+                format!("{command} >{COMPILE_LOG_NAME} 2>&1"),
+            ],
+            environ: config.environ.clone(),
+            layers: vec![layer, image],
+            work_dir: Path::new("/").join(COMPILE_DIR),
+            time_limit: COMPILE_TIME_LIMIT,
+            real_time_limit: COMPILE_REAL_TIME_LIMIT,
+            memory_limit: COMPILE_MEMORY_LIMIT,
+        };
+        let mut process = block_in_place(|| safeexec.create_process(process_config))?;
+        process.start().await?;
+        let report = process.wait().await?;
+        let output_dir = process.upper_dir().join(COMPILE_DIR);
+        let mut log =
+            block_in_place(|| read_log(&output_dir.join(COMPILE_LOG_NAME), COMPILE_LOG_LIMIT))?;
+        let mut exit_code = report.exit_code;
+        if report.time > COMPILE_TIME_LIMIT || report.real_time > COMPILE_REAL_TIME_LIMIT {
+            log.push_str("\nCompilation time limit exceeded");
+            if exit_code == 0 {
+                exit_code = -1;
+            }
+        }
+        if exit_code == 0 {
+            let output = output_dir.join(BINARY_NAME);
+            if tokio::fs::metadata(&output).await.is_err() {
+                return Err("Compiler did not produce binary".into());
+            }
+            tokio::fs::copy(output, binary).await?;
+        }
+        Ok(CompileReport { exit_code, log })
+    }
+
+    /// Returns layer with unpacked image of compiler.
+    ///
+    /// Images are unpacked once and reused by following compilations.
+    pub(super) async fn compiler_layer(&self, compiler: &Compiler) -> Result<PathBuf, Error> {
+        let file_id = compiler
+            .image_file_id
+            .ok_or(format!("Compiler {:?} has no image", compiler.name))?;
+        let safeexec = self.safeexec.as_ref().ok_or("Safeexec is not configured")?;
+        let path = self.temp_dir.join("layers").join(format!("file-{file_id}"));
+        let _guard = self.layer_lock.lock().await;
+        if tokio::fs::metadata(&path).await.is_ok() {
+            return Ok(path);
+        }
+        let file = self.file_manager().load(file_id).await?;
+        let temp_dir = self.create_temp_dir()?;
+        let layer = temp_dir.join("layer");
+        block_in_place(|| safeexec.unpack_layer(file.path(), &layer))?;
+        tokio::fs::create_dir_all(self.temp_dir.join("layers")).await?;
+        tokio::fs::rename(layer, &path).await?;
+        Ok(path)
+    }
+}
+
+/// Reads beginning of log that fits into limit.
+pub(super) fn read_log(path: &Path, limit: u64) -> Result<String, Error> {
+    let file = match std::fs::File::open(path) {
+        Ok(v) => v,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut data = Vec::new();
+    file.take(limit).read_to_end(&mut data)?;
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
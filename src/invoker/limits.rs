@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::models::{TakeOptions, TaskKind};
+
+/// Kinds of tasks that can be run by invoker.
+const KNOWN_KINDS: [TaskKind; 2] = [TaskKind::JudgeSolution, TaskKind::UpdateProblemPackage];
+
+/// Limits of amount of concurrently running tasks of specific kinds.
+#[derive(Clone, Default)]
+pub struct KindLimits {
+    semaphores: HashMap<TaskKind, Arc<Semaphore>>,
+}
+
+impl KindLimits {
+    pub fn new(limits: &HashMap<TaskKind, usize>) -> Self {
+        let semaphores = limits
+            .iter()
+            .map(|(kind, limit)| (*kind, Arc::new(Semaphore::new(*limit))))
+            .collect();
+        Self { semaphores }
+    }
+
+    /// Restricts options to kinds of tasks that have available permits.
+    ///
+    /// Returned permits should be held until task is taken.
+    pub fn acquire(&self, options: &TakeOptions) -> (TakeOptions, KindPermits) {
+        let mut permits = Vec::new();
+        let mut unavailable = Vec::new();
+        for (kind, semaphore) in &self.semaphores {
+            if options.kinds.as_ref().is_some_and(|v| !v.contains(kind)) {
+                continue;
+            }
+            match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permits.push((*kind, permit)),
+                Err(_) => unavailable.push(*kind),
+            }
+        }
+        let mut options = options.clone();
+        if !unavailable.is_empty() {
+            let kinds = options.kinds.unwrap_or_else(|| KNOWN_KINDS.to_vec());
+            options.kinds = Some(
+                kinds
+                    .into_iter()
+                    .filter(|v| !unavailable.contains(v))
+                    .collect(),
+            );
+        }
+        (options, KindPermits { permits })
+    }
+}
+
+/// Permits acquired for limited kinds of tasks.
+pub struct KindPermits {
+    permits: Vec<(TaskKind, OwnedSemaphorePermit)>,
+}
+
+impl KindPermits {
+    /// Returns permit for kind of taken task releasing other permits.
+    ///
+    /// Returns none if kind is not limited.
+    pub fn into_permit(self, kind: TaskKind) -> Option<OwnedSemaphorePermit> {
+        self.permits
+            .into_iter()
+            .find(|(v, _)| *v == kind)
+            .map(|(_, permit)| permit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::models::{TakeOptions, TaskKind};
+
+    use super::KindLimits;
+
+    #[derive(Default)]
+    struct Counter {
+        active: AtomicUsize,
+        max_active: AtomicUsize,
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn kind_limits_restrict_concurrency() {
+        let limits = KindLimits::new(&HashMap::from([(TaskKind::UpdateProblemPackage, 1)]));
+        let mut queue = Vec::new();
+        for _ in 0..4 {
+            queue.push(TaskKind::UpdateProblemPackage);
+            queue.push(TaskKind::JudgeSolution);
+            queue.push(TaskKind::JudgeSolution);
+        }
+        let queue = Arc::new(Mutex::new(queue));
+        let counters: Arc<HashMap<TaskKind, Counter>> = Arc::new(HashMap::from([
+            (TaskKind::JudgeSolution, Counter::default()),
+            (TaskKind::UpdateProblemPackage, Counter::default()),
+        ]));
+        let mut workers = Vec::new();
+        for _ in 0..4 {
+            let (limits, queue, counters) = (limits.clone(), queue.clone(), counters.clone());
+            workers.push(tokio::spawn(async move {
+                loop {
+                    let (options, permits) = limits.acquire(&TakeOptions::default());
+                    let kind = {
+                        let mut queue = queue.lock().unwrap();
+                        if queue.is_empty() {
+                            return;
+                        }
+                        let index = queue.iter().position(|v| match &options.kinds {
+                            Some(kinds) => kinds.contains(v),
+                            None => true,
+                        });
+                        index.map(|v| queue.remove(v))
+                    };
+                    let Some(kind) = kind else {
+                        drop(permits);
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        continue;
+                    };
+                    let _permit = permits.into_permit(kind);
+                    let counter = &counters[&kind];
+                    let active = counter.active.fetch_add(1, Ordering::SeqCst) + 1;
+                    counter.max_active.fetch_max(active, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    counter.active.fetch_sub(1, Ordering::SeqCst);
+                }
+            }));
+        }
+        for worker in workers {
+            worker.await.unwrap();
+        }
+        assert!(queue.lock().unwrap().is_empty());
+        let max_active = |kind| counters[&kind].max_active.load(Ordering::SeqCst);
+        assert_eq!(max_active(TaskKind::UpdateProblemPackage), 1);
+        assert!(max_active(TaskKind::JudgeSolution) > 1);
+    }
+
+    #[test]
+    fn kind_limits_keep_worker_kinds() {
+        let limits = KindLimits::new(&HashMap::from([(TaskKind::UpdateProblemPackage, 1)]));
+        let (options, _permits) = limits.acquire(&TakeOptions::default());
+        assert!(options.kinds.is_none());
+        let (options, _) = limits.acquire(&TakeOptions::default());
+        assert_eq!(options.kinds, Some(vec![TaskKind::JudgeSolution]));
+        let judge = TakeOptions::default().with_kinds(vec![TaskKind::JudgeSolution]);
+        let (options, permits) = limits.acquire(&judge);
+        assert_eq!(options.kinds, Some(vec![TaskKind::JudgeSolution]));
+        assert!(permits.into_permit(TaskKind::JudgeSolution).is_none());
+    }
+}
@@ -1,6 +1,14 @@
 pub mod safeexec;
 pub mod tasks;
 
+mod archive;
 mod base;
+mod checker;
+mod compiler;
+mod limits;
 
+pub use archive::*;
 pub use base::*;
+pub use checker::*;
+pub use compiler::*;
+pub use limits::*;
@@ -5,19 +5,23 @@ use std::sync::atomic::{AtomicI64, Ordering};
 use std::time::Duration;
 
 use path_clean::PathClean;
-use sbox::{BaseMounts, BinNewIdMapper, Cgroup, Container, Gid, OverlayMount, Uid};
+use sbox::{run_as_root, BaseMounts, BinNewIdMapper, Cgroup, Container, Gid, OverlayMount, Uid};
+use serde::{Deserialize, Serialize};
+use tar::Archive;
 
 use crate::core::Error;
 
 use super::Process;
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ProcessConfig {
     pub command: Vec<String>,
     pub environ: Vec<String>,
     pub layers: Vec<PathBuf>,
     pub work_dir: PathBuf,
+    #[serde(with = "solve_db_types::duration_ms")]
     pub time_limit: Duration,
+    #[serde(with = "solve_db_types::duration_ms")]
     pub real_time_limit: Duration,
     pub memory_limit: u64,
 }
@@ -101,6 +105,19 @@ impl Manager {
         })
     }
 
+    /// Unpacks tar archive into directory that can be used as layer.
+    ///
+    /// Owners of files are preserved, so archive is unpacked as root of
+    /// user namespace.
+    pub fn unpack_layer(&self, archive: &Path, path: &Path) -> Result<(), Error> {
+        let mut archive = Archive::new(File::open(archive)?);
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_ownerships(true);
+        archive.set_unpack_xattrs(true);
+        let path = path.to_owned();
+        run_as_root(&self.user_mapper, move || Ok(archive.unpack(path)?))
+    }
+
     fn setup_cgroup(cgroup_path: &Path) -> Result<(), Error> {
         if let Err(err) = std::fs::create_dir(cgroup_path) {
             if err.kind() != std::io::ErrorKind::AlreadyExists {
@@ -113,9 +130,7 @@ impl Manager {
             .open(cgroup_path.join("cgroup.subtree_control"))?;
         for line in content.split(|c| *c == b'\n').filter(|v| !v.is_empty()) {
             let line = std::str::from_utf8(line)?;
-            let data = line
-                .split(|c| c == ' ')
-                .fold("".to_owned(), |acc, v| acc + " +" + v);
+            let data = line.split(' ').fold("".to_owned(), |acc, v| acc + " +" + v);
             subtree_file.write_all(data.as_bytes())?;
         }
         Ok(())
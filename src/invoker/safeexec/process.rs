@@ -7,6 +7,7 @@ use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::Uid;
 use sbox::{run_as_root, BinNewIdMapper, Cgroup, Gid, InitProcess};
+use serde::{Deserialize, Serialize};
 use tokio::task::{spawn_blocking, JoinHandle};
 use tokio_util::sync::CancellationToken;
 
@@ -14,10 +15,13 @@ use crate::core::{blocking_await, Error};
 
 use super::ProcessConfig;
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Report {
     pub exit_code: i32,
     pub memory: u64,
+    #[serde(with = "solve_db_types::duration_ms")]
     pub time: Duration,
+    #[serde(with = "solve_db_types::duration_ms")]
     pub real_time: Duration,
 }
 
@@ -50,6 +54,11 @@ impl Process {
         Ok(())
     }
 
+    /// Returns directory with files that were written by process.
+    pub fn upper_dir(&self) -> PathBuf {
+        self.state_path.join("upper")
+    }
+
     pub async fn wait(&mut self) -> Result<Report, Error> {
         match self.join_handle.take() {
             Some(v) => v.await?,
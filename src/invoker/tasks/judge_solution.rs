@@ -1,19 +1,45 @@
+use std::collections::HashMap;
 use std::io::Write as _;
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use slog::Logger;
+use solve_db_types::Score;
 use tokio::task::block_in_place;
 use tokio_util::sync::CancellationToken;
 
 use crate::core::Error;
-use crate::invoker::{Invoker, TempDir};
-use crate::models::{Context, JudgeSolutionTaskConfig, ObjectStore, Problem, Solution};
+use crate::invoker::safeexec::ProcessConfig;
+use crate::invoker::{compare_tokens, CompileReport, Invoker, TempDir};
+use crate::managers::files::File;
+use crate::models::{
+    Compiler, Context, JudgeReport, JudgeSolutionTaskConfig, JudgeSolutionTaskState, ObjectStore,
+    Problem, ProblemPackage, Solution, TestReport, UsageReport, Verdict,
+};
 
 use super::{Task, TaskProcess};
 
 pub struct JudgeSolutionTask {
     invoker: Arc<Invoker>,
     temp_dir: Option<TempDir>,
+    compiler: Option<Compiler>,
+    package: Option<ProblemPackage>,
+    tests: Vec<PreparedTest>,
+    checker: Option<PreparedChecker>,
+}
+
+/// Test of problem with loaded files.
+struct PreparedTest {
+    input: File,
+    answer: File,
+    input_file_id: i64,
+}
+
+/// Compiled checker of problem.
+struct PreparedChecker {
+    compiler: Compiler,
+    binary: File,
 }
 
 impl JudgeSolutionTask {
@@ -21,6 +47,10 @@ impl JudgeSolutionTask {
         Self {
             invoker,
             temp_dir: None,
+            compiler: None,
+            package: None,
+            tests: Vec::new(),
+            checker: None,
         }
     }
 }
@@ -28,6 +58,12 @@ impl JudgeSolutionTask {
 const SOLUTION_SOURCE_PATH: &str = "solution.src";
 const SOLUTION_BINARY_PATH: &str = "solution.bin";
 
+/// Directory inside of sandbox where solution is run.
+const RUN_DIR: &str = "sandbox";
+const RUN_BINARY_NAME: &str = "solution";
+const RUN_INPUT_NAME: &str = "input";
+const RUN_OUTPUT_NAME: &str = "output";
+
 impl JudgeSolutionTask {
     async fn prepare_temp_dir(&mut self) -> Result<(), Error> {
         self.temp_dir = Some(self.invoker.create_temp_dir()?);
@@ -38,7 +74,7 @@ impl JudgeSolutionTask {
         &mut self,
         solution: &Solution,
         logger: &Logger,
-    ) -> Result<(), Error> {
+    ) -> Result<CompileReport, Error> {
         let source_path = self.temp_dir.as_ref().unwrap().join(SOLUTION_SOURCE_PATH);
         slog::debug!(
             logger,
@@ -53,17 +89,234 @@ impl JudgeSolutionTask {
             block_in_place(|| file.write_all(content.as_bytes()))?;
             block_in_place(|| file.sync_all())?;
         }
+        let compiler = self
+            .invoker
+            .compiler_store()
+            .get(Context::new(), solution.compiler_id)
+            .await?
+            .ok_or(format!("Cannot find compiler: {}", solution.compiler_id))?;
         let binary_path = self.temp_dir.as_ref().unwrap().join(SOLUTION_BINARY_PATH);
         slog::debug!(
             logger,
             "Compile solution";
-            "binary_path" => binary_path.display()
+            "binary_path" => binary_path.display(),
+            "compiler" => &compiler.name,
         );
-        todo!()
+        let report = self
+            .invoker
+            .compile(&compiler, &source_path, &binary_path)
+            .await?;
+        self.compiler = Some(compiler);
+        Ok(report)
+    }
+
+    async fn prepare_problem(&mut self, problem: &Problem, logger: &Logger) -> Result<(), Error> {
+        let package = problem
+            .parse_config()?
+            .package
+            .ok_or(format!("Problem {} has no package", problem.id))?;
+        slog::debug!(
+            logger,
+            "Prepare problem";
+            "revision" => package.revision,
+            "tests" => package.tests.len(),
+        );
+        let resources: HashMap<_, _> = self
+            .invoker
+            .problem_resource_store()
+            .find_by_problem(Context::new(), problem.id)
+            .await?
+            .into_iter()
+            .map(|v| (v.name, v.file_id))
+            .collect();
+        let file_id = |name: &str| -> Result<i64, Error> {
+            match resources.get(name) {
+                Some(v) => Ok(*v),
+                None => Err(format!("Problem {} has no resource {name:?}", problem.id).into()),
+            }
+        };
+        let mut ids = Vec::new();
+        for test in &package.tests {
+            ids.push((file_id(&test.input)?, file_id(&test.answer)?));
+        }
+        let file_manager = self.invoker.file_manager();
+        let _prefetch = file_manager
+            .prefetch(&ids.iter().flat_map(|v| [v.0, v.1]).collect::<Vec<_>>())
+            .await?;
+        for (input_file_id, answer_file_id) in ids {
+            self.tests.push(PreparedTest {
+                input: file_manager.load(input_file_id).await?,
+                answer: file_manager.load(answer_file_id).await?,
+                input_file_id,
+            });
+        }
+        if package.checker.is_some() {
+            let compiled = package
+                .compiled_checker
+                .as_ref()
+                .ok_or(format!("Checker of problem {} is not compiled", problem.id))?;
+            let compiler = self
+                .invoker
+                .compiler_store()
+                .get(Context::new(), compiled.compiler_id)
+                .await?
+                .ok_or(format!("Cannot find compiler: {}", compiled.compiler_id))?;
+            self.checker = Some(PreparedChecker {
+                compiler,
+                binary: file_manager.load(file_id(&compiled.binary)?).await?,
+            });
+        }
+        self.package = Some(package);
+        Ok(())
     }
 
-    async fn prepare_problem(&mut self, _problem: &Problem, _logger: &Logger) -> Result<(), Error> {
-        todo!()
+    /// Runs solution on tests and returns report with verdict.
+    ///
+    /// Testing stops on first rejected test unless points are enabled.
+    async fn run_tests(
+        &self,
+        task: &Task,
+        config: &JudgeSolutionTaskConfig,
+        shutdown: &CancellationToken,
+        logger: &Logger,
+    ) -> Result<JudgeReport, Error> {
+        let compiler = self.compiler.as_ref().unwrap();
+        let package = self.package.as_ref().unwrap();
+        let temp_dir = self.temp_dir.as_ref().unwrap();
+        let image = self.invoker.compiler_layer(compiler).await?;
+        let safeexec = self
+            .invoker
+            .safeexec
+            .as_ref()
+            .ok_or("Safeexec is not configured")?;
+        let compiler_config = compiler.parse_config()?;
+        let run_command = &compiler_config.run_command;
+        let time_limit = Duration::from(package.time_limit);
+        let real_time_limit = time_limit * 2;
+        let solution_layer = temp_dir.join("solution-layer");
+        tokio::fs::create_dir_all(solution_layer.join(RUN_DIR)).await?;
+        tokio::fs::copy(
+            temp_dir.join(SOLUTION_BINARY_PATH),
+            solution_layer.join(RUN_DIR).join(RUN_BINARY_NAME),
+        )
+        .await?;
+        let total_tests = self.tests.len() as u32;
+        let mut report = JudgeReport {
+            points: config.enable_points.then_some(Score::ZERO),
+            usage: Some(UsageReport::default()),
+            ..Default::default()
+        };
+        for (i, test) in self.tests.iter().enumerate() {
+            if shutdown.is_cancelled() {
+                return Err("Task was interrupted".into());
+            }
+            task.update_state(|state: &mut JudgeSolutionTaskState| {
+                state.test = i as u32 + 1;
+                state.total_tests = Some(total_tests);
+            })
+            .await?;
+            let input_layer = temp_dir.join(format!("test-{}", i + 1));
+            tokio::fs::create_dir_all(input_layer.join(RUN_DIR)).await?;
+            tokio::fs::copy(
+                test.input.path(),
+                input_layer.join(RUN_DIR).join(RUN_INPUT_NAME),
+            )
+            .await?;
+            let process_config = ProcessConfig {
+                command: vec![
+                    "/bin/sh".into(),
+                    "-c".into(),
+                    format!("exec {run_command} <{RUN_INPUT_NAME} >{RUN_OUTPUT_NAME}"),
+                ],
+                environ: compiler_config.environ.clone(),
+                layers: vec![input_layer.clone(), solution_layer.clone(), image.clone()],
+                work_dir: Path::new("/").join(RUN_DIR),
+                time_limit,
+                real_time_limit,
+                memory_limit: package.memory_limit,
+            };
+            let mut process = block_in_place(|| safeexec.create_process(process_config))?;
+            process.start().await?;
+            let usage = process.wait().await?;
+            let (verdict, checker_points, check_log) =
+                if usage.time > time_limit || usage.real_time > real_time_limit {
+                    (Verdict::TimeLimitExceeded, None, None)
+                } else if usage.memory > package.memory_limit {
+                    (Verdict::MemoryLimitExceeded, None, None)
+                } else if usage.exit_code != 0 {
+                    (Verdict::RuntimeError, None, None)
+                } else {
+                    let output = process.upper_dir().join(RUN_DIR).join(RUN_OUTPUT_NAME);
+                    self.check_output(test, &output).await?
+                };
+            drop(process);
+            block_in_place(|| std::fs::remove_dir_all(&input_layer))?;
+            slog::debug!(
+                logger,
+                "Test completed";
+                "test" => i + 1,
+                "verdict" => verdict.to_string(),
+            );
+            // Accepted test gives one point unless checker reports points.
+            let points = config.enable_points.then(|| match verdict {
+                Verdict::Accepted => checker_points.unwrap_or(Score::from_int(1)),
+                Verdict::PartiallyAccepted => checker_points.unwrap_or(Score::ZERO),
+                _ => Score::ZERO,
+            });
+            if let (Some(total), Some(points)) = (&mut report.points, points) {
+                *total = total.saturating_add(points);
+            }
+            if let Some(total) = &mut report.usage {
+                total.time_ms = total.time_ms.max(usage.time.as_millis() as u64);
+                total.memory_bytes = total.memory_bytes.max(usage.memory);
+            }
+            report.tests.push(TestReport {
+                verdict,
+                time_ms: usage.time.as_millis() as u64,
+                memory_bytes: usage.memory,
+                points,
+                check_log,
+                input_file_id: Some(test.input_file_id),
+                ..Default::default()
+            });
+            if verdict != Verdict::Accepted && !config.enable_points {
+                break;
+            }
+        }
+        report.verdict = report
+            .aggregate_verdict()
+            .ok_or("Problem package has no tests")?;
+        Ok(report)
+    }
+
+    /// Checks output of solution with checker of problem.
+    ///
+    /// Output is compared with answer token by token if problem has no
+    /// checker.
+    async fn check_output(
+        &self,
+        test: &PreparedTest,
+        output: &Path,
+    ) -> Result<(Verdict, Option<Score>, Option<String>), Error> {
+        let Some(checker) = &self.checker else {
+            let verdict = match block_in_place(|| compare_tokens(output, test.answer.path()))? {
+                true => Verdict::Accepted,
+                false => Verdict::WrongAnswer,
+            };
+            return Ok((verdict, None, None));
+        };
+        let report = self
+            .invoker
+            .run_checker(
+                &checker.compiler,
+                checker.binary.path(),
+                test.input.path(),
+                output,
+                test.answer.path(),
+            )
+            .await?;
+        let log = (!report.log.is_empty()).then_some(report.log);
+        Ok((report.verdict, report.points, log))
     }
 }
 
@@ -73,7 +326,7 @@ impl TaskProcess for JudgeSolutionTask {
         mut self: Box<Self>,
         task: Task,
         logger: slog::Logger,
-        _shutdown: CancellationToken,
+        shutdown: CancellationToken,
     ) -> Result<(), Error> {
         let config: JudgeSolutionTaskConfig = task.parse_config().await?;
         let solution = self
@@ -89,8 +342,33 @@ impl TaskProcess for JudgeSolutionTask {
             .await?
             .ok_or(format!("Cannot find problem: {}", solution.problem_id))?;
         self.prepare_temp_dir().await?;
-        self.prepare_solution(&solution, &logger).await?;
+        let compile_report = self.prepare_solution(&solution, &logger).await?;
+        if !compile_report.success() {
+            // Compilation error is verdict of solution, not failure of task.
+            slog::info!(logger, "Solution is not compiled"; "exit_code" => compile_report.exit_code);
+            let report = JudgeReport {
+                verdict: Verdict::CompilationError,
+                compile_log: Some(compile_report.log),
+                ..Default::default()
+            };
+            self.invoker
+                .solution_store()
+                .update_report(Context::new(), solution.id, Some(&report))
+                .await?;
+            return Ok(());
+        }
+        task.update_state(|state: &mut JudgeSolutionTaskState| state.compiled = true)
+            .await?;
         self.prepare_problem(&problem, &logger).await?;
-        todo!()
+        let mut report = self.run_tests(&task, &config, &shutdown, &logger).await?;
+        if !compile_report.log.is_empty() {
+            report.compile_log = Some(compile_report.log);
+        }
+        slog::info!(logger, "Solution is judged"; "verdict" => report.verdict.to_string());
+        self.invoker
+            .solution_store()
+            .update_report(Context::new(), solution.id, Some(&report))
+            .await?;
+        Ok(())
     }
 }
@@ -1,18 +1,250 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
 
+use serde::Deserialize;
+use solve_db_types::DurationMs;
+use tokio::task::block_in_place;
 use tokio_util::sync::CancellationToken;
 
 use crate::core::Error;
-use crate::invoker::Invoker;
-use crate::models::UpdateProblemPackageTaskConfig;
+use crate::invoker::{extract_zip, ExtractLimits, Invoker};
+use crate::managers::files::{LocalFile, PendingFile};
+use crate::models::{
+    write_tx_options, CompiledChecker, Compiler, Context, ObjectStore, ProblemPackage,
+    ProblemResource, ProblemResourceKind, ProblemTest, StoreError, UpdateProblemPackageStage,
+    UpdateProblemPackageTaskConfig, UpdateProblemPackageTaskState,
+};
 
 use super::{Task, TaskProcess};
 
-pub struct UpdateProblemPackageTask {}
+/// Path of manifest inside of package.
+const MANIFEST_PATH: &str = "problem.json";
+
+/// Prefix of names of resources that are registered from package.
+const RESOURCE_PREFIX: &str = "package/";
+
+/// Manifest of problem package.
+#[derive(Clone, Debug, Deserialize)]
+struct PackageManifest {
+    time_limit: DurationMs,
+    memory_limit: u64,
+    tests: Vec<ManifestTest>,
+    #[serde(default)]
+    checker: Option<String>,
+    /// Name of compiler that is used for checker.
+    #[serde(default)]
+    checker_compiler: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ManifestTest {
+    input: String,
+    answer: String,
+}
+
+impl PackageManifest {
+    /// Checks that limits are set and all referenced files are extracted.
+    fn validate(&self, files: &HashSet<String>) -> Result<(), Error> {
+        if self.time_limit == DurationMs::ZERO {
+            return Err("Time limit should be positive".into());
+        }
+        if self.memory_limit == 0 {
+            return Err("Memory limit should be positive".into());
+        }
+        if self.tests.is_empty() {
+            return Err("Package should have at least one test".into());
+        }
+        for path in self.files().keys() {
+            if !files.contains(*path) {
+                return Err(format!("Package has no file {path:?}").into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns paths of referenced files with kinds of their resources.
+    fn files(&self) -> BTreeMap<&str, ProblemResourceKind> {
+        let mut files = BTreeMap::new();
+        for test in &self.tests {
+            files.insert(test.input.as_str(), ProblemResourceKind::Test);
+            files.insert(test.answer.as_str(), ProblemResourceKind::Test);
+        }
+        if let Some(checker) = &self.checker {
+            files.insert(checker.as_str(), ProblemResourceKind::Checker);
+        }
+        files
+    }
+}
+
+/// Name of resource with compiled checker.
+const CHECKER_BINARY_NAME: &str = "compiled/checker";
+
+fn resource_name(path: &str) -> String {
+    format!("{RESOURCE_PREFIX}{path}")
+}
+
+pub struct UpdateProblemPackageTask {
+    invoker: Arc<Invoker>,
+    limits: ExtractLimits,
+}
 
 impl UpdateProblemPackageTask {
-    pub fn new(_invoker: Arc<Invoker>) -> Self {
-        Self {}
+    pub fn new(invoker: Arc<Invoker>) -> Self {
+        Self {
+            invoker,
+            limits: Default::default(),
+        }
+    }
+
+    /// Sets limits of extracted package.
+    pub fn with_limits(mut self, limits: ExtractLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    async fn set_stage(
+        task: &Task,
+        stage: UpdateProblemPackageStage,
+        progress: f64,
+    ) -> Result<(), Error> {
+        task.update_state(|state: &mut UpdateProblemPackageTaskState| {
+            state.stage = stage;
+            state.progress = progress;
+        })
+        .await
+    }
+
+    /// Uploads referenced files of package.
+    ///
+    /// Uploaded files are deleted if they are not confirmed.
+    async fn upload_files(
+        &self,
+        task: &Task,
+        dir: &Path,
+        manifest: &PackageManifest,
+        shutdown: &CancellationToken,
+    ) -> Result<Vec<(String, ProblemResourceKind, PendingFile)>, Error> {
+        let files = manifest.files();
+        let total = files.len();
+        let mut pending = Vec::with_capacity(total);
+        for (i, (path, kind)) in files.into_iter().enumerate() {
+            if shutdown.is_cancelled() {
+                return Err("Task was interrupted".into());
+            }
+            let file = self.upload_file(&dir.join(path)).await?;
+            pending.push((resource_name(path), kind, file));
+            let progress = (i + 1) as f64 / total as f64;
+            Self::set_stage(task, UpdateProblemPackageStage::Upload, progress).await?;
+        }
+        Ok(pending)
+    }
+
+    async fn upload_file(&self, path: &Path) -> Result<PendingFile, Error> {
+        let name = path.file_name().map(|v| v.to_string_lossy().into_owned());
+        let file = LocalFile::new(path.to_owned(), name)?;
+        self.invoker
+            .file_manager()
+            .upload(Context::new(), file)
+            .await
+    }
+
+    /// Compiles checker of package with compiler declared in manifest.
+    async fn compile_checker(
+        &self,
+        dir: &Path,
+        checker: &str,
+        manifest: &PackageManifest,
+        binary: &Path,
+    ) -> Result<Compiler, Error> {
+        let name = manifest
+            .checker_compiler
+            .as_ref()
+            .ok_or("Package has no compiler of checker")?;
+        let compiler = self
+            .invoker
+            .compiler_store()
+            .get_by_name(Context::new(), name)
+            .await?
+            .ok_or(format!("Cannot find compiler: {name:?}"))?;
+        let report = self
+            .invoker
+            .compile(&compiler, &dir.join(checker), binary)
+            .await?;
+        if !report.success() {
+            return Err(format!("Cannot compile checker: {}", report.log).into());
+        }
+        Ok(compiler)
+    }
+
+    /// Replaces resources and package of problem in one transaction.
+    async fn save_package(
+        &self,
+        config: &UpdateProblemPackageTaskConfig,
+        manifest: &PackageManifest,
+        files: Vec<(String, ProblemResourceKind, PendingFile)>,
+        compiled_checker: Option<CompiledChecker>,
+    ) -> Result<i64, Error> {
+        let problems = self.invoker.problem_store();
+        let resources = self.invoker.problem_resource_store();
+        let mut tx = problems.db().transaction(write_tx_options()).await?;
+        let mut problem = problems
+            .get(Context::new().with_tx(&mut tx), config.problem_id)
+            .await?
+            .ok_or(StoreError::NotFound)?;
+        let mut problem_config = problem.parse_config()?;
+        let revision = match &problem_config.package {
+            Some(v) => v.revision + 1,
+            None => 1,
+        };
+        for resource in resources
+            .find_by_problem(Context::new().with_tx(&mut tx), problem.id)
+            .await?
+        {
+            if matches!(
+                resource.kind,
+                ProblemResourceKind::Test | ProblemResourceKind::Checker
+            ) {
+                resources
+                    .delete(Context::new().with_tx(&mut tx), resource.id)
+                    .await?;
+            }
+        }
+        for (name, kind, file) in files {
+            let file = file.confirm(Context::new().with_tx(&mut tx)).await?;
+            let resource = ProblemResource {
+                problem_id: problem.id,
+                kind,
+                name,
+                file_id: file.id,
+                ..Default::default()
+            };
+            resources
+                .create_resource(Context::new().with_tx(&mut tx), resource)
+                .await?;
+        }
+        problem_config.package = Some(ProblemPackage {
+            revision,
+            file_id: config.file_id,
+            time_limit: manifest.time_limit,
+            memory_limit: manifest.memory_limit,
+            tests: manifest
+                .tests
+                .iter()
+                .map(|v| ProblemTest {
+                    input: resource_name(&v.input),
+                    answer: resource_name(&v.answer),
+                })
+                .collect(),
+            checker: manifest.checker.as_deref().map(resource_name),
+            compiled_checker,
+        });
+        problem.set_config(&problem_config)?;
+        problems
+            .update(Context::new().with_tx(&mut tx), problem)
+            .await?;
+        tx.commit().await?;
+        Ok(revision)
     }
 }
 
@@ -21,10 +253,64 @@ impl TaskProcess for UpdateProblemPackageTask {
     async fn run(
         self: Box<Self>,
         task: Task,
-        _logger: slog::Logger,
-        _shutdown: CancellationToken,
+        logger: slog::Logger,
+        shutdown: CancellationToken,
     ) -> Result<(), Error> {
-        let _config: UpdateProblemPackageTaskConfig = task.parse_config().await?;
-        todo!()
+        let config: UpdateProblemPackageTaskConfig = task.parse_config().await?;
+        Self::set_stage(&task, UpdateProblemPackageStage::Download, 0.0).await?;
+        self.invoker
+            .problem_store()
+            .get(Context::new(), config.problem_id)
+            .await?
+            .ok_or(StoreError::NotFound)?;
+        let file = self.invoker.file_manager().load(config.file_id).await?;
+        let temp_dir = self.invoker.create_temp_dir()?;
+        let dir = temp_dir.join("package");
+        Self::set_stage(&task, UpdateProblemPackageStage::Extract, 0.0).await?;
+        let files = block_in_place(|| extract_zip(file.path(), &dir, &self.limits))?;
+        Self::set_stage(&task, UpdateProblemPackageStage::Validate, 0.0).await?;
+        let files: HashSet<_> = files.into_iter().collect();
+        if !files.contains(MANIFEST_PATH) {
+            return Err(format!("Package has no {MANIFEST_PATH}").into());
+        }
+        let manifest: PackageManifest =
+            serde_json::from_slice(&tokio::fs::read(dir.join(MANIFEST_PATH)).await?)
+                .map_err(|err| format!("Invalid {MANIFEST_PATH}: {err}"))?;
+        manifest.validate(&files)?;
+        let checker_binary = temp_dir.join("checker");
+        let checker_compiler = match &manifest.checker {
+            Some(checker) if config.compile => {
+                Self::set_stage(&task, UpdateProblemPackageStage::Compile, 0.0).await?;
+                Some(
+                    self.compile_checker(&dir, checker, &manifest, &checker_binary)
+                        .await?,
+                )
+            }
+            _ => None,
+        };
+        Self::set_stage(&task, UpdateProblemPackageStage::Upload, 0.0).await?;
+        let mut files = self.upload_files(&task, &dir, &manifest, &shutdown).await?;
+        let compiled_checker = match checker_compiler {
+            Some(compiler) => {
+                let file = self.upload_file(&checker_binary).await?;
+                files.push((
+                    CHECKER_BINARY_NAME.to_owned(),
+                    ProblemResourceKind::Checker,
+                    file,
+                ));
+                Some(CompiledChecker {
+                    compiler_id: compiler.id,
+                    binary: CHECKER_BINARY_NAME.to_owned(),
+                })
+            }
+            None => None,
+        };
+        Self::set_stage(&task, UpdateProblemPackageStage::Save, 0.0).await?;
+        let revision = self
+            .save_package(&config, &manifest, files, compiled_checker)
+            .await?;
+        Self::set_stage(&task, UpdateProblemPackageStage::Save, 1.0).await?;
+        slog::info!(logger, "Problem package updated"; "problem_id" => config.problem_id, "revision" => revision);
+        Ok(())
     }
 }
@@ -1,5 +1,6 @@
 use std::sync::Arc;
 
+use axum::http::StatusCode;
 use axum::{routing, Router};
 use clap::Parser;
 use solve::config::{parse_file, Config};
@@ -37,6 +38,15 @@ async fn ping() -> &'static str {
     "pong"
 }
 
+async fn health(core: Arc<Core>) -> (StatusCode, String) {
+    let health = core.health().await;
+    let status = match health.is_healthy() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, format!("{health:?}"))
+}
+
 async fn server_main(config: Config, _args: ServerArgs) -> Result<(), Error> {
     let shutdown = CancellationToken::new();
     let mut core = Core::new(&config)?;
@@ -46,8 +56,11 @@ async fn server_main(config: Config, _args: ServerArgs) -> Result<(), Error> {
         Some(v) => v,
         None => return Err("Expected server section in config".into()),
     };
+    if let Some(events_config) = &config.events {
+        core.spawn_event_pruner(events_config, shutdown.clone());
+    }
     #[allow(unused)]
-    let server = Server::new(core, server_config)?;
+    let server = Server::new(core.clone(), server_config)?;
     tokio::spawn({
         let shutdown = shutdown.clone();
         async move {
@@ -57,7 +70,9 @@ async fn server_main(config: Config, _args: ServerArgs) -> Result<(), Error> {
             shutdown.cancel();
         }
     });
-    let router = Router::new().route("/ping", routing::get(ping));
+    let router = Router::new()
+        .route("/ping", routing::get(ping))
+        .route("/health", routing::get(move || health(core.clone())));
     let addr = format!("{}:{}", server_config.host, server_config.port);
     let listener = TcpListener::bind(addr).await?;
     Ok(axum::serve(listener, router)
@@ -0,0 +1,99 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+use rand::Rng as _;
+use tokio_util::sync::CancellationToken;
+
+use crate::core::Error;
+
+use super::hash::hex_encode;
+use super::{FileStorage, LocalFile};
+
+/// Upload of file that is written in parts.
+///
+/// Parts can be written concurrently and in any order, but all parts from
+/// zero to the last index should be written before completion.
+#[async_trait::async_trait]
+pub trait UploadSession: Send + Sync {
+    /// Writes part with specified index replacing previously written one.
+    async fn write_part(&self, index: u64, bytes: Bytes) -> Result<(), Error>;
+
+    /// Assembles written parts into object.
+    async fn complete(self: Box<Self>) -> Result<(), Error>;
+
+    /// Discards written parts.
+    async fn abort(self: Box<Self>) -> Result<(), Error>;
+}
+
+/// Session that buffers parts in temporary directory and uploads assembled
+/// file using [`FileStorage::upload`] on completion.
+pub struct BufferedUploadSession<'a, S: ?Sized> {
+    storage: &'a S,
+    key: String,
+    dir: PathBuf,
+    parts: Mutex<BTreeSet<u64>>,
+}
+
+impl<'a, S: FileStorage + ?Sized> BufferedUploadSession<'a, S> {
+    pub async fn new(storage: &'a S, key: &str) -> Result<Self, Error> {
+        let rand_bytes = rand::thread_rng().gen::<[u8; 16]>();
+        let dir = std::env::temp_dir().join(format!("solve-upload-{}", hex_encode(&rand_bytes)));
+        tokio::fs::create_dir(&dir).await?;
+        Ok(Self {
+            storage,
+            key: key.to_owned(),
+            dir,
+            parts: Default::default(),
+        })
+    }
+
+    fn part_path(&self, index: u64) -> PathBuf {
+        self.dir.join(format!("{index}.part"))
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: FileStorage + ?Sized> UploadSession for BufferedUploadSession<'_, S> {
+    async fn write_part(&self, index: u64, bytes: Bytes) -> Result<(), Error> {
+        tokio::fs::write(self.part_path(index), bytes).await?;
+        self.parts.lock().unwrap().insert(index);
+        Ok(())
+    }
+
+    async fn complete(self: Box<Self>) -> Result<(), Error> {
+        let parts = std::mem::take(&mut *self.parts.lock().unwrap());
+        if !parts.iter().copied().eq(0..parts.len() as u64) {
+            Err("Some parts of upload are missing")?
+        }
+        let path = self.dir.join("file");
+        let mut file = tokio::fs::File::create(&path).await?;
+        for index in parts {
+            let part_path = self.part_path(index);
+            let mut part = tokio::fs::File::open(&part_path).await?;
+            tokio::io::copy(&mut part, &mut file).await?;
+            tokio::fs::remove_file(&part_path).await?;
+        }
+        file.sync_all().await?;
+        drop(file);
+        let file = LocalFile::new(path, None)?;
+        self.storage
+            .upload(&self.key, Box::pin(file), &CancellationToken::new())
+            .await?;
+        tokio::fs::remove_dir_all(&self.dir).await?;
+        Ok(())
+    }
+
+    async fn abort(self: Box<Self>) -> Result<(), Error> {
+        tokio::fs::remove_dir_all(&self.dir).await?;
+        Ok(())
+    }
+}
+
+impl<S: ?Sized> Drop for BufferedUploadSession<'_, S> {
+    fn drop(&mut self) {
+        // Buffered parts should not be left if session is not finished.
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
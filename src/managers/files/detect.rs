@@ -0,0 +1,165 @@
+use crate::models::FileKind;
+
+/// Amount of leading bytes that are used for detection of file type.
+pub(super) const HEAD_SIZE: usize = 512;
+
+/// Maximal length of sanitized file name in bytes.
+pub(super) const MAX_NAME_LEN: usize = 255;
+
+const MAGIC_TYPES: &[(&[u8], &str, FileKind, &[&str])] = &[
+    (
+        b"PK\x03\x04",
+        "application/zip",
+        FileKind::ProblemPackage,
+        &["zip"],
+    ),
+    (
+        b"PK\x05\x06",
+        "application/zip",
+        FileKind::ProblemPackage,
+        &["zip"],
+    ),
+    (
+        b"\x1f\x8b",
+        "application/gzip",
+        FileKind::CompilerImage,
+        &["gz", "tgz"],
+    ),
+    (b"\x89PNG\r\n\x1a\n", "image/png", FileKind::Other, &["png"]),
+    (
+        b"\xff\xd8\xff",
+        "image/jpeg",
+        FileKind::Other,
+        &["jpg", "jpeg"],
+    ),
+    (b"%PDF-", "application/pdf", FileKind::Other, &["pdf"]),
+];
+
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "c", "cc", "cpp", "cxx", "h", "hpp", "cs", "go", "java", "js", "kt", "pas", "py", "rs",
+];
+
+/// Detects content type and kind of file by leading bytes and name.
+///
+/// Leading bytes take precedence over extension of name.
+pub(super) fn detect_file_type(name: &str, head: &[u8]) -> (Option<String>, Option<FileKind>) {
+    for (magic, content_type, kind, _) in MAGIC_TYPES {
+        if head.starts_with(magic) {
+            return (Some(content_type.to_string()), Some(*kind));
+        }
+    }
+    let extension = match name.rsplit_once('.') {
+        Some((_, v)) => v.to_ascii_lowercase(),
+        None => return (None, None),
+    };
+    if !is_text(head) {
+        return (None, None);
+    }
+    if extension == "txt" {
+        return (Some("text/plain".into()), Some(FileKind::Other));
+    }
+    if SOURCE_EXTENSIONS.contains(&extension.as_str()) {
+        return (Some("text/plain".into()), Some(FileKind::SolutionSource));
+    }
+    (None, None)
+}
+
+/// Returns content type detected by leading bytes if it does not match
+/// extension of file.
+pub(super) fn mismatched_content_type(extension: &str, head: &[u8]) -> Option<&'static str> {
+    let extension = extension.to_ascii_lowercase();
+    for (magic, content_type, _, extensions) in MAGIC_TYPES {
+        if head.starts_with(magic) {
+            return match extensions.contains(&extension.as_str()) {
+                true => None,
+                false => Some(content_type),
+            };
+        }
+    }
+    // Extension of binary format requires its leading bytes.
+    for (_, _, _, extensions) in MAGIC_TYPES {
+        if extensions.contains(&extension.as_str()) {
+            return Some(match is_text(head) {
+                true => "text/plain",
+                false => "application/octet-stream",
+            });
+        }
+    }
+    None
+}
+
+/// Returns last component of name without control characters.
+///
+/// Long names are truncated to [`MAX_NAME_LEN`] bytes keeping extension.
+pub(super) fn sanitize_file_name(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    if name.len() <= MAX_NAME_LEN {
+        return Some(name.to_owned());
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() && extension.len() < 16 => {
+            (stem, &name[stem.len()..])
+        }
+        _ => (name, ""),
+    };
+    let mut len = MAX_NAME_LEN - extension.len();
+    while !stem.is_char_boundary(len) {
+        len -= 1;
+    }
+    Some(format!("{}{}", &stem[..len], extension))
+}
+
+fn is_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        // Head can end in the middle of multibyte character.
+        Err(err) => err.error_len().is_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mismatched_content_type, sanitize_file_name, MAX_NAME_LEN};
+
+    #[test]
+    fn sanitize_names() {
+        assert_eq!(sanitize_file_name("a.cpp").as_deref(), Some("a.cpp"));
+        assert_eq!(
+            sanitize_file_name("../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(
+            sanitize_file_name("C:\\Users\\a\x07.py ").as_deref(),
+            Some("a.py")
+        );
+        assert_eq!(sanitize_file_name("dir/"), None);
+        assert_eq!(sanitize_file_name(".."), None);
+        let name = sanitize_file_name(&format!("{}.zip", "я".repeat(200))).unwrap();
+        assert!(name.len() <= MAX_NAME_LEN);
+        assert!(name.ends_with("я.zip"));
+    }
+
+    #[test]
+    fn mismatched_content_types() {
+        assert_eq!(mismatched_content_type("zip", b"PK\x03\x04"), None);
+        assert_eq!(mismatched_content_type("JPG", b"\xff\xd8\xff"), None);
+        assert_eq!(mismatched_content_type("cpp", b"int main() {}"), None);
+        assert_eq!(
+            mismatched_content_type("png", b"PK\x03\x04"),
+            Some("application/zip")
+        );
+        assert_eq!(mismatched_content_type("png", b"text"), Some("text/plain"));
+        assert_eq!(
+            mismatched_content_type("txt", b"%PDF-1.4"),
+            Some("application/pdf")
+        );
+    }
+}
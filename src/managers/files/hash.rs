@@ -0,0 +1,109 @@
+use std::fmt::Write as _;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use md5::Digest as _;
+use tokio::io::AsyncWrite;
+
+use super::UploadResult;
+
+/// Encodes bytes as lowercase hex string with two digits per byte.
+pub fn hex_encode(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for v in bytes {
+        write!(&mut s, "{:02x}", v).unwrap();
+    }
+    s
+}
+
+/// Writer that computes hashes of written bytes.
+pub(super) struct HashingWriter<W> {
+    writer: W,
+    md5: md5::Md5,
+    sha3_224: sha3::Sha3_224,
+}
+
+impl<W> HashingWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            md5: md5::Md5::new(),
+            sha3_224: sha3::Sha3_224::new(),
+        }
+    }
+
+    /// Returns inner writer and hashes of written bytes.
+    pub fn finish(self, size: u64) -> (W, UploadResult) {
+        let result = UploadResult {
+            size,
+            md5: hex_encode(&self.md5.finalize()),
+            sha3_224: hex_encode(&self.sha3_224.finalize()),
+        };
+        (self.writer, result)
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for HashingWriter<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, std::io::Error>> {
+        let size = ready!(Pin::new(&mut self.writer).poll_write(cx, buf))?;
+        self.md5.update(&buf[..size]);
+        self.sha3_224.update(&buf[..size]);
+        Poll::Ready(Ok(size))
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Pin::new(&mut self.writer).poll_shutdown(cx)
+    }
+}
+
+/// Computes size and hashes of file.
+pub async fn hash_file(path: &Path) -> Result<UploadResult, std::io::Error> {
+    let mut reader = tokio::fs::File::open(path).await?;
+    let mut writer = HashingWriter::new(tokio::io::sink());
+    let size = tokio::io::copy(&mut reader, &mut writer).await?;
+    Ok(writer.finish(size).1)
+}
+
+#[cfg(test)]
+mod tests {
+    use md5::Digest as _;
+
+    use super::hex_encode;
+
+    #[test]
+    fn hex_encode_bytes() {
+        assert_eq!(hex_encode(&[]), "");
+        assert_eq!(hex_encode(&[0, 1, 15, 16, 255]), "00010f10ff");
+    }
+
+    #[test]
+    fn hex_encode_digests() {
+        assert_eq!(
+            hex_encode(&md5::Md5::digest(b"abc")),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            hex_encode(&md5::Md5::digest(b"")),
+            "d41d8cd98f00b204e9800998ecf8427e"
+        );
+        assert_eq!(
+            hex_encode(&sha3::Sha3_224::digest(b"abc")),
+            "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf"
+        );
+    }
+}
@@ -1,14 +1,12 @@
-use hashing_reader::HashingReader;
-use md5::Digest as _;
 use rand::Rng as _;
-use std::fmt::Write as _;
-use std::io::Seek as _;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
-use tokio::task::block_in_place;
+use tokio::io::AsyncRead;
+use tokio_util::sync::CancellationToken;
 
 use crate::core::Error;
 
+use super::hash::{hex_encode, HashingWriter};
 use super::{FileInfo, FileStorage, UploadResult};
 
 pub struct LocalStorage {
@@ -17,28 +15,48 @@ pub struct LocalStorage {
 
 impl LocalStorage {
     pub fn new(path: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(path)?;
         Ok(Self {
             path: path.to_owned(),
         })
     }
+
+    /// Returns path of object with specified key inside of storage.
+    fn key_path(&self, key: &str) -> Result<PathBuf, Error> {
+        if key.is_empty() {
+            Err("Key cannot be empty")?
+        }
+        let mut path = self.path.clone();
+        for part in key.split('/') {
+            if part.is_empty() || part == "." || part == ".." {
+                Err(format!("Invalid key: {key:?}"))?
+            }
+            path.push(part);
+        }
+        Ok(path)
+    }
 }
 
-fn to_hex(bytes: Vec<u8>) -> Result<String, Error> {
-    let mut s = String::new();
-    for v in bytes {
-        write!(&mut s, "{:x}", v)?;
+/// Writes file to specified path computing its hashes in one pass.
+async fn write_object(path: &Path, file: Pin<Box<dyn FileInfo>>) -> Result<UploadResult, Error> {
+    let mut reader: Pin<Box<dyn AsyncRead + Send>> = match file.path() {
+        Some(file_path) => Box::pin(tokio::fs::File::open(file_path).await?),
+        None => file.into_async_reader(),
+    };
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
     }
-    Ok(s)
+    let mut writer = HashingWriter::new(tokio::fs::File::create(path).await?);
+    let size = tokio::io::copy(&mut reader, &mut writer).await?;
+    let (file, result) = writer.finish(size);
+    file.sync_all().await?;
+    Ok(result)
 }
 
 #[async_trait::async_trait]
 impl FileStorage for LocalStorage {
     async fn load(&self, key: &str) -> Result<PathBuf, Error> {
-        let key = key.replace('/', std::path::MAIN_SEPARATOR_STR);
-        if key.is_empty() {
-            Err("Key cannot be empty")?
-        }
-        Ok(self.path.clone().join(key))
+        self.key_path(key)
     }
 
     async fn free(&self, _key: &str, _value: PathBuf) {}
@@ -50,70 +68,59 @@ impl FileStorage for LocalStorage {
             .unwrap()
             .as_micros()
             .to_le_bytes();
-        let mut key = String::new();
-        for v in &rand_bytes[..2] {
-            write!(&mut key, "{:x}", v)?;
-        }
-        write!(&mut key, "/")?;
-        for v in &rand_bytes[2..] {
-            write!(&mut key, "{:x}", v)?;
-        }
-        for v in time_bytes {
-            write!(&mut key, "{:x}", v)?;
-        }
+        let key = format!(
+            "{}/{}{}",
+            hex_encode(&rand_bytes[..2]),
+            hex_encode(&rand_bytes[2..]),
+            hex_encode(&time_bytes),
+        );
         Ok(key)
     }
 
-    async fn upload(&self, key: &str, file: Pin<Box<dyn FileInfo>>) -> Result<UploadResult, Error> {
-        let key = key.replace('/', std::path::MAIN_SEPARATOR_STR);
-        if key.is_empty() {
-            Err("Key cannot be empty")?
-        }
-        if let Some(file_path) = file.path() {
-            let mut file = block_in_place(|| std::fs::File::open(&file_path))?;
-            let md5 = {
-                let mut hash = md5::Md5::new();
-                block_in_place(|| std::io::copy(&mut file, &mut hash))?;
-                to_hex(hash.finalize().to_vec())?
-            };
-            block_in_place(|| file.seek(std::io::SeekFrom::Start(0)))?;
-            let sha3_224 = {
-                let mut hash = sha3::Sha3_224::new();
-                block_in_place(|| std::io::copy(&mut file, &mut hash))?;
-                to_hex(hash.finalize().to_vec())?
-            };
-            block_in_place(|| file.seek(std::io::SeekFrom::Start(0)))?;
-            let mut storage_file = block_in_place(|| std::fs::File::create(self.path.join(key)))?;
-            let size = block_in_place(|| std::io::copy(&mut file, &mut storage_file))?;
-            block_in_place(|| storage_file.sync_all())?;
-            Ok(UploadResult {
-                size,
-                md5,
-                sha3_224,
-            })
-        } else {
-            let file = file.into_reader();
-            let (file, md5_hash) = HashingReader::<_, md5::Md5>::new(file);
-            let (mut file, sha3_hash) = HashingReader::<_, sha3::Sha3_224>::new(file);
-            let mut storage_file = block_in_place(|| std::fs::File::create(self.path.join(key)))?;
-            let size = block_in_place(|| std::io::copy(&mut file, &mut storage_file))?;
-            block_in_place(|| storage_file.sync_all())?;
-            let md5 = to_hex(block_in_place(|| md5_hash.recv())?.unwrap())?;
-            let sha3_224 = to_hex(block_in_place(|| sha3_hash.recv())?.unwrap())?;
-            Ok(UploadResult {
-                size,
-                md5,
-                sha3_224,
-            })
+    async fn upload(
+        &self,
+        key: &str,
+        file: Pin<Box<dyn FileInfo>>,
+        cancel: &CancellationToken,
+    ) -> Result<UploadResult, Error> {
+        let storage_path = self.key_path(key)?;
+        let result = tokio::select! {
+            result = write_object(&storage_path, file) => result,
+            _ = cancel.cancelled() => Err("Upload is cancelled".into()),
+        };
+        if result.is_err() {
+            // Partial file should not be left in storage.
+            if let Err(err) = tokio::fs::remove_file(&storage_path).await {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
         }
+        result
     }
 
     async fn delete(&self, key: &str) -> Result<(), Error> {
-        let key = key.replace('/', std::path::MAIN_SEPARATOR_STR);
-        if key.is_empty() {
-            Err("Key cannot be empty")?
+        let path = self.key_path(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {}
+            // Object is already deleted.
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        // Remove shard directory if it is empty now.
+        if let Some(parent) = path.parent() {
+            if parent != self.path {
+                match tokio::fs::remove_dir(parent).await {
+                    Ok(()) => {}
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            std::io::ErrorKind::DirectoryNotEmpty | std::io::ErrorKind::NotFound
+                        ) => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
         }
-        tokio::fs::remove_dir_all(self.path.join(key)).await?;
         Ok(())
     }
 }
@@ -1,20 +1,41 @@
+mod chunked;
+mod detect;
+mod hash;
 mod local_storage;
+mod s3_storage;
 
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, SeekFrom};
 use std::num::NonZeroUsize;
+use std::ops::Range;
 use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 use std::pin::Pin;
 use std::sync::Arc;
+use std::task::{ready, Poll};
 use std::time::Duration;
 
+use bytes::Bytes;
+pub use chunked::{BufferedUploadSession, UploadSession};
+use detect::{detect_file_type, mismatched_content_type, sanitize_file_name, HEAD_SIZE};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{FutureExt, Stream, StreamExt};
+use hash::{hash_file, HashingWriter};
+use http::Uri;
 use local_storage::LocalStorage;
+use s3_storage::S3Storage;
 use solve_db_types::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, ReadBuf, Take};
+use tokio::task::{block_in_place, JoinHandle};
+use tokio_util::io::{StreamReader, SyncIoBridge};
+use tokio_util::sync::CancellationToken;
 
-use crate::config::StorageConfig;
+use crate::config::{ChunkedUpload, FileLimits, StorageConfig, VerifyMode};
 use crate::core::Error;
 use crate::db::builder::{column, Select};
-use crate::models::{self, AsyncIter, Context, Event, FileMeta, FileStatus, ObjectStore};
+use crate::models::{
+    self, run_in_tx, write_tx_options, AsyncIter, Context, Event, FileKind, FileMeta, FileStatus,
+    ObjectStore, ReadConsistency, StoreError,
+};
 
 pub struct UploadResult {
     pub size: u64,
@@ -30,9 +51,68 @@ pub trait FileStorage: Send + Sync {
 
     async fn generate_key(&self) -> Result<String, Error>;
 
-    async fn upload(&self, key: &str, file: Pin<Box<dyn FileInfo>>) -> Result<UploadResult, Error>;
+    /// Uploads file with specified key.
+    ///
+    /// Partially uploaded file is removed if upload fails or is cancelled.
+    async fn upload(
+        &self,
+        key: &str,
+        file: Pin<Box<dyn FileInfo>>,
+        cancel: &CancellationToken,
+    ) -> Result<UploadResult, Error>;
+
+    /// Begins upload of file in parts with specified key.
+    ///
+    /// By default parts are buffered in temporary directory and uploaded
+    /// using [`FileStorage::upload`] on completion.
+    async fn begin_upload(&self, key: &str) -> Result<Box<dyn UploadSession + '_>, Error> {
+        Ok(Box::new(BufferedUploadSession::new(self, key).await?))
+    }
 
     async fn delete(&self, key: &str) -> Result<(), Error>;
+
+    /// Returns URL that allows to download file directly from storage.
+    async fn external_url(&self, _key: &str, _expire: Duration) -> Result<Option<Uri>, Error> {
+        Ok(None)
+    }
+
+    /// Checks that storage is available by uploading, loading and deleting
+    /// small probe object.
+    async fn check(&self) -> Result<StorageHealth, Error> {
+        const PROBE: &[u8] = b"probe";
+        let start = std::time::Instant::now();
+        let key = self.generate_key().await?;
+        let file = Box::pin(MemoryFile::new(PROBE.to_vec(), None));
+        let result = async {
+            self.upload(&key, file, &CancellationToken::new()).await?;
+            let path = self.load(&key).await?;
+            let bytes = tokio::fs::read(&path).await;
+            self.free(&key, path).await;
+            if bytes? != PROBE {
+                Err("Probe object is corrupted")?
+            }
+            Ok::<_, Error>(())
+        }
+        .await;
+        let delete_result = self.delete(&key).await;
+        result?;
+        delete_result?;
+        Ok(StorageHealth::Probed {
+            latency: start.elapsed(),
+        })
+    }
+}
+
+/// Result of successful check of storage.
+#[derive(Clone, Debug)]
+pub enum StorageHealth {
+    /// Probe object was uploaded, loaded and deleted.
+    Probed {
+        /// Duration of round trip of probe object.
+        latency: Duration,
+    },
+    /// Storage cannot store probe object, so it is not checked.
+    NotProbed { reason: String },
 }
 
 #[derive(Clone)]
@@ -53,6 +133,104 @@ impl File {
     pub async fn open(&self) -> Result<tokio::fs::File, std::io::Error> {
         tokio::fs::File::open(self.path.as_path()).await
     }
+
+    /// Returns size of file in bytes.
+    pub fn size(&self) -> Result<u64, Error> {
+        if let Some(size) = self.parse_meta()?.size {
+            return Ok(size);
+        }
+        Ok(block_in_place(|| std::fs::metadata(self.path.as_path()))?.len())
+    }
+
+    /// Returns reader of specified range of file or of whole file.
+    ///
+    /// Cached path is kept alive until reader is dropped.
+    pub async fn reader(
+        &self,
+        range: Option<Range<u64>>,
+    ) -> Result<impl AsyncRead + Send + Unpin, Error> {
+        let size = self.size()?;
+        let range = range.unwrap_or(0..size);
+        if range.start > range.end || range.end > size {
+            Err(format!(
+                "Invalid range {}..{} for file of size {}",
+                range.start, range.end, size
+            ))?;
+        }
+        let mut file = self.open().await?;
+        file.seek(SeekFrom::Start(range.start)).await?;
+        Ok(FileReader {
+            reader: tokio::io::AsyncReadExt::take(file, range.end - range.start),
+            _path: self.path.clone(),
+        })
+    }
+}
+
+struct FileReader {
+    reader: Take<tokio::fs::File>,
+    _path: solve_cache::Object<PathBuf>,
+}
+
+impl AsyncRead for FileReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+/// File that is pinned for long running operation.
+///
+/// Path of file is kept alive while lease exists even if it is evicted from
+/// cache. Lease remembers path and hash of file, so changes of file row can
+/// be detected before results of operation are saved.
+pub struct FileLease {
+    file: File,
+    sha3_224: Option<String>,
+    expire_time: Instant,
+    files: Arc<models::FileStore>,
+}
+
+impl FileLease {
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn path(&self) -> &Path {
+        self.file.path()
+    }
+
+    pub fn expire_time(&self) -> Instant {
+        self.expire_time
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.expire_time
+    }
+
+    /// Extends lease so it expires not earlier than after specified duration.
+    pub fn extend(&mut self, ttl: Duration) {
+        let expire_time = Instant::now() + ttl;
+        if expire_time > self.expire_time {
+            self.expire_time = expire_time;
+        }
+    }
+
+    /// Returns true if file was changed, deleted or became unavailable
+    /// after lease was taken.
+    pub async fn is_stale(&self, ctx: Context<'_, '_>) -> Result<bool, Error> {
+        let ctx = ctx.with_consistency(ReadConsistency::Strong);
+        let file = match self.files.get(ctx, self.file.file.id).await? {
+            Some(v) => v,
+            None => return Ok(true),
+        };
+        if file.status != FileStatus::Available || file.path != self.file.file.path {
+            return Ok(true);
+        }
+        Ok(file.parse_meta()?.sha3_224 != self.sha3_224)
+    }
 }
 
 #[derive(Clone)]
@@ -83,6 +261,13 @@ pub trait FileInfo: Send + Sync {
     fn path(&self) -> Option<PathBuf>;
 
     fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync>;
+
+    /// Returns asynchronous reader of file.
+    ///
+    /// By default synchronous reader is read in blocking thread.
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        bridge_reader(self.into_reader())
+    }
 }
 
 pub struct MemoryFile {
@@ -118,15 +303,19 @@ pub struct LocalFile {
     name: Option<String>,
     path: PathBuf,
     size: u64,
+    file: std::fs::File,
 }
 
 impl LocalFile {
+    /// Opens local file and reads its size.
     pub fn new(path: PathBuf, name: Option<String>) -> Result<Self, Error> {
-        let meta = std::fs::metadata(&path)?;
+        let file = std::fs::File::open(&path)?;
+        let meta = file.metadata()?;
         Ok(LocalFile {
             name,
             path,
             size: meta.size(),
+            file,
         })
     }
 }
@@ -145,16 +334,396 @@ impl FileInfo for LocalFile {
     }
 
     fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
-        unimplemented!()
+        Box::new(Pin::into_inner(self).file)
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        Box::pin(tokio::fs::File::from_std(Pin::into_inner(self).file))
+    }
+}
+
+/// File that is read from asynchronous reader or stream.
+pub struct StreamFileInfo {
+    name: Option<String>,
+    size: Option<u64>,
+    reader: std::sync::Mutex<Pin<Box<dyn AsyncRead + Send>>>,
+}
+
+impl StreamFileInfo {
+    pub fn new<R: AsyncRead + Send + 'static>(
+        reader: R,
+        name: Option<String>,
+        size: Option<u64>,
+    ) -> Self {
+        StreamFileInfo {
+            name,
+            size,
+            reader: std::sync::Mutex::new(Box::pin(reader)),
+        }
+    }
+
+    pub fn from_stream<S, E>(stream: S, name: Option<String>, size: Option<u64>) -> Self
+    where
+        S: Stream<Item = Result<Bytes, E>> + Send + 'static,
+        E: Into<std::io::Error>,
+    {
+        Self::new(StreamReader::new(stream.fuse()), name, size)
+    }
+}
+
+impl FileInfo for StreamFileInfo {
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Box::new(BlockingReader::new(self.into_async_reader()))
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        Pin::into_inner(self).reader.into_inner().unwrap()
+    }
+}
+
+/// Synchronous reader of asynchronous reader.
+///
+/// Should be used outside of asynchronous context.
+struct BlockingReader {
+    reader: std::sync::Mutex<SyncIoBridge<Pin<Box<dyn AsyncRead + Send>>>>,
+}
+
+impl BlockingReader {
+    fn new(reader: Pin<Box<dyn AsyncRead + Send>>) -> Self {
+        let reader = std::sync::Mutex::new(SyncIoBridge::new(reader));
+        Self { reader }
     }
 }
 
+impl Read for BlockingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.get_mut().unwrap().read(buf)
+    }
+}
+
+/// Reads synchronous reader in blocking thread.
+fn bridge_reader(mut reader: Box<dyn Read + Send + Sync>) -> Pin<Box<dyn AsyncRead + Send>> {
+    const CHUNK_SIZE: usize = 64 * 1024;
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Bytes>>(4);
+    tokio::task::spawn_blocking(move || loop {
+        let mut buf = vec![0; CHUNK_SIZE];
+        let chunk = match reader.read(&mut buf) {
+            Ok(0) => return,
+            Ok(size) => {
+                buf.truncate(size);
+                Ok(Bytes::from(buf))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => Err(err),
+        };
+        let is_err = chunk.is_err();
+        if tx.blocking_send(chunk).is_err() || is_err {
+            return;
+        }
+    });
+    let stream = futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    Box::pin(StreamReader::new(stream.fuse()))
+}
+
+/// File with already consumed leading bytes.
+struct HeadFile {
+    name: Option<String>,
+    size: Option<u64>,
+    head: Vec<u8>,
+    reader: std::sync::Mutex<Pin<Box<dyn AsyncRead + Send>>>,
+}
+
+impl FileInfo for HeadFile {
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Box::new(BlockingReader::new(self.into_async_reader()))
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        let this = Pin::into_inner(self);
+        let head = Cursor::new(this.head);
+        let reader = this.reader.into_inner().unwrap();
+        Box::pin(AsyncReadExt::chain(head, reader))
+    }
+}
+
+/// File with overridden name.
+struct NamedFile {
+    file: Pin<Box<dyn FileInfo>>,
+    name: String,
+}
+
+impl FileInfo for NamedFile {
+    fn name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        self.file.path()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.file.size()
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Pin::into_inner(self).file.into_reader()
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        Pin::into_inner(self).file.into_async_reader()
+    }
+}
+
+/// Callback that receives amount of uploaded bytes and size of file if it is
+/// known.
+pub type UploadProgress = Arc<dyn Fn(u64, Option<u64>) + Send + Sync>;
+
+/// Options of [`FileManager::upload_with_options`].
+#[derive(Clone, Default)]
+pub struct UploadOptions {
+    pub progress: Option<UploadProgress>,
+    pub cancel: Option<CancellationToken>,
+}
+
+impl UploadOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_progress<F>(mut self, progress: F) -> Self
+    where
+        F: Fn(u64, Option<u64>) + Send + Sync + 'static,
+    {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+}
+
+/// File that reports progress of reading.
+struct ProgressFile {
+    file: Pin<Box<dyn FileInfo>>,
+    progress: UploadProgress,
+}
+
+impl FileInfo for ProgressFile {
+    fn name(&self) -> Option<String> {
+        self.file.name()
+    }
+
+    // Path is hidden, so storage reads file through reader.
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.file.size()
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Box::new(BlockingReader::new(self.into_async_reader()))
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        let this = Pin::into_inner(self);
+        let size = this.file.size();
+        Box::pin(ProgressReader {
+            reader: this.file.into_async_reader(),
+            size,
+            uploaded: 0,
+            progress: this.progress,
+        })
+    }
+}
+
+struct ProgressReader {
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+    size: Option<u64>,
+    uploaded: u64,
+    progress: UploadProgress,
+}
+
+impl AsyncRead for ProgressReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        ready!(this.reader.as_mut().poll_read(cx, buf))?;
+        let size = (buf.filled().len() - filled) as u64;
+        if size > 0 {
+            this.uploaded += size;
+            (this.progress)(this.uploaded, this.size);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// File that fails to be read past limit.
+struct LimitedFile {
+    file: Pin<Box<dyn FileInfo>>,
+    limit: u64,
+    error: UploadError,
+}
+
+impl FileInfo for LimitedFile {
+    fn name(&self) -> Option<String> {
+        self.file.name()
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        self.file.path()
+    }
+
+    fn size(&self) -> Option<u64> {
+        self.file.size()
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Box::new(BlockingReader::new(self.into_async_reader()))
+    }
+
+    fn into_async_reader(self: Pin<Box<Self>>) -> Pin<Box<dyn AsyncRead + Send>> {
+        let this = Pin::into_inner(self);
+        // One extra byte is requested to detect that limit is exceeded.
+        let reader = this
+            .file
+            .into_async_reader()
+            .take(this.limit.saturating_add(1));
+        Box::pin(LimitedReader {
+            reader,
+            remaining: this.limit,
+            error: this.error,
+        })
+    }
+}
+
+struct LimitedReader {
+    reader: Take<Pin<Box<dyn AsyncRead + Send>>>,
+    remaining: u64,
+    error: UploadError,
+}
+
+impl AsyncRead for LimitedReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut self.reader).poll_read(cx, buf))?;
+        let size = (buf.filled().len() - filled) as u64;
+        if size > self.remaining {
+            return Poll::Ready(Err(std::io::Error::other(self.error.clone())));
+        }
+        self.remaining -= size;
+        Poll::Ready(Ok(()))
+    }
+}
+
+async fn read_head<R: AsyncRead + Unpin>(reader: R) -> Result<Vec<u8>, std::io::Error> {
+    let mut head = Vec::with_capacity(HEAD_SIZE);
+    reader.take(HEAD_SIZE as u64).read_to_end(&mut head).await?;
+    Ok(head)
+}
+
+/// Reads leading bytes of file into head and returns file that still contains them.
+async fn peek_head<T: FileInfo + 'static>(
+    file: T,
+    head: &mut Vec<u8>,
+) -> Result<Pin<Box<dyn FileInfo>>, Error> {
+    if let Some(path) = file.path() {
+        *head = read_head(tokio::fs::File::open(path).await?).await?;
+        return Ok(Box::pin(file));
+    }
+    let name = file.name();
+    let size = file.size();
+    let mut reader = Box::pin(file).into_async_reader();
+    *head = read_head(&mut reader).await?;
+    Ok(Box::pin(HeadFile {
+        name,
+        size,
+        head: head.clone(),
+        reader: std::sync::Mutex::new(reader),
+    }))
+}
+
 type Cache = solve_cache::LruCache<String, PathBuf>;
 
+type CacheManager = solve_cache::Manager<FileStore, Cache, String, PathBuf>;
+
+/// Invalidates cached paths of changed files.
+struct PathCacheObserver {
+    manager: Arc<CacheManager>,
+}
+
+impl models::StoreObserver<models::File> for PathCacheObserver {
+    fn on_event(&self, event: &models::FileEvent) {
+        if event.kind() == models::EventKind::Create {
+            return;
+        }
+        // Observer is called from commit, so invalidation must not block it.
+        let manager = self.manager.clone();
+        let path = event.object().path.clone();
+        tokio::spawn(async move { manager.delete(&path).await });
+    }
+}
+
+/// Schedules deletion of files of deleted problem resources.
+struct ResourceFileObserver {
+    manager: Arc<FileManager>,
+}
+
+impl models::StoreObserver<models::ProblemResource> for ResourceFileObserver {
+    fn on_event(&self, event: &models::ProblemResourceEvent) {
+        if event.kind() != models::EventKind::Delete {
+            return;
+        }
+        let manager = self.manager.clone();
+        let file_id = event.object().file_id;
+        tokio::spawn(async move { manager.delete(file_id).await });
+    }
+}
+
 pub struct FileManager {
-    manager: solve_cache::Manager<FileStore, Cache, String, PathBuf>,
+    manager: Arc<CacheManager>,
     storage: Arc<dyn FileStorage>,
     files: Arc<models::FileStore>,
+    limits: FileLimits,
+    verify_mode: VerifyMode,
+    chunked_upload: ChunkedUpload,
 }
 
 impl FileManager {
@@ -164,14 +733,144 @@ impl FileManager {
         };
         // TODO: Make dynamic capacity.
         let cache = solve_cache::LruCache::new(NonZeroUsize::new(1024).unwrap());
+        let manager = Arc::new(solve_cache::Manager::new(store, cache));
+        files.add_observer(Arc::new(PathCacheObserver {
+            manager: manager.clone(),
+        }));
         Self {
-            manager: solve_cache::Manager::new(store, cache),
+            manager,
             storage,
             files,
+            limits: Default::default(),
+            verify_mode: Default::default(),
+            chunked_upload: Default::default(),
         }
     }
 
+    pub fn with_limits(mut self, limits: FileLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_verify_mode(mut self, verify_mode: VerifyMode) -> Self {
+        self.verify_mode = verify_mode;
+        self
+    }
+
+    pub fn with_chunked_upload(mut self, chunked_upload: ChunkedUpload) -> Self {
+        self.chunked_upload = chunked_upload;
+        self
+    }
+
+    /// Deletes file of problem resource after resource is deleted.
+    pub fn watch_problem_resources(self: &Arc<Self>, resources: &models::ProblemResourceStore) {
+        resources.add_observer(Arc::new(ResourceFileObserver {
+            manager: self.clone(),
+        }));
+    }
+
+    /// Loads available file.
+    ///
+    /// File in storage is verified against meta according to verify mode. If
+    /// verification fails, file is loaded from storage once again.
     pub async fn load(&self, id: i64) -> Result<File, Error> {
+        let file = self.load_unverified(id).await?;
+        if self.verify_mode == VerifyMode::Never {
+            return Ok(file);
+        }
+        let meta = file.parse_meta()?;
+        if self.verify(&meta, file.path()).await.is_ok() {
+            return Ok(file);
+        }
+        let File { file, path } = file;
+        drop(path);
+        self.manager.delete(&file.path).await;
+        let path = self.manager.reload(&file.path).await?;
+        self.verify(&meta, &path).await?;
+        Ok(File { file, path })
+    }
+
+    async fn verify(&self, meta: &FileMeta, path: &Path) -> Result<(), Error> {
+        if let Some(expected) = meta.size {
+            let actual = tokio::fs::metadata(path).await?.len();
+            if actual != expected {
+                return Err(IntegrityError::SizeMismatch { expected, actual }.into());
+            }
+        }
+        if self.verify_mode != VerifyMode::Always {
+            return Ok(());
+        }
+        if let Some(expected) = &meta.sha3_224 {
+            let actual = hash_file(path).await?.sha3_224;
+            if actual != *expected {
+                return Err(IntegrityError::HashMismatch {
+                    expected: expected.clone(),
+                    actual,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that storage of files is available.
+    pub async fn check_storage(&self) -> Result<StorageHealth, Error> {
+        self.storage.check().await
+    }
+
+    /// Loads file and pins it for specified duration.
+    pub async fn lease(&self, id: i64, ttl: Duration) -> Result<FileLease, Error> {
+        let file = self.load(id).await?;
+        let sha3_224 = file.parse_meta()?.sha3_224;
+        Ok(FileLease {
+            file,
+            sha3_224,
+            expire_time: Instant::now() + ttl,
+            files: self.files.clone(),
+        })
+    }
+
+    /// Loads object with specified storage key bypassing checks of file row.
+    pub async fn load_by_key(&self, key: &str) -> Result<solve_cache::Object<PathBuf>, Error> {
+        Ok(self.manager.load(&key.to_owned()).await?)
+    }
+
+    /// Starts loading of available files in background.
+    ///
+    /// Errors of loading are ignored here and are returned by [`Self::load`]
+    /// of corresponding file.
+    pub async fn prefetch(&self, ids: &[i64]) -> Result<PrefetchGuard, Error> {
+        let mut tasks = Vec::new();
+        if ids.is_empty() {
+            return Ok(PrefetchGuard { tasks });
+        }
+        let mut files = self
+            .files
+            .find(
+                Context::new(),
+                Select::new().with_where(column("id").in_values(ids.to_vec())),
+            )
+            .await?;
+        while let Some(file) = files.next().await {
+            let file = file?;
+            if file.status != models::FileStatus::Available {
+                continue;
+            }
+            let manager = self.manager.clone();
+            tasks.push(tokio::spawn(async move {
+                let _ = manager.load(&file.path).await;
+            }));
+        }
+        Ok(PrefetchGuard { tasks })
+    }
+
+    async fn load_unverified(&self, id: i64) -> Result<File, Error> {
+        let file = self.find_available(id).await?;
+        let path = self.manager.load(&file.path).await?;
+        Ok(File { file, path })
+    }
+
+    async fn find_available(&self, id: i64) -> Result<models::File, Error> {
         let file = self
             .files
             .find(
@@ -185,15 +884,140 @@ impl FileManager {
         if file.status != models::FileStatus::Available {
             Err(format!("File has invalid status: {}", file.status))?;
         }
-        let path = self.manager.load(&file.path).await?;
-        Ok(File { file, path })
+        Ok(file)
+    }
+
+    /// Returns URL that allows to download file directly from storage.
+    ///
+    /// Returns none if storage does not support external URLs.
+    pub async fn download_url(&self, id: i64, expire: Duration) -> Result<Option<Uri>, Error> {
+        let file = self.find_available(id).await?;
+        file.parse_meta()?;
+        self.storage.external_url(&file.path, expire).await
+    }
+
+    /// Uploads file on behalf of account from context.
+    ///
+    /// Pending file is always created in separate transaction, so abandoned
+    /// uploads can be cleaned up.
+    pub async fn upload<T: FileInfo + 'static>(
+        &self,
+        ctx: Context<'_, '_>,
+        file: T,
+    ) -> Result<PendingFile, Error> {
+        self.upload_with_options(ctx, file, UploadOptions::new())
+            .await
+    }
+
+    /// Uploads file that satisfies specified policy.
+    ///
+    /// Name of file is sanitized and leading bytes of file should match its
+    /// extension and expected kind.
+    pub async fn upload_validated<T: FileInfo + 'static>(
+        &self,
+        ctx: Context<'_, '_>,
+        file: T,
+        policy: &UploadPolicy,
+    ) -> Result<PendingFile, Error> {
+        let name = file.name().unwrap_or_default();
+        let name = sanitize_file_name(&name).ok_or(UploadError::InvalidName)?;
+        let extension = match name.rsplit_once('.') {
+            Some((_, v)) => v.to_ascii_lowercase(),
+            None => String::new(),
+        };
+        if !policy.allowed_extensions.is_empty()
+            && !policy
+                .allowed_extensions
+                .iter()
+                .any(|v| v.eq_ignore_ascii_case(&extension))
+        {
+            return Err(UploadError::ExtensionNotAllowed { extension }.into());
+        }
+        if let (Some(limit), Some(size)) = (policy.max_size, file.size()) {
+            if size > limit {
+                return Err(UploadError::FileTooLarge { limit }.into());
+            }
+        }
+        let mut head = Vec::new();
+        let file = peek_head(file, &mut head).await?;
+        if !extension.is_empty() {
+            if let Some(content_type) = mismatched_content_type(&extension, &head) {
+                return Err(UploadError::ContentMismatch {
+                    extension,
+                    content_type: content_type.to_owned(),
+                }
+                .into());
+            }
+        }
+        if let Some(expected) = policy.expected_kind {
+            let (_, actual) = detect_file_type(&name, &head);
+            if actual != Some(expected) {
+                return Err(UploadError::KindMismatch { expected, actual }.into());
+            }
+        }
+        let file = NamedFile { file, name };
+        match policy.max_size {
+            Some(limit) => {
+                let file = LimitedFile {
+                    file: Box::pin(file),
+                    limit,
+                    error: UploadError::FileTooLarge { limit },
+                };
+                self.upload(ctx, file).await
+            }
+            None => self.upload(ctx, file).await,
+        }
+    }
+
+    /// Uploads file that can be aborted using cancellation token.
+    pub async fn upload_with_cancel<T: FileInfo + 'static>(
+        &self,
+        ctx: Context<'_, '_>,
+        file: T,
+        cancel: &CancellationToken,
+    ) -> Result<PendingFile, Error> {
+        let options = UploadOptions::new().with_cancel(cancel.clone());
+        self.upload_with_options(ctx, file, options).await
     }
 
-    pub async fn upload<T: FileInfo + 'static>(&self, file: T) -> Result<PendingFile, Error> {
+    /// Uploads file reporting its progress.
+    ///
+    /// Partial object and pending row are removed if upload is cancelled.
+    pub async fn upload_with_options<T: FileInfo + 'static>(
+        &self,
+        ctx: Context<'_, '_>,
+        file: T,
+        options: UploadOptions,
+    ) -> Result<PendingFile, Error> {
+        let cancel = &options.cancel.unwrap_or_default();
+        let ctx = ctx.with_consistency(ReadConsistency::Strong);
+        let account_id = ctx.account_id;
+        let name = file.name().unwrap_or_default();
+        let size = file.size();
+        let mut head = Vec::new();
+        let file = peek_head(file, &mut head).await?;
+        let (content_type, kind) = detect_file_type(&name, &head);
+        let (limit, error) = self.upload_limit(ctx, kind).await?;
+        if let Some(limit) = limit {
+            if size.unwrap_or_default() > limit {
+                return Err(error.into());
+            }
+        }
+        let file: Pin<Box<dyn FileInfo>> = match limit {
+            Some(limit) => Box::pin(LimitedFile { file, limit, error }),
+            None => file,
+        };
+        let file: Pin<Box<dyn FileInfo>> = match options.progress {
+            Some(progress) => Box::pin(ProgressFile { file, progress }),
+            None => file,
+        };
         let key = self.storage.generate_key().await?;
         let meta = models::FileMeta {
-            name: file.name().unwrap_or_default(),
-            size: file.size(),
+            name,
+            size,
+            content_type,
+            kind,
+            account_id,
             ..Default::default()
         };
         let mut model = models::File {
@@ -203,8 +1027,28 @@ impl FileManager {
             ..Default::default()
         };
         model.set_meta(&meta)?;
-        let event = self.files.create(Context::new(), model).await?;
-        let result = self.storage.upload(&key, Box::pin(file)).await?;
+        let files = self.files.clone();
+        let event = run_in_tx(self.files.db(), write_tx_options(), move |ctx| {
+            async move { files.create(ctx, model).await }.boxed()
+        })
+        .await?;
+        let result = if size.is_some_and(|v| v > self.chunked_upload.threshold) {
+            self.upload_chunked(&key, file, cancel).await
+        } else {
+            self.storage.upload(&key, file, cancel).await
+        };
+        let result = match result {
+            Ok(v) => v,
+            Err(err) => {
+                // Partial file is removed by storage. If row cannot be
+                // deleted now, it is removed by cleanup of expired files.
+                let id = event.object().id;
+                let ctx = Context::new();
+                let predicate = column("status").equal(FileStatus::Pending);
+                let _ = self.files.delete_where(ctx, id, predicate).await;
+                return Err(UploadError::unwrap_io(err));
+            }
+        };
         let new_meta = models::FileMeta {
             size: Some(result.size),
             md5: Some(result.md5),
@@ -215,14 +1059,45 @@ impl FileManager {
         model.set_meta(&new_meta)?;
         Ok(PendingFile {
             model,
+            kind: None,
             files: self.files.clone(),
+            storage: self.storage.clone(),
+            done: false,
         })
     }
 
+    /// Uploads file in parts computing its hashes incrementally.
+    async fn upload_chunked(
+        &self,
+        key: &str,
+        file: Pin<Box<dyn FileInfo>>,
+        cancel: &CancellationToken,
+    ) -> Result<UploadResult, Error> {
+        let session = self.storage.begin_upload(key).await?;
+        let part_size = self.chunked_upload.part_size.max(1);
+        let concurrency = self.chunked_upload.concurrency.max(1);
+        let result = tokio::select! {
+            result = write_parts(session.as_ref(), file, part_size, concurrency) => result,
+            _ = cancel.cancelled() => Err("Upload is cancelled".into()),
+        };
+        match result {
+            Ok(result) => {
+                session.complete().await?;
+                Ok(result)
+            }
+            Err(err) => {
+                let _ = session.abort().await;
+                Err(err)
+            }
+        }
+    }
+
     pub async fn delete(&self, id: i64) -> Result<(), Error> {
-        let model = match self.files.get(Context::new(), id).await? {
+        // Model is updated below, so it should not be read from replica.
+        let ctx = Context::new().with_consistency(ReadConsistency::Strong);
+        let model = match self.files.get(ctx, id).await? {
             Some(v) => v,
-            None => Err("File does not exist")?,
+            None => return Err(StoreError::NotFound.into()),
         };
         let mut expire_time = Instant::now() + Duration::from_secs(60);
         if matches!(model.status, models::FileStatus::Pending) {
@@ -234,51 +1109,618 @@ impl FileManager {
             }
         }
         let key = model.path.clone();
-        let status = model.status.clone();
+        let status = model.status;
         let model = models::File {
             status: models::FileStatus::Pending,
             expire_time: Some(expire_time),
             ..model
         };
-        self.files
+        let result = self
+            .files
             .update_where(Context::new(), model, column("status").equal(status))
-            .await?;
+            .await;
+        if let Err(err) = result {
+            if StoreError::is_conflict(&err) {
+                return Err("File was modified concurrently".into());
+            }
+            return Err(err);
+        }
         self.storage.delete(&key).await?;
-        self.files
+        let result = self
+            .files
             .delete_where(
                 Context::new(),
                 id,
                 column("status").equal(models::FileStatus::Pending),
             )
-            .await?;
-        Ok(())
+            .await;
+        match result {
+            Ok(_) => Ok(()),
+            // File is already deleted by concurrent call.
+            Err(err) if StoreError::is_not_found(&err) => Ok(()),
+            Err(err) if StoreError::is_conflict(&err) => {
+                Err("File was modified concurrently".into())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Recomputes size and hashes of available file.
+    ///
+    /// Hashes of files uploaded before hex encoding was fixed have digits
+    /// missing, so they should be recomputed once for all available files.
+    pub async fn rehash(&self, id: i64) -> Result<models::File, Error> {
+        let file = self.load_unverified(id).await?;
+        let result = hash_file(file.path()).await?;
+        let mut model = file.file;
+        let meta = FileMeta {
+            size: Some(result.size),
+            md5: Some(result.md5),
+            sha3_224: Some(result.sha3_224),
+            ..model.parse_meta()?
+        };
+        model.set_meta(&meta)?;
+        let ctx = Context::new().with_consistency(ReadConsistency::Strong);
+        let predicate = column("status").equal(FileStatus::Available);
+        Ok(self
+            .files
+            .update_where(ctx, model, predicate)
+            .await?
+            .into_object())
+    }
+
+    /// Moves available files from source storage to target storage.
+    ///
+    /// Row of file is updated only after object is uploaded to target and its
+    /// hashes are verified, and source object is deleted only after that, so
+    /// row always points to valid object. Context cannot contain transaction,
+    /// because objects are deleted before it would be committed.
+    pub async fn migrate_storage(
+        &self,
+        source: Arc<dyn FileStorage>,
+        target: Arc<dyn FileStorage>,
+        mut ctx: Context<'_, '_>,
+        options: MigrateOptions,
+        on_progress: &(dyn Fn(&MigrateProgress) + Send + Sync),
+    ) -> Result<MigrateProgress, Error> {
+        if ctx.tx.is_some() {
+            Err("Storage migration cannot be run in transaction")?
+        }
+        let mut progress = MigrateProgress::default();
+        let mut last_id = 0;
+        loop {
+            let mut files = Vec::new();
+            {
+                let select = Select::new()
+                    .with_where(
+                        column("status")
+                            .equal(FileStatus::Available)
+                            .and(column("id").greater(last_id)),
+                    )
+                    .with_limit(options.batch.max(1));
+                let mut rows = self.files.find(ctx.reborrow(), select).await?;
+                while let Some(file) = rows.next().await {
+                    files.push(file?);
+                }
+            }
+            let Some(last) = files.last() else {
+                return Ok(progress);
+            };
+            last_id = last.id;
+            if options.dry_run {
+                for file in files {
+                    progress.bytes += file.parse_meta()?.size.unwrap_or_default();
+                    progress.migrated += 1;
+                    on_progress(&progress);
+                }
+                continue;
+            }
+            // Objects are copied concurrently, rows are updated one by one.
+            let copies: Vec<_> = futures_util::stream::iter(files)
+                .map(|file| {
+                    let source = source.clone();
+                    let target = target.clone();
+                    async move {
+                        let result = copy_object(&*source, &*target, &file).await;
+                        (file, result)
+                    }
+                })
+                .buffered(options.concurrency.max(1))
+                .collect()
+                .await;
+            for (file, result) in copies {
+                let (key, meta) = match result {
+                    Ok(v) => v,
+                    Err(_) => {
+                        progress.failed += 1;
+                        on_progress(&progress);
+                        continue;
+                    }
+                };
+                let old_key = file.path.clone();
+                let mut model = file;
+                model.path = key.clone();
+                model.set_meta(&meta)?;
+                let predicate = column("status")
+                    .equal(FileStatus::Available)
+                    .and(column("path").equal(old_key.clone()));
+                let ctx = ctx.reborrow().with_consistency(ReadConsistency::Strong);
+                if self
+                    .files
+                    .update_where(ctx, model, predicate)
+                    .await
+                    .is_err()
+                {
+                    // Row still points to source object.
+                    let _ = target.delete(&key).await;
+                    progress.failed += 1;
+                    on_progress(&progress);
+                    continue;
+                }
+                // Orphaned source object does not break migrated file.
+                let _ = source.delete(&old_key).await;
+                progress.migrated += 1;
+                progress.bytes += meta.size.unwrap_or_default();
+                on_progress(&progress);
+            }
+        }
     }
+
+    /// Deletes pending files whose uploads were abandoned.
+    ///
+    /// Returns amount of deleted files.
+    pub async fn cleanup_expired(&self, ctx: Context<'_, '_>, limit: usize) -> Result<u64, Error> {
+        let mut ctx = ctx.with_consistency(ReadConsistency::Strong);
+        let predicate = column("status")
+            .equal(FileStatus::Pending)
+            .and(column("expire_time").less(Instant::now()));
+        let mut files = Vec::new();
+        {
+            let select = Select::new().with_where(predicate).with_limit(limit);
+            let mut rows = self.files.find(ctx.reborrow(), select).await?;
+            while let Some(file) = rows.next().await {
+                files.push(file?);
+            }
+        }
+        let mut count = 0;
+        for file in files {
+            if let Err(err) = self.storage.delete(&file.path).await {
+                if !is_not_found_io(&err) {
+                    return Err(err);
+                }
+            }
+            let result = self
+                .files
+                .delete_where(
+                    ctx.reborrow(),
+                    file.id,
+                    column("status").equal(FileStatus::Pending),
+                )
+                .await;
+            match result {
+                Ok(_) => count += 1,
+                // File is already deleted or confirmed by concurrent call.
+                Err(err) if StoreError::is_not_found(&err) || StoreError::is_conflict(&err) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(count)
+    }
+
+    /// Returns maximal size of uploaded file and error that is returned when
+    /// file exceeds it.
+    async fn upload_limit(
+        &self,
+        ctx: Context<'_, '_>,
+        kind: Option<FileKind>,
+    ) -> Result<(Option<u64>, UploadError), Error> {
+        let file_limit = kind
+            .and_then(|v| self.limits.kind_max_file_size.get(&v).copied())
+            .or(self.limits.max_file_size);
+        let error = UploadError::FileTooLarge {
+            limit: file_limit.unwrap_or_default(),
+        };
+        let (Some(quota), Some(account_id)) = (self.limits.account_quota, ctx.account_id) else {
+            return Ok((file_limit, error));
+        };
+        let usage = self.files.account_usage(ctx, account_id).await?;
+        let quota_limit = quota.saturating_sub(usage);
+        match file_limit {
+            Some(limit) if limit <= quota_limit => Ok((Some(limit), error)),
+            _ => Ok((
+                Some(quota_limit),
+                UploadError::QuotaExceeded { quota, usage },
+            )),
+        }
+    }
+
+    /// Spawns job that periodically deletes expired pending files.
+    pub fn spawn_cleanup(
+        self: &Arc<Self>,
+        logger: slog::Logger,
+        interval: Duration,
+        batch_size: usize,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match manager.cleanup_expired(Context::new(), batch_size).await {
+                    Ok(count) => {
+                        slog::debug!(logger, "Deleted expired files"; "count" => count)
+                    }
+                    Err(err) => {
+                        slog::warn!(logger, "Cannot delete expired files"; "error" => err.to_string())
+                    }
+                }
+                let sleep = tokio::time::timeout(interval, shutdown.cancelled());
+                if let Ok(()) = sleep.await {
+                    return;
+                }
+            }
+        })
+    }
+}
+
+/// Restrictions of file uploaded with [`FileManager::upload_validated`].
+#[derive(Clone, Debug, Default)]
+pub struct UploadPolicy {
+    /// Allowed extensions of file name, any extension is allowed if empty.
+    pub allowed_extensions: Vec<String>,
+    /// Maximal size of file in bytes.
+    pub max_size: Option<u64>,
+    /// Kind that is expected to be detected for file.
+    pub expected_kind: Option<FileKind>,
 }
 
+/// Error of upload that exceeds configured limits or violates policy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UploadError {
+    /// File is larger than maximal allowed size.
+    FileTooLarge { limit: u64 },
+    /// File does not fit into remaining quota of account.
+    QuotaExceeded { quota: u64, usage: u64 },
+    /// Name of file is empty after sanitization.
+    InvalidName,
+    /// Extension of file is not allowed by policy.
+    ExtensionNotAllowed { extension: String },
+    /// Leading bytes of file do not match its extension.
+    ContentMismatch {
+        extension: String,
+        content_type: String,
+    },
+    /// Detected kind of file differs from expected one.
+    KindMismatch {
+        expected: FileKind,
+        actual: Option<FileKind>,
+    },
+}
+
+impl UploadError {
+    pub fn of(err: &Error) -> Option<&UploadError> {
+        err.downcast_ref()
+    }
+
+    /// Extracts upload error that was passed through reader of file.
+    fn unwrap_io(err: Error) -> Error {
+        let Some(io_err) = err.downcast_ref::<std::io::Error>() else {
+            return err;
+        };
+        match io_err
+            .get_ref()
+            .and_then(|v| v.downcast_ref::<UploadError>())
+        {
+            Some(v) => v.clone().into(),
+            None => err,
+        }
+    }
+}
+
+impl std::fmt::Display for UploadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UploadError::FileTooLarge { limit } => {
+                write!(f, "file is larger than {limit} bytes")
+            }
+            UploadError::QuotaExceeded { quota, usage } => {
+                write!(
+                    f,
+                    "quota of {quota} bytes is exceeded, {usage} bytes are used"
+                )
+            }
+            UploadError::InvalidName => write!(f, "file name is invalid"),
+            UploadError::ExtensionNotAllowed { extension } => {
+                write!(f, "extension {extension:?} is not allowed")
+            }
+            UploadError::ContentMismatch {
+                extension,
+                content_type,
+            } => {
+                write!(
+                    f,
+                    "content of type {content_type} does not match extension {extension:?}"
+                )
+            }
+            UploadError::KindMismatch { expected, actual } => {
+                write!(f, "expected file of kind {expected:?}, got {actual:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UploadError {}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MigrateOptions {
+    /// Amount of files that are read from database at once.
+    pub batch: usize,
+    /// Only counts files that would be migrated.
+    pub dry_run: bool,
+    /// Amount of objects that are copied concurrently.
+    pub concurrency: usize,
+}
+
+impl Default for MigrateOptions {
+    fn default() -> Self {
+        Self {
+            batch: 100,
+            dry_run: false,
+            concurrency: 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MigrateProgress {
+    pub migrated: u64,
+    pub failed: u64,
+    /// Total size of migrated files.
+    pub bytes: u64,
+}
+
+/// Writes file to upload session part by part computing its hashes.
+async fn write_parts(
+    session: &dyn UploadSession,
+    file: Pin<Box<dyn FileInfo>>,
+    part_size: u64,
+    concurrency: usize,
+) -> Result<UploadResult, Error> {
+    let mut reader = file.into_async_reader();
+    let mut writer = HashingWriter::new(tokio::io::sink());
+    let mut size = 0;
+    let mut writes = FuturesUnordered::new();
+    // Parts are read sequentially to compute hashes, but written concurrently.
+    for index in 0.. {
+        while writes.len() >= concurrency {
+            writes.next().await.expect("writes are not empty")?;
+        }
+        let mut part = Vec::new();
+        {
+            let mut take = (&mut reader).take(part_size);
+            let read = take.read_to_end(&mut part);
+            tokio::pin!(read);
+            // Pending writes make progress while next part is being read.
+            loop {
+                tokio::select! {
+                    result = &mut read => {
+                        result?;
+                        break;
+                    }
+                    Some(result) = writes.next() => result?,
+                }
+            }
+        }
+        if part.is_empty() {
+            break;
+        }
+        writer.write_all(&part).await?;
+        size += part.len() as u64;
+        writes.push(session.write_part(index, Bytes::from(part)));
+    }
+    while let Some(result) = writes.next().await {
+        result?;
+    }
+    Ok(writer.finish(size).1)
+}
+
+/// Copies object of file to target storage under new key.
+///
+/// Returns new key and meta of file with verified hashes.
+async fn copy_object(
+    source: &dyn FileStorage,
+    target: &dyn FileStorage,
+    file: &models::File,
+) -> Result<(String, FileMeta), Error> {
+    let meta = file.parse_meta()?;
+    let path = source.load(&file.path).await?;
+    let result = match LocalFile::new(path.clone(), None) {
+        Ok(local) => {
+            let key = target.generate_key().await?;
+            let cancel = CancellationToken::new();
+            target
+                .upload(&key, Box::pin(local), &cancel)
+                .await
+                .map(|result| (key, result))
+        }
+        Err(err) => Err(err),
+    };
+    source.free(&file.path, path).await;
+    let (key, result) = result?;
+    let matches = meta.size.is_none_or(|v| v == result.size)
+        && meta.md5.as_ref().is_none_or(|v| *v == result.md5)
+        && meta.sha3_224.as_ref().is_none_or(|v| *v == result.sha3_224);
+    if !matches {
+        let _ = target.delete(&key).await;
+        Err(format!("Hashes of file {} do not match", file.id))?
+    }
+    let meta = FileMeta {
+        size: Some(result.size),
+        md5: Some(result.md5),
+        sha3_224: Some(result.sha3_224),
+        ..meta
+    };
+    Ok((key, meta))
+}
+
+/// Error of file in storage that does not match its meta.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IntegrityError {
+    SizeMismatch { expected: u64, actual: u64 },
+    HashMismatch { expected: String, actual: String },
+}
+
+impl IntegrityError {
+    pub fn of(err: &Error) -> Option<&IntegrityError> {
+        err.downcast_ref()
+    }
+}
+
+impl std::fmt::Display for IntegrityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IntegrityError::SizeMismatch { expected, actual } => {
+                write!(f, "file has size {actual}, expected {expected}")
+            }
+            IntegrityError::HashMismatch { expected, actual } => {
+                write!(f, "file has hash {actual}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for IntegrityError {}
+
+fn is_not_found_io(err: &Error) -> bool {
+    match err.downcast_ref::<std::io::Error>() {
+        Some(err) => err.kind() == std::io::ErrorKind::NotFound,
+        None => false,
+    }
+}
+
+/// Files that are loaded in background.
+///
+/// Loading is cancelled when guard is dropped.
+#[must_use]
+pub struct PrefetchGuard {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl PrefetchGuard {
+    /// Waits until all files are loaded.
+    pub async fn wait(mut self) {
+        for task in std::mem::take(&mut self.tasks) {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for PrefetchGuard {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Uploaded file that is not confirmed yet.
+///
+/// File is deleted from storage and database if it is dropped without
+/// confirmation.
 pub struct PendingFile {
     model: models::File,
+    kind: Option<FileKind>,
     files: Arc<models::FileStore>,
+    storage: Arc<dyn FileStorage>,
+    done: bool,
 }
 
 impl PendingFile {
-    pub async fn confirm(self, ctx: models::Context<'_, '_>) -> Result<models::File, Error> {
-        let mut model = self.model;
+    /// Overrides detected kind of file.
+    pub fn with_kind(mut self, kind: FileKind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub async fn confirm(mut self, ctx: models::Context<'_, '_>) -> Result<models::File, Error> {
+        let mut model = self.model.clone();
+        if let Some(kind) = self.kind {
+            let mut meta = model.parse_meta()?;
+            meta.kind = Some(kind);
+            model.set_meta(&meta)?;
+        }
         model.status = FileStatus::Available;
         model.expire_time = None;
-        Ok(self
+        let ctx = ctx.with_consistency(ReadConsistency::Strong);
+        let event = self
             .files
             .update_where(ctx, model, column("status").equal(FileStatus::Pending))
-            .await?
-            .into_object())
+            .await?;
+        self.done = true;
+        Ok(event.into_object())
+    }
+
+    /// Deletes uploaded file from storage and database.
+    pub async fn abort(mut self) -> Result<(), Error> {
+        self.done = true;
+        abort_upload(
+            self.files.clone(),
+            self.storage.clone(),
+            self.model.id,
+            self.model.path.clone(),
+        )
+        .await
     }
 }
 
+impl Drop for PendingFile {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        let Ok(handle) = tokio::runtime::Handle::try_current() else {
+            // File will be deleted by cleanup of expired files.
+            return;
+        };
+        handle.spawn(abort_upload(
+            self.files.clone(),
+            self.storage.clone(),
+            self.model.id,
+            self.model.path.clone(),
+        ));
+    }
+}
+
+async fn abort_upload(
+    files: Arc<models::FileStore>,
+    storage: Arc<dyn FileStorage>,
+    id: i64,
+    key: String,
+) -> Result<(), Error> {
+    // Row is deleted first, so confirmed file cannot lose its object.
+    let result = files
+        .delete_where(
+            Context::new(),
+            id,
+            column("status").equal(FileStatus::Pending),
+        )
+        .await;
+    match result {
+        Ok(_) => {}
+        Err(err) if StoreError::is_not_found(&err) => {}
+        Err(err) => return Err(err),
+    }
+    storage.delete(&key).await
+}
+
 pub fn new_storage(config: &StorageConfig) -> Result<Arc<dyn FileStorage>, Error> {
     match config {
         StorageConfig::Local(config) => {
             let storage = LocalStorage::new(&config.files_dir)?;
             Ok(Arc::new(storage))
         }
-        StorageConfig::S3(_config) => unimplemented!(),
+        StorageConfig::S3(config) => {
+            let storage = S3Storage::new(config)?;
+            Ok(Arc::new(storage))
+        }
     }
 }
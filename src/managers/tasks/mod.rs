@@ -1,50 +1,487 @@
+mod scheduler;
+mod subscriptions;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use serde::de::DeserializeOwned;
+use serde::Serialize;
+use solve_db::IntoValue;
 use solve_db_types::{Instant, JSON};
-use tokio::sync::Mutex;
+use tokio::sync::{watch, Mutex};
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 
 use crate::core::Error;
-use crate::db::builder::column;
-use crate::models::{self, Context, Event, ObjectStore, TaskKind, TaskStatus};
+use crate::db::builder::{column, Order, Select};
+use crate::models::{
+    self, AsyncIter, Context, Event, JudgeSolutionTaskConfig, ObjectStore, StoreError, TakeOptions,
+    TakeOutcome, TaskKind, TaskStatus, UpdateProblemPackageTaskConfig,
+};
+
+pub use subscriptions::TaskSnapshot;
+
+use subscriptions::Subscriptions;
+
+/// Default maximal amount of attempts to run task.
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 3;
 
 pub struct TaskManager {
     tasks: Arc<models::TaskStore>,
+    lease: Duration,
+    max_attempts: i32,
+    parent_failure: ParentFailurePolicy,
+    subscriptions: Arc<Subscriptions>,
+    periodic_tasks: Option<Arc<models::PeriodicTaskStore>>,
+    schedule_interval: Duration,
 }
 
 impl TaskManager {
     pub fn new(tasks: Arc<models::TaskStore>) -> Self {
-        Self { tasks }
+        Self {
+            subscriptions: Arc::new(Subscriptions::new(tasks.clone(), Duration::from_secs(1))),
+            tasks,
+            lease: Duration::from_secs(30),
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            parent_failure: Default::default(),
+            periodic_tasks: None,
+            schedule_interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets duration for which taken task is owned by worker without ping.
+    pub fn with_lease(mut self, lease: Duration) -> Self {
+        self.lease = lease;
+        self
+    }
+
+    /// Sets maximal amount of attempts to run tasks created by manager.
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts;
+        self
     }
 
-    pub async fn take_task(&self) -> Result<Option<Task>, Error> {
-        let task = match self
+    /// Sets interval of polling of tasks with subscriptions.
+    ///
+    /// Polling is required to observe changes made by other processes.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.subscriptions = Arc::new(Subscriptions::new(self.tasks.clone(), interval));
+        self
+    }
+
+    /// Subscribes to changes of status, state and expiration of task.
+    ///
+    /// Subscription is removed when all receivers are dropped.
+    pub async fn subscribe(&self, id: i64) -> Result<watch::Receiver<TaskSnapshot>, Error> {
+        self.subscriptions.subscribe(id).await
+    }
+
+    /// Returns amount of tasks with active subscriptions.
+    pub fn subscription_count(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    /// Sets store of periodic task definitions used by scheduler.
+    pub fn with_periodic_tasks(mut self, store: Arc<models::PeriodicTaskStore>) -> Self {
+        self.periodic_tasks = Some(store);
+        self
+    }
+
+    /// Sets interval of checking periodic tasks by scheduler.
+    pub fn with_schedule_interval(mut self, interval: Duration) -> Self {
+        self.schedule_interval = interval;
+        self
+    }
+
+    /// Sets action applied to children of failed tasks.
+    pub fn with_parent_failure(mut self, policy: ParentFailurePolicy) -> Self {
+        self.parent_failure = policy;
+        self
+    }
+
+    /// Cancels queued or running task.
+    ///
+    /// Running task is stopped by its worker on next ping. Returns false if
+    /// task is not found or is already finished.
+    pub async fn cancel(&self, id: i64) -> Result<bool, Error> {
+        let predicate = column("id")
+            .equal(id)
+            .and(column("status").in_values(vec![TaskStatus::Queued, TaskStatus::Running]));
+        let count = self
             .tasks
-            .take_task(Context::new(), Duration::from_secs(30))
-            .await?
-        {
+            .update_fields_where(
+                Context::new(),
+                vec![
+                    ("status".into(), TaskStatus::Cancelled.into_value()),
+                    ("finish_time".into(), Instant::now().into_value()),
+                ],
+                predicate,
+                true,
+            )
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Creates queued task that judges solution.
+    ///
+    /// Task is created in transaction of context if it is provided.
+    pub async fn enqueue_judge_solution(
+        &self,
+        ctx: Context<'_, '_>,
+        config: JudgeSolutionTaskConfig,
+    ) -> Result<i64, Error> {
+        self.enqueue(ctx, TaskKind::JudgeSolution, config).await
+    }
+
+    /// Creates queued task that updates problem package.
+    ///
+    /// Task is created in transaction of context if it is provided.
+    pub async fn enqueue_update_problem_package(
+        &self,
+        ctx: Context<'_, '_>,
+        config: UpdateProblemPackageTaskConfig,
+    ) -> Result<i64, Error> {
+        self.enqueue(ctx, TaskKind::UpdateProblemPackage, config)
+            .await
+    }
+
+    /// Creates queued task that is not taken until specified time.
+    pub async fn enqueue_at<T: Serialize>(
+        &self,
+        kind: TaskKind,
+        config: T,
+        when: Instant,
+    ) -> Result<models::Task, Error> {
+        let mut task = models::Task {
+            kind,
+            status: TaskStatus::Queued,
+            not_before: Some(when),
+            // Task is not waiting in queue before scheduled time.
+            queued_time: Some(when),
+            max_attempts: self.max_attempts,
+            ..Default::default()
+        };
+        task.set_config(config)?;
+        let event = self.tasks.create(Context::new(), task).await?;
+        Ok(event.into_object())
+    }
+
+    /// Creates queued task that is not taken until parent task succeeds.
+    pub async fn enqueue_child<T: Serialize>(
+        &self,
+        mut ctx: Context<'_, '_>,
+        parent_id: i64,
+        kind: TaskKind,
+        config: T,
+    ) -> Result<i64, Error> {
+        let parent = match self.tasks.get(ctx.reborrow(), parent_id).await? {
             Some(v) => v,
-            None => return Ok(None),
+            None => return Err(StoreError::NotFound.into()),
+        };
+        let now = Instant::now();
+        let mut task = models::Task {
+            kind,
+            status: TaskStatus::Queued,
+            queued_time: Some(now),
+            max_attempts: self.max_attempts,
+            parent_id: Some(parent_id),
+            ..Default::default()
+        };
+        task.set_config(config)?;
+        if parent.status == TaskStatus::Failed && self.parent_failure == ParentFailurePolicy::Fail {
+            task.status = TaskStatus::Failed;
+            task.finish_time = Some(now);
+            merge_error(&mut task.state, parent_failed_error(now))?;
+        }
+        let event = self.tasks.create(ctx, task).await?;
+        Ok(event.object().id)
+    }
+
+    async fn enqueue<T: Serialize>(
+        &self,
+        ctx: Context<'_, '_>,
+        kind: TaskKind,
+        config: T,
+    ) -> Result<i64, Error> {
+        let mut task = models::Task {
+            kind,
+            status: TaskStatus::Queued,
+            queued_time: Some(Instant::now()),
+            max_attempts: self.max_attempts,
+            ..Default::default()
         };
+        task.set_config(config)?;
+        let event = self.tasks.create(ctx, task).await?;
+        Ok(event.object().id)
+    }
+
+    /// Recovers running tasks that were not pinged by their owners in time.
+    ///
+    /// Task that was pinged concurrently by its owner is left as is.
+    pub async fn recover_expired(
+        &self,
+        mut ctx: Context<'_, '_>,
+        policy: RecoverPolicy,
+    ) -> Result<RecoveredTasks, Error> {
+        let now = Instant::now();
+        let expired = column("status")
+            .equal(TaskStatus::Running)
+            .and(column("expire_time").less(now));
+        let tasks: Vec<_> = {
+            let select = Select::new()
+                .with_where(expired.clone())
+                .with_order(vec![Order::asc("id")]);
+            let mut rows = self.tasks.find(ctx.reborrow(), select).await?;
+            let mut tasks = Vec::new();
+            while let Some(task) = rows.next().await {
+                tasks.push(task?);
+            }
+            tasks
+        };
+        let mut recovered = RecoveredTasks::default();
+        for task in tasks {
+            let requeue = policy == RecoverPolicy::Requeue && can_retry(&task, self.max_attempts);
+            let new_task = if requeue {
+                models::Task {
+                    status: TaskStatus::Queued,
+                    expire_time: None,
+                    attempts: task.attempts + 1,
+                    queued_time: Some(now),
+                    ..task
+                }
+            } else {
+                let error = models::TaskError {
+                    message: "Task was abandoned".into(),
+                    kind: "abandoned".into(),
+                    time: now,
+                };
+                let mut state = task.state.clone();
+                merge_error(&mut state, error)?;
+                models::Task {
+                    status: TaskStatus::Failed,
+                    expire_time: None,
+                    state,
+                    finish_time: Some(now),
+                    ..task
+                }
+            };
+            let id = new_task.id;
+            // Version check rejects update if owner has pinged task.
+            match self
+                .tasks
+                .update_where(ctx.reborrow(), new_task, expired.clone())
+                .await
+            {
+                Ok(_) if requeue => recovered.requeued += 1,
+                Ok(_) => {
+                    recovered.failed += 1;
+                    if self.parent_failure == ParentFailurePolicy::Fail {
+                        fail_children(&self.tasks, ctx.reborrow(), id).await?;
+                    }
+                }
+                Err(err) if StoreError::is_conflict(&err) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(recovered)
+    }
+
+    /// Returns statistics of tasks finished within window grouped by kind.
+    ///
+    /// Statistics are computed from events of tasks written within window.
+    pub async fn stats(
+        &self,
+        ctx: Context<'_, '_>,
+        window: Duration,
+    ) -> Result<HashMap<TaskKind, TaskKindStats>, Error> {
+        let since = Instant::now() - window;
+        let select = Select::new().with_where(column("event_time").greater_equal(since));
+        let mut finished: HashMap<i64, models::Task> = HashMap::new();
+        {
+            let mut events = self.tasks.find_events(ctx, select).await?;
+            while let Some(event) = events.next().await {
+                let task = event?.into_object();
+                if task.status.is_finished() && task.finish_time.is_some_and(|v| v >= since) {
+                    finished.insert(task.id, task);
+                }
+            }
+        }
+        let mut latencies: HashMap<TaskKind, (Vec<Duration>, Vec<Duration>)> = HashMap::new();
+        let mut stats: HashMap<TaskKind, TaskKindStats> = HashMap::new();
+        for task in finished.into_values() {
+            let kind_stats = stats.entry(task.kind).or_default();
+            kind_stats.finished += 1;
+            if task.status == TaskStatus::Failed {
+                kind_stats.failed += 1;
+            }
+            let (queue, execution) = latencies.entry(task.kind).or_default();
+            if let (Some(queued), Some(start)) = (task.queued_time, task.start_time) {
+                queue.push(elapsed(queued, start));
+            }
+            if let (Some(start), Some(finish)) = (task.start_time, task.finish_time) {
+                execution.push(elapsed(start, finish));
+            }
+        }
+        for (kind, (queue, execution)) in latencies {
+            let kind_stats = stats.entry(kind).or_default();
+            kind_stats.queue_latency = Percentiles::new(queue);
+            kind_stats.execution_time = Percentiles::new(execution);
+        }
+        Ok(stats)
+    }
+
+    /// Spawns job that periodically recovers expired tasks.
+    pub fn spawn_recover_expired(
+        self: &Arc<Self>,
+        logger: slog::Logger,
+        interval: Duration,
+        policy: RecoverPolicy,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match manager.recover_expired(Context::new(), policy).await {
+                    Ok(v) => {
+                        slog::debug!(logger, "Recovered expired tasks"; "requeued" => v.requeued, "failed" => v.failed)
+                    }
+                    Err(err) => {
+                        slog::warn!(logger, "Cannot recover expired tasks"; "error" => err.to_string())
+                    }
+                }
+                let sleep = tokio::time::timeout(interval, shutdown.cancelled());
+                if let Ok(()) = sleep.await {
+                    return;
+                }
+            }
+        })
+    }
+
+    pub async fn take_task(&self, options: &TakeOptions) -> Result<Option<Task>, Error> {
+        Ok(self.claim_task(options).await?.claimed())
+    }
+
+    /// Takes task reporting why task was not taken.
+    pub async fn claim_task(&self, options: &TakeOptions) -> Result<TakeOutcome<Task>, Error> {
+        let outcome = self
+            .tasks
+            .take_task(Context::new(), self.lease, options)
+            .await?;
+        Ok(outcome.map(|task| self.new_task(task)))
+    }
+
+    fn new_task(&self, task: models::Task) -> Task {
         assert_eq!(task.status, TaskStatus::Running);
+        self.subscriptions.notify(&task);
         let inner = Arc::new(TaskInner {
             task: Mutex::new(task.clone()),
             stored_task: Mutex::new(task),
             tasks: self.tasks.clone(),
+            lease: self.lease,
+            max_attempts: self.max_attempts,
+            parent_failure: self.parent_failure,
+            subscriptions: self.subscriptions.clone(),
+            cancelled: AtomicBool::new(false),
+            state_write_time: Default::default(),
         });
-        Ok(Some(Task { inner }))
+        Task { inner }
+    }
+}
+
+/// Action applied to running tasks with expired time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RecoverPolicy {
+    /// Returns task back to queue if it has remaining attempts.
+    #[default]
+    Requeue,
+    /// Marks task as failed.
+    Fail,
+}
+
+/// Amounts of tasks recovered by [`TaskManager::recover_expired`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecoveredTasks {
+    pub requeued: u64,
+    pub failed: u64,
+}
+
+/// Statistics of finished tasks of one kind.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskKindStats {
+    pub finished: u64,
+    pub failed: u64,
+    /// Time between queueing and start of tasks.
+    pub queue_latency: Percentiles,
+    /// Time between start and finish of tasks.
+    pub execution_time: Percentiles,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Percentiles {
+    fn new(mut values: Vec<Duration>) -> Self {
+        values.sort();
+        // Uses nearest-rank method.
+        let rank = |p: usize| match values.len() {
+            0 => Duration::ZERO,
+            n => values[(n * p).div_ceil(100).max(1) - 1],
+        };
+        Self {
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: rank(100),
+        }
     }
 }
 
+/// Action applied to queued children of failed task.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParentFailurePolicy {
+    /// Children are failed too.
+    #[default]
+    Fail,
+    /// Children stay queued until parent is run again and succeeds.
+    Keep,
+}
+
+/// Returns true if task has remaining attempts.
+///
+/// Tasks created without limit of attempts use limit of manager.
+fn can_retry(task: &models::Task, max_attempts: i32) -> bool {
+    let max_attempts = match task.max_attempts {
+        0 => max_attempts,
+        v => v,
+    };
+    task.attempts + 1 < max_attempts
+}
+
 struct TaskInner {
     task: Mutex<models::Task>,
     stored_task: Mutex<models::Task>,
     tasks: Arc<models::TaskStore>,
+    lease: Duration,
+    max_attempts: i32,
+    parent_failure: ParentFailurePolicy,
+    subscriptions: Arc<Subscriptions>,
+    /// Task was cancelled while it was running.
+    cancelled: AtomicBool,
+    /// Time of last write of state made by [`Task::update_state`].
+    state_write_time: std::sync::Mutex<Option<std::time::Instant>>,
 }
 
+/// Minimal interval between writes of state made by [`Task::update_state`].
+const STATE_WRITE_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
 pub struct Task {
     inner: Arc<TaskInner>,
@@ -66,6 +503,46 @@ impl Task {
         task.status
     }
 
+    /// Returns true if task was cancelled while it was running.
+    ///
+    /// Cancellation is detected when update of task fails.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub async fn get_attempts(&self) -> i32 {
+        let task = self.inner.task.lock().await;
+        task.attempts
+    }
+
+    /// Returns true if failed task can be retried.
+    pub async fn can_retry(&self) -> bool {
+        let task = self.inner.task.lock().await;
+        can_retry(&task, self.inner.max_attempts)
+    }
+
+    /// Returns failed task back to queue so it is taken after backoff.
+    pub async fn retry_later(&self, backoff: Duration) -> Result<(), Error> {
+        let mut task = self.inner.task.lock().await;
+        let now = Instant::now();
+        let new_task = models::Task {
+            status: TaskStatus::Queued,
+            expire_time: None,
+            not_before: Some(now + backoff),
+            attempts: task.attempts + 1,
+            queued_time: Some(now + backoff),
+            ..task.clone()
+        };
+        *task = self.update(new_task, now).await?;
+        Ok(())
+    }
+
+    /// Returns time that task waited in queue before it was taken.
+    pub async fn get_queue_latency(&self) -> Option<Duration> {
+        let task = self.inner.task.lock().await;
+        Some(elapsed(task.queued_time?, task.start_time?))
+    }
+
     pub async fn parse_config<T: DeserializeOwned>(&self) -> Result<T, Error> {
         let task = self.inner.task.lock().await;
         task.parse_config()
@@ -73,8 +550,35 @@ impl Task {
 
     pub async fn set_status(&self, status: TaskStatus) -> Result<(), Error> {
         let mut task = self.inner.task.lock().await;
+        let now = Instant::now();
+        let finish_time = match status.is_finished() {
+            true => Some(now),
+            false => task.finish_time,
+        };
         let new_task = models::Task {
             status,
+            finish_time,
+            ..task.clone()
+        };
+        *task = self.update(new_task, now).await?;
+        if status == TaskStatus::Failed && self.inner.parent_failure == ParentFailurePolicy::Fail {
+            fail_children(&self.inner.tasks, Context::new(), task.id).await?;
+        }
+        Ok(())
+    }
+
+    /// Records error into `error` field of state keeping other fields.
+    pub async fn set_error(&self, err: &Error) -> Result<(), Error> {
+        let mut task = self.inner.task.lock().await;
+        let error = models::TaskError {
+            message: err.to_string(),
+            kind: error_kind(err).to_owned(),
+            time: Instant::now(),
+        };
+        let mut state = task.state.clone();
+        merge_error(&mut state, error)?;
+        let new_task = models::Task {
+            state,
             ..task.clone()
         };
         *task = self.update(new_task, Instant::now()).await?;
@@ -96,19 +600,70 @@ impl Task {
         Ok(())
     }
 
+    /// Reads, mutates and writes typed state of task.
+    ///
+    /// State is written at most once per second, more frequent changes are
+    /// kept in memory and are written with next update of task.
+    pub async fn update_state<T, F>(&self, f: F) -> Result<(), Error>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        let mut task = self.inner.task.lock().await;
+        let mut state: T = match task.state == JSON::default() {
+            true => T::default(),
+            false => task.parse_state()?,
+        };
+        f(&mut state);
+        let state = JSON::from_serialize(state)?;
+        let now = std::time::Instant::now();
+        let write_time = *self.inner.state_write_time.lock().unwrap();
+        if write_time.is_some_and(|v| now.duration_since(v) < STATE_WRITE_INTERVAL) {
+            task.state = state;
+            return Ok(());
+        }
+        let new_task = models::Task {
+            state,
+            ..task.clone()
+        };
+        *task = self.update(new_task, Instant::now()).await?;
+        *self.inner.state_write_time.lock().unwrap() = Some(now);
+        Ok(())
+    }
+
     pub async fn set_deferred_state(&self, state: JSON) {
         let mut task = self.inner.task.lock().await;
         task.state = state;
     }
 
+    /// Extends lease of task by writing only its expiration time.
+    ///
+    /// Task is marked as expired if it was changed or taken concurrently.
     pub async fn ping(&self, duration: Duration) -> Result<(), Error> {
         let mut task = self.inner.task.lock().await;
+        let mut stored_task = self.inner.stored_task.lock().await;
         let now = Instant::now();
-        let new_task = models::Task {
-            expire_time: Some(now + duration),
-            ..task.clone()
+        let guard_expiry = match stored_task.expire_time {
+            Some(v) if v >= now => v,
+            _ => return Err(StoreError::Conflict.into()),
         };
-        *task = self.update(new_task, now).await?;
+        let expire_time = now + duration;
+        if !self
+            .inner
+            .tasks
+            .extend_expiry(Context::new(), stored_task.id, guard_expiry, expire_time)
+            .await?
+        {
+            stored_task.expire_time = None;
+            task.expire_time = None;
+            self.check_cancelled(stored_task.id).await;
+            return Err(StoreError::Conflict.into());
+        }
+        stored_task.expire_time = Some(expire_time);
+        stored_task.version += 1;
+        task.expire_time = stored_task.expire_time;
+        task.version = stored_task.version;
+        self.inner.subscriptions.notify(&stored_task);
         Ok(())
     }
 
@@ -123,52 +678,91 @@ impl Task {
         tokio::spawn(clone.run_pinger(shutdown, logger))
     }
 
+    /// Pings task when half of lease remains until task expires or
+    /// is changed concurrently.
     async fn run_pinger(self, shutdown: CancellationToken, logger: slog::Logger) {
+        const RETRY_DELAY: Duration = Duration::from_secs(1);
+        let lease = self.inner.lease;
+        let mut delay = Duration::ZERO;
         loop {
-            let sleep = tokio::time::timeout(Duration::from_secs(1), shutdown.cancelled());
+            let sleep = tokio::time::timeout(delay, shutdown.cancelled());
             if let Ok(()) = sleep.await {
                 return;
             }
-            if self.is_expires_after(Duration::ZERO).await {
+            let Some(remaining) = self.remaining_lease().await else {
                 shutdown.cancel();
                 return;
-            }
-            if !self.is_expires_after(Duration::from_secs(15)).await {
+            };
+            delay = remaining.saturating_sub(lease / 2);
+            if !delay.is_zero() {
                 continue;
             }
-            if let Err(err) = self.ping(Duration::from_secs(30)).await {
-                slog::warn!(logger, "Cannot ping task"; "error" => err.to_string());
+            match self.ping(lease).await {
+                Ok(()) => {
+                    slog::debug!(logger, "Pinged task");
+                    delay = lease - lease / 2;
+                }
+                Err(err) if StoreError::is_conflict(&err) => {
+                    if self.is_cancelled() {
+                        slog::info!(logger, "Task was cancelled");
+                    } else {
+                        slog::warn!(logger, "Task was expired or modified concurrently");
+                    }
+                    shutdown.cancel();
+                    return;
+                }
+                Err(err) => {
+                    slog::warn!(logger, "Cannot ping task"; "error" => err.to_string());
+                    delay = RETRY_DELAY.min(remaining / 2);
+                }
             }
-            slog::debug!(logger, "Pinged task");
         }
     }
 
-    async fn is_expires_after(&self, delta: Duration) -> bool {
+    /// Returns time remaining until task expires.
+    async fn remaining_lease(&self) -> Option<Duration> {
         let task = self.inner.task.lock().await;
-        Self::is_expired(&task, Instant::now() + delta)
+        let now = Instant::now();
+        match task.expire_time {
+            Some(v) if v >= now => Some(elapsed(now, v)),
+            _ => None,
+        }
     }
 
     async fn update(&self, new_task: models::Task, now: Instant) -> Result<models::Task, Error> {
         let mut task = self.inner.stored_task.lock().await;
+        // Expired task can be already taken by another worker.
         if Self::is_expired(&task, now) {
-            return Err("task expired".into());
+            return Err(StoreError::Conflict.into());
         }
-        let event = self
-            .inner
-            .tasks
-            .update_where(
-                Context::new(),
-                new_task,
-                column("kind")
-                    .equal(task.kind)
-                    .and(column("status").equal(task.status))
-                    .and(column("expire_time").equal(task.expire_time)),
-            )
-            .await?;
+        // Version check rejects update if task was changed concurrently.
+        let new_task = models::Task {
+            version: task.version,
+            ..new_task
+        };
+        let event = match self.inner.tasks.update(Context::new(), new_task).await {
+            Ok(v) => v,
+            Err(err) => {
+                if StoreError::is_conflict(&err) {
+                    self.check_cancelled(task.id).await;
+                }
+                return Err(err);
+            }
+        };
         *task = event.into_object();
+        self.inner.subscriptions.notify(&task);
         Ok(task.clone())
     }
 
+    /// Remembers that task was cancelled if it has cancelled status.
+    async fn check_cancelled(&self, id: i64) {
+        if let Ok(Some(task)) = self.inner.tasks.get(Context::new(), id).await {
+            if task.status == TaskStatus::Cancelled {
+                self.inner.cancelled.store(true, Ordering::SeqCst);
+            }
+        }
+    }
+
     fn is_expired(task: &models::Task, now: Instant) -> bool {
         match task.expire_time {
             Some(v) => v < now,
@@ -176,3 +770,84 @@ impl Task {
         }
     }
 }
+
+/// Writes error into `error` field of state keeping other fields.
+fn merge_error(state: &mut JSON, error: models::TaskError) -> Result<(), Error> {
+    state.merge(&JSON::from_serialize(
+        serde_json::json!({ "error": error }),
+    )?);
+    Ok(())
+}
+
+fn parent_failed_error(time: Instant) -> models::TaskError {
+    models::TaskError {
+        message: "Parent task failed".into(),
+        kind: "parent_failed".into(),
+        time,
+    }
+}
+
+/// Fails queued descendants of failed task.
+///
+/// Returns amount of failed tasks.
+async fn fail_children(
+    tasks: &models::TaskStore,
+    mut ctx: Context<'_, '_>,
+    parent_id: i64,
+) -> Result<u64, Error> {
+    let mut parents = vec![parent_id];
+    let mut count = 0;
+    while let Some(parent_id) = parents.pop() {
+        let queued = column("status").equal(TaskStatus::Queued);
+        let children: Vec<_> = {
+            let select =
+                Select::new().with_where(column("parent_id").equal(parent_id).and(queued.clone()));
+            let mut rows = tasks.find(ctx.reborrow(), select).await?;
+            let mut children = Vec::new();
+            while let Some(task) = rows.next().await {
+                children.push(task?);
+            }
+            children
+        };
+        for child in children {
+            let now = Instant::now();
+            let id = child.id;
+            let mut state = child.state.clone();
+            merge_error(&mut state, parent_failed_error(now))?;
+            let new_task = models::Task {
+                status: TaskStatus::Failed,
+                finish_time: Some(now),
+                state,
+                ..child
+            };
+            match tasks
+                .update_where(ctx.reborrow(), new_task, queued.clone())
+                .await
+            {
+                Ok(_) => {
+                    count += 1;
+                    parents.push(id);
+                }
+                Err(err) if StoreError::is_conflict(&err) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    Ok(count)
+}
+
+/// Returns time elapsed between instants or zero if they are reordered.
+fn elapsed(from: Instant, to: Instant) -> Duration {
+    let delta = DateTime::<Utc>::from(to) - DateTime::<Utc>::from(from);
+    delta.to_std().unwrap_or_default()
+}
+
+fn error_kind(err: &Error) -> &'static str {
+    match StoreError::of(err) {
+        Some(StoreError::NotFound) => "not_found",
+        Some(StoreError::Conflict) => "conflict",
+        Some(StoreError::InvalidObject(_)) => "invalid_object",
+        Some(StoreError::Database(_)) => "database",
+        None => "internal",
+    }
+}
@@ -0,0 +1,80 @@
+use solve_db_types::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::core::Error;
+use crate::db::builder::{column, Select};
+use crate::models::{write_tx_options, AsyncIter, Context, ObjectStore, PeriodicTask};
+
+use super::TaskManager;
+
+impl TaskManager {
+    /// Enqueues tasks for periodic definitions until shutdown.
+    ///
+    /// Several schedulers can run concurrently, every run of periodic task
+    /// is enqueued only once.
+    pub async fn run_scheduler(&self, shutdown: CancellationToken) -> Result<(), Error> {
+        loop {
+            self.schedule_periodic(Instant::now()).await?;
+            let sleep = tokio::time::timeout(self.schedule_interval, shutdown.cancelled());
+            if let Ok(()) = sleep.await {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Enqueues tasks for periodic definitions that are due at specified time.
+    ///
+    /// Returns identifiers of enqueued tasks. After downtime only one
+    /// catch-up run is enqueued for every definition.
+    pub async fn schedule_periodic(&self, now: Instant) -> Result<Vec<i64>, Error> {
+        let Some(periodic_tasks) = &self.periodic_tasks else {
+            return Err("Periodic tasks are not configured".into());
+        };
+        let definitions: Vec<_> = {
+            let select = Select::new().with_where(column("enabled").equal(true));
+            let mut rows = periodic_tasks.find(Context::new(), select).await?;
+            let mut definitions = Vec::new();
+            while let Some(definition) = rows.next().await {
+                definitions.push(definition?);
+            }
+            definitions
+        };
+        let mut ids = Vec::new();
+        for definition in definitions {
+            let Some(last_run) = next_run_time(&definition, now)? else {
+                continue;
+            };
+            let mut tx = periodic_tasks.db().transaction(write_tx_options()).await?;
+            if !periodic_tasks
+                .claim_run(Context::new().with_tx(&mut tx), &definition, last_run)
+                .await?
+            {
+                continue;
+            }
+            let id = self
+                .enqueue(
+                    Context::new().with_tx(&mut tx),
+                    definition.kind,
+                    &definition.config,
+                )
+                .await?;
+            tx.commit().await?;
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Returns time of run that should be recorded as last run of periodic task.
+///
+/// Missed runs are collapsed into single run at current time.
+fn next_run_time(task: &PeriodicTask, now: Instant) -> Result<Option<Instant>, Error> {
+    let next_run = match task.next_run(now)? {
+        Some(v) if v <= now => v,
+        _ => return Ok(None),
+    };
+    match task.parse_schedule()?.next_after(next_run) {
+        Some(v) if v > now => Ok(Some(next_run)),
+        _ => Ok(Some(now)),
+    }
+}
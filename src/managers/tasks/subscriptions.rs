@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use solve_db_types::{Instant, JSON};
+use tokio::sync::watch;
+
+use crate::core::Error;
+use crate::models::{self, Context, ObjectStore, StoreError, TaskStatus};
+
+/// Observable part of task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskSnapshot {
+    pub status: TaskStatus,
+    pub state: JSON,
+    pub expire_time: Option<Instant>,
+    pub version: i64,
+}
+
+impl From<&models::Task> for TaskSnapshot {
+    fn from(task: &models::Task) -> Self {
+        Self {
+            status: task.status,
+            state: task.state.clone(),
+            expire_time: task.expire_time,
+            version: task.version,
+        }
+    }
+}
+
+/// Registry of subscriptions to changes of tasks.
+///
+/// Changes made in current process are published immediately, changes made
+/// by other processes are found by periodic polling.
+pub(super) struct Subscriptions {
+    tasks: Arc<models::TaskStore>,
+    poll_interval: Duration,
+    senders: Mutex<HashMap<i64, Arc<watch::Sender<TaskSnapshot>>>>,
+}
+
+impl Subscriptions {
+    pub fn new(tasks: Arc<models::TaskStore>, poll_interval: Duration) -> Self {
+        Self {
+            tasks,
+            poll_interval,
+            senders: Default::default(),
+        }
+    }
+
+    pub async fn subscribe(
+        self: &Arc<Self>,
+        id: i64,
+    ) -> Result<watch::Receiver<TaskSnapshot>, Error> {
+        if let Some(sender) = self.senders.lock().unwrap().get(&id) {
+            return Ok(sender.subscribe());
+        }
+        let task = match self.tasks.get(Context::new(), id).await? {
+            Some(v) => v,
+            None => return Err(StoreError::NotFound.into()),
+        };
+        let mut senders = self.senders.lock().unwrap();
+        // Subscription can be registered concurrently while task is read.
+        if let Some(sender) = senders.get(&id) {
+            let receiver = sender.subscribe();
+            drop(senders);
+            self.notify(&task);
+            return Ok(receiver);
+        }
+        let (sender, receiver) = watch::channel(TaskSnapshot::from(&task));
+        let sender = Arc::new(sender);
+        senders.insert(id, sender.clone());
+        tokio::spawn(self.clone().run_poller(id, sender));
+        Ok(receiver)
+    }
+
+    /// Publishes snapshot of task if it is newer than published one.
+    pub fn notify(&self, task: &models::Task) {
+        let senders = self.senders.lock().unwrap();
+        if let Some(sender) = senders.get(&task.id) {
+            publish(sender, task);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.senders.lock().unwrap().len()
+    }
+
+    async fn run_poller(self: Arc<Self>, id: i64, sender: Arc<watch::Sender<TaskSnapshot>>) {
+        loop {
+            tokio::select! {
+                _ = sender.closed() => {
+                    let mut senders = self.senders.lock().unwrap();
+                    // New receiver can be subscribed before lock is taken.
+                    if sender.receiver_count() == 0 {
+                        senders.remove(&id);
+                        return;
+                    }
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {
+                    if let Ok(Some(task)) = self.tasks.get(Context::new(), id).await {
+                        publish(&sender, &task);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn publish(sender: &watch::Sender<TaskSnapshot>, task: &models::Task) {
+    sender.send_if_modified(|snapshot| {
+        if task.version <= snapshot.version {
+            return false;
+        }
+        *snapshot = TaskSnapshot::from(task);
+        true
+    });
+}
@@ -3,6 +3,9 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 use solve_db::{Database, FromRow, IntoRow, Value};
 
+use crate::core::Error;
+use crate::db::builder::Column;
+
 use super::{object_store_impl, BaseEvent, Object, PersistentStore};
 
 #[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
@@ -57,6 +60,10 @@ impl AccountStore {
             "solve_account_event",
         ))
     }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0.create_tables(vec![Column::big_int("kind")]).await
+    }
 }
 
 object_store_impl!(AccountStore, Account, AccountEvent);
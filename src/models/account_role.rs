@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use solve_db::{Database, FromRow, IntoRow};
+
+use crate::core::Error;
+use crate::db::builder::{column, exists, Column, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Object, ObjectStore,
+    PersistentStore, RoleEdgeStore, RoleSet, RoleStore,
+};
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct AccountRole {
+    pub id: i64,
+    pub account_id: i64,
+    pub role_id: i64,
+}
+
+impl Object for AccountRole {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+}
+
+pub type AccountRoleEvent = BaseEvent<AccountRole>;
+
+pub struct AccountRoleStore(PersistentStore<AccountRole>);
+
+impl AccountRoleStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_account_role",
+            "solve_account_role_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("account_id"),
+                Column::big_int("role_id"),
+            ])
+            .await
+    }
+
+    pub async fn find_by_account<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        account_id: i64,
+    ) -> Result<Vec<AccountRole>, Error> {
+        let mut rows = self
+            .find(
+                ctx,
+                Select::new().with_where(column("account_id").equal(account_id)),
+            )
+            .await?;
+        let mut roles = Vec::new();
+        while let Some(role) = rows.next().await {
+            roles.push(role?);
+        }
+        Ok(roles)
+    }
+
+    /// Assigns role to account if it is not assigned yet.
+    pub async fn assign(
+        &self,
+        ctx: Context<'_, '_>,
+        account_id: i64,
+        role_id: i64,
+    ) -> Result<AccountRoleEvent, Error> {
+        if ctx.tx.is_some() {
+            return self.assign_tx(ctx, account_id, role_id).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let event = self
+            .assign_tx(ctx.with_tx(&mut tx), account_id, role_id)
+            .await?;
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn assign_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        account_id: i64,
+        role_id: i64,
+    ) -> Result<AccountRoleEvent, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let predicate = column("account_id")
+            .equal(account_id)
+            .and(column("role_id").equal(role_id));
+        let count = self
+            .count(Context::new().with_tx(tx), predicate.clone())
+            .await?;
+        if count > 0 {
+            return Err(format!("Account {account_id} already has role {role_id}").into());
+        }
+        let predicate = !exists(
+            Select::new()
+                .with_table(self.0.table())
+                .with_columns(vec![AccountRole::ID.to_owned()])
+                .with_where(predicate),
+        );
+        let role = AccountRole {
+            account_id,
+            role_id,
+            ..Default::default()
+        };
+        self.create_where(ctx.with_tx(tx), role, predicate).await
+    }
+}
+
+object_store_impl!(AccountRoleStore, AccountRole, AccountRoleEvent);
+
+/// Resolves roles of accounts.
+pub struct AccountRoles {
+    account_roles: Arc<AccountRoleStore>,
+    roles: Arc<RoleStore>,
+    role_edges: Arc<RoleEdgeStore>,
+}
+
+impl AccountRoles {
+    pub fn new(
+        account_roles: Arc<AccountRoleStore>,
+        roles: Arc<RoleStore>,
+        role_edges: Arc<RoleEdgeStore>,
+    ) -> Self {
+        Self {
+            account_roles,
+            roles,
+            role_edges,
+        }
+    }
+
+    /// Returns names of roles assigned to account including inherited ones.
+    pub async fn resolve(&self, account_id: i64) -> Result<HashSet<String>, Error> {
+        let role_ids: Vec<_> = self
+            .account_roles
+            .find_by_account(Context::new(), account_id)
+            .await?
+            .into_iter()
+            .map(|v| v.role_id)
+            .collect();
+        let role_set = RoleSet::load(&self.roles, &self.role_edges).await?;
+        Ok(role_set.get_names(&role_ids))
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use solve_db::{IsolationLevel, TransactionOptions, Value};
+use solve_db_types::EventRange;
+use tokio::sync::Mutex;
+
+use crate::core::Error;
+use crate::db::builder::{Predicate, Select};
+
+use super::{
+    AsyncIter, BaseEvent, Context, Event, EventConsumer, EventKind, Object, ObjectStore, Page,
+    PageRequest, PersistentStore, RowsIter, WriteHorizon,
+};
+
+/// Secondary index of cached objects.
+pub trait CacheIndex<O>: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Returns index key of object or none if object is not indexed.
+    fn key(&self, object: &O) -> Option<String>;
+}
+
+struct CacheState<O: Object> {
+    objects: HashMap<O::Id, O>,
+    /// Id of last applied event for every object including deleted ones.
+    versions: HashMap<O::Id, i64>,
+    indexes: Vec<HashMap<String, HashSet<O::Id>>>,
+}
+
+impl<O: Object> CacheState<O> {
+    fn insert(&mut self, object: O, indexes: &[Box<dyn CacheIndex<O>>]) {
+        let id = object.id();
+        for (index, values) in indexes.iter().zip(self.indexes.iter_mut()) {
+            if let Some(key) = index.key(&object) {
+                values.entry(key).or_default().insert(id.clone());
+            }
+        }
+        self.objects.insert(id, object);
+    }
+
+    fn remove(&mut self, id: &O::Id, indexes: &[Box<dyn CacheIndex<O>>]) {
+        let Some(object) = self.objects.remove(id) else {
+            return;
+        };
+        for (index, values) in indexes.iter().zip(self.indexes.iter_mut()) {
+            if let Some(key) = index.key(&object) {
+                if let Some(ids) = values.get_mut(&key) {
+                    ids.remove(id);
+                    if ids.is_empty() {
+                        values.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, event: &BaseEvent<O>, indexes: &[Box<dyn CacheIndex<O>>]) {
+        let id = event.object().id();
+        if let Some(version) = self.versions.get(&id) {
+            if *version >= event.id() {
+                return;
+            }
+        }
+        match event.kind() {
+            EventKind::Create | EventKind::Update => {
+                self.remove(&id, indexes);
+                self.insert(event.object().clone(), indexes);
+            }
+            EventKind::Delete => self.remove(&id, indexes),
+            EventKind::BatchUpdate | EventKind::Unknown(_) => return,
+        }
+        self.versions.insert(id, event.id());
+    }
+}
+
+/// Store that keeps all objects in memory.
+///
+/// Cache is filled by [`CachedStore::init`] and then synchronized with
+/// events by [`CachedStore::sync`]. Writes without transaction are applied
+/// to cache immediately, writes inside transaction become visible after
+/// sync.
+pub struct CachedStore<O: Object> {
+    store: Arc<PersistentStore<O>>,
+    consumer: Mutex<EventConsumer<PersistentStore<O>>>,
+    state: RwLock<CacheState<O>>,
+    indexes: Vec<Box<dyn CacheIndex<O>>>,
+}
+
+impl<O: Object> CachedStore<O> {
+    pub fn new(store: Arc<PersistentStore<O>>) -> Self {
+        Self {
+            consumer: Mutex::new(EventConsumer::new(store.clone())),
+            store,
+            state: RwLock::new(CacheState {
+                objects: Default::default(),
+                versions: Default::default(),
+                indexes: Default::default(),
+            }),
+            indexes: Default::default(),
+        }
+    }
+
+    pub fn with_index<T: CacheIndex<O> + 'static>(mut self, index: T) -> Self {
+        self.indexes.push(Box::new(index));
+        self.state
+            .get_mut()
+            .unwrap()
+            .indexes
+            .push(Default::default());
+        self
+    }
+
+    /// Loads all objects from store.
+    pub async fn init(&self) -> Result<(), Error> {
+        const INIT_CHUNK_SIZE: usize = 1000;
+        let mut tx = self
+            .store
+            .db()
+            .transaction(TransactionOptions {
+                isolation_level: IsolationLevel::RepeatableRead,
+                read_only: true,
+            })
+            .await?;
+        let last_event_id = self
+            .store
+            .last_event_id(Context::new().with_tx(&mut tx))
+            .await?;
+        let mut objects = Vec::new();
+        {
+            let mut rows = self
+                .store
+                .find_chunked(
+                    Context::new().with_tx(&mut tx),
+                    Select::new(),
+                    INIT_CHUNK_SIZE,
+                )
+                .await?;
+            while let Some(object) = rows.next().await {
+                objects.push(object?);
+            }
+        }
+        tx.rollback().await?;
+        let mut consumer = self.consumer.lock().await;
+        let mut state = self.state.write().unwrap();
+        state.objects.clear();
+        state.versions.clear();
+        state.indexes.iter_mut().for_each(HashMap::clear);
+        for object in objects {
+            state.versions.insert(object.id(), last_event_id);
+            state.insert(object, &self.indexes);
+        }
+        *consumer =
+            EventConsumer::new(self.store.clone()).with_range(EventRange::new(last_event_id + 1));
+        Ok(())
+    }
+
+    /// Applies events that were written after last sync.
+    pub async fn sync(&self, mut ctx: Context<'_, '_>) -> Result<(), Error> {
+        const LIMIT: usize = 1000;
+        let mut consumer = self.consumer.lock().await;
+        loop {
+            let events = consumer.poll(ctx.reborrow(), LIMIT).await?;
+            {
+                let mut state = self.state.write().unwrap();
+                for event in &events {
+                    state.apply(event, &self.indexes);
+                }
+            }
+            if events.len() < LIMIT {
+                return Ok(());
+            }
+        }
+    }
+
+    pub fn get_cached(&self, id: &O::Id) -> Option<O> {
+        self.state.read().unwrap().objects.get(id).cloned()
+    }
+
+    /// Returns cached objects matching predicate in arbitrary order.
+    pub fn find_cached<F: Fn(&O) -> bool>(&self, predicate: F) -> Vec<O> {
+        let state = self.state.read().unwrap();
+        state
+            .objects
+            .values()
+            .filter(|v| predicate(v))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns cached objects with specified key of index in arbitrary order.
+    pub fn find_by_index(&self, name: &str, key: &str) -> Vec<O> {
+        let Some(pos) = self.indexes.iter().position(|v| v.name() == name) else {
+            panic!("Unknown index: {name}");
+        };
+        let state = self.state.read().unwrap();
+        match state.indexes[pos].get(key) {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| state.objects.get(id).cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn apply_local(&self, local: bool, event: &BaseEvent<O>) {
+        if local {
+            self.state.write().unwrap().apply(event, &self.indexes);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<O: Object> ObjectStore for CachedStore<O> {
+    type Id = O::Id;
+    type Object = O;
+    type Event = BaseEvent<O>;
+    type FindIter<'a> = RowsIter<'a, O>;
+    type FindEventsIter<'a> = RowsIter<'a, BaseEvent<O>>;
+
+    async fn find<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        select: Select,
+    ) -> Result<Self::FindIter<'a>, Error> {
+        self.store.find(ctx, select).await
+    }
+
+    async fn find_events<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        select: Select,
+    ) -> Result<Self::FindEventsIter<'a>, Error> {
+        self.store.find_events(ctx, select).await
+    }
+
+    async fn last_event_id(&self, ctx: Context<'_, '_>) -> Result<i64, Error> {
+        self.store.last_event_id(ctx).await
+    }
+
+    async fn write_horizon(&self, ctx: Context<'_, '_>) -> Result<Option<WriteHorizon>, Error> {
+        self.store.write_horizon(ctx).await
+    }
+
+    async fn get<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        id: Self::Id,
+    ) -> Result<Option<Self::Object>, Error> {
+        self.store.get(ctx, id).await
+    }
+
+    async fn get_many<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        ids: &[O::Id],
+    ) -> Result<HashMap<O::Id, O>, Error> {
+        self.store.get_many(ctx, ids).await
+    }
+
+    async fn find_page<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        request: PageRequest,
+    ) -> Result<Page<O>, Error> {
+        self.store.find_page(ctx, request).await
+    }
+
+    async fn count<'a>(&'a self, ctx: Context<'a, '_>, predicate: Predicate) -> Result<i64, Error> {
+        self.store.count(ctx, predicate).await
+    }
+
+    async fn exists<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        predicate: Predicate,
+    ) -> Result<bool, Error> {
+        self.store.exists(ctx, predicate).await
+    }
+
+    async fn create(&self, ctx: Context<'_, '_>, object: O) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.create(ctx, object).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+
+    async fn create_batch(
+        &self,
+        ctx: Context<'_, '_>,
+        objects: Vec<O>,
+    ) -> Result<Vec<Self::Event>, Error> {
+        let local = ctx.tx.is_none();
+        let events = self.store.create_batch(ctx, objects).await?;
+        events.iter().for_each(|v| self.apply_local(local, v));
+        Ok(events)
+    }
+
+    async fn create_where(
+        &self,
+        ctx: Context<'_, '_>,
+        object: O,
+        predicate: Predicate,
+    ) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.create_where(ctx, object, predicate).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+
+    async fn update(&self, ctx: Context<'_, '_>, object: O) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.update(ctx, object).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+
+    async fn update_where(
+        &self,
+        ctx: Context<'_, '_>,
+        object: O,
+        predicate: Predicate,
+    ) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.update_where(ctx, object, predicate).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+
+    /// Updated objects are applied to cache on next sync only if
+    /// `emit_events` is set, otherwise they are loaded on next init.
+    async fn update_fields_where(
+        &self,
+        ctx: Context<'_, '_>,
+        set: Vec<(String, Value)>,
+        predicate: Predicate,
+        emit_events: bool,
+    ) -> Result<u64, Error> {
+        self.store
+            .update_fields_where(ctx, set, predicate, emit_events)
+            .await
+    }
+
+    async fn delete(&self, ctx: Context<'_, '_>, id: O::Id) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.delete(ctx, id).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+
+    async fn delete_where(
+        &self,
+        ctx: Context<'_, '_>,
+        id: O::Id,
+        predicate: Predicate,
+    ) -> Result<Self::Event, Error> {
+        let local = ctx.tx.is_none();
+        let event = self.store.delete_where(ctx, id, predicate).await?;
+        self.apply_local(local, &event);
+        Ok(event)
+    }
+}
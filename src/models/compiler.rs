@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::JSON;
+
+use crate::core::Error;
+use crate::db::builder::{column, Column, Select};
+
+use super::{
+    object_store_impl, AsyncIter, BaseEvent, Context, Object, ObjectStore, PersistentStore,
+};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompilerConfig {
+    pub language: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compile_command: Option<String>,
+    pub run_command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environ: Vec<String>,
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Compiler {
+    pub id: i64,
+    pub name: String,
+    pub config: JSON,
+    pub image_file_id: Option<i64>,
+}
+
+impl Compiler {
+    pub fn set_config(&mut self, config: &CompilerConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_config(&self) -> Result<CompilerConfig, Error> {
+        self.config.parse_as()
+    }
+}
+
+impl Object for Compiler {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+pub type CompilerEvent = BaseEvent<Compiler>;
+
+pub struct CompilerStore(PersistentStore<Compiler>);
+
+impl CompilerStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_compiler",
+            "solve_compiler_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::text("name"),
+                Column::text("config"),
+                Column::big_int("image_file_id").nullable(),
+            ])
+            .await
+    }
+
+    pub async fn get_by_name<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        name: &str,
+    ) -> Result<Option<Compiler>, Error> {
+        let mut iter = self
+            .find(
+                ctx,
+                Select::new()
+                    .with_where(column("name").equal(name))
+                    .with_limit(1),
+            )
+            .await?;
+        match iter.next().await {
+            Some(v) => Ok(Some(v?)),
+            None => Ok(None),
+        }
+    }
+}
+
+object_store_impl!(CompilerStore, Compiler, CompilerEvent);
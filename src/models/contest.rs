@@ -0,0 +1,139 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::{DurationMs, Instant, JSON};
+
+use crate::core::Error;
+use crate::db::builder::Column;
+
+use super::{object_store_impl, BaseEvent, Context, Object, PersistentStore, SoftDelete};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContestStage {
+    NotStarted,
+    Started,
+    Finished,
+}
+
+impl std::fmt::Display for ContestStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContestConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub begin_time: Option<Instant>,
+    #[serde(default)]
+    pub duration: DurationMs,
+    #[serde(default)]
+    pub enable_registration: bool,
+    #[serde(default)]
+    pub enable_upsolving: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze_time: Option<Instant>,
+}
+
+impl ContestConfig {
+    /// Returns stage of contest at specified time.
+    ///
+    /// Contest without begin time is never started.
+    pub fn stage(&self, now: Instant) -> ContestStage {
+        let Some(begin_time) = self.begin_time else {
+            return ContestStage::NotStarted;
+        };
+        if now < begin_time {
+            ContestStage::NotStarted
+        } else if now < begin_time + self.duration {
+            ContestStage::Started
+        } else {
+            ContestStage::Finished
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Contest {
+    pub id: i64,
+    pub owner_id: Option<i64>,
+    pub config: JSON,
+    pub title: String,
+    pub create_time: Instant,
+    pub deleted_at: Option<Instant>,
+}
+
+impl Contest {
+    pub fn set_config(&mut self, config: &ContestConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_config(&self) -> Result<ContestConfig, Error> {
+        self.config.parse_as()
+    }
+
+    pub fn stage(&self, now: Instant) -> Result<ContestStage, Error> {
+        Ok(self.parse_config()?.stage(now))
+    }
+}
+
+impl Object for Contest {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.title.is_empty()
+    }
+}
+
+impl SoftDelete for Contest {
+    fn deleted_at(&self) -> Option<Instant> {
+        self.deleted_at
+    }
+
+    fn set_deleted_at(&mut self, time: Option<Instant>) {
+        self.deleted_at = time;
+    }
+}
+
+pub type ContestEvent = BaseEvent<Contest>;
+
+pub struct ContestStore(PersistentStore<Contest>);
+
+impl ContestStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_contest",
+            "solve_contest_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("owner_id").nullable(),
+                Column::text("config"),
+                Column::text("title"),
+                Column::big_int("create_time"),
+                Column::big_int("deleted_at").nullable(),
+            ])
+            .await
+    }
+
+    pub async fn soft_delete(&self, ctx: Context<'_, '_>, id: i64) -> Result<ContestEvent, Error> {
+        self.0.soft_delete(ctx, id).await
+    }
+}
+
+object_store_impl!(ContestStore, Contest, ContestEvent);
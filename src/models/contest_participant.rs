@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow, Value};
+use solve_db_types::{Instant, JSON};
+
+use crate::core::Error;
+use crate::db::builder::{column, exists, Column, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Contest, ContestStage, Context,
+    Event, Object, ObjectStore, PersistentStore,
+};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
+#[repr(i8)]
+#[serde(rename_all = "snake_case")]
+pub enum ContestParticipantKind {
+    #[default]
+    Regular = 1,
+    Upsolving = 2,
+    Manager = 3,
+    Observer = 4,
+    Unknown(i8),
+}
+
+impl std::fmt::Display for ContestParticipantKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct ContestParticipant {
+    pub id: i64,
+    pub contest_id: i64,
+    pub account_id: i64,
+    pub kind: ContestParticipantKind,
+    pub config: JSON,
+}
+
+impl Object for ContestParticipant {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !matches!(self.kind, ContestParticipantKind::Unknown(_))
+    }
+}
+
+pub type ContestParticipantEvent = BaseEvent<ContestParticipant>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegisterError {
+    RegistrationDisabled,
+    UpsolvingDisabled,
+    ContestNotFinished,
+    ContestFinished,
+    AlreadyRegistered,
+}
+
+impl std::fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::RegistrationDisabled => "Registration is disabled",
+            Self::UpsolvingDisabled => "Upsolving is disabled",
+            Self::ContestNotFinished => "Contest is not finished",
+            Self::ContestFinished => "Contest is finished",
+            Self::AlreadyRegistered => "Account is already registered",
+        })
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+pub struct ContestParticipantStore(PersistentStore<ContestParticipant>);
+
+impl ContestParticipantStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_contest_participant",
+            "solve_contest_participant_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("contest_id"),
+                Column::big_int("account_id"),
+                Column::big_int("kind"),
+                Column::text("config"),
+            ])
+            .await
+    }
+
+    pub async fn find_by_contest_account<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        contest_id: i64,
+        account_id: i64,
+    ) -> Result<Vec<ContestParticipant>, Error> {
+        let mut rows = self
+            .find(
+                ctx,
+                Select::new().with_where(
+                    column("contest_id")
+                        .equal(contest_id)
+                        .and(column("account_id").equal(account_id)),
+                ),
+            )
+            .await?;
+        let mut participants = Vec::new();
+        while let Some(participant) = rows.next().await {
+            participants.push(participant?);
+        }
+        Ok(participants)
+    }
+
+    /// Registers account as participant of contest.
+    ///
+    /// Regular participants can register only when registration is enabled
+    /// and contest is not finished, upsolving participants only when
+    /// upsolving is enabled and contest is finished. Errors caused by
+    /// contest settings are reported as [`RegisterError`].
+    pub async fn register(
+        &self,
+        ctx: Context<'_, '_>,
+        contest: &Contest,
+        account_id: i64,
+        kind: ContestParticipantKind,
+    ) -> Result<ContestParticipant, Error> {
+        let config = contest.parse_config()?;
+        let stage = config.stage(Instant::now());
+        match kind {
+            ContestParticipantKind::Regular => {
+                if !config.enable_registration {
+                    return Err(RegisterError::RegistrationDisabled.into());
+                }
+                if stage == ContestStage::Finished {
+                    return Err(RegisterError::ContestFinished.into());
+                }
+            }
+            ContestParticipantKind::Upsolving => {
+                if !config.enable_upsolving {
+                    return Err(RegisterError::UpsolvingDisabled.into());
+                }
+                if stage != ContestStage::Finished {
+                    return Err(RegisterError::ContestNotFinished.into());
+                }
+            }
+            _ => {}
+        }
+        let participant = ContestParticipant {
+            contest_id: contest.id,
+            account_id,
+            kind,
+            ..Default::default()
+        };
+        if ctx.tx.is_some() {
+            return self.register_tx(ctx, participant).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let participant = self.register_tx(ctx.with_tx(&mut tx), participant).await?;
+        tx.commit().await?;
+        Ok(participant)
+    }
+
+    async fn register_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        participant: ContestParticipant,
+    ) -> Result<ContestParticipant, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let predicate = column("contest_id")
+            .equal(participant.contest_id)
+            .and(column("account_id").equal(participant.account_id))
+            .and(column("kind").equal(participant.kind));
+        let count = self
+            .count(Context::new().with_tx(tx), predicate.clone())
+            .await?;
+        if count > 0 {
+            return Err(RegisterError::AlreadyRegistered.into());
+        }
+        let predicate = !exists(
+            Select::new()
+                .with_table(self.0.table())
+                .with_columns(vec![ContestParticipant::ID.to_owned()])
+                .with_where(predicate),
+        );
+        let event = self
+            .create_where(ctx.with_tx(tx), participant, predicate)
+            .await?;
+        Ok(event.into_object())
+    }
+}
+
+object_store_impl!(
+    ContestParticipantStore,
+    ContestParticipant,
+    ContestParticipantEvent
+);
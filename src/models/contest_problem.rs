@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::{Score, JSON};
+
+use crate::core::Error;
+use crate::db::builder::{column, exists, Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Object, ObjectStore,
+    PersistentStore,
+};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContestProblemConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points: Option<Score>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locales: Vec<String>,
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct ContestProblem {
+    pub id: i64,
+    pub contest_id: i64,
+    pub problem_id: i64,
+    pub code: String,
+    pub config: JSON,
+}
+
+impl ContestProblem {
+    pub fn set_config(&mut self, config: &ContestProblemConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_config(&self) -> Result<ContestProblemConfig, Error> {
+        self.config.parse_as()
+    }
+}
+
+impl Object for ContestProblem {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.code.is_empty()
+    }
+}
+
+pub type ContestProblemEvent = BaseEvent<ContestProblem>;
+
+pub struct ContestProblemStore(PersistentStore<ContestProblem>);
+
+impl ContestProblemStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_contest_problem",
+            "solve_contest_problem_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("contest_id"),
+                Column::big_int("problem_id"),
+                Column::text("code"),
+                Column::text("config"),
+            ])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_contest_problem_code_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["contest_id".to_owned(), "code".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns problems of contest ordered by code.
+    pub async fn find_by_contest<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        contest_id: i64,
+    ) -> Result<Vec<ContestProblem>, Error> {
+        let mut rows = self
+            .find(
+                ctx,
+                Select::new().with_where(column("contest_id").equal(contest_id)),
+            )
+            .await?;
+        let mut problems = Vec::new();
+        while let Some(problem) = rows.next().await {
+            problems.push(problem?);
+        }
+        problems.sort_by(|a, b| a.code.cmp(&b.code));
+        Ok(problems)
+    }
+
+    /// Creates problem if contest has no other problem with the same code.
+    pub async fn create_problem(
+        &self,
+        ctx: Context<'_, '_>,
+        problem: ContestProblem,
+    ) -> Result<ContestProblemEvent, Error> {
+        if ctx.tx.is_some() {
+            return self.create_problem_tx(ctx, problem).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let event = self
+            .create_problem_tx(ctx.with_tx(&mut tx), problem)
+            .await?;
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn create_problem_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        problem: ContestProblem,
+    ) -> Result<ContestProblemEvent, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let predicate = column("contest_id")
+            .equal(problem.contest_id)
+            .and(column("code").equal(problem.code.clone()));
+        let count = self
+            .count(Context::new().with_tx(tx), predicate.clone())
+            .await?;
+        if count > 0 {
+            return Err(format!(
+                "Contest {} already has problem with code {:?}",
+                problem.contest_id, problem.code
+            )
+            .into());
+        }
+        let predicate = !exists(
+            Select::new()
+                .with_table(self.0.table())
+                .with_columns(vec![ContestProblem::ID.to_owned()])
+                .with_where(predicate),
+        );
+        self.create_where(ctx.with_tx(tx), problem, predicate).await
+    }
+
+    /// Deletes all problems of contest.
+    ///
+    /// Returns number of deleted problems.
+    pub async fn delete_by_contest(
+        &self,
+        ctx: Context<'_, '_>,
+        contest_id: i64,
+    ) -> Result<usize, Error> {
+        if ctx.tx.is_some() {
+            return self.delete_by_contest_tx(ctx, contest_id).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let count = self
+            .delete_by_contest_tx(ctx.with_tx(&mut tx), contest_id)
+            .await?;
+        tx.commit().await?;
+        Ok(count)
+    }
+
+    async fn delete_by_contest_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        contest_id: i64,
+    ) -> Result<usize, Error> {
+        assert!(ctx.tx.is_some(), "transaction is required");
+        let problems = self.find_by_contest(ctx.reborrow(), contest_id).await?;
+        for problem in &problems {
+            self.delete(ctx.reborrow(), problem.id).await?;
+        }
+        Ok(problems.len())
+    }
+}
+
+object_store_impl!(ContestProblemStore, ContestProblem, ContestProblemEvent);
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use solve_db_types::{EventRange, Instant};
+
+use crate::core::Error;
+use crate::db::builder::{column, Select};
+
+use super::{AsyncIter, Context, Event, ObjectStore};
+
+/// Maximal amount of gap ids that are polled with single query.
+const GAP_CHUNK_SIZE: usize = 500;
+
+/// Maximal amount of events that are read with single query.
+const READ_CHUNK_SIZE: usize = 1000;
+
+type SaveFn = Box<dyn Fn(&EventRange) -> Result<(), Error> + Send + Sync>;
+
+/// Consumer of events written by store.
+///
+/// Event ids are allocated before commit, so events can become visible out
+/// of order. Skipped ids are tracked as gaps and polled again until they
+/// appear or their writers are finished, so every committed event is
+/// delivered at least once. Stores without write horizon fall back to gap
+/// window that expires gaps after specified time.
+///
+/// Consumer without restored position starts from events written after its
+/// first poll.
+pub struct EventConsumer<S: ObjectStore> {
+    store: Arc<S>,
+    range: Option<EventRange>,
+    gap_window: Duration,
+    save_fn: Option<SaveFn>,
+}
+
+impl<S: ObjectStore + Sync> EventConsumer<S> {
+    pub fn new(store: Arc<S>) -> Self {
+        Self {
+            store,
+            range: None,
+            gap_window: Duration::from_secs(60),
+            save_fn: None,
+        }
+    }
+
+    /// Restores position of consumer.
+    pub fn with_range(mut self, range: EventRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    /// Sets how long skipped event ids are waited for if store has no
+    /// write horizon.
+    pub fn with_gap_window(mut self, gap_window: Duration) -> Self {
+        self.gap_window = gap_window;
+        self
+    }
+
+    /// Sets function that is called with new position after every poll.
+    pub fn with_save_fn<F>(mut self, save_fn: F) -> Self
+    where
+        F: Fn(&EventRange) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.save_fn = Some(Box::new(save_fn));
+        self
+    }
+
+    /// Returns position of consumer if it is restored or polled.
+    pub fn range(&self) -> Option<&EventRange> {
+        self.range.as_ref()
+    }
+
+    /// Returns at most `limit` events that were not consumed yet.
+    pub async fn poll(
+        &mut self,
+        mut ctx: Context<'_, '_>,
+        limit: usize,
+    ) -> Result<Vec<S::Event>, Error> {
+        // Horizon is taken before polling, so writers of gaps that are
+        // finished before it have their events visible to poll.
+        let horizon = self.store.write_horizon(ctx.reborrow()).await?;
+        let range = match &mut self.range {
+            Some(range) => range,
+            None => {
+                let last_event_id = self.store.last_event_id(ctx.reborrow()).await?;
+                self.range.insert(EventRange::new(last_event_id + 1))
+            }
+        };
+        if let Some(horizon) = horizon {
+            range.set_horizon(horizon.xmax);
+        }
+        let id_column = <S::Event as Event>::ID;
+        let gaps: Vec<_> = range.gaps().collect();
+        let mut predicates: Vec<_> = gaps
+            .chunks(GAP_CHUNK_SIZE)
+            .map(|chunk| column(id_column).in_values(chunk.to_vec()))
+            .collect();
+        predicates.push(column(id_column).greater_equal(range.end()));
+        let now = Instant::now();
+        let mut events = Vec::new();
+        for predicate in predicates {
+            if events.len() >= limit {
+                break;
+            }
+            let chunk_size = (limit - events.len()).min(READ_CHUNK_SIZE);
+            let mut rows = self
+                .store
+                .find_events_chunked(
+                    ctx.reborrow(),
+                    Select::new().with_where(predicate),
+                    chunk_size,
+                )
+                .await?;
+            while events.len() < limit {
+                let Some(event) = rows.next().await else {
+                    break;
+                };
+                let event = event?;
+                if range.add_at(event.id(), now) {
+                    events.push(event);
+                }
+            }
+        }
+        match horizon {
+            Some(horizon) => range.expire_finished_gaps(horizon.xmin),
+            None => range.expire_gaps(now, self.gap_window),
+        }
+        if let Some(save_fn) = &self.save_fn {
+            save_fn(range)?;
+        }
+        Ok(events)
+    }
+}
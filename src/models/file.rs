@@ -1,11 +1,14 @@
 use std::sync::Arc;
 
 use crate::core::Error;
+use crate::db::builder::{column, Column, CreateIndex, Select};
 use serde::{Deserialize, Serialize};
 use solve_db::{Database, FromRow, IntoRow, Value};
 use solve_db_types::{Instant, JSON};
 
-use super::{object_store_impl, BaseEvent, Object, PersistentStore};
+use super::{
+    object_store_impl, AsyncIter, BaseEvent, Context, Object, ObjectStore, PersistentStore,
+};
 
 #[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
 #[repr(i8)]
@@ -23,12 +26,25 @@ impl std::fmt::Display for FileStatus {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    ProblemPackage,
+    SolutionSource,
+    CompilerImage,
+    Other,
+}
+
 #[derive(Clone, Default, Debug, Serialize, Deserialize)]
 pub struct FileMeta {
     pub name: String,
     pub size: Option<u64>,
     pub md5: Option<String>,
     pub sha3_224: Option<String>,
+    pub content_type: Option<String>,
+    pub kind: Option<FileKind>,
+    /// Account that uploaded file.
+    pub account_id: Option<i64>,
 }
 
 impl std::fmt::Display for FileMeta {
@@ -44,16 +60,23 @@ pub struct File {
     pub expire_time: Option<Instant>,
     pub path: String,
     pub meta: JSON,
+    /// Account that uploaded file, copied from meta.
+    pub account_id: Option<i64>,
+    /// Size of file in bytes, copied from meta.
+    pub size: Option<i64>,
 }
 
 impl File {
+    /// Sets meta and columns that duplicate it.
     pub fn set_meta(&mut self, meta: &FileMeta) -> Result<(), Error> {
-        self.meta = serde_json::to_value(meta)?.into();
+        self.meta = JSON::from_serialize(meta)?;
+        self.account_id = meta.account_id;
+        self.size = meta.size.map(i64::try_from).transpose()?;
         Ok(())
     }
 
     pub fn parse_meta(&self) -> Result<FileMeta, Error> {
-        Ok(serde_json::from_value(self.meta.clone().into())?)
+        self.meta.parse_as()
     }
 }
 
@@ -81,6 +104,41 @@ impl FileStore {
     pub fn new(db: Arc<Database>) -> Self {
         Self(PersistentStore::new(db, "solve_file", "solve_file_event"))
     }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("status"),
+                Column::big_int("expire_time").nullable(),
+                Column::text("path"),
+                Column::text("meta"),
+                Column::big_int("account_id").nullable(),
+                Column::big_int("size").nullable(),
+            ])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_file_account_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["account_id".to_owned()]),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns total size of available files uploaded by account.
+    pub async fn account_usage(&self, ctx: Context<'_, '_>, account_id: i64) -> Result<u64, Error> {
+        let predicate = column("account_id")
+            .equal(account_id)
+            .and(column("status").equal(FileStatus::Available));
+        let mut rows = self.find(ctx, Select::new().with_where(predicate)).await?;
+        let mut usage = 0;
+        while let Some(file) = rows.next().await {
+            usage += u64::try_from(file?.size.unwrap_or_default())?;
+        }
+        Ok(usage)
+    }
 }
 
 object_store_impl!(FileStore, File, FileEvent);
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::core::Error;
+
+use super::StoreError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StoreOperation {
+    Find,
+    Create,
+    Update,
+    Delete,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StoreOutcome {
+    Success,
+    Conflict,
+    Error,
+}
+
+impl StoreOutcome {
+    pub fn of<T>(result: &Result<T, Error>) -> Self {
+        match result {
+            Ok(_) => StoreOutcome::Success,
+            Err(err) if StoreError::is_conflict(err) => StoreOutcome::Conflict,
+            Err(_) => StoreOutcome::Error,
+        }
+    }
+}
+
+/// Sink for metrics of store operations labeled by table name.
+pub trait StoreMetrics: Send + Sync {
+    /// Records completed operation.
+    fn observe(
+        &self,
+        table: &str,
+        operation: StoreOperation,
+        outcome: StoreOutcome,
+        duration: Duration,
+    );
+
+    /// Records rows returned by find.
+    fn add_rows(&self, table: &str, rows: u64);
+}
+
+/// Upper bounds of latency histogram buckets.
+pub const LATENCY_BUCKETS: &[Duration] = &[
+    Duration::from_millis(1),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(5),
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OperationStats {
+    pub count: u64,
+    pub conflicts: u64,
+    pub errors: u64,
+    /// Amount of operations per latency bucket with one extra bucket for
+    /// operations slower than all bounds.
+    pub latency_buckets: Vec<u64>,
+    pub latency_sum: Duration,
+}
+
+#[derive(Default)]
+struct MetricsState {
+    operations: HashMap<(String, StoreOperation), OperationStats>,
+    rows: HashMap<String, u64>,
+}
+
+/// Metrics registry that keeps all values in memory.
+#[derive(Default)]
+pub struct MemoryStoreMetrics {
+    state: Mutex<MetricsState>,
+}
+
+impl MemoryStoreMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stats(&self, table: &str, operation: StoreOperation) -> OperationStats {
+        let state = self.state.lock().unwrap();
+        state
+            .operations
+            .get(&(table.to_owned(), operation))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn rows(&self, table: &str) -> u64 {
+        let state = self.state.lock().unwrap();
+        state.rows.get(table).copied().unwrap_or_default()
+    }
+}
+
+impl StoreMetrics for MemoryStoreMetrics {
+    fn observe(
+        &self,
+        table: &str,
+        operation: StoreOperation,
+        outcome: StoreOutcome,
+        duration: Duration,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let stats = state
+            .operations
+            .entry((table.to_owned(), operation))
+            .or_default();
+        stats.count += 1;
+        match outcome {
+            StoreOutcome::Success => {}
+            StoreOutcome::Conflict => stats.conflicts += 1,
+            StoreOutcome::Error => stats.errors += 1,
+        }
+        if stats.latency_buckets.is_empty() {
+            stats.latency_buckets = vec![0; LATENCY_BUCKETS.len() + 1];
+        }
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|v| duration <= *v)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        stats.latency_buckets[bucket] += 1;
+        stats.latency_sum += duration;
+    }
+
+    fn add_rows(&self, table: &str, rows: u64) {
+        let mut state = self.state.lock().unwrap();
+        *state.rows.entry(table.to_owned()).or_default() += rows;
+    }
+}
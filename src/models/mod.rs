@@ -1,17 +1,51 @@
 mod account;
+mod account_role;
+mod cached_store;
+mod compiler;
+mod contest;
+mod contest_participant;
+mod contest_problem;
+mod event_consumer;
 mod file;
+mod metrics;
 mod object;
+mod page;
+mod periodic_task;
 mod persistent_store;
 mod problem;
+mod problem_resource;
+mod problem_statement;
+mod role;
+mod session;
+mod setting;
 mod solution;
 mod store;
 mod task;
+mod token;
+mod user;
 
 pub use account::*;
+pub use account_role::*;
+pub use cached_store::*;
+pub use compiler::*;
+pub use contest::*;
+pub use contest_participant::*;
+pub use contest_problem::*;
+pub use event_consumer::*;
 pub use file::*;
+pub use metrics::*;
 pub use object::*;
+pub use page::*;
+pub use periodic_task::*;
 pub use persistent_store::*;
 pub use problem::*;
+pub use problem_resource::*;
+pub use problem_statement::*;
+pub use role::*;
+pub use session::*;
+pub use setting::*;
 pub use solution::*;
 pub use store::*;
 pub use task::*;
+pub use token::*;
+pub use user::*;
@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::hash::Hash;
 
 use crate::core::Error;
 use crate::db::builder::Expression;
@@ -8,10 +9,22 @@ use solve_db::{FromRow, IntoRow, IntoValue, Row, Value};
 use solve_db_types::Instant;
 
 pub trait Object: FromRow + IntoRow + Default + Clone + Send + Sync + 'static {
-    type Id: Clone + Into<Expression> + Default + Display + Send + Sync + PartialEq + 'static;
+    type Id: Clone
+        + Into<Expression>
+        + IntoValue
+        + Default
+        + Display
+        + Send
+        + Sync
+        + Eq
+        + Hash
+        + 'static;
 
     const ID: &'static str = "id";
 
+    /// Whether id is generated by database instead of application.
+    const GENERATED_ID: bool = true;
+
     fn id(&self) -> Self::Id;
 
     fn set_id(&mut self, id: Self::Id);
@@ -28,6 +41,28 @@ pub trait Object: FromRow + IntoRow + Default + Clone + Send + Sync + 'static {
     }
 }
 
+pub(super) const DELETED_AT_COLUMN: &str = "deleted_at";
+
+/// Object that is hidden instead of being removed on deletion.
+///
+/// Stores hide objects with non-null `deleted_at` column from reads.
+pub trait SoftDelete: Object {
+    fn deleted_at(&self) -> Option<Instant>;
+
+    fn set_deleted_at(&mut self, time: Option<Instant>);
+}
+
+pub(super) const VERSION_COLUMN: &str = "version";
+
+/// Object with version that is checked and incremented on every update.
+///
+/// Stores detect versioned objects by `version` column.
+pub trait Versioned: Object {
+    fn version(&self) -> i64;
+
+    fn set_version(&mut self, version: i64);
+}
+
 pub trait Event: FromRow + IntoRow + Default + Clone + Send + Sync + 'static {
     type Object: Object;
 
@@ -57,6 +92,13 @@ pub trait Event: FromRow + IntoRow + Default + Clone + Send + Sync + 'static {
 
     fn set_object(&mut self, object: Self::Object);
 
+    /// Returns object state before event.
+    ///
+    /// Previous state is known only for events returned by writes.
+    fn prev_object(&self) -> Option<&Self::Object> {
+        None
+    }
+
     fn columns() -> Vec<String> {
         IntoRow::into_row(Self::default())
             .into_iter()
@@ -72,6 +114,8 @@ pub enum EventKind {
     Create = 1,
     Delete = 2,
     Update = 3,
+    /// Marks objects updated in batch without events of objects.
+    BatchUpdate = 4,
     Unknown(i8),
 }
 
@@ -88,6 +132,7 @@ pub struct BaseEvent<O> {
     account_id: Option<i64>,
     kind: EventKind,
     object: O,
+    prev_object: Option<O>,
 }
 
 impl<O: Object> BaseEvent<O> {
@@ -107,13 +152,25 @@ impl<O: Object> BaseEvent<O> {
         }
     }
 
-    pub fn delete(id: O::Id) -> Self {
-        let mut value = Self {
+    pub fn delete(object: O) -> Self {
+        Self {
             kind: EventKind::Delete,
+            object,
             ..Default::default()
-        };
-        value.mut_object().set_id(id);
-        value
+        }
+    }
+
+    /// Creates marker event with default object.
+    pub fn batch_update() -> Self {
+        Self {
+            kind: EventKind::BatchUpdate,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_prev_object(mut self, prev_object: O) -> Self {
+        self.prev_object = Some(prev_object);
+        self
     }
 }
 
@@ -125,6 +182,7 @@ impl<O: Object> Default for BaseEvent<O> {
             account_id: Default::default(),
             kind: EventKind::Create,
             object: Default::default(),
+            prev_object: None,
         }
     }
 }
@@ -137,6 +195,7 @@ impl<O: Object> FromRow for BaseEvent<O> {
             account_id: row.get_parsed("event_account_id")?,
             kind: row.get_parsed("event_kind")?,
             object: FromRow::from_row(row)?,
+            prev_object: None,
         })
     }
 }
@@ -202,4 +261,8 @@ impl<O: Object<Id = I>, I> Event for BaseEvent<O> {
     fn set_object(&mut self, object: O) {
         self.object = object
     }
+
+    fn prev_object(&self) -> Option<&O> {
+        self.prev_object.as_ref()
+    }
 }
@@ -0,0 +1,214 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use solve_db::Value;
+
+use crate::core::Error;
+use crate::db::builder::{column, Expression, Order, Predicate};
+
+/// Opaque position after last object of page.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cursor(String);
+
+impl Cursor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Cursor {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Cursor {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl std::fmt::Display for Cursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PageRequest {
+    pub predicate: Predicate,
+    /// Sort keys of page. Object id is always used as last key.
+    pub order: Vec<Order>,
+    pub limit: usize,
+    pub cursor: Option<Cursor>,
+}
+
+impl PageRequest {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            predicate: true.into(),
+            order: Vec::new(),
+            limit,
+            cursor: None,
+        }
+    }
+
+    pub fn with_where<T: Into<Predicate>>(mut self, predicate: T) -> Self {
+        self.predicate = predicate.into();
+        self
+    }
+
+    pub fn with_order(mut self, order: Vec<Order>) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn with_cursor(mut self, cursor: Option<Cursor>) -> Self {
+        self.cursor = cursor;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Page<O> {
+    pub objects: Vec<O>,
+    /// Cursor of next page or none if this page is last.
+    pub next_cursor: Option<Cursor>,
+}
+
+const CURSOR_MAC_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize)]
+enum CursorValue {
+    #[serde(rename = "b")]
+    Bool(bool),
+    #[serde(rename = "i")]
+    BigInt(i64),
+    #[serde(rename = "f")]
+    Double(f64),
+    #[serde(rename = "t")]
+    Text(String),
+    #[serde(rename = "x")]
+    Blob(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorPayload {
+    order: Vec<(String, bool)>,
+    values: Vec<CursorValue>,
+}
+
+/// Returns sort keys of page with id column as last key.
+pub(super) fn page_order(order: &[Order], id: &str) -> Vec<Order> {
+    let mut order = order.to_vec();
+    if !order.iter().any(|v| v.column() == id) {
+        match order.last() {
+            Some(Order::Desc(_)) => order.push(Order::desc(id)),
+            _ => order.push(Order::asc(id)),
+        }
+    }
+    order
+}
+
+fn cursor_mac(key: &[u8], scope: &str, payload: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key size");
+    mac.update(scope.as_bytes());
+    mac.update(&[0]);
+    mac.update(payload);
+    mac
+}
+
+pub(super) fn encode_cursor(
+    key: &[u8],
+    scope: &str,
+    order: &[Order],
+    values: Vec<Value>,
+) -> Result<Cursor, Error> {
+    let mut payload = CursorPayload {
+        order: order
+            .iter()
+            .map(|v| (v.column().to_owned(), v.is_desc()))
+            .collect(),
+        values: Vec::with_capacity(values.len()),
+    };
+    for (order, value) in order.iter().zip(values) {
+        payload.values.push(match value {
+            Value::Null => {
+                return Err(format!("Cannot paginate by null column: {}", order.column()).into())
+            }
+            Value::Bool(v) => CursorValue::Bool(v),
+            Value::BigInt(v) => CursorValue::BigInt(v),
+            Value::Double(v) => CursorValue::Double(v),
+            Value::Text(v) => CursorValue::Text(v),
+            Value::Blob(v) => CursorValue::Blob(URL_SAFE_NO_PAD.encode(v)),
+        });
+    }
+    let mut data = serde_json::to_vec(&payload)?;
+    let mac = cursor_mac(key, scope, &data).finalize().into_bytes();
+    data.extend_from_slice(&mac[..CURSOR_MAC_LEN]);
+    Ok(Cursor(URL_SAFE_NO_PAD.encode(data)))
+}
+
+pub(super) fn decode_cursor(
+    key: &[u8],
+    scope: &str,
+    order: &[Order],
+    cursor: &Cursor,
+) -> Result<Vec<Value>, Error> {
+    let data = URL_SAFE_NO_PAD
+        .decode(cursor.as_str())
+        .map_err(|_| "Invalid cursor")?;
+    if data.len() < CURSOR_MAC_LEN {
+        return Err("Invalid cursor".into());
+    }
+    let (data, tag) = data.split_at(data.len() - CURSOR_MAC_LEN);
+    cursor_mac(key, scope, data)
+        .verify_truncated_left(tag)
+        .map_err(|_| "Invalid cursor")?;
+    let payload: CursorPayload = serde_json::from_slice(data).map_err(|_| "Invalid cursor")?;
+    let same_order = payload.order.len() == order.len()
+        && payload
+            .order
+            .iter()
+            .zip(order)
+            .all(|(l, r)| l.0 == r.column() && l.1 == r.is_desc());
+    if !same_order || payload.values.len() != order.len() {
+        return Err("Cursor does not match page order".into());
+    }
+    let mut values = Vec::with_capacity(payload.values.len());
+    for value in payload.values {
+        values.push(match value {
+            CursorValue::Bool(v) => Value::Bool(v),
+            CursorValue::BigInt(v) => Value::BigInt(v),
+            CursorValue::Double(v) => Value::Double(v),
+            CursorValue::Text(v) => Value::Text(v),
+            CursorValue::Blob(v) => {
+                Value::Blob(URL_SAFE_NO_PAD.decode(v).map_err(|_| "Invalid cursor")?)
+            }
+        });
+    }
+    Ok(values)
+}
+
+/// Returns predicate that matches rows strictly after specified sort keys.
+pub(super) fn keyset_predicate(order: &[Order], values: Vec<Value>) -> Predicate {
+    assert_eq!(order.len(), values.len());
+    let mut predicate = Predicate::Bool(false);
+    for i in (0..order.len()).rev() {
+        let value = Expression::Value(values[i].clone());
+        let mut part = if order[i].is_desc() {
+            column(order[i].column()).less(value)
+        } else {
+            column(order[i].column()).greater(value)
+        };
+        for j in 0..i {
+            part = column(order[j].column())
+                .equal(Expression::Value(values[j].clone()))
+                .and(part);
+        }
+        predicate = part.or(predicate);
+    }
+    predicate
+}
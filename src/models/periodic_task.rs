@@ -0,0 +1,276 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, DurationRound, TimeDelta, Timelike, Utc};
+use serde::Serialize;
+use solve_db::{Database, FromRow, IntoRow, IntoValue};
+use solve_db_types::{Instant, JSON};
+
+use crate::core::Error;
+use crate::db::builder::{column, Column};
+
+use super::setting::parse_duration;
+use super::{
+    object_store_impl, BaseEvent, Context, Object, ObjectStore, PersistentStore, TaskKind,
+};
+
+/// Schedule of periodic task.
+///
+/// Schedule is either fixed interval like `@every 30s` or cron expression
+/// with five fields: minute, hour, day of month, month and day of week.
+/// Times of cron expressions are in UTC.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Schedule {
+    Interval(Duration),
+    Cron(CronSchedule),
+}
+
+impl Schedule {
+    /// Returns first time of run strictly after specified time.
+    pub fn next_after(&self, time: Instant) -> Option<Instant> {
+        match self {
+            Self::Interval(v) => Some(time + *v),
+            Self::Cron(v) => v.next_after(time),
+        }
+    }
+}
+
+impl std::str::FromStr for Schedule {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if let Some(interval) = value.strip_prefix("@every") {
+            return match parse_duration(interval.trim()) {
+                Some(v) if !v.is_zero() => Ok(Self::Interval(v)),
+                _ => Err(format!("Invalid interval: {interval:?}").into()),
+            };
+        }
+        Ok(Self::Cron(value.parse()?))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Day of month and day of week are both restricted, so any of them
+    /// should match.
+    any_day: bool,
+}
+
+impl CronSchedule {
+    /// Returns first matching minute strictly after specified time.
+    pub fn next_after(&self, time: Instant) -> Option<Instant> {
+        // Five years are enough to find match of any valid expression.
+        const MAX_STEPS: usize = 5 * 366 * 24;
+        let time = DateTime::<Utc>::from(time);
+        let mut time = time.duration_trunc(TimeDelta::minutes(1)).ok()? + TimeDelta::minutes(1);
+        for _ in 0..MAX_STEPS {
+            if !has_bit(self.months, time.month()) {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    v => (time.year(), v + 1),
+                };
+                time = time
+                    .with_day(1)?
+                    .with_year(year)?
+                    .with_month(month)?
+                    .with_hour(0)?
+                    .with_minute(0)?;
+                continue;
+            }
+            if !self.matches_day(&time) {
+                time = time.with_hour(0)?.with_minute(0)? + TimeDelta::days(1);
+                continue;
+            }
+            if !has_bit(self.hours, time.hour()) {
+                time = time.with_minute(0)? + TimeDelta::hours(1);
+                continue;
+            }
+            if !has_bit(self.minutes, time.minute()) {
+                time += TimeDelta::minutes(1);
+                continue;
+            }
+            return Some(time.into());
+        }
+        None
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day = has_bit(self.days, time.day());
+        let weekday = has_bit(self.weekdays, time.weekday().num_days_from_sunday());
+        if self.any_day {
+            day || weekday
+        } else {
+            day && weekday
+        }
+    }
+}
+
+impl std::str::FromStr for CronSchedule {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<_> = value.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!("Cron expression should have 5 fields: {value:?}").into());
+        }
+        let mut weekdays = parse_cron_field(fields[4], 0, 7)?;
+        // Both 0 and 7 mean Sunday.
+        if has_bit(weekdays, 7) {
+            weekdays |= 1;
+        }
+        Ok(Self {
+            minutes: parse_cron_field(fields[0], 0, 59)?,
+            hours: parse_cron_field(fields[1], 0, 23)?,
+            days: parse_cron_field(fields[2], 1, 31)?,
+            months: parse_cron_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+}
+
+fn has_bit(mask: u64, bit: u32) -> bool {
+    mask & (1 << bit) != 0
+}
+
+/// Parses field of cron expression into bit mask of allowed values.
+fn parse_cron_field(field: &str, min: u32, max: u32) -> Result<u64, Error> {
+    let invalid = || -> Error { format!("Invalid cron field: {field:?}").into() };
+    let parse = |v: &str| -> Result<u32, Error> {
+        match v.parse() {
+            Ok(v) if (min..=max).contains(&v) => Ok(v),
+            _ => Err(invalid()),
+        }
+    };
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (begin, end) = if range == "*" {
+            (min, max)
+        } else if let Some((begin, end)) = range.split_once('-') {
+            (parse(begin)?, parse(end)?)
+        } else {
+            let begin = parse(range)?;
+            (begin, if part.contains('/') { max } else { begin })
+        };
+        if begin > end {
+            return Err(invalid());
+        }
+        for v in (begin..=end).step_by(step) {
+            mask |= 1 << v;
+        }
+    }
+    Ok(mask)
+}
+
+/// Definition of task that is enqueued periodically.
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct PeriodicTask {
+    pub id: i64,
+    pub kind: TaskKind,
+    pub config: JSON,
+    /// Schedule in format of [`Schedule`].
+    pub schedule: String,
+    /// Time of last enqueued run.
+    pub last_run: Option<Instant>,
+    pub enabled: bool,
+}
+
+impl PeriodicTask {
+    pub fn set_config<T: Serialize>(&mut self, config: T) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_schedule(&self) -> Result<Schedule, Error> {
+        self.schedule.parse()
+    }
+
+    /// Returns time of next run.
+    ///
+    /// Task that was never run is run immediately.
+    pub fn next_run(&self, now: Instant) -> Result<Option<Instant>, Error> {
+        match self.last_run {
+            Some(v) => Ok(self.parse_schedule()?.next_after(v)),
+            None => Ok(Some(now)),
+        }
+    }
+}
+
+impl Object for PeriodicTask {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        self.parse_schedule().is_ok()
+    }
+}
+
+pub type PeriodicTaskEvent = BaseEvent<PeriodicTask>;
+
+pub struct PeriodicTaskStore(PersistentStore<PeriodicTask>);
+
+impl PeriodicTaskStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_periodic_task",
+            "solve_periodic_task_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("kind"),
+                Column::text("config"),
+                Column::text("schedule"),
+                Column::big_int("last_run").nullable(),
+                Column::bool("enabled"),
+            ])
+            .await
+    }
+
+    /// Moves time of last run of task if it was not moved concurrently.
+    ///
+    /// Returns false if task was claimed by someone else.
+    pub async fn claim_run(
+        &self,
+        ctx: Context<'_, '_>,
+        task: &PeriodicTask,
+        last_run: Instant,
+    ) -> Result<bool, Error> {
+        let count = self
+            .update_fields_where(
+                ctx,
+                vec![("last_run".into(), last_run.into_value())],
+                column("id")
+                    .equal(task.id)
+                    .and(column("last_run").equal(task.last_run)),
+                true,
+            )
+            .await?;
+        Ok(count == 1)
+    }
+}
+
+object_store_impl!(PeriodicTaskStore, PeriodicTask, PeriodicTaskEvent);
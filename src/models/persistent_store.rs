@@ -1,11 +1,63 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, RwLock};
 
-use solve_db::{Database, Executor, FromRow, IntoRow, IsolationLevel, Rows, TransactionOptions};
+use futures_util::future::{BoxFuture, FutureExt};
+use solve_db::{
+    Database, Dialect, Executor, FromRow, IntoRow, IntoValue, IsolationLevel, RawQuery, Rows,
+    SimpleRow, Transaction, TransactionOptions, Value, ValueKind,
+};
+use solve_db_types::Instant;
 
 use crate::core::Error;
-use crate::db::builder::{column, Delete, Insert, Predicate, Select, Update};
+use crate::db::builder::{
+    column, Aggregate, Column, ColumnType, CreateTable, Delete, Expression, Insert, Predicate,
+    Select, Update,
+};
 
-use super::{AsyncIter, BaseEvent, Context, Event, EventKind, Object, ObjectStore};
+use super::object::{DELETED_AT_COLUMN, VERSION_COLUMN};
+use super::page::{decode_cursor, encode_cursor, keyset_predicate, page_order};
+use super::store::check_chunked_select;
+use super::{
+    AsyncIter, BaseEvent, Context, Event, EventKind, Object, ObjectStore, Page, PageRequest,
+    SoftDelete, StoreError, StoreMetrics, StoreOperation, StoreOutcome, WriteHorizon,
+};
+
+/// Types of columns of object table.
+#[derive(Clone, Debug, Default)]
+pub struct TypeMap(HashMap<String, Column>);
+
+impl TypeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_column(mut self, column: Column) -> Self {
+        self.0.insert(column.name().to_owned(), column);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Column> {
+        self.0.get(name)
+    }
+}
+
+impl From<Vec<Column>> for TypeMap {
+    fn from(columns: Vec<Column>) -> Self {
+        columns
+            .into_iter()
+            .fold(Self::new(), |types, column| types.with_column(column))
+    }
+}
+
+/// Observer of changes of objects in store.
+pub trait StoreObserver<O: Object>: Send + Sync {
+    /// Called after transaction that produced event is committed.
+    fn on_event(&self, event: &BaseEvent<O>);
+}
+
+type ArchiveFn<O> = Box<dyn Fn(&[BaseEvent<O>]) -> Result<(), Error> + Send + Sync>;
 
 pub struct PersistentStore<O: Object> {
     db: Arc<Database>,
@@ -13,6 +65,12 @@ pub struct PersistentStore<O: Object> {
     event_table: String,
     columns: Vec<String>,
     event_columns: Vec<String>,
+    cursor_key: Vec<u8>,
+    archive_fn: Option<ArchiveFn<O>>,
+    observers: RwLock<Vec<Arc<dyn StoreObserver<O>>>>,
+    soft_delete: bool,
+    versioned: bool,
+    metrics: Option<Arc<dyn StoreMetrics>>,
     _phantom: PhantomData<O>,
 }
 
@@ -24,57 +82,402 @@ impl<O: Object> PersistentStore<O> {
     ) -> Self {
         let columns = O::columns();
         let event_columns = BaseEvent::<O>::columns();
+        let soft_delete = columns.iter().any(|v| v == DELETED_AT_COLUMN);
+        let versioned = columns.iter().any(|v| v == VERSION_COLUMN);
         Self {
             db,
             columns,
             event_columns,
             table: table.into(),
             event_table: event_table.into(),
+            cursor_key: Vec::new(),
+            archive_fn: None,
+            observers: Default::default(),
+            soft_delete,
+            versioned,
+            metrics: None,
             _phantom: PhantomData,
         }
     }
 
+    /// Sets sink for metrics of operations on table of store.
+    pub fn with_metrics(mut self, metrics: Arc<dyn StoreMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Sets secret key used for signing page cursors.
+    pub fn with_cursor_key(mut self, key: Vec<u8>) -> Self {
+        self.cursor_key = key;
+        self
+    }
+
     pub fn db(&self) -> &Database {
         self.db.as_ref()
     }
 
-    async fn create_object(&self, tx: &mut impl Executor<'_>, object: O) -> Result<O, Error> {
-        assert!(object.is_valid());
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    pub fn add_observer(&self, observer: Arc<dyn StoreObserver<O>>) {
+        self.observers.write().unwrap().push(observer);
+    }
+
+    /// Finds objects ordered by id reading them in chunks of bounded size.
+    ///
+    /// Unlike find, connection is held only while chunk is being read, so
+    /// slow consumers do not pin connections of pool. Select must not have
+    /// order and limit, because they are used for continuation.
+    pub async fn find_chunked<'a, 'b>(
+        &'a self,
+        ctx: Context<'a, 'b>,
+        select: Select,
+        chunk_size: usize,
+    ) -> Result<ChunkedIter<'a, 'b, O>, Error> {
+        assert!(chunk_size > 0);
+        check_chunked_select(&select)?;
+        Ok(ChunkedIter {
+            store: self,
+            tx: ctx.tx,
+            select,
+            chunk_size,
+            last_id: None,
+            chunk: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Sets function that receives every batch of pruned events.
+    ///
+    /// Batch is deleted only if function succeeds.
+    pub fn with_archive_fn<F>(mut self, archive_fn: F) -> Self
+    where
+        F: Fn(&[BaseEvent<O>]) -> Result<(), Error> + Send + Sync + 'static,
+    {
+        self.archive_fn = Some(Box::new(archive_fn));
+        self
+    }
+
+    /// Deletes events written before specified time and returns their amount.
+    ///
+    /// Events are deleted in batches of bounded size to avoid long locks.
+    /// Every batch uses separate transaction unless context already has one.
+    /// Without transaction events are listed in chunks, so connection is not
+    /// held between batches.
+    pub async fn prune_events(
+        &self,
+        mut ctx: Context<'_, '_>,
+        before: Instant,
+        batch_size: usize,
+    ) -> Result<u64, Error> {
+        assert!(batch_size > 0);
+        let predicate = column("event_time").less(before);
+        let mut total = 0;
+        if let Some(tx) = ctx.tx.take() {
+            loop {
+                let pruned = self
+                    .prune_events_batch(tx, predicate.clone(), batch_size)
+                    .await?;
+                total += pruned as u64;
+                if pruned < batch_size {
+                    return Ok(total);
+                }
+            }
+        }
+        let mut events = self
+            .find_events_chunked(
+                Context::new(),
+                Select::new().with_where(predicate),
+                batch_size,
+            )
+            .await?;
+        let mut ids = Vec::with_capacity(batch_size);
+        loop {
+            let event = events.next().await.transpose()?;
+            let done = event.is_none();
+            ids.extend(event.map(|v| v.id()));
+            if ids.len() < batch_size && !done {
+                continue;
+            }
+            if !ids.is_empty() {
+                let predicate = column(BaseEvent::<O>::ID).in_values(std::mem::take(&mut ids));
+                let mut tx = self.db.transaction(write_tx_options()).await?;
+                total += self
+                    .prune_events_batch(&mut tx, predicate, batch_size)
+                    .await? as u64;
+                tx.commit().await?;
+            }
+            if done {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Deletes all objects matching predicate with single query and returns
+    /// their delete events.
+    pub async fn delete_all_where(
+        &self,
+        mut ctx: Context<'_, '_>,
+        predicate: Predicate,
+    ) -> Result<Vec<BaseEvent<O>>, Error> {
+        if let Some(tx) = ctx.tx.take() {
+            return self.delete_objects(tx, ctx.account_id, predicate).await;
+        }
+        let mut tx = self.write_transaction().await?;
+        let events = self
+            .delete_objects(&mut tx, ctx.account_id, predicate)
+            .await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
+        Ok(events)
+    }
+
+    /// Creates object and event tables using specified column types.
+    ///
+    /// Type of id column defaults to auto increment BIGINT primary key.
+    pub async fn create_tables(&self, types: Vec<Column>) -> Result<(), Error> {
+        self.create_schema(Context::new(), types.into()).await
+    }
+
+    /// Creates object and event tables if they do not exist.
+    ///
+    /// Types of columns that are missing in type map are inferred from
+    /// values of default object, so only nullable columns have to be listed.
+    pub async fn create_schema(
+        &self,
+        mut ctx: Context<'_, '_>,
+        types: TypeMap,
+    ) -> Result<(), Error> {
+        let defaults: HashMap<_, _> = O::default().into_row().into_iter().collect();
+        let get_column = |name: &str| {
+            if let Some(column) = types.get(name) {
+                return Ok(column.clone());
+            }
+            if name == O::ID && O::GENERATED_ID {
+                return Ok(Column::big_int(O::ID).primary_key().auto_increment());
+            }
+            let kind = match defaults.get(name).map(Value::kind) {
+                Some(ValueKind::Bool) => ColumnType::Bool,
+                Some(ValueKind::BigInt) => ColumnType::BigInt,
+                Some(ValueKind::Double) => ColumnType::Double,
+                Some(ValueKind::Text) => ColumnType::Text,
+                Some(ValueKind::Blob) => ColumnType::Blob,
+                Some(ValueKind::Null) | None => {
+                    return Err(Error::from(format!("Unknown type of column: {}", name)))
+                }
+            };
+            match name == O::ID {
+                true => Ok(Column::new(name, kind).primary_key()),
+                false => Ok(Column::new(name, kind)),
+            }
+        };
+        let mut columns = Vec::new();
+        for name in &self.columns {
+            columns.push(get_column(name)?);
+        }
+        let mut event_columns = Vec::new();
+        for name in &self.event_columns {
+            event_columns.push(match name.as_str() {
+                "event_id" => Column::big_int(name).primary_key().auto_increment(),
+                "event_time" | "event_kind" => Column::big_int(name),
+                "event_account_id" => Column::big_int(name).nullable(),
+                _ => {
+                    let column = get_column(name)?;
+                    let object_column = Column::new(name, column.kind());
+                    match column.is_nullable() {
+                        true => object_column.nullable(),
+                        false => object_column,
+                    }
+                }
+            });
+        }
+        let queries = [
+            CreateTable::new(&self.table).with_columns(columns),
+            CreateTable::new(&self.event_table).with_columns(event_columns),
+        ];
+        for query in queries {
+            let query = query.with_if_not_exists();
+            match ctx.tx.as_deref_mut() {
+                Some(tx) => tx.execute(query).await?,
+                None => self.db.execute(query).await?,
+            };
+        }
+        Ok(())
+    }
+
+    /// Checks that columns of object table match columns of object.
+    pub async fn check_schema(&self, mut ctx: Context<'_, '_>) -> Result<(), Error> {
+        let mut builder = self.db.builder();
+        builder.push_str("SELECT * FROM ");
+        builder.push_name(&self.table);
+        builder.push_str(" LIMIT 0");
+        let query = builder.build();
+        let rows = match ctx.tx.as_deref_mut() {
+            Some(tx) => tx.query(query).await?,
+            None => self.db.query(query).await?,
+        };
+        let live: HashSet<_> = rows.columns().iter().map(String::as_str).collect();
+        let missing: Vec<_> = self
+            .columns
+            .iter()
+            .filter(|v| !live.contains(v.as_str()))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Table {} has no columns: {}",
+                self.table,
+                missing.join(", ")
+            )
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Returns predicate that hides soft deleted objects.
+    fn not_deleted(&self) -> Predicate {
+        match self.soft_delete {
+            true => Predicate::IsNull(Box::new(column(DELETED_AT_COLUMN))),
+            false => Predicate::Bool(true),
+        }
+    }
+
+    /// Records duration and outcome of store operation into metrics.
+    fn record_metrics<T>(
+        &self,
+        operation: StoreOperation,
+        start: std::time::Instant,
+        result: &Result<T, Error>,
+    ) {
+        if let Some(metrics) = &self.metrics {
+            let outcome = StoreOutcome::of(result);
+            metrics.observe(&self.table, operation, outcome, start.elapsed());
+        }
+    }
+
+    async fn write_transaction(&self) -> Result<Transaction<'_>, Error> {
+        self.db
+            .transaction(write_tx_options())
+            .await
+            .map_err(StoreError::wrap)
+    }
+
+    /// Notifies observers about events after commit of transaction.
+    fn notify_on_commit(&self, tx: &mut Transaction<'_>, events: &[BaseEvent<O>]) {
+        let observers = self.observers.read().unwrap().clone();
+        if observers.is_empty() || events.is_empty() {
+            return;
+        }
+        let events = events.to_vec();
+        tx.on_commit(move || {
+            for event in &events {
+                for observer in &observers {
+                    observer.on_event(event);
+                }
+            }
+        });
+    }
+
+    async fn create_object(
+        &self,
+        tx: &mut impl Executor<'_>,
+        object: O,
+        predicate: Option<Predicate>,
+    ) -> Result<O, Error> {
+        if !object.is_valid() {
+            return Err(StoreError::InvalidObject("Object is not valid".into()).into());
+        }
         let row: Vec<_> = object
             .into_row()
             .into_iter()
-            .filter(|v| v.0 != O::ID)
+            .filter(|v| !O::GENERATED_ID || v.0 != O::ID)
             .collect();
-        let query = Insert::new()
+        let mut query = Insert::new()
             .with_table(&self.table)
             .with_row(row)
             .with_returning(self.columns.clone());
+        if let Some(predicate) = predicate {
+            query = query.with_where(predicate);
+        }
         let mut rows = tx.query(query).await?;
         let row = match rows.next().await {
             Some(Ok(v)) => v,
             Some(Err(v)) => return Err(v),
-            None => return Err("Empty query result".into()),
+            None => return Err(StoreError::Conflict.into()),
         };
         FromRow::from_row(&row)
     }
 
+    async fn create_objects(
+        &self,
+        tx: &mut impl Executor<'_>,
+        objects: Vec<O>,
+    ) -> Result<Vec<O>, Error> {
+        let rows: Vec<_> = objects
+            .into_iter()
+            .map(|object| {
+                object
+                    .into_row()
+                    .into_iter()
+                    .filter(|v| !O::GENERATED_ID || v.0 != O::ID)
+                    .collect::<SimpleRow>()
+            })
+            .collect();
+        insert_rows(tx, &self.table, rows, &self.columns).await
+    }
+
+    /// Returns object with specified id including soft deleted one.
+    async fn select_object(
+        &self,
+        tx: &mut impl Executor<'_>,
+        id: O::Id,
+    ) -> Result<Option<O>, Error> {
+        let query = Select::new()
+            .with_table(&self.table)
+            .with_columns(self.columns.clone())
+            .with_where(column(O::ID).equal(id))
+            .with_limit(1);
+        let mut rows = tx.query(query).await?;
+        match rows.next().await {
+            Some(Ok(v)) => Ok(Some(FromRow::from_row(&v)?)),
+            Some(Err(v)) => Err(v),
+            None => Ok(None),
+        }
+    }
+
+    /// Updates object and returns its previous and new state.
     async fn update_object(
         &self,
         tx: &mut impl Executor<'_>,
         object: O,
         predicate: Option<Predicate>,
-    ) -> Result<O, Error> {
-        assert!(object.is_valid());
+    ) -> Result<(O, O), Error> {
+        if !object.is_valid() {
+            return Err(StoreError::InvalidObject("Object is not valid".into()).into());
+        }
         let id = object.id();
-        let row: Vec<_> = object
+        let prev_object = match self.select_object(tx, id.clone()).await? {
+            Some(v) => v,
+            None => return Err(StoreError::NotFound.into()),
+        };
+        let mut row: Vec<_> = object
             .into_row()
             .into_iter()
             .filter(|v| v.0 != O::ID)
             .collect();
-        let predicate = match predicate {
+        let mut predicate = match predicate {
             Some(v) => column(O::ID).equal(id).and(v),
             None => column(O::ID).equal(id),
         };
+        if self.versioned {
+            let value = match row.iter_mut().find(|v| v.0 == VERSION_COLUMN) {
+                Some(v) => &mut v.1,
+                None => unreachable!("version column is always present"),
+            };
+            let version: i64 = value.parse()?;
+            predicate = predicate.and(column(VERSION_COLUMN).equal(version));
+            *value = (version + 1).into_value();
+        }
         let query = Update::new()
             .with_table(&self.table)
             .with_row(row)
@@ -84,9 +487,9 @@ impl<O: Object> PersistentStore<O> {
         let row = match rows.next().await {
             Some(Ok(v)) => v,
             Some(Err(v)) => return Err(v),
-            None => return Err("Empty query result".into()),
+            None => return Err(StoreError::Conflict.into()),
         };
-        FromRow::from_row(&row)
+        Ok((prev_object, FromRow::from_row(&row)?))
     }
 
     async fn delete_object(
@@ -94,25 +497,125 @@ impl<O: Object> PersistentStore<O> {
         tx: &mut impl Executor<'_>,
         id: O::Id,
         predicate: Option<Predicate>,
-    ) -> Result<(), Error> {
+    ) -> Result<O, Error> {
         let predicate = match predicate {
             Some(v) => column(O::ID).equal(id.clone()).and(v),
             None => column(O::ID).equal(id.clone()),
         };
-        let query = Delete::new().with_table(&self.table).with_where(predicate);
-        let status = tx.execute(query).await?;
-        match status.rows_affected() {
-            Some(1) => Ok(()),
-            _ => Err(format!("Cannot delete object with id: {}", id).into()),
+        let query = Delete::new()
+            .with_table(&self.table)
+            .with_where(predicate)
+            .with_returning(self.columns.clone());
+        let row = {
+            let mut rows = tx.query(query).await?;
+            match rows.next().await {
+                Some(Ok(v)) => Some(v),
+                Some(Err(v)) => return Err(v),
+                None => None,
+            }
+        };
+        match row {
+            Some(row) => FromRow::from_row(&row),
+            // Object exists, so it does not match predicate.
+            None if self.select_object(tx, id).await?.is_some() => Err(StoreError::Conflict.into()),
+            None => Err(StoreError::NotFound.into()),
         }
     }
 
+    async fn delete_objects(
+        &self,
+        tx: &mut Transaction<'_>,
+        account_id: Option<i64>,
+        predicate: Predicate,
+    ) -> Result<Vec<BaseEvent<O>>, Error> {
+        let query = Delete::new()
+            .with_table(&self.table)
+            .with_where(predicate)
+            .with_returning(self.columns.clone());
+        let start = std::time::Instant::now();
+        let events = async {
+            let mut events = Vec::new();
+            {
+                let mut rows = tx.query(query).await?;
+                while let Some(row) = rows.next().await {
+                    events.push(BaseEvent::delete(FromRow::from_row(&row?)?));
+                }
+            }
+            self.create_events(tx, account_id, events).await
+        }
+        .await
+        .map_err(StoreError::wrap);
+        self.record_metrics(StoreOperation::Delete, start, &events);
+        let events = events?;
+        self.notify_on_commit(tx, &events);
+        Ok(events)
+    }
+
+    async fn prune_events_batch(
+        &self,
+        tx: &mut impl Executor<'_>,
+        predicate: Predicate,
+        batch_size: usize,
+    ) -> Result<usize, Error> {
+        let id_column = BaseEvent::<O>::ID;
+        let select = Select::new()
+            .with_table(&self.event_table)
+            .with_columns(vec![id_column.to_owned()])
+            .with_where(predicate)
+            .with_order_by(vec![id_column.to_owned()])
+            .with_limit(batch_size);
+        let query = Delete::new()
+            .with_table(&self.event_table)
+            .with_where(column(id_column).in_select(select))
+            .with_returning(self.event_columns.clone());
+        let mut events = Vec::new();
+        {
+            let mut rows = tx.query(query).await?;
+            while let Some(row) = rows.next().await {
+                events.push(BaseEvent::<O>::from_row(&row?)?);
+            }
+        }
+        if let Some(archive_fn) = &self.archive_fn {
+            if !events.is_empty() {
+                events.sort_by_key(|v| v.id());
+                archive_fn(&events)?;
+            }
+        }
+        Ok(events.len())
+    }
+
+    async fn create_events(
+        &self,
+        tx: &mut impl Executor<'_>,
+        account_id: Option<i64>,
+        events: Vec<BaseEvent<O>>,
+    ) -> Result<Vec<BaseEvent<O>>, Error> {
+        let time = Instant::now();
+        let rows: Vec<_> = events
+            .into_iter()
+            .map(|mut event| {
+                event.set_time(time);
+                event.set_account_id(account_id);
+                event
+                    .into_row()
+                    .into_iter()
+                    .filter(|v| v.0 != BaseEvent::<O>::ID)
+                    .collect::<SimpleRow>()
+            })
+            .collect();
+        insert_rows(tx, &self.event_table, rows, &self.event_columns).await
+    }
+
     async fn create_event(
         &self,
         tx: &mut impl Executor<'_>,
-        event: BaseEvent<O>,
+        account_id: Option<i64>,
+        mut event: BaseEvent<O>,
     ) -> Result<BaseEvent<O>, Error> {
         assert!(!matches!(event.kind(), EventKind::Unknown(_)));
+        event.set_time(Instant::now());
+        event.set_account_id(account_id);
+        let prev_object = event.prev_object().cloned();
         let row: Vec<_> = event
             .into_row()
             .into_iter()
@@ -128,8 +631,91 @@ impl<O: Object> PersistentStore<O> {
             Some(Err(v)) => return Err(v),
             None => return Err("Empty query result".into()),
         };
-        FromRow::from_row(&row)
+        let event: BaseEvent<O> = FromRow::from_row(&row)?;
+        Ok(match prev_object {
+            Some(v) => event.with_prev_object(v),
+            None => event,
+        })
+    }
+}
+
+impl<O: SoftDelete> PersistentStore<O> {
+    /// Marks object as deleted and writes delete event.
+    ///
+    /// Soft deleted object can be restored by update with empty deletion time.
+    pub async fn soft_delete(
+        &self,
+        ctx: Context<'_, '_>,
+        id: O::Id,
+    ) -> Result<BaseEvent<O>, Error> {
+        if ctx.tx.is_some() {
+            let start = std::time::Instant::now();
+            let event = self.soft_delete_tx(ctx, id).await.map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Delete, start, &event);
+            return event;
+        }
+        let mut tx = self.write_transaction().await?;
+        let event = self.soft_delete_tx(ctx.with_tx(&mut tx), id).await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
+        Ok(event)
+    }
+
+    async fn soft_delete_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        id: O::Id,
+    ) -> Result<BaseEvent<O>, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let mut object = match self.get(Context::new().with_tx(tx), id.clone()).await? {
+            Some(v) => v,
+            None => return Err(StoreError::NotFound.into()),
+        };
+        object.set_deleted_at(Some(Instant::now()));
+        let predicate = Predicate::IsNull(Box::new(column(DELETED_AT_COLUMN)));
+        let (prev_object, object) = self.update_object(tx, object, Some(predicate)).await?;
+        let event = BaseEvent::delete(object).with_prev_object(prev_object);
+        let event = self.create_event(tx, ctx.account_id, event).await?;
+        self.notify_on_commit(tx, std::slice::from_ref(&event));
+        Ok(event)
+    }
+}
+
+/// Maximal amount of bound values in single statement.
+const MAX_QUERY_VALUES: usize = 900;
+
+/// Inserts rows using multi-row inserts and returns created rows.
+///
+/// Created rows are returned in the same order as specified ones.
+async fn insert_rows<T: FromRow>(
+    tx: &mut impl Executor<'_>,
+    table: &str,
+    rows: Vec<SimpleRow>,
+    returning: &[String],
+) -> Result<Vec<T>, Error> {
+    let chunk_size = match rows.first() {
+        Some(row) => (MAX_QUERY_VALUES / row.len().max(1)).max(1),
+        None => return Ok(Vec::new()),
+    };
+    let mut result = Vec::with_capacity(rows.len());
+    let mut iter = rows.into_iter().peekable();
+    while iter.peek().is_some() {
+        let chunk: Vec<_> = iter.by_ref().take(chunk_size).collect();
+        let len = chunk.len();
+        let query = Insert::new()
+            .with_table(table)
+            .with_rows(chunk)
+            .with_returning(returning.to_vec());
+        let mut rows = tx.query(query).await?;
+        let mut created = 0;
+        while let Some(row) = rows.next().await {
+            result.push(FromRow::from_row(&row?)?);
+            created += 1;
+        }
+        if created != len {
+            return Err("Cannot create objects".into());
+        }
     }
+    Ok(result)
 }
 
 pub fn write_tx_options() -> TransactionOptions {
@@ -139,8 +725,45 @@ pub fn write_tx_options() -> TransactionOptions {
     }
 }
 
+pub fn read_tx_options() -> TransactionOptions {
+    TransactionOptions {
+        isolation_level: IsolationLevel::RepeatableRead,
+        read_only: true,
+    }
+}
+
+/// Runs function with context of new transaction.
+///
+/// Transaction is committed when function succeeds and is rolled back when
+/// function fails or panics. Function should own handles of used stores.
+pub async fn run_in_tx<F, T>(db: &Database, options: TransactionOptions, f: F) -> Result<T, Error>
+where
+    F: for<'a, 'b> FnOnce(Context<'a, 'b>) -> BoxFuture<'a, Result<T, Error>>,
+{
+    let mut tx = db.transaction(options).await?;
+    let result = AssertUnwindSafe(f(Context::new().with_tx(&mut tx)))
+        .catch_unwind()
+        .await;
+    match result {
+        Ok(Ok(v)) => {
+            tx.commit().await?;
+            Ok(v)
+        }
+        Ok(Err(err)) => {
+            tx.rollback().await?;
+            Err(err)
+        }
+        Err(panic) => {
+            // Transaction should not be left open during unwinding.
+            drop(tx.rollback().await);
+            std::panic::resume_unwind(panic)
+        }
+    }
+}
+
 pub struct RowsIter<'a, T> {
     rows: Rows<'a>,
+    metrics: Option<(&'a dyn StoreMetrics, &'a str)>,
     _phantom: PhantomData<T>,
 }
 
@@ -150,40 +773,180 @@ impl<'a, T: Send + FromRow> AsyncIter<'a> for RowsIter<'a, T> {
 
     async fn next(&mut self) -> Option<Result<Self::Item, Error>> {
         match self.rows.next().await {
-            Some(Ok(v)) => Some(FromRow::from_row(&v)),
+            Some(Ok(v)) => {
+                if let Some((metrics, table)) = self.metrics {
+                    metrics.add_rows(table, 1);
+                }
+                Some(FromRow::from_row(&v))
+            }
             Some(Err(v)) => Some(Err(v)),
             None => None,
         }
     }
 }
 
+pub struct ChunkedIter<'a, 'b, O: Object> {
+    store: &'a PersistentStore<O>,
+    tx: Option<&'a mut Transaction<'b>>,
+    select: Select,
+    chunk_size: usize,
+    last_id: Option<O::Id>,
+    chunk: VecDeque<O>,
+    done: bool,
+}
+
+impl<O: Object> ChunkedIter<'_, '_, O> {
+    async fn read_chunk(&mut self) -> Result<(), Error> {
+        let mut select = self
+            .select
+            .clone()
+            .with_order_by(vec![O::ID.to_owned()])
+            .with_limit(self.chunk_size);
+        if let Some(id) = self.last_id.take() {
+            select = select.and_where(column(O::ID).greater(id));
+        }
+        let ctx = Context {
+            tx: self.tx.as_deref_mut(),
+            ..Context::new()
+        };
+        let mut rows = self.store.find(ctx, select).await?;
+        while let Some(object) = rows.next().await {
+            self.chunk.push_back(object?);
+        }
+        self.done = self.chunk.len() < self.chunk_size;
+        self.last_id = self.chunk.back().map(Object::id);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, O: Object> AsyncIter<'a> for ChunkedIter<'a, '_, O> {
+    type Item = O;
+
+    async fn next(&mut self) -> Option<Result<Self::Item, Error>> {
+        if self.chunk.is_empty() && !self.done {
+            if let Err(err) = self.read_chunk().await {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.chunk.pop_front().map(Ok)
+    }
+}
+
 #[async_trait::async_trait]
 impl<O: Object> ObjectStore for PersistentStore<O> {
     type Id = O::Id;
     type Object = O;
     type Event = BaseEvent<O>;
     type FindIter<'a> = RowsIter<'a, O>;
+    type FindEventsIter<'a> = RowsIter<'a, BaseEvent<O>>;
 
     async fn find<'a>(
         &'a self,
         mut ctx: Context<'a, '_>,
         select: Select,
     ) -> Result<Self::FindIter<'a>, Error> {
+        let select = match select.includes_deleted() {
+            true => select,
+            false => select.and_where(self.not_deleted()),
+        };
+        let select = match select.has_order() {
+            true => select,
+            false => select.with_order_by(vec![O::ID.to_owned()]),
+        };
         let query = select
             .with_table(&self.table)
-            .with_columns(self.columns.clone())
-            .with_order_by(vec![O::ID.to_owned()]);
+            .with_columns(self.columns.clone());
+        let start = std::time::Instant::now();
+        let rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await
+        } else {
+            self.db
+                .query_with_options(ctx.consistency.connection_options(), query)
+                .await
+        };
+        self.record_metrics(StoreOperation::Find, start, &rows);
+        Ok(RowsIter {
+            rows: rows?,
+            metrics: self
+                .metrics
+                .as_deref()
+                .map(|metrics| (metrics, self.table.as_str())),
+            _phantom: PhantomData,
+        })
+    }
+
+    async fn find_events<'a>(
+        &'a self,
+        mut ctx: Context<'a, '_>,
+        select: Select,
+    ) -> Result<Self::FindEventsIter<'a>, Error> {
+        let select = match select.has_order() {
+            true => select,
+            false => select.with_order_by(vec![BaseEvent::<O>::ID.to_owned()]),
+        };
+        let query = select
+            .with_table(&self.event_table)
+            .with_columns(self.event_columns.clone());
         let rows = if let Some(tx) = ctx.tx.take() {
             tx.query(query).await?
         } else {
-            self.db.query(query).await?
+            self.db
+                .query_with_options(ctx.consistency.connection_options(), query)
+                .await?
         };
         Ok(RowsIter {
             rows,
+            metrics: None,
             _phantom: PhantomData,
         })
     }
 
+    async fn write_horizon(&self, mut ctx: Context<'_, '_>) -> Result<Option<WriteHorizon>, Error> {
+        // Writers of SQLite are serialized, so gaps never appear there except
+        // rolled back transactions.
+        if self.db.builder().dialect() != Dialect::Postgres {
+            return Ok(None);
+        }
+        let query = RawQuery::new(
+            "SELECT txid_snapshot_xmin(txid_current_snapshot()), txid_snapshot_xmax(txid_current_snapshot())",
+            Vec::new(),
+        );
+        let mut rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await?
+        } else {
+            self.db.query(query).await?
+        };
+        let row = match rows.next().await {
+            Some(Ok(v)) => v,
+            Some(Err(v)) => return Err(v),
+            None => return Err("Empty query result".into()),
+        };
+        Ok(Some(WriteHorizon {
+            xmin: row.get_parsed(0)?,
+            xmax: row.get_parsed(1)?,
+        }))
+    }
+
+    async fn last_event_id(&self, mut ctx: Context<'_, '_>) -> Result<i64, Error> {
+        let query = Select::new()
+            .with_table(&self.event_table)
+            .with_aggregate(Aggregate::Max(BaseEvent::<O>::ID.to_owned()));
+        let mut rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await?
+        } else {
+            self.db.query(query).await?
+        };
+        let row = match rows.next().await {
+            Some(Ok(v)) => v,
+            Some(Err(v)) => return Err(v),
+            None => return Err("Empty query result".into()),
+        };
+        let id: Option<i64> = row.get_parsed(0)?;
+        Ok(id.unwrap_or(0))
+    }
+
     async fn get<'a>(
         &'a self,
         ctx: Context<'a, '_>,
@@ -203,27 +966,240 @@ impl<O: Object> ObjectStore for PersistentStore<O> {
         }
     }
 
+    async fn get_many<'a>(
+        &'a self,
+        mut ctx: Context<'a, '_>,
+        ids: &[O::Id],
+    ) -> Result<HashMap<O::Id, O>, Error> {
+        let mut seen = HashSet::new();
+        let ids: Vec<_> = ids.iter().filter(|v| seen.insert(*v)).cloned().collect();
+        let mut objects = HashMap::with_capacity(ids.len());
+        for chunk in ids.chunks(MAX_QUERY_VALUES) {
+            let query = Select::new()
+                .with_table(&self.table)
+                .with_columns(self.columns.clone())
+                .with_where(
+                    column(O::ID)
+                        .in_values(chunk.to_vec())
+                        .and(self.not_deleted()),
+                );
+            let mut rows = match ctx.tx.as_deref_mut() {
+                Some(tx) => tx.query(query).await?,
+                None => {
+                    self.db
+                        .query_with_options(ctx.consistency.connection_options(), query)
+                        .await?
+                }
+            };
+            while let Some(row) = rows.next().await {
+                let object = O::from_row(&row?)?;
+                objects.insert(object.id(), object);
+            }
+        }
+        Ok(objects)
+    }
+
+    async fn find_page<'a>(
+        &'a self,
+        mut ctx: Context<'a, '_>,
+        request: PageRequest,
+    ) -> Result<Page<O>, Error> {
+        if request.limit == 0 {
+            return Err("Page limit should be positive".into());
+        }
+        let order = page_order(&request.order, O::ID);
+        for v in &order {
+            if !self.columns.iter().any(|c| c == v.column()) {
+                return Err(format!("Unknown column: {}", v.column()).into());
+            }
+        }
+        let mut predicate = request.predicate.and(self.not_deleted());
+        if let Some(cursor) = &request.cursor {
+            let values = decode_cursor(&self.cursor_key, &self.table, &order, cursor)?;
+            predicate = predicate.and(keyset_predicate(&order, values));
+        }
+        let query = Select::new()
+            .with_table(&self.table)
+            .with_columns(self.columns.clone())
+            .with_where(predicate)
+            .with_order(order.clone())
+            .with_limit(request.limit + 1);
+        let mut rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await?
+        } else {
+            self.db
+                .query_with_options(ctx.consistency.connection_options(), query)
+                .await?
+        };
+        let mut objects = Vec::new();
+        while let Some(row) = rows.next().await {
+            objects.push(O::from_row(&row?)?);
+        }
+        if objects.len() <= request.limit {
+            return Ok(Page {
+                objects,
+                next_cursor: None,
+            });
+        }
+        objects.truncate(request.limit);
+        let row = objects.last().unwrap().clone().into_row();
+        let values = order
+            .iter()
+            .map(|v| match row.iter().find(|(name, _)| name == v.column()) {
+                Some((_, value)) => value.clone(),
+                None => unreachable!("order columns are validated"),
+            })
+            .collect();
+        let next_cursor = encode_cursor(&self.cursor_key, &self.table, &order, values)?;
+        Ok(Page {
+            objects,
+            next_cursor: Some(next_cursor),
+        })
+    }
+
+    async fn count<'a>(
+        &'a self,
+        mut ctx: Context<'a, '_>,
+        predicate: Predicate,
+    ) -> Result<i64, Error> {
+        let query = Select::new()
+            .with_table(&self.table)
+            .with_aggregate(Aggregate::Count)
+            .with_where(predicate.and(self.not_deleted()));
+        let mut rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await?
+        } else {
+            self.db
+                .query_with_options(ctx.consistency.connection_options(), query)
+                .await?
+        };
+        let row = match rows.next().await {
+            Some(Ok(v)) => v,
+            Some(Err(v)) => return Err(v),
+            None => return Err("Empty query result".into()),
+        };
+        row.get_parsed(0)
+    }
+
+    async fn exists<'a>(
+        &'a self,
+        mut ctx: Context<'a, '_>,
+        predicate: Predicate,
+    ) -> Result<bool, Error> {
+        let query = Select::new()
+            .with_table(&self.table)
+            .with_columns(vec![O::ID.to_owned()])
+            .with_where(predicate.and(self.not_deleted()))
+            .with_limit(1);
+        let mut rows = if let Some(tx) = ctx.tx.take() {
+            tx.query(query).await?
+        } else {
+            self.db
+                .query_with_options(ctx.consistency.connection_options(), query)
+                .await?
+        };
+        match rows.next().await {
+            Some(Ok(_)) => Ok(true),
+            Some(Err(v)) => Err(v),
+            None => Ok(false),
+        }
+    }
+
     async fn create(&self, mut ctx: Context<'_, '_>, object: O) -> Result<Self::Event, Error> {
         if let Some(tx) = ctx.tx.take() {
-            let object = self.create_object(tx, object).await?;
-            let event = self.create_event(tx, BaseEvent::create(object)).await?;
+            let start = std::time::Instant::now();
+            let event = async {
+                let object = self.create_object(tx, object, None).await?;
+                self.create_event(tx, ctx.account_id, BaseEvent::create(object))
+                    .await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Create, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
             return Ok(event);
         }
-        let mut tx = self.db.transaction(write_tx_options()).await?;
+        let mut tx = self.write_transaction().await?;
         let event = self.create(ctx.with_tx(&mut tx), object).await?;
-        tx.commit().await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
+        Ok(event)
+    }
+
+    async fn create_batch(
+        &self,
+        mut ctx: Context<'_, '_>,
+        objects: Vec<O>,
+    ) -> Result<Vec<Self::Event>, Error> {
+        if objects.iter().any(|v| !v.is_valid()) {
+            return Err(StoreError::InvalidObject("Batch contains invalid object".into()).into());
+        }
+        if let Some(tx) = ctx.tx.take() {
+            let start = std::time::Instant::now();
+            let events = async {
+                let objects = self.create_objects(tx, objects).await?;
+                let events = objects.into_iter().map(BaseEvent::create).collect();
+                self.create_events(tx, ctx.account_id, events).await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Create, start, &events);
+            let events = events?;
+            self.notify_on_commit(tx, &events);
+            return Ok(events);
+        }
+        let mut tx = self.write_transaction().await?;
+        let events = self.create_batch(ctx.with_tx(&mut tx), objects).await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
+        Ok(events)
+    }
+
+    async fn create_where(
+        &self,
+        mut ctx: Context<'_, '_>,
+        object: O,
+        predicate: Predicate,
+    ) -> Result<Self::Event, Error> {
+        if let Some(tx) = ctx.tx.take() {
+            let start = std::time::Instant::now();
+            let event = async {
+                let object = self.create_object(tx, object, Some(predicate)).await?;
+                self.create_event(tx, ctx.account_id, BaseEvent::create(object))
+                    .await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Create, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
+            return Ok(event);
+        }
+        let mut tx = self.write_transaction().await?;
+        let event = self
+            .create_where(ctx.with_tx(&mut tx), object, predicate)
+            .await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
         Ok(event)
     }
 
     async fn update(&self, mut ctx: Context<'_, '_>, object: O) -> Result<Self::Event, Error> {
         if let Some(tx) = ctx.tx.take() {
-            let object = self.update_object(tx, object, None).await?;
-            let event = self.create_event(tx, BaseEvent::update(object)).await?;
+            let start = std::time::Instant::now();
+            let event = async {
+                let (prev_object, object) = self.update_object(tx, object, None).await?;
+                let event = BaseEvent::update(object).with_prev_object(prev_object);
+                self.create_event(tx, ctx.account_id, event).await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Update, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
             return Ok(event);
         }
-        let mut tx = self.db.transaction(write_tx_options()).await?;
+        let mut tx = self.write_transaction().await?;
         let event = self.update(ctx.with_tx(&mut tx), object).await?;
-        tx.commit().await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
         Ok(event)
     }
 
@@ -234,27 +1210,111 @@ impl<O: Object> ObjectStore for PersistentStore<O> {
         predicate: Predicate,
     ) -> Result<Self::Event, Error> {
         if let Some(tx) = ctx.tx.take() {
-            let object = self.update_object(tx, object, Some(predicate)).await?;
-            let event = self.create_event(tx, BaseEvent::update(object)).await?;
+            let start = std::time::Instant::now();
+            let event = async {
+                let (prev_object, object) = self.update_object(tx, object, Some(predicate)).await?;
+                let event = BaseEvent::update(object).with_prev_object(prev_object);
+                self.create_event(tx, ctx.account_id, event).await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Update, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
             return Ok(event);
         }
-        let mut tx = self.db.transaction(write_tx_options()).await?;
+        let mut tx = self.write_transaction().await?;
         let event = self
             .update_where(ctx.with_tx(&mut tx), object, predicate)
             .await?;
-        tx.commit().await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
         Ok(event)
     }
 
+    async fn update_fields_where(
+        &self,
+        mut ctx: Context<'_, '_>,
+        set: Vec<(String, Value)>,
+        predicate: Predicate,
+        emit_events: bool,
+    ) -> Result<u64, Error> {
+        if set.iter().any(|v| v.0 == O::ID) {
+            return Err(StoreError::InvalidObject("Cannot update id of objects".into()).into());
+        }
+        if let Some(tx) = ctx.tx.take() {
+            let mut update: Vec<(String, Expression)> =
+                set.into_iter().map(|(k, v)| (k, v.into())).collect();
+            if self.versioned {
+                update.push((VERSION_COLUMN.into(), column(VERSION_COLUMN).plus(1)));
+            }
+            let query = Update::new()
+                .with_table(&self.table)
+                .with_update(update)
+                .with_where(predicate);
+            let start = std::time::Instant::now();
+            if !emit_events {
+                let result = async {
+                    let count = tx.execute(query).await?.rows_affected().unwrap_or_default();
+                    if count == 0 {
+                        return Ok((count, None));
+                    }
+                    let event = self
+                        .create_event(tx, ctx.account_id, BaseEvent::batch_update())
+                        .await?;
+                    Ok((count, Some(event)))
+                }
+                .await
+                .map_err(StoreError::wrap);
+                self.record_metrics(StoreOperation::Update, start, &result);
+                let (count, event) = result?;
+                if let Some(event) = event {
+                    self.notify_on_commit(tx, std::slice::from_ref(&event));
+                }
+                return Ok(count);
+            }
+            let events = async {
+                let mut events = Vec::new();
+                {
+                    let mut rows = tx.query(query.with_returning(self.columns.clone())).await?;
+                    while let Some(row) = rows.next().await {
+                        events.push(BaseEvent::update(FromRow::from_row(&row?)?));
+                    }
+                }
+                self.create_events(tx, ctx.account_id, events).await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Update, start, &events);
+            let events = events?;
+            self.notify_on_commit(tx, &events);
+            return Ok(events.len() as u64);
+        }
+        let mut tx = self.write_transaction().await?;
+        let count = self
+            .update_fields_where(ctx.with_tx(&mut tx), set, predicate, emit_events)
+            .await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
+        Ok(count)
+    }
+
     async fn delete(&self, mut ctx: Context<'_, '_>, id: O::Id) -> Result<Self::Event, Error> {
         if let Some(tx) = ctx.tx.take() {
-            self.delete_object(tx, id.clone(), None).await?;
-            let event = self.create_event(tx, BaseEvent::delete(id)).await?;
+            let start = std::time::Instant::now();
+            let event = async {
+                let object = self.delete_object(tx, id, None).await?;
+                self.create_event(tx, ctx.account_id, BaseEvent::delete(object))
+                    .await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Delete, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
             return Ok(event);
         }
-        let mut tx = self.db.transaction(write_tx_options()).await?;
+        let mut tx = self.write_transaction().await?;
         let event = self.delete(ctx.with_tx(&mut tx), id).await?;
-        tx.commit().await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
         Ok(event)
     }
 
@@ -265,25 +1325,90 @@ impl<O: Object> ObjectStore for PersistentStore<O> {
         predicate: Predicate,
     ) -> Result<Self::Event, Error> {
         if let Some(tx) = ctx.tx.take() {
-            self.delete_object(tx, id.clone(), Some(predicate)).await?;
-            let event = self.create_event(tx, BaseEvent::delete(id)).await?;
+            let start = std::time::Instant::now();
+            let event = async {
+                let object = self.delete_object(tx, id, Some(predicate)).await?;
+                self.create_event(tx, ctx.account_id, BaseEvent::delete(object))
+                    .await
+            }
+            .await
+            .map_err(StoreError::wrap);
+            self.record_metrics(StoreOperation::Delete, start, &event);
+            let event = event?;
+            self.notify_on_commit(tx, std::slice::from_ref(&event));
             return Ok(event);
         }
-        let mut tx = self.db.transaction(write_tx_options()).await?;
-        let event = self.delete(ctx.with_tx(&mut tx), id).await?;
-        tx.commit().await?;
+        let mut tx = self.write_transaction().await?;
+        let event = self
+            .delete_where(ctx.with_tx(&mut tx), id, predicate)
+            .await?;
+        tx.commit().await.map_err(StoreError::wrap)?;
         Ok(event)
     }
 }
 
 macro_rules! object_store_impl {
     ($store:ident, $object:ident, $event:ident) => {
+        object_store_impl!($store, $object, $event, i64);
+    };
+    ($store:ident, $object:ident, $event:ident, $id:ty) => {
+        impl $store {
+            pub fn add_observer(
+                &self,
+                observer: std::sync::Arc<dyn $crate::models::StoreObserver<$object>>,
+            ) {
+                self.0.add_observer(observer)
+            }
+
+            pub fn db(&self) -> &solve_db::Database {
+                self.0.db()
+            }
+
+            pub fn with_metrics(
+                mut self,
+                metrics: std::sync::Arc<dyn $crate::models::StoreMetrics>,
+            ) -> Self {
+                self.0 = self.0.with_metrics(metrics);
+                self
+            }
+
+            /// Sets secret key used for signing page cursors.
+            pub fn with_cursor_key(mut self, key: Vec<u8>) -> Self {
+                self.0 = self.0.with_cursor_key(key);
+                self
+            }
+
+            /// Finds objects ordered by id reading them in chunks of bounded size.
+            pub async fn find_chunked<'a, 'b>(
+                &'a self,
+                ctx: $crate::models::Context<'a, 'b>,
+                select: $crate::db::builder::Select,
+                chunk_size: usize,
+            ) -> std::result::Result<
+                $crate::models::ChunkedIter<'a, 'b, $object>,
+                $crate::core::Error,
+            > {
+                self.0.find_chunked(ctx, select, chunk_size).await
+            }
+
+            /// Deletes events written before specified time.
+            pub async fn prune_events(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+                before: solve_db_types::Instant,
+                batch_size: usize,
+            ) -> std::result::Result<u64, $crate::core::Error> {
+                self.0.prune_events(ctx, before, batch_size).await
+            }
+        }
+
         #[async_trait::async_trait]
         impl $crate::models::ObjectStore for $store {
-            type Id = i64;
+            type Id = $id;
             type Object = $object;
             type Event = $event;
             type FindIter<'a> = $crate::models::RowsIter<'a, $object>;
+            type FindEventsIter<'a> = $crate::models::RowsIter<'a, $event>;
 
             async fn find<'a>(
                 &'a self,
@@ -293,6 +1418,29 @@ macro_rules! object_store_impl {
                 self.0.find(ctx, select).await
             }
 
+            async fn find_events<'a>(
+                &'a self,
+                ctx: $crate::models::Context<'a, '_>,
+                select: $crate::db::builder::Select,
+            ) -> std::result::Result<Self::FindEventsIter<'a>, $crate::core::Error> {
+                self.0.find_events(ctx, select).await
+            }
+
+            async fn last_event_id(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+            ) -> std::result::Result<i64, $crate::core::Error> {
+                self.0.last_event_id(ctx).await
+            }
+
+            async fn write_horizon(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+            ) -> std::result::Result<Option<$crate::models::WriteHorizon>, $crate::core::Error>
+            {
+                self.0.write_horizon(ctx).await
+            }
+
             async fn get<'a>(
                 &'a self,
                 ctx: $crate::models::Context<'a, '_>,
@@ -301,6 +1449,41 @@ macro_rules! object_store_impl {
                 self.0.get(ctx, id).await
             }
 
+            async fn get_many<'a>(
+                &'a self,
+                ctx: $crate::models::Context<'a, '_>,
+                ids: &[Self::Id],
+            ) -> std::result::Result<
+                std::collections::HashMap<Self::Id, Self::Object>,
+                $crate::core::Error,
+            > {
+                self.0.get_many(ctx, ids).await
+            }
+
+            async fn find_page<'a>(
+                &'a self,
+                ctx: $crate::models::Context<'a, '_>,
+                request: $crate::models::PageRequest,
+            ) -> std::result::Result<$crate::models::Page<Self::Object>, $crate::core::Error> {
+                self.0.find_page(ctx, request).await
+            }
+
+            async fn count<'a>(
+                &'a self,
+                ctx: $crate::models::Context<'a, '_>,
+                predicate: $crate::db::builder::Predicate,
+            ) -> std::result::Result<i64, $crate::core::Error> {
+                self.0.count(ctx, predicate).await
+            }
+
+            async fn exists<'a>(
+                &'a self,
+                ctx: $crate::models::Context<'a, '_>,
+                predicate: $crate::db::builder::Predicate,
+            ) -> std::result::Result<bool, $crate::core::Error> {
+                self.0.exists(ctx, predicate).await
+            }
+
             async fn create(
                 &self,
                 ctx: $crate::models::Context<'_, '_>,
@@ -309,6 +1492,23 @@ macro_rules! object_store_impl {
                 self.0.create(ctx, object).await
             }
 
+            async fn create_batch(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+                objects: Vec<Self::Object>,
+            ) -> std::result::Result<Vec<Self::Event>, $crate::core::Error> {
+                self.0.create_batch(ctx, objects).await
+            }
+
+            async fn create_where(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+                object: Self::Object,
+                predicate: $crate::db::builder::Predicate,
+            ) -> std::result::Result<Self::Event, $crate::core::Error> {
+                self.0.create_where(ctx, object, predicate).await
+            }
+
             async fn update(
                 &self,
                 ctx: $crate::models::Context<'_, '_>,
@@ -326,6 +1526,18 @@ macro_rules! object_store_impl {
                 self.0.update_where(ctx, object, predicate).await
             }
 
+            async fn update_fields_where(
+                &self,
+                ctx: $crate::models::Context<'_, '_>,
+                set: Vec<(String, solve_db::Value)>,
+                predicate: $crate::db::builder::Predicate,
+                emit_events: bool,
+            ) -> std::result::Result<u64, $crate::core::Error> {
+                self.0
+                    .update_fields_where(ctx, set, predicate, emit_events)
+                    .await
+            }
+
             async fn delete(
                 &self,
                 ctx: $crate::models::Context<'_, '_>,
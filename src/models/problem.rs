@@ -1,12 +1,74 @@
 use std::sync::Arc;
 
+use serde::{Deserialize, Serialize};
 use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::{DurationMs, Instant, JSON};
 
-use super::{object_store_impl, BaseEvent, Object, PersistentStore};
+use crate::core::Error;
+use crate::db::builder::Column;
+
+use super::{object_store_impl, BaseEvent, Context, Object, PersistentStore, SoftDelete};
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProblemConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<ProblemPackage>,
+}
+
+/// Contents of package registered by last update of package.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProblemPackage {
+    /// Revision is increased by every update of package.
+    pub revision: i64,
+    /// Archive of package.
+    pub file_id: i64,
+    pub time_limit: DurationMs,
+    /// Memory limit in bytes.
+    pub memory_limit: u64,
+    pub tests: Vec<ProblemTest>,
+    /// Name of resource with source of checker.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checker: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiled_checker: Option<CompiledChecker>,
+}
+
+/// Checker that was compiled during update of package.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompiledChecker {
+    /// Compiler that is used to run checker.
+    pub compiler_id: i64,
+    /// Name of resource with binary of checker.
+    pub binary: String,
+}
+
+/// Names of resources with input and answer of test.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProblemTest {
+    pub input: String,
+    pub answer: String,
+}
 
 #[derive(Clone, Default, Debug, FromRow, IntoRow)]
 pub struct Problem {
     pub id: i64,
+    pub config: JSON,
+    pub deleted_at: Option<Instant>,
+}
+
+impl Problem {
+    pub fn set_config(&mut self, config: &ProblemConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    /// Returns config of problem, problem without config has default one.
+    pub fn parse_config(&self) -> Result<ProblemConfig, Error> {
+        if self.config == JSON::default() {
+            return Ok(Default::default());
+        }
+        self.config.parse_as()
+    }
 }
 
 impl Object for Problem {
@@ -21,6 +83,16 @@ impl Object for Problem {
     }
 }
 
+impl SoftDelete for Problem {
+    fn deleted_at(&self) -> Option<Instant> {
+        self.deleted_at
+    }
+
+    fn set_deleted_at(&mut self, time: Option<Instant>) {
+        self.deleted_at = time;
+    }
+}
+
 pub type ProblemEvent = BaseEvent<Problem>;
 
 pub struct ProblemStore(PersistentStore<Problem>);
@@ -33,6 +105,19 @@ impl ProblemStore {
             "solve_problem_event",
         ))
     }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::text("config"),
+                Column::big_int("deleted_at").nullable(),
+            ])
+            .await
+    }
+
+    pub async fn soft_delete(&self, ctx: Context<'_, '_>, id: i64) -> Result<ProblemEvent, Error> {
+        self.0.soft_delete(ctx, id).await
+    }
 }
 
 object_store_impl!(ProblemStore, Problem, ProblemEvent);
@@ -0,0 +1,181 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow, Value};
+use solve_db_types::JSON;
+
+use crate::core::Error;
+use crate::db::builder::{column, exists, Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Object, ObjectStore,
+    PersistentStore,
+};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
+#[repr(i8)]
+#[serde(rename_all = "snake_case")]
+pub enum ProblemResourceKind {
+    #[default]
+    Attachment = 1,
+    Statement = 2,
+    Image = 3,
+    /// Input or answer of test from problem package.
+    Test = 4,
+    /// Source of checker from problem package.
+    Checker = 5,
+    Unknown(i8),
+}
+
+impl std::fmt::Display for ProblemResourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProblemResourceConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct ProblemResource {
+    pub id: i64,
+    pub problem_id: i64,
+    pub kind: ProblemResourceKind,
+    pub name: String,
+    pub file_id: i64,
+    pub config: JSON,
+}
+
+impl ProblemResource {
+    pub fn set_config(&mut self, config: &ProblemResourceConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_config(&self) -> Result<ProblemResourceConfig, Error> {
+        self.config.parse_as()
+    }
+}
+
+impl Object for ProblemResource {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+pub type ProblemResourceEvent = BaseEvent<ProblemResource>;
+
+pub struct ProblemResourceStore(PersistentStore<ProblemResource>);
+
+impl ProblemResourceStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_problem_resource",
+            "solve_problem_resource_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("problem_id"),
+                Column::big_int("kind"),
+                Column::text("name"),
+                Column::big_int("file_id"),
+                Column::text("config"),
+            ])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_problem_resource_name_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["problem_id".to_owned(), "name".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns resources of problem ordered by name.
+    pub async fn find_by_problem<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        problem_id: i64,
+    ) -> Result<Vec<ProblemResource>, Error> {
+        let mut rows = self
+            .find(
+                ctx,
+                Select::new().with_where(column("problem_id").equal(problem_id)),
+            )
+            .await?;
+        let mut resources = Vec::new();
+        while let Some(resource) = rows.next().await {
+            resources.push(resource?);
+        }
+        resources.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(resources)
+    }
+
+    /// Creates resource if problem has no other resource with the same name.
+    pub async fn create_resource(
+        &self,
+        ctx: Context<'_, '_>,
+        resource: ProblemResource,
+    ) -> Result<ProblemResourceEvent, Error> {
+        if ctx.tx.is_some() {
+            return self.create_resource_tx(ctx, resource).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let event = self
+            .create_resource_tx(ctx.with_tx(&mut tx), resource)
+            .await?;
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn create_resource_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        resource: ProblemResource,
+    ) -> Result<ProblemResourceEvent, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let predicate = column("problem_id")
+            .equal(resource.problem_id)
+            .and(column("name").equal(resource.name.clone()));
+        let count = self
+            .count(Context::new().with_tx(tx), predicate.clone())
+            .await?;
+        if count > 0 {
+            return Err(format!(
+                "Problem {} already has resource with name {:?}",
+                resource.problem_id, resource.name
+            )
+            .into());
+        }
+        let predicate = !exists(
+            Select::new()
+                .with_table(self.0.table())
+                .with_columns(vec![ProblemResource::ID.to_owned()])
+                .with_where(predicate),
+        );
+        self.create_where(ctx.with_tx(tx), resource, predicate)
+            .await
+    }
+}
+
+object_store_impl!(ProblemResourceStore, ProblemResource, ProblemResourceEvent);
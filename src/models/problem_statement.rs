@@ -0,0 +1,201 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::JSON;
+
+use crate::core::Error;
+use crate::db::builder::{column, Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, AsyncIter, BaseEvent, Context, Object, ObjectStore, PersistentStore,
+};
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementFormat {
+    Html,
+    #[default]
+    Markdown,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProblemStatementConfig {
+    #[serde(default)]
+    pub format: StatementFormat,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub legend: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub input: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub output: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub notes: String,
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct ProblemStatement {
+    pub id: i64,
+    pub problem_id: i64,
+    pub locale: String,
+    pub title: String,
+    pub config: JSON,
+}
+
+impl ProblemStatement {
+    pub fn set_config(&mut self, config: &ProblemStatementConfig) -> Result<(), Error> {
+        self.config = JSON::from_serialize(config)?;
+        Ok(())
+    }
+
+    pub fn parse_config(&self) -> Result<ProblemStatementConfig, Error> {
+        self.config.parse_as()
+    }
+}
+
+impl Object for ProblemStatement {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.locale.is_empty()
+    }
+}
+
+pub type ProblemStatementEvent = BaseEvent<ProblemStatement>;
+
+struct Locales {
+    allowed: Vec<String>,
+    default: String,
+}
+
+pub struct ProblemStatementStore(PersistentStore<ProblemStatement>, Locales);
+
+impl ProblemStatementStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(
+            PersistentStore::new(
+                db,
+                "solve_problem_statement",
+                "solve_problem_statement_event",
+            ),
+            Locales {
+                allowed: vec!["en".to_owned(), "ru".to_owned()],
+                default: "en".to_owned(),
+            },
+        )
+    }
+
+    /// Sets locales that statements can be written in.
+    pub fn with_locales(mut self, locales: Vec<String>) -> Self {
+        self.1.allowed = locales;
+        self
+    }
+
+    /// Sets locale that is used when statement in requested locale is missing.
+    pub fn with_default_locale(mut self, locale: String) -> Self {
+        self.1.default = locale;
+        self
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("problem_id"),
+                Column::text("locale"),
+                Column::text("title"),
+                Column::text("config"),
+            ])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_problem_statement_locale_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["problem_id".to_owned(), "locale".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns statements of problem ordered by locale.
+    pub async fn find_by_problem<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        problem_id: i64,
+    ) -> Result<Vec<ProblemStatement>, Error> {
+        let mut rows = self
+            .find(
+                ctx,
+                Select::new().with_where(column("problem_id").equal(problem_id)),
+            )
+            .await?;
+        let mut statements = Vec::new();
+        while let Some(statement) = rows.next().await {
+            statements.push(statement?);
+        }
+        statements.sort_by(|a, b| a.locale.cmp(&b.locale));
+        Ok(statements)
+    }
+
+    /// Returns statement of problem in specified locale.
+    ///
+    /// Falls back to statement in default locale and then to any statement.
+    pub async fn find_by_problem_locale<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        problem_id: i64,
+        locale: &str,
+    ) -> Result<Option<ProblemStatement>, Error> {
+        let statements = self.find_by_problem(ctx, problem_id).await?;
+        let position = statements
+            .iter()
+            .position(|v| v.locale == locale)
+            .or_else(|| statements.iter().position(|v| v.locale == self.1.default));
+        Ok(match position {
+            Some(i) => statements.into_iter().nth(i),
+            None => statements.into_iter().next(),
+        })
+    }
+
+    /// Creates statement if its locale is allowed.
+    pub async fn create_statement(
+        &self,
+        ctx: Context<'_, '_>,
+        statement: ProblemStatement,
+    ) -> Result<ProblemStatementEvent, Error> {
+        self.check_locale(&statement.locale)?;
+        self.create(ctx, statement).await
+    }
+
+    /// Updates statement if its locale is allowed.
+    pub async fn update_statement(
+        &self,
+        ctx: Context<'_, '_>,
+        statement: ProblemStatement,
+    ) -> Result<ProblemStatementEvent, Error> {
+        self.check_locale(&statement.locale)?;
+        self.update(ctx, statement).await
+    }
+
+    fn check_locale(&self, locale: &str) -> Result<(), Error> {
+        if !self.1.allowed.iter().any(|v| v == locale) {
+            return Err(format!("Unknown locale: {:?}", locale).into());
+        }
+        Ok(())
+    }
+}
+
+object_store_impl!(
+    ProblemStatementStore,
+    ProblemStatement,
+    ProblemStatementEvent
+);
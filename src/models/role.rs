@@ -0,0 +1,330 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use solve_db::{Database, FromRow, IntoRow};
+
+use crate::core::Error;
+use crate::db::builder::{Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Event, Object, ObjectStore,
+    PersistentStore,
+};
+
+pub const GUEST_GROUP_ROLE: &str = "guest_group";
+pub const USER_GROUP_ROLE: &str = "user_group";
+pub const ADMIN_GROUP_ROLE: &str = "admin_group";
+
+pub const LOGIN_ROLE: &str = "login";
+pub const LOGOUT_ROLE: &str = "logout";
+pub const REGISTER_ROLE: &str = "register";
+pub const STATUS_ROLE: &str = "status";
+pub const OBSERVE_PROBLEMS_ROLE: &str = "observe_problems";
+pub const OBSERVE_CONTESTS_ROLE: &str = "observe_contests";
+pub const CREATE_PROBLEM_ROLE: &str = "create_problem";
+pub const CREATE_CONTEST_ROLE: &str = "create_contest";
+pub const OBSERVE_SETTINGS_ROLE: &str = "observe_settings";
+pub const UPDATE_SETTINGS_ROLE: &str = "update_settings";
+
+/// Returns built-in roles with their children.
+pub fn builtin_roles() -> Vec<(&'static str, Vec<&'static str>)> {
+    vec![
+        (LOGIN_ROLE, vec![]),
+        (LOGOUT_ROLE, vec![]),
+        (REGISTER_ROLE, vec![]),
+        (STATUS_ROLE, vec![]),
+        (OBSERVE_PROBLEMS_ROLE, vec![]),
+        (OBSERVE_CONTESTS_ROLE, vec![]),
+        (CREATE_PROBLEM_ROLE, vec![]),
+        (CREATE_CONTEST_ROLE, vec![]),
+        (OBSERVE_SETTINGS_ROLE, vec![]),
+        (UPDATE_SETTINGS_ROLE, vec![]),
+        (
+            GUEST_GROUP_ROLE,
+            vec![
+                LOGIN_ROLE,
+                REGISTER_ROLE,
+                STATUS_ROLE,
+                OBSERVE_PROBLEMS_ROLE,
+                OBSERVE_CONTESTS_ROLE,
+            ],
+        ),
+        (
+            USER_GROUP_ROLE,
+            vec![
+                LOGOUT_ROLE,
+                STATUS_ROLE,
+                OBSERVE_PROBLEMS_ROLE,
+                OBSERVE_CONTESTS_ROLE,
+            ],
+        ),
+        (
+            ADMIN_GROUP_ROLE,
+            vec![
+                USER_GROUP_ROLE,
+                CREATE_PROBLEM_ROLE,
+                CREATE_CONTEST_ROLE,
+                OBSERVE_SETTINGS_ROLE,
+                UPDATE_SETTINGS_ROLE,
+            ],
+        ),
+    ]
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Role {
+    pub id: i64,
+    pub name: String,
+}
+
+impl Object for Role {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.name.is_empty()
+    }
+}
+
+pub type RoleEvent = BaseEvent<Role>;
+
+pub struct RoleStore(PersistentStore<Role>);
+
+impl RoleStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(db, "solve_role", "solve_role_event"))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0.create_tables(vec![Column::text("name")]).await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_role_name_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["name".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+object_store_impl!(RoleStore, Role, RoleEvent);
+
+/// Edge from role to its child role.
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct RoleEdge {
+    pub id: i64,
+    pub role_id: i64,
+    pub child_id: i64,
+}
+
+impl Object for RoleEdge {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        self.role_id != self.child_id
+    }
+}
+
+pub type RoleEdgeEvent = BaseEvent<RoleEdge>;
+
+pub struct RoleEdgeStore(PersistentStore<RoleEdge>);
+
+impl RoleEdgeStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_role_edge",
+            "solve_role_edge_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("role_id"),
+                Column::big_int("child_id"),
+            ])
+            .await
+    }
+}
+
+object_store_impl!(RoleEdgeStore, RoleEdge, RoleEdgeEvent);
+
+/// Creates missing built-in roles and edges between them.
+pub async fn create_builtin_roles(
+    ctx: Context<'_, '_>,
+    roles: &RoleStore,
+    edges: &RoleEdgeStore,
+) -> Result<(), Error> {
+    if ctx.tx.is_some() {
+        return create_builtin_roles_tx(ctx, roles, edges).await;
+    }
+    let mut tx = roles.0.db().transaction(write_tx_options()).await?;
+    create_builtin_roles_tx(ctx.with_tx(&mut tx), roles, edges).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+async fn create_builtin_roles_tx(
+    mut ctx: Context<'_, '_>,
+    roles: &RoleStore,
+    edges: &RoleEdgeStore,
+) -> Result<(), Error> {
+    assert!(ctx.tx.is_some(), "transaction is required");
+    let mut role_ids = HashMap::new();
+    {
+        let mut rows = roles.find(ctx.reborrow(), Select::new()).await?;
+        while let Some(role) = rows.next().await {
+            let role = role?;
+            role_ids.insert(role.name, role.id);
+        }
+    }
+    let mut edge_ids = HashSet::new();
+    {
+        let mut rows = edges.find(ctx.reborrow(), Select::new()).await?;
+        while let Some(edge) = rows.next().await {
+            let edge = edge?;
+            edge_ids.insert((edge.role_id, edge.child_id));
+        }
+    }
+    let builtin = builtin_roles();
+    for (name, _) in &builtin {
+        if role_ids.contains_key(*name) {
+            continue;
+        }
+        let role = Role {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let event = roles.create(ctx.reborrow(), role).await?;
+        role_ids.insert(name.to_string(), event.object().id);
+    }
+    for (name, children) in &builtin {
+        let role_id = role_ids[*name];
+        for child in children {
+            let child_id = role_ids[*child];
+            if edge_ids.contains(&(role_id, child_id)) {
+                continue;
+            }
+            let edge = RoleEdge {
+                role_id,
+                child_id,
+                ..Default::default()
+            };
+            edges.create(ctx.reborrow(), edge).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Resolves roles using graph of role edges.
+#[derive(Clone, Debug, Default)]
+pub struct RoleSet {
+    names: HashMap<i64, String>,
+    children: HashMap<i64, Vec<i64>>,
+}
+
+impl RoleSet {
+    /// Builds role graph rejecting unknown roles and cycles.
+    pub fn new(roles: Vec<Role>, edges: Vec<RoleEdge>) -> Result<Self, Error> {
+        let names: HashMap<_, _> = roles.into_iter().map(|v| (v.id, v.name)).collect();
+        let mut children: HashMap<i64, Vec<i64>> = HashMap::new();
+        for edge in edges {
+            for id in [edge.role_id, edge.child_id] {
+                if !names.contains_key(&id) {
+                    return Err(format!("Unknown role with id: {id}").into());
+                }
+            }
+            children
+                .entry(edge.role_id)
+                .or_default()
+                .push(edge.child_id);
+        }
+        let set = Self { names, children };
+        set.check_cycles()?;
+        Ok(set)
+    }
+
+    pub async fn load(roles: &RoleStore, edges: &RoleEdgeStore) -> Result<Self, Error> {
+        let mut role_list = Vec::new();
+        let mut rows = roles.find(Context::new(), Select::new()).await?;
+        while let Some(role) = rows.next().await {
+            role_list.push(role?);
+        }
+        let mut edge_list = Vec::new();
+        let mut rows = edges.find(Context::new(), Select::new()).await?;
+        while let Some(edge) = rows.next().await {
+            edge_list.push(edge?);
+        }
+        Self::new(role_list, edge_list)
+    }
+
+    /// Returns names of specified roles and all their descendants.
+    pub fn get_names(&self, ids: &[i64]) -> HashSet<String> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<_> = ids.to_vec();
+        while let Some(id) = stack.pop() {
+            if !visited.insert(id) {
+                continue;
+            }
+            if let Some(children) = self.children.get(&id) {
+                stack.extend(children);
+            }
+        }
+        visited
+            .into_iter()
+            .filter_map(|id| self.names.get(&id).cloned())
+            .collect()
+    }
+
+    fn check_cycles(&self) -> Result<(), Error> {
+        // Roles that are completely visited are marked with true, roles
+        // that are on current path are marked with false.
+        let mut state: HashMap<i64, bool> = HashMap::new();
+        for &root in self.names.keys() {
+            if state.contains_key(&root) {
+                continue;
+            }
+            let mut stack = vec![(root, 0)];
+            state.insert(root, false);
+            while let Some((id, pos)) = stack.last_mut() {
+                let children = self.children.get(id).map(Vec::as_slice).unwrap_or(&[]);
+                let Some(&child) = children.get(*pos) else {
+                    state.insert(*id, true);
+                    stack.pop();
+                    continue;
+                };
+                *pos += 1;
+                match state.get(&child) {
+                    Some(true) => {}
+                    Some(false) => {
+                        return Err(format!("Cycle detected at role: {}", self.names[&child]).into())
+                    }
+                    None => {
+                        state.insert(child, false);
+                        stack.push((child, 0));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
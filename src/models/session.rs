@@ -0,0 +1,133 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::{Instant, IpAddr};
+
+use crate::core::Error;
+use crate::db::builder::{column, Column};
+
+use super::user::constant_time_eq;
+use super::{object_store_impl, BaseEvent, Context, Event, Object, ObjectStore, PersistentStore};
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Session {
+    pub id: i64,
+    pub account_id: i64,
+    pub secret: String,
+    pub create_time: Instant,
+    pub expire_time: Instant,
+    pub real_ip: IpAddr,
+    pub user_agent: String,
+}
+
+impl Session {
+    /// Generates new random secret.
+    pub fn generate_secret(&mut self) {
+        let secret: [u8; 32] = rand::random();
+        self.secret = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret);
+    }
+
+    /// Returns cookie value in `{id}_{secret}` format.
+    pub fn cookie(&self) -> String {
+        format!("{}_{}", self.id, self.secret)
+    }
+}
+
+impl Object for Session {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+}
+
+pub type SessionEvent = BaseEvent<Session>;
+
+pub struct SessionStore(PersistentStore<Session>);
+
+impl SessionStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(
+            db,
+            "solve_session",
+            "solve_session_event",
+        ))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("account_id"),
+                Column::text("secret"),
+                Column::big_int("create_time"),
+                Column::big_int("expire_time"),
+                Column::text("real_ip"),
+                Column::text("user_agent"),
+            ])
+            .await
+    }
+
+    pub async fn create_for_account(
+        &self,
+        ctx: Context<'_, '_>,
+        account_id: i64,
+        ttl: Duration,
+    ) -> Result<Session, Error> {
+        let now = Instant::now();
+        let mut session = Session {
+            account_id,
+            create_time: now,
+            expire_time: now + ttl,
+            ..Default::default()
+        };
+        session.generate_secret();
+        Ok(self.create(ctx, session).await?.into_object())
+    }
+
+    /// Returns session for cookie value if secret matches and session is not expired.
+    pub async fn find_by_cookie<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        value: &str,
+    ) -> Result<Option<Session>, Error> {
+        let Some((id, secret)) = value.split_once('_') else {
+            return Ok(None);
+        };
+        let Ok(id) = id.parse() else {
+            return Ok(None);
+        };
+        let Some(session) = self.get(ctx, id).await? else {
+            return Ok(None);
+        };
+        if !constant_time_eq(session.secret.as_bytes(), secret.as_bytes()) {
+            return Ok(None);
+        }
+        if session.expire_time <= Instant::now() {
+            return Ok(None);
+        }
+        Ok(Some(session))
+    }
+
+    /// Deletes sessions that expired before specified time.
+    ///
+    /// Returns number of deleted sessions.
+    pub async fn delete_expired(
+        &self,
+        ctx: Context<'_, '_>,
+        before: Instant,
+    ) -> Result<usize, Error> {
+        let events = self
+            .0
+            .delete_all_where(ctx, column("expire_time").less(before))
+            .await?;
+        Ok(events.len())
+    }
+}
+
+object_store_impl!(SessionStore, Session, SessionEvent);
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use solve_db::{Database, FromRow, IntoRow};
+use tokio::sync::RwLock;
+
+use crate::core::Error;
+use crate::db::builder::{column, Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Object, ObjectStore,
+    PersistentStore,
+};
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Setting {
+    pub id: i64,
+    pub key: String,
+    pub value: String,
+}
+
+impl Object for Setting {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.key.is_empty()
+    }
+}
+
+pub type SettingEvent = BaseEvent<Setting>;
+
+struct CachedSettings {
+    values: HashMap<String, String>,
+    load_time: std::time::Instant,
+}
+
+struct SettingCache {
+    ttl: Duration,
+    settings: RwLock<Option<CachedSettings>>,
+}
+
+pub struct SettingStore(PersistentStore<Setting>, SettingCache);
+
+impl SettingStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(
+            PersistentStore::new(db, "solve_setting", "solve_setting_event"),
+            SettingCache {
+                ttl: Duration::from_secs(30),
+                settings: Default::default(),
+            },
+        )
+    }
+
+    /// Sets how long loaded settings are cached.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.1.ttl = ttl;
+        self
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![Column::text("key"), Column::text("value")])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_setting_key_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["key".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Returns raw value of setting.
+    pub async fn get_value(&self, key: &str) -> Result<Option<String>, Error> {
+        {
+            let settings = self.1.settings.read().await;
+            if let Some(settings) = settings.as_ref() {
+                if settings.load_time.elapsed() < self.1.ttl {
+                    return Ok(settings.values.get(key).cloned());
+                }
+            }
+        }
+        let mut values = HashMap::new();
+        let mut rows = self.find(Context::new(), Select::new()).await?;
+        while let Some(setting) = rows.next().await {
+            let setting = setting?;
+            values.insert(setting.key, setting.value);
+        }
+        let value = values.get(key).cloned();
+        *self.1.settings.write().await = Some(CachedSettings {
+            values,
+            load_time: std::time::Instant::now(),
+        });
+        Ok(value)
+    }
+
+    pub async fn get_string(&self, key: &str, default: &str) -> Result<String, Error> {
+        Ok(self
+            .get_value(key)
+            .await?
+            .unwrap_or_else(|| default.to_owned()))
+    }
+
+    /// Returns integer value of setting or default if it cannot be parsed.
+    pub async fn get_i64(&self, key: &str, default: i64) -> Result<i64, Error> {
+        Ok(self
+            .get_value(key)
+            .await?
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(default))
+    }
+
+    /// Returns boolean value of setting or default if it cannot be parsed.
+    pub async fn get_bool(&self, key: &str, default: bool) -> Result<bool, Error> {
+        Ok(self
+            .get_value(key)
+            .await?
+            .and_then(|v| match v.trim() {
+                "true" | "1" => Some(true),
+                "false" | "0" => Some(false),
+                _ => None,
+            })
+            .unwrap_or(default))
+    }
+
+    /// Returns duration value of setting or default if it cannot be parsed.
+    ///
+    /// Duration is specified with `ms`, `s`, `m` or `h` suffix, plain number
+    /// is treated as amount of seconds.
+    pub async fn get_duration(&self, key: &str, default: Duration) -> Result<Duration, Error> {
+        Ok(self
+            .get_value(key)
+            .await?
+            .and_then(|v| parse_duration(v.trim()))
+            .unwrap_or(default))
+    }
+
+    /// Creates or updates setting.
+    pub async fn set(
+        &self,
+        ctx: Context<'_, '_>,
+        key: &str,
+        value: &str,
+    ) -> Result<SettingEvent, Error> {
+        let event = if ctx.tx.is_some() {
+            self.set_tx(ctx, key, value).await?
+        } else {
+            let mut tx = self.0.db().transaction(write_tx_options()).await?;
+            let event = self.set_tx(ctx.with_tx(&mut tx), key, value).await?;
+            tx.commit().await?;
+            event
+        };
+        self.1.settings.write().await.take();
+        Ok(event)
+    }
+
+    async fn set_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        key: &str,
+        value: &str,
+    ) -> Result<SettingEvent, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let setting = {
+            let mut rows = self
+                .find(
+                    Context::new().with_tx(tx),
+                    Select::new()
+                        .with_where(column("key").equal(key))
+                        .with_limit(1),
+                )
+                .await?;
+            match rows.next().await {
+                Some(v) => Some(v?),
+                None => None,
+            }
+        };
+        match setting {
+            Some(setting) => {
+                let setting = Setting {
+                    value: value.to_owned(),
+                    ..setting
+                };
+                self.update(ctx.with_tx(tx), setting).await
+            }
+            None => {
+                let setting = Setting {
+                    key: key.to_owned(),
+                    value: value.to_owned(),
+                    ..Default::default()
+                };
+                self.create(ctx.with_tx(tx), setting).await
+            }
+        }
+    }
+}
+
+pub(super) fn parse_duration(value: &str) -> Option<Duration> {
+    let (value, scale) = if let Some(v) = value.strip_suffix("ms") {
+        (v, 1)
+    } else if let Some(v) = value.strip_suffix('s') {
+        (v, 1000)
+    } else if let Some(v) = value.strip_suffix('m') {
+        (v, 60 * 1000)
+    } else if let Some(v) = value.strip_suffix('h') {
+        (v, 60 * 60 * 1000)
+    } else {
+        (value, 1000)
+    };
+    let value: u64 = value.parse().ok()?;
+    Some(Duration::from_millis(value.checked_mul(scale)?))
+}
+
+object_store_impl!(SettingStore, Setting, SettingEvent);
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
-use solve_db::{Database, FromRow, IntoRow, Value};
-use solve_db_types::{Instant, JSON};
+use solve_db::{Database, FromRow, IntoRow, IntoValue, Value};
+use solve_db_types::{Instant, Score, JSON};
 
 use crate::core::Error;
+use crate::db::builder::{column, Column};
 
-use super::{object_store_impl, BaseEvent, Object, PersistentStore};
+use super::{
+    object_store_impl, BaseEvent, Context, Object, ObjectStore, PersistentStore, StoreError,
+};
 
 #[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
 #[repr(i8)]
@@ -47,9 +50,69 @@ impl std::fmt::Display for Verdict {
     }
 }
 
-#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    #[serde(default)]
+    pub time_ms: u64,
+    #[serde(default)]
+    pub memory_bytes: u64,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TestReport {
+    pub verdict: Verdict,
+    #[serde(default)]
+    pub time_ms: u64,
+    #[serde(default)]
+    pub memory_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points: Option<Score>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check_log: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_file_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_file_id: Option<i64>,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
 pub struct JudgeReport {
     pub verdict: Verdict,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub points: Option<Score>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<TestReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<UsageReport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compile_log: Option<String>,
+}
+
+impl JudgeReport {
+    /// Returns overall verdict computed from verdicts of tests.
+    ///
+    /// Returns none if report has no tests.
+    pub fn aggregate_verdict(&self) -> Option<Verdict> {
+        if self.tests.is_empty() {
+            return None;
+        }
+        if self.tests.iter().any(|v| v.verdict == Verdict::Failed) {
+            return Some(Verdict::Failed);
+        }
+        let first_rejected = self.tests.iter().find(|v| v.verdict != Verdict::Accepted);
+        let verdict = match first_rejected {
+            Some(test) => {
+                let any_accepted = self.tests.iter().any(|v| v.verdict == Verdict::Accepted);
+                if self.points.is_some() && any_accepted {
+                    Verdict::PartiallyAccepted
+                } else {
+                    test.verdict
+                }
+            }
+            None => Verdict::Accepted,
+        };
+        Some(verdict)
+    }
 }
 
 #[derive(Clone, Default, Debug, FromRow, IntoRow)]
@@ -67,12 +130,12 @@ pub struct Solution {
 
 impl Solution {
     pub fn set_report(&mut self, report: Option<JudgeReport>) -> Result<(), Error> {
-        self.report = serde_json::to_value(report)?.into();
+        self.report = JSON::from_serialize(report)?;
         Ok(())
     }
 
     pub fn parse_report(&self) -> Result<Option<JudgeReport>, Error> {
-        Ok(serde_json::from_value(self.report.clone().into())?)
+        self.report.parse_as()
     }
 }
 
@@ -104,6 +167,43 @@ impl SolutionStore {
             "solve_solution_event",
         ))
     }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("kind"),
+                Column::big_int("problem_id"),
+                Column::big_int("compiler_id"),
+                Column::big_int("author_id"),
+                Column::text("report"),
+                Column::big_int("create_time"),
+                Column::text("content").nullable(),
+                Column::big_int("content_id").nullable(),
+            ])
+            .await
+    }
+
+    /// Replaces report of solution keeping other fields.
+    pub async fn update_report(
+        &self,
+        ctx: Context<'_, '_>,
+        id: i64,
+        report: Option<&JudgeReport>,
+    ) -> Result<(), Error> {
+        let report = JSON::from_serialize(report)?;
+        let count = self
+            .update_fields_where(
+                ctx,
+                vec![("report".into(), report.into_value())],
+                column("id").equal(id),
+                true,
+            )
+            .await?;
+        if count == 0 {
+            return Err(StoreError::NotFound.into());
+        }
+        Ok(())
+    }
 }
 
 object_store_impl!(SolutionStore, Solution, SolutionEvent);
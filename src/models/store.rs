@@ -1,13 +1,36 @@
-use solve_db::Transaction;
+use std::collections::{HashMap, VecDeque};
+
+use solve_db::{ConnectionOptions, Transaction, Value};
 
 use crate::core::Error;
-use crate::db::builder::{Predicate, Select};
+use crate::db::builder::{column, Order, Predicate, Select};
+
+use super::{Event, Object, Page, PageRequest};
+
+/// Consistency of reads that are performed outside of transaction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadConsistency {
+    /// Reads observe all committed writes.
+    ///
+    /// Read-modify-write sequences should always use strong reads.
+    #[default]
+    Strong,
+    /// Reads can be served by replicas that lag behind primary.
+    Eventual,
+}
 
-use super::{Event, Object};
+impl ReadConsistency {
+    pub fn connection_options(self) -> ConnectionOptions {
+        ConnectionOptions {
+            read_only: self == ReadConsistency::Eventual,
+        }
+    }
+}
 
 pub struct Context<'a, 'b> {
     pub tx: Option<&'a mut Transaction<'b>>,
     pub account_id: Option<i64>,
+    pub consistency: ReadConsistency,
 }
 
 impl<'a, 'b> Context<'a, 'b> {
@@ -15,6 +38,7 @@ impl<'a, 'b> Context<'a, 'b> {
         Self {
             tx: Default::default(),
             account_id: Default::default(),
+            consistency: Default::default(),
         }
     }
 
@@ -24,6 +48,31 @@ impl<'a, 'b> Context<'a, 'b> {
             ..self
         }
     }
+
+    /// Sets account that is recorded on written events.
+    pub fn with_account_id(self, account_id: i64) -> Self {
+        Self {
+            account_id: Some(account_id),
+            ..self
+        }
+    }
+
+    /// Sets consistency of reads outside of transaction.
+    pub fn with_consistency(self, consistency: ReadConsistency) -> Self {
+        Self {
+            consistency,
+            ..self
+        }
+    }
+
+    /// Returns context that shares transaction and account with this one.
+    pub fn reborrow(&mut self) -> Context<'_, 'b> {
+        Context {
+            tx: self.tx.as_deref_mut(),
+            account_id: self.account_id,
+            consistency: self.consistency,
+        }
+    }
 }
 
 impl<'a, 'b> Default for Context<'a, 'b> {
@@ -32,6 +81,72 @@ impl<'a, 'b> Default for Context<'a, 'b> {
     }
 }
 
+/// Error returned by writes of stores.
+///
+/// Writes of `PersistentStore` always return errors that can be downcasted
+/// to this type.
+#[derive(Debug)]
+pub enum StoreError {
+    /// Object to write does not exist.
+    NotFound,
+    /// Object was modified concurrently or does not match predicate of write.
+    Conflict,
+    InvalidObject(String),
+    Database(Error),
+}
+
+impl StoreError {
+    /// Returns store error of boxed error if any.
+    pub fn of(err: &Error) -> Option<&StoreError> {
+        err.downcast_ref()
+    }
+
+    pub fn is_not_found(err: &Error) -> bool {
+        matches!(Self::of(err), Some(StoreError::NotFound))
+    }
+
+    pub fn is_conflict(err: &Error) -> bool {
+        matches!(Self::of(err), Some(StoreError::Conflict))
+    }
+
+    /// Wraps error into `StoreError::Database` unless it is store error.
+    pub(crate) fn wrap(err: Error) -> Error {
+        if err.is::<StoreError>() {
+            return err;
+        }
+        StoreError::Database(err).into()
+    }
+}
+
+impl std::fmt::Display for StoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreError::NotFound => f.write_str("Object does not exist"),
+            StoreError::Conflict => f.write_str("Object was modified concurrently"),
+            StoreError::InvalidObject(v) => write!(f, "Invalid object: {v}"),
+            StoreError::Database(v) => v.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StoreError::Database(v) => Some(v.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// Snapshot of write transactions of database.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteHorizon {
+    /// All transactions with lower ids are finished.
+    pub xmin: i64,
+    /// All transactions with greater or equal ids are not started yet.
+    pub xmax: i64,
+}
+
 #[async_trait::async_trait]
 pub trait AsyncIter<'a>: Send {
     type Item;
@@ -49,24 +164,136 @@ pub trait ObjectStore: Send {
     where
         Self: 'a;
 
+    type FindEventsIter<'a>: AsyncIter<'a, Item = Self::Event>
+    where
+        Self: 'a;
+
     async fn find<'a>(
         &'a self,
         ctx: Context<'a, '_>,
         select: Select,
     ) -> Result<Self::FindIter<'a>, Error>;
 
+    /// Finds events ordered by id.
+    async fn find_events<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        select: Select,
+    ) -> Result<Self::FindEventsIter<'a>, Error>;
+
+    /// Finds events ordered by id reading them in chunks of bounded size.
+    ///
+    /// Connection is held only while chunk is being read. Select must not
+    /// have order and limit, because they are used for continuation.
+    async fn find_events_chunked<'a, 'b>(
+        &'a self,
+        ctx: Context<'a, 'b>,
+        select: Select,
+        chunk_size: usize,
+    ) -> Result<EventsChunkedIter<'a, 'b, Self>, Error>
+    where
+        Self: Sized + Sync,
+    {
+        assert!(chunk_size > 0);
+        check_chunked_select(&select)?;
+        Ok(EventsChunkedIter {
+            store: self,
+            tx: ctx.tx,
+            select,
+            chunk_size,
+            last_id: None,
+            chunk: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Returns id of last written event or zero if there are no events.
+    async fn last_event_id(&self, ctx: Context<'_, '_>) -> Result<i64, Error> {
+        let select = Select::new()
+            .with_order(vec![Order::desc(<Self::Event as Event>::ID)])
+            .with_limit(1);
+        let mut events = self.find_events(ctx, select).await?;
+        match events.next().await {
+            Some(event) => Ok(event?.id()),
+            None => Ok(0),
+        }
+    }
+
+    /// Returns horizon of write transactions if database can provide it.
+    async fn write_horizon(&self, _ctx: Context<'_, '_>) -> Result<Option<WriteHorizon>, Error> {
+        Ok(None)
+    }
+
     async fn get<'a>(
         &'a self,
         ctx: Context<'a, '_>,
         id: Self::Id,
     ) -> Result<Option<Self::Object>, Error>;
 
+    /// Returns objects with specified ids.
+    ///
+    /// Missing objects are absent in result.
+    async fn get_many<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        ids: &[Self::Id],
+    ) -> Result<HashMap<Self::Id, Self::Object>, Error>;
+
+    /// Finds page of objects after cursor of request.
+    async fn find_page<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        request: PageRequest,
+    ) -> Result<Page<Self::Object>, Error>;
+
+    async fn count<'a>(&'a self, ctx: Context<'a, '_>, predicate: Predicate) -> Result<i64, Error> {
+        let mut iter = self.find(ctx, Select::new().with_where(predicate)).await?;
+        let mut count = 0;
+        while let Some(v) = iter.next().await {
+            v?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Returns true if at least one object matches predicate.
+    async fn exists<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        predicate: Predicate,
+    ) -> Result<bool, Error> {
+        let mut iter = self
+            .find(ctx, Select::new().with_where(predicate).with_limit(1))
+            .await?;
+        match iter.next().await {
+            Some(v) => v.map(|_| true),
+            None => Ok(false),
+        }
+    }
+
     async fn create(
         &self,
         ctx: Context<'_, '_>,
         object: Self::Object,
     ) -> Result<Self::Event, Error>;
 
+    /// Creates objects atomically with one event per object.
+    ///
+    /// Events are returned in the same order as objects.
+    async fn create_batch(
+        &self,
+        ctx: Context<'_, '_>,
+        objects: Vec<Self::Object>,
+    ) -> Result<Vec<Self::Event>, Error>;
+
+    /// Creates object only if predicate is satisfied.
+    async fn create_where(
+        &self,
+        ctx: Context<'_, '_>,
+        object: Self::Object,
+        predicate: Predicate,
+    ) -> Result<Self::Event, Error>;
+
     async fn update(
         &self,
         ctx: Context<'_, '_>,
@@ -80,6 +307,20 @@ pub trait ObjectStore: Send {
         predicate: Predicate,
     ) -> Result<Self::Event, Error>;
 
+    /// Updates fields of all objects matching predicate and returns amount
+    /// of updated objects.
+    ///
+    /// Events of updated objects are written only if `emit_events` is set,
+    /// otherwise single [`super::EventKind::BatchUpdate`] event is written
+    /// that tells observers and event consumers about update.
+    async fn update_fields_where(
+        &self,
+        ctx: Context<'_, '_>,
+        set: Vec<(String, Value)>,
+        predicate: Predicate,
+        emit_events: bool,
+    ) -> Result<u64, Error>;
+
     async fn delete(&self, ctx: Context<'_, '_>, id: Self::Id) -> Result<Self::Event, Error>;
 
     async fn delete_where(
@@ -89,3 +330,61 @@ pub trait ObjectStore: Send {
         predicate: Predicate,
     ) -> Result<Self::Event, Error>;
 }
+
+/// Returns error if select has order or limit that chunked find replaces.
+pub(super) fn check_chunked_select(select: &Select) -> Result<(), Error> {
+    if select.has_order() || select.has_limit() {
+        return Err("Chunked find does not support order and limit".into());
+    }
+    Ok(())
+}
+
+pub struct EventsChunkedIter<'a, 'b, S: ObjectStore> {
+    store: &'a S,
+    tx: Option<&'a mut Transaction<'b>>,
+    select: Select,
+    chunk_size: usize,
+    last_id: Option<i64>,
+    chunk: VecDeque<S::Event>,
+    done: bool,
+}
+
+impl<S: ObjectStore + Sync> EventsChunkedIter<'_, '_, S> {
+    async fn read_chunk(&mut self) -> Result<(), Error> {
+        let id_column = <S::Event as Event>::ID;
+        let mut select = self
+            .select
+            .clone()
+            .with_order_by(vec![id_column.to_owned()])
+            .with_limit(self.chunk_size);
+        if let Some(id) = self.last_id.take() {
+            select = select.and_where(column(id_column).greater(id));
+        }
+        let ctx = Context {
+            tx: self.tx.as_deref_mut(),
+            ..Context::new()
+        };
+        let mut rows = self.store.find_events(ctx, select).await?;
+        while let Some(event) = rows.next().await {
+            self.chunk.push_back(event?);
+        }
+        self.done = self.chunk.len() < self.chunk_size;
+        self.last_id = self.chunk.back().map(Event::id);
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<'a, S: ObjectStore + Sync> AsyncIter<'a> for EventsChunkedIter<'a, '_, S> {
+    type Item = S::Event;
+
+    async fn next(&mut self) -> Option<Result<Self::Item, Error>> {
+        if self.chunk.is_empty() && !self.done {
+            if let Err(err) = self.read_chunk().await {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.chunk.pop_front().map(Ok)
+    }
+}
@@ -1,19 +1,24 @@
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 use std::time::Duration;
 
+use rand::seq::SliceRandom as _;
+
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use solve_db::{Database, FromRow, IntoRow, Value};
+use solve_db::{
+    Database, FromRow, IntoRow, IntoValue, IsolationLevel, Row, TransactionOptions, Value,
+};
 use solve_db_types::{Instant, JSON};
 
 use crate::core::Error;
-use crate::db::builder::{column, Select};
-use crate::models::{write_tx_options, Context, ObjectStore};
+use crate::db::builder::{column, Column, Locking, Order, Predicate, Select};
+use crate::models::{Context, ObjectStore, StoreError};
 
-use super::{object_store_impl, AsyncIter, BaseEvent, Event, Object, PersistentStore};
+use super::{object_store_impl, AsyncIter, BaseEvent, Event, Object, PersistentStore, Versioned};
 
-#[derive(Clone, Copy, Default, Debug, PartialEq, Value, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash, Value, Serialize, Deserialize)]
 #[repr(i8)]
 #[serde(rename_all = "snake_case")]
 pub enum TaskKind {
@@ -38,16 +43,24 @@ pub enum TaskStatus {
     Running = 1,
     Succeeded = 2,
     Failed = 3,
+    Cancelled = 4,
     Unknown(i64),
 }
 
+impl TaskStatus {
+    /// Returns true if task will not be run anymore.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed | Self::Cancelled)
+    }
+}
+
 impl std::fmt::Display for TaskStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.serialize(f)
     }
 }
 
-#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+#[derive(Clone, Default, Debug, IntoRow)]
 pub struct Task {
     pub id: i64,
     pub kind: TaskKind,
@@ -55,25 +68,71 @@ pub struct Task {
     pub status: TaskStatus,
     pub state: JSON,
     pub expire_time: Option<Instant>,
+    pub version: i64,
+    /// Tasks with higher priority are taken first.
+    pub priority: i32,
+    /// Task is not taken until this time.
+    pub not_before: Option<Instant>,
+    /// Amount of failed attempts to run task.
+    pub attempts: i32,
+    /// Maximal amount of attempts to run task before it fails.
+    pub max_attempts: i32,
+    /// Time since which task is waiting in queue.
+    pub queued_time: Option<Instant>,
+    /// Time when task was taken last time.
+    pub start_time: Option<Instant>,
+    /// Time when task was finished.
+    pub finish_time: Option<Instant>,
+    /// Task is not taken until its parent task succeeds.
+    pub parent_id: Option<i64>,
+}
+
+impl FromRow for Task {
+    fn from_row(row: &Row) -> Result<Self, solve_db::Error> {
+        Ok(Self {
+            id: row.get_parsed("id")?,
+            kind: row.get_parsed("kind")?,
+            config: row.get_parsed("config")?,
+            status: row.get_parsed("status")?,
+            state: row.get_parsed("state")?,
+            expire_time: row.get_parsed("expire_time")?,
+            version: row.get_parsed("version")?,
+            // Tasks written before priorities were introduced have no priority.
+            priority: row
+                .get_parsed::<_, Option<i32>>("priority")?
+                .unwrap_or_default(),
+            not_before: row.get_parsed("not_before")?,
+            attempts: row
+                .get_parsed::<_, Option<i32>>("attempts")?
+                .unwrap_or_default(),
+            max_attempts: row
+                .get_parsed::<_, Option<i32>>("max_attempts")?
+                .unwrap_or_default(),
+            queued_time: row.get_parsed("queued_time")?,
+            start_time: row.get_parsed("start_time")?,
+            finish_time: row.get_parsed("finish_time")?,
+            parent_id: row.get_parsed("parent_id")?,
+        })
+    }
 }
 
 impl Task {
     pub fn set_config<T: Serialize>(&mut self, config: T) -> Result<(), Error> {
-        self.config = serde_json::to_value(config)?.into();
+        self.config = JSON::from_serialize(config)?;
         Ok(())
     }
 
     pub fn parse_config<T: DeserializeOwned>(&self) -> Result<T, Error> {
-        Ok(serde_json::from_value(self.config.clone().into())?)
+        self.config.parse_as()
     }
 
     pub fn set_state<T: Serialize>(&mut self, state: T) -> Result<(), Error> {
-        self.state = serde_json::to_value(state)?.into();
+        self.state = JSON::from_serialize(state)?;
         Ok(())
     }
 
     pub fn parse_state<T: DeserializeOwned>(&self) -> Result<T, Error> {
-        Ok(serde_json::from_value(self.state.clone().into())?)
+        self.state.parse_as()
     }
 }
 
@@ -93,14 +152,24 @@ impl Object for Task {
     }
 }
 
-#[derive(Clone, Default, Serialize, Deserialize)]
+impl Versioned for Task {
+    fn version(&self) -> i64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: i64) {
+        self.version = version;
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct JudgeSolutionTaskConfig {
     pub solution_id: i64,
     #[serde(default, skip_serializing_if = "<&bool as std::ops::Not>::not")]
     pub enable_points: bool,
 }
 
-#[derive(Clone, Default, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct UpdateProblemPackageTaskConfig {
     pub problem_id: i64,
     pub file_id: i64,
@@ -108,61 +177,371 @@ pub struct UpdateProblemPackageTaskConfig {
     pub compile: bool,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JudgeSolutionTaskState {
+    /// Number of test that is running now.
+    #[serde(default)]
+    pub test: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_tests: Option<u32>,
+    #[serde(default)]
+    pub compiled: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateProblemPackageStage {
+    #[default]
+    Download,
+    Extract,
+    Validate,
+    Compile,
+    Upload,
+    Save,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateProblemPackageTaskState {
+    #[serde(default)]
+    pub stage: UpdateProblemPackageStage,
+    /// Progress of current stage from 0 to 1.
+    #[serde(default)]
+    pub progress: f64,
+}
+
+/// Error of failed attempt to run task stored in `error` field of state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TaskError {
+    pub message: String,
+    /// Kind of error, e.g. `conflict` or `internal`.
+    pub kind: String,
+    pub time: Instant,
+}
+
+/// Restrictions of tasks that can be taken.
+#[derive(Clone, Debug, Default)]
+pub struct TakeOptions {
+    /// Only tasks of specified kinds are taken.
+    pub kinds: Option<Vec<TaskKind>>,
+    /// Only tasks with not lower priority are taken.
+    pub min_priority: Option<i32>,
+}
+
+impl TakeOptions {
+    pub fn with_kinds(mut self, kinds: Vec<TaskKind>) -> Self {
+        self.kinds = Some(kinds);
+        self
+    }
+
+    pub fn with_min_priority(mut self, min_priority: i32) -> Self {
+        self.min_priority = Some(min_priority);
+        self
+    }
+
+    fn predicate(&self) -> Predicate {
+        let succeeded = Select::new()
+            .with_table(TASK_TABLE)
+            .with_columns(vec![Task::ID.into()])
+            .with_where(column("status").equal(TaskStatus::Succeeded));
+        // Tasks of unknown kinds are filtered out in query, otherwise
+        // they would occupy the single locked row forever.
+        let mut predicate = column("status")
+            .equal(TaskStatus::Queued)
+            .and(column("kind").in_values(vec![
+                TaskKind::JudgeSolution,
+                TaskKind::UpdateProblemPackage,
+            ]))
+            .and(
+                column("not_before")
+                    .equal(Value::Null)
+                    .or(column("not_before").less_equal(Instant::now())),
+            )
+            .and(
+                column("parent_id")
+                    .equal(Value::Null)
+                    .or(column("parent_id").in_select(succeeded)),
+            );
+        if let Some(kinds) = &self.kinds {
+            predicate = predicate.and(column("kind").in_values(kinds.clone()));
+        }
+        if let Some(min_priority) = self.min_priority {
+            predicate = predicate.and(column("priority").greater_equal(min_priority));
+        }
+        predicate
+    }
+}
+
 pub type TaskEvent = BaseEvent<Task>;
 
-pub struct TaskStore(PersistentStore<Task>);
+const TASK_TABLE: &str = "solve_task";
+
+/// Result of attempt to take task.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TakeOutcome<T = Task> {
+    Claimed(T),
+    /// There are no queued tasks that match options.
+    Empty,
+    /// Matching tasks were taken by concurrent workers.
+    Contended {
+        conflicts: u64,
+    },
+}
+
+impl<T> TakeOutcome<T> {
+    /// Returns taken task if any.
+    pub fn claimed(self) -> Option<T> {
+        match self {
+            Self::Claimed(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> TakeOutcome<U> {
+        match self {
+            Self::Claimed(v) => TakeOutcome::Claimed(f(v)),
+            Self::Empty => TakeOutcome::Empty,
+            Self::Contended { conflicts } => TakeOutcome::Contended { conflicts },
+        }
+    }
+}
+
+/// Counters of attempts to take tasks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClaimStats {
+    /// Amount of taken tasks.
+    pub claims: u64,
+    /// Amount of attempts that failed because task was taken concurrently.
+    pub conflicts: u64,
+}
+
+#[derive(Default)]
+struct ClaimCounters {
+    claims: AtomicU64,
+    conflicts: AtomicU64,
+}
+
+pub struct TaskStore(PersistentStore<Task>, ClaimCounters);
 
 impl TaskStore {
     pub fn new(db: Arc<Database>) -> Self {
-        Self(PersistentStore::new(db, "solve_task", "solve_task_event"))
+        Self(
+            PersistentStore::new(db, TASK_TABLE, "solve_task_event"),
+            ClaimCounters::default(),
+        )
     }
 
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("kind"),
+                Column::text("config"),
+                Column::big_int("status"),
+                Column::text("state"),
+                Column::big_int("expire_time").nullable(),
+                Column::big_int("version"),
+                Column::big_int("priority"),
+                Column::big_int("not_before").nullable(),
+                Column::big_int("attempts"),
+                Column::big_int("max_attempts"),
+                Column::big_int("queued_time").nullable(),
+                Column::big_int("start_time").nullable(),
+                Column::big_int("finish_time").nullable(),
+                Column::big_int("parent_id").nullable(),
+            ])
+            .await
+    }
+
+    /// Takes queued task with highest priority that matches options.
+    ///
+    /// Tasks scheduled for future time are skipped.
     pub async fn take_task(
         &self,
         ctx: Context<'_, '_>,
         duration: Duration,
-    ) -> Result<Option<Task>, Error> {
+        options: &TakeOptions,
+    ) -> Result<TakeOutcome, Error> {
         if ctx.tx.is_some() {
             return Err("Cannot take task in transaction".into());
         }
-        let mut tx = self.0.db().transaction(write_tx_options()).await?;
-        let task = {
-            let mut rows = self
-                .find(
-                    Context::new().with_tx(&mut tx),
-                    Select::new()
-                        .with_where(column("status").equal(TaskStatus::Queued))
-                        .with_limit(5),
-                )
-                .await?;
-            loop {
-                match rows.next().await {
-                    Some(Ok(v)) => match v.kind {
-                        TaskKind::Unknown(_) => continue,
-                        _ => break v,
-                    },
-                    Some(Err(v)) => return Err(v),
-                    None => return Ok(None),
-                }
-            }
+        if options.kinds.as_ref().is_some_and(|v| v.is_empty()) {
+            return Ok(TakeOutcome::Empty);
+        }
+        let outcome = if self.0.db().builder().supports_locking() {
+            self.take_locked_task(ctx, duration, options).await?
+        } else {
+            self.take_guarded_task(duration, options).await?
         };
+        if let TakeOutcome::Claimed(_) = outcome {
+            self.1.claims.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(outcome)
+    }
+
+    /// Returns counters of attempts to take tasks.
+    pub fn claim_stats(&self) -> ClaimStats {
+        ClaimStats {
+            claims: self.1.claims.load(Ordering::Relaxed),
+            conflicts: self.1.conflicts.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Moves expiration time of running task without rewriting other fields.
+    ///
+    /// Returns false if task is not running or its expiration time differs
+    /// from guard, so task was changed or taken concurrently.
+    pub async fn extend_expiry(
+        &self,
+        ctx: Context<'_, '_>,
+        id: i64,
+        guard_expiry: Instant,
+        new_expiry: Instant,
+    ) -> Result<bool, Error> {
+        let predicate = column("id")
+            .equal(id)
+            .and(column("status").equal(TaskStatus::Running))
+            .and(column("expire_time").equal(guard_expiry));
+        let count = self
+            .update_fields_where(
+                ctx,
+                vec![("expire_time".into(), new_expiry.into_value())],
+                predicate,
+                true,
+            )
+            .await?;
+        Ok(count > 0)
+    }
+
+    /// Takes task skipping rows that are being taken by concurrent workers.
+    async fn take_locked_task(
+        &self,
+        ctx: Context<'_, '_>,
+        duration: Duration,
+        options: &TakeOptions,
+    ) -> Result<TakeOutcome, Error> {
+        let tx_options = TransactionOptions {
+            isolation_level: IsolationLevel::ReadCommitted,
+            read_only: false,
+        };
+        let mut tx = self.0.db().transaction(tx_options).await?;
+        // Every selected row stays locked until commit, so take only one
+        // row to leave the rest for concurrent workers.
+        let select = Select::new()
+            .with_where(options.predicate())
+            .with_order(vec![Order::desc("priority"), Order::asc(Task::ID)])
+            .with_limit(1)
+            .with_locking(Locking::ForUpdate { skip_locked: true });
+        let mut rows = self.find(Context::new().with_tx(&mut tx), select).await?;
+        let task = match rows.next().await {
+            Some(Ok(v)) => v,
+            Some(Err(v)) => return Err(v),
+            None => return Ok(TakeOutcome::Empty),
+        };
+        drop(rows);
+        let now = Instant::now();
         let new_task = Task {
             status: TaskStatus::Running,
-            expire_time: Some(Instant::now() + duration),
+            expire_time: Some(now + duration),
+            start_time: Some(now),
             ..task
         };
-        let event = self
-            .update_where(
-                ctx.with_tx(&mut tx),
-                new_task,
-                column("kind")
-                    .equal(task.kind)
-                    .and(column("status").equal(task.status))
-                    .and(column("expire_time").equal(task.expire_time)),
-            )
-            .await?;
+        let event = match self.update(ctx.with_tx(&mut tx), new_task).await {
+            Ok(v) => v,
+            Err(err) if StoreError::is_conflict(&err) => {
+                self.1.conflicts.fetch_add(1, Ordering::Relaxed);
+                return Ok(TakeOutcome::Contended { conflicts: 1 });
+            }
+            Err(err) => return Err(err),
+        };
         tx.commit().await?;
-        Ok(Some(event.into_object()))
+        Ok(TakeOutcome::Claimed(event.into_object()))
+    }
+
+    /// Takes task using version of task to detect concurrent takes.
+    ///
+    /// Candidates with equal priority are tried in random order, so
+    /// concurrent workers spread over different tasks instead of fighting
+    /// over the same one. Every candidate is tried in its own small
+    /// transaction before window of candidates is selected again.
+    async fn take_guarded_task(
+        &self,
+        duration: Duration,
+        options: &TakeOptions,
+    ) -> Result<TakeOutcome, Error> {
+        const CANDIDATES: usize = 8;
+        const MAX_ROUNDS: usize = 3;
+        let mut conflicts = 0;
+        for _ in 0..MAX_ROUNDS {
+            let select = Select::new()
+                .with_where(options.predicate())
+                .with_order(vec![Order::desc("priority"), Order::asc(Task::ID)])
+                .with_limit(CANDIDATES);
+            let mut rows = self.find(Context::new(), select).await?;
+            let mut candidates = Vec::new();
+            while let Some(task) = rows.next().await {
+                candidates.push(task?);
+            }
+            drop(rows);
+            if candidates.is_empty() {
+                return Ok(TakeOutcome::Empty);
+            }
+            candidates.shuffle(&mut rand::thread_rng());
+            candidates.sort_by_key(|v| std::cmp::Reverse(v.priority));
+            for task in candidates {
+                let now = Instant::now();
+                let expire_time = now + duration;
+                // Single guarded statement does not read before write, so it
+                // waits for concurrent writers instead of failing.
+                let predicate = column(Task::ID)
+                    .equal(task.id)
+                    .and(column("version").equal(task.version));
+                let count = self
+                    .update_fields_where(
+                        Context::new(),
+                        vec![
+                            ("status".into(), TaskStatus::Running.into_value()),
+                            ("expire_time".into(), expire_time.into_value()),
+                            ("start_time".into(), now.into_value()),
+                        ],
+                        predicate,
+                        true,
+                    )
+                    .await?;
+                if count == 0 {
+                    self.1.conflicts.fetch_add(1, Ordering::Relaxed);
+                    conflicts += 1;
+                    continue;
+                }
+                return Ok(TakeOutcome::Claimed(Task {
+                    status: TaskStatus::Running,
+                    expire_time: Some(expire_time),
+                    start_time: Some(now),
+                    version: task.version + 1,
+                    ..task
+                }));
+            }
+        }
+        Ok(TakeOutcome::Contended { conflicts })
+    }
+
+    /// Returns running tasks with expired time back to queue.
+    ///
+    /// Returns amount of requeued tasks.
+    pub async fn requeue_expired(&self, ctx: Context<'_, '_>) -> Result<u64, Error> {
+        let now = Instant::now();
+        let predicate = column("status")
+            .equal(TaskStatus::Running)
+            .and(column("expire_time").less(now));
+        self.update_fields_where(
+            ctx,
+            vec![
+                ("status".into(), TaskStatus::Queued.into_value()),
+                ("expire_time".into(), Value::Null),
+                ("queued_time".into(), now.into_value()),
+            ],
+            predicate,
+            true,
+        )
+        .await
     }
 }
 
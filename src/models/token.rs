@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::{Instant, JSON};
+
+use crate::core::Error;
+use crate::db::builder::Column;
+
+use super::user::constant_time_eq;
+use super::{object_store_impl, BaseEvent, Context, Event, Object, ObjectStore, PersistentStore};
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct Token {
+    pub id: i64,
+    pub account_id: i64,
+    pub secret_hash: String,
+    pub scopes: JSON,
+    pub create_time: Instant,
+    pub expire_time: Instant,
+}
+
+impl Token {
+    pub fn set_scopes(&mut self, scopes: &[String]) -> Result<(), Error> {
+        self.scopes = JSON::from_serialize(scopes)?;
+        Ok(())
+    }
+
+    pub fn parse_scopes(&self) -> Result<Vec<String>, Error> {
+        self.scopes.parse_as()
+    }
+
+    /// Reports whether token grants specified scope.
+    ///
+    /// Scope `*` grants everything and scope with `*` suffix grants all
+    /// scopes with the same prefix.
+    pub fn has_scope(&self, scope: &str) -> Result<bool, Error> {
+        Ok(self
+            .parse_scopes()?
+            .iter()
+            .any(|v| match v.strip_suffix('*') {
+                Some(prefix) => scope.starts_with(prefix),
+                None => v == scope,
+            }))
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    base64::engine::general_purpose::STANDARD.encode(Sha256::digest(secret.as_bytes()))
+}
+
+impl Object for Token {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+}
+
+pub type TokenEvent = BaseEvent<Token>;
+
+pub struct TokenStore(PersistentStore<Token>);
+
+impl TokenStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(db, "solve_token", "solve_token_event"))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("account_id"),
+                Column::text("secret_hash"),
+                Column::text("scopes"),
+                Column::big_int("create_time"),
+                Column::big_int("expire_time"),
+            ])
+            .await
+    }
+
+    /// Creates token and returns it with token string in `{id}_{secret}`
+    /// format.
+    ///
+    /// Only hash of secret is stored, so token string cannot be recovered.
+    pub async fn create_token(
+        &self,
+        ctx: Context<'_, '_>,
+        account_id: i64,
+        scopes: &[String],
+        ttl: Duration,
+    ) -> Result<(Token, String), Error> {
+        let secret: [u8; 32] = rand::random();
+        let secret = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(secret);
+        let now = Instant::now();
+        let mut token = Token {
+            account_id,
+            secret_hash: hash_secret(&secret),
+            create_time: now,
+            expire_time: now + ttl,
+            ..Default::default()
+        };
+        token.set_scopes(scopes)?;
+        let token = self.create(ctx, token).await?.into_object();
+        let value = format!("{}_{}", token.id, secret);
+        Ok((token, value))
+    }
+
+    /// Returns token if it matches token string, is not expired and grants
+    /// specified scope.
+    pub async fn authenticate<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        value: &str,
+        scope: &str,
+    ) -> Result<Option<Token>, Error> {
+        let Some((id, secret)) = value.split_once('_') else {
+            return Ok(None);
+        };
+        let Ok(id) = id.parse() else {
+            return Ok(None);
+        };
+        let Some(token) = self.get(ctx, id).await? else {
+            return Ok(None);
+        };
+        let hash = hash_secret(secret);
+        if !constant_time_eq(token.secret_hash.as_bytes(), hash.as_bytes()) {
+            return Ok(None);
+        }
+        if token.expire_time <= Instant::now() {
+            return Ok(None);
+        }
+        if !token.has_scope(scope)? {
+            return Ok(None);
+        }
+        Ok(Some(token))
+    }
+}
+
+object_store_impl!(TokenStore, Token, TokenEvent);
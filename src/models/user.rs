@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use solve_db::{Database, FromRow, IntoRow};
+use solve_db_types::Instant;
+
+use crate::core::Error;
+use crate::db::builder::{column, exists, Column, CreateIndex, Select};
+
+use super::{
+    object_store_impl, write_tx_options, AsyncIter, BaseEvent, Context, Object, ObjectStore,
+    PersistentStore, StoreError,
+};
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+pub struct User {
+    pub id: i64,
+    pub account_id: i64,
+    pub login: String,
+    pub password_hash: String,
+    pub password_salt: String,
+    pub email: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub create_time: Instant,
+}
+
+impl User {
+    /// Sets password hash using new random salt.
+    ///
+    /// Global salt is taken from `security.password_salt` config.
+    pub fn set_password(&mut self, password: &str, global_salt: &str) {
+        let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>()).expect("valid salt size");
+        let hash = password_hasher(global_salt)
+            .hash_password(password.as_bytes(), &salt)
+            .expect("valid password hash params");
+        self.password_salt = salt.as_str().to_owned();
+        self.password_hash = hash.to_string();
+    }
+
+    pub fn check_password(&self, password: &str, global_salt: &str) -> bool {
+        let Ok(hash) = PasswordHash::new(&self.password_hash) else {
+            return false;
+        };
+        password_hasher(global_salt)
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    }
+}
+
+/// Compares all bytes to avoid leaking position of first mismatch.
+pub(super) fn constant_time_eq(lhs: &[u8], rhs: &[u8]) -> bool {
+    lhs.len() == rhs.len() && lhs.iter().zip(rhs).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+}
+
+/// Returns Argon2id hasher that uses global salt as secret.
+///
+/// Per-user salt and hash params are stored in PHC string of password hash.
+fn password_hasher(global_salt: &str) -> Argon2<'_> {
+    Argon2::new_with_secret(
+        global_salt.as_bytes(),
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .expect("valid secret size")
+}
+
+impl Object for User {
+    type Id = i64;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+}
+
+pub type UserEvent = BaseEvent<User>;
+
+pub struct UserStore(PersistentStore<User>);
+
+impl UserStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self(PersistentStore::new(db, "solve_user", "solve_user_event"))
+    }
+
+    pub async fn create_tables(&self) -> Result<(), Error> {
+        self.0
+            .create_tables(vec![
+                Column::big_int("account_id"),
+                Column::text("login"),
+                Column::text("password_hash"),
+                Column::text("password_salt"),
+                Column::text("email").nullable(),
+                Column::text("first_name").nullable(),
+                Column::text("last_name").nullable(),
+                Column::big_int("create_time"),
+            ])
+            .await?;
+        self.0
+            .db()
+            .execute(
+                CreateIndex::new("solve_user_login_idx")
+                    .with_table(self.0.table())
+                    .with_columns(vec!["login".to_owned()])
+                    .with_unique(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_by_login<'a>(
+        &'a self,
+        ctx: Context<'a, '_>,
+        login: &str,
+    ) -> Result<Option<User>, Error> {
+        let mut iter = self
+            .find(
+                ctx,
+                Select::new()
+                    .with_where(column("login").equal(login))
+                    .with_limit(1),
+            )
+            .await?;
+        match iter.next().await {
+            Some(v) => Ok(Some(v?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Creates user if there is no other user with the same login.
+    pub async fn create_user(&self, ctx: Context<'_, '_>, user: User) -> Result<UserEvent, Error> {
+        if ctx.tx.is_some() {
+            return self.create_user_tx(ctx, user).await;
+        }
+        let mut tx = self.0.db().transaction(write_tx_options()).await?;
+        let event = self.create_user_tx(ctx.with_tx(&mut tx), user).await?;
+        tx.commit().await?;
+        Ok(event)
+    }
+
+    async fn create_user_tx(
+        &self,
+        mut ctx: Context<'_, '_>,
+        user: User,
+    ) -> Result<UserEvent, Error> {
+        let tx = ctx.tx.take().expect("transaction is required");
+        let login = user.login.clone();
+        if self
+            .get_by_login(Context::new().with_tx(tx), &login)
+            .await?
+            .is_some()
+        {
+            return Err(StoreError::Conflict.into());
+        }
+        // Concurrent transaction can insert user with the same login.
+        let predicate = !exists(
+            Select::new()
+                .with_table(self.0.table())
+                .with_columns(vec![User::ID.to_owned()])
+                .with_where(column("login").equal(login)),
+        );
+        self.create_where(ctx.with_tx(tx), user, predicate).await
+    }
+}
+
+object_store_impl!(UserStore, User, UserEvent);
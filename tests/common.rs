@@ -1,8 +1,12 @@
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use rand::distributions::{Alphanumeric, DistString};
+use solve::config::{DatabaseConfig, SQLiteConfig};
 use solve::core::Error;
+use solve::db::new_database;
+use solve_db::Database;
 
 pub struct TempDir(PathBuf);
 
@@ -38,3 +42,21 @@ pub fn temp_dir() -> Result<TempDir, Error> {
     std::fs::create_dir_all(&path)?;
     Ok(TempDir(path))
 }
+
+/// Creates database in new temporary directory.
+///
+/// Directory should outlive database, so both are returned.
+#[allow(unused)]
+pub fn sqlite_db() -> (TempDir, Arc<Database>) {
+    let tmpdir = temp_dir().unwrap();
+    let config = SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db = Arc::new(new_database(&DatabaseConfig::SQLite(config)).unwrap());
+    (tmpdir, db)
+}
@@ -12,6 +12,8 @@ fn test_parse_str() {
         },
         "invoker": {
             "workers": 4,
+            "worker_kinds": [["judge_solution"], []],
+            "kind_limits": {"update_problem_package": 1},
             "safeexec": {
                 "path": "safeexec",
                 "cgroup": {{ env "TEST_CGROUP" | json }}
@@ -31,6 +33,14 @@ fn test_parse_str() {
     let invoker = config.invoker.as_ref().unwrap();
     let safeexec = invoker.safeexec.as_ref().unwrap();
     assert_eq!(safeexec.cgroup, "safeexec");
+    assert_eq!(
+        invoker.worker_kinds,
+        vec![vec![solve::models::TaskKind::JudgeSolution], vec![]]
+    );
+    assert_eq!(
+        invoker.kind_limits,
+        [(solve::models::TaskKind::UpdateProblemPackage, 1)].into()
+    );
 }
 
 #[test]
@@ -1,5 +1,12 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 use solve::core::{blocking_await, Error};
 use solve::db::new_database;
+use solve::models::{
+    write_tx_options, Compiler, CompilerStore, Context, Event, EventConsumer, ObjectStore,
+    TakeOptions, Task, TaskStatus, TaskStore, User, UserStore,
+};
 use solve_db::{
     ConnectionOptions, Database, FromRow, IntoRow, IntoValue, RawQuery, Row, SimpleRow, Value,
 };
@@ -7,6 +14,7 @@ use solve_db_types::JSON;
 
 mod common;
 
+#[allow(unused)]
 struct TestTypesRow {
     pub id: i64,
     pub int64: i64,
@@ -33,15 +41,15 @@ impl FromRow for TestTypesRow {
 
 impl IntoRow for TestTypesRow {
     fn into_row(self) -> SimpleRow {
-        let mut row = Vec::new();
-        row.push(("id".into(), self.id.into_value()));
-        row.push(("int64".into(), self.int64.into_value()));
-        row.push(("null_int64".into(), self.null_int64.into_value()));
-        row.push(("string".into(), self.string.into_value()));
-        row.push(("null_string".into(), self.null_string.into_value()));
-        row.push(("json".into(), self.json.into_value()));
-        row.push(("null_json".into(), self.null_json.into_value()));
-        row
+        vec![
+            ("id".into(), self.id.into_value()),
+            ("int64".into(), self.int64.into_value()),
+            ("null_int64".into(), self.null_int64.into_value()),
+            ("string".into(), self.string.into_value()),
+            ("null_string".into(), self.null_string.into_value()),
+            ("json".into(), self.json.into_value()),
+            ("null_json".into(), self.null_json.into_value()),
+        ]
     }
 }
 
@@ -177,6 +185,188 @@ async fn test_postgres() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_postgres_take_task() {
+    let host = match std::env::var("POSTGRES_HOST") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let port = match std::env::var("POSTGRES_PORT") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let config = solve::config::PostgresConfig {
+        user: std::env::var("POSTGRES_USER").unwrap_or("postgres".into()),
+        hosts: vec![format!("{host}:{port}")],
+        password: std::env::var("POSTGRES_PASSWORD").unwrap_or("postgres".into()),
+        name: std::env::var("POSTGRES_NAME").unwrap_or("postgres".into()),
+        sslmode: "".into(),
+    };
+    let db = Arc::new(new_database(&solve::config::DatabaseConfig::Postgres(config)).unwrap());
+    let _cleanup = {
+        let mut conn = db.connection(ConnectionOptions::default()).await.unwrap();
+        Defer::new(move || {
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_task""#)).unwrap();
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_task_event""#)).unwrap();
+        })
+    };
+    let store = TaskStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    // Task of unknown kind at the head of queue should not stall workers.
+    let task = store.create(Context::new(), Task::default()).await.unwrap();
+    let query = format!(
+        r#"UPDATE "solve_task" SET "kind" = 100 WHERE "id" = {}"#,
+        task.into_object().id
+    );
+    db.execute(query.as_str()).await.unwrap();
+    for _ in 0..2 {
+        store.create(Context::new(), Task::default()).await.unwrap();
+    }
+    let options = TakeOptions::default();
+    let (task1, task2) = tokio::join!(
+        store.take_task(Context::new(), Duration::from_secs(30), &options),
+        store.take_task(Context::new(), Duration::from_secs(30), &options),
+    );
+    let task1 = task1.unwrap().claimed().unwrap();
+    let task2 = task2.unwrap().claimed().unwrap();
+    assert_ne!(task1.id, task2.id);
+    assert_eq!(task1.status, TaskStatus::Running);
+    assert_eq!(task2.status, TaskStatus::Running);
+    assert!(store
+        .take_task(Context::new(), Duration::from_secs(30), &options)
+        .await
+        .unwrap()
+        .claimed()
+        .is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_postgres_create_user() {
+    let host = match std::env::var("POSTGRES_HOST") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let port = match std::env::var("POSTGRES_PORT") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let config = solve::config::PostgresConfig {
+        user: std::env::var("POSTGRES_USER").unwrap_or("postgres".into()),
+        hosts: vec![format!("{host}:{port}")],
+        password: std::env::var("POSTGRES_PASSWORD").unwrap_or("postgres".into()),
+        name: std::env::var("POSTGRES_NAME").unwrap_or("postgres".into()),
+        sslmode: "".into(),
+    };
+    let db = Arc::new(new_database(&solve::config::DatabaseConfig::Postgres(config)).unwrap());
+    let _cleanup = {
+        let mut conn = db.connection(ConnectionOptions::default()).await.unwrap();
+        Defer::new(move || {
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_user""#)).unwrap();
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_user_event""#)).unwrap();
+        })
+    };
+    let store = UserStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let user = User {
+        account_id: 1,
+        login: "test".into(),
+        ..Default::default()
+    };
+    let event = store
+        .create_user(Context::new(), user.clone())
+        .await
+        .unwrap();
+    assert_eq!(event.object().login, "test");
+    assert!(store.create_user(Context::new(), user).await.is_err());
+}
+
+fn new_compiler() -> Compiler {
+    Compiler {
+        name: "test".into(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_postgres_event_consumer() {
+    let host = match std::env::var("POSTGRES_HOST") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let port = match std::env::var("POSTGRES_PORT") {
+        Ok(v) => v,
+        Err(_) => return,
+    };
+    let config = solve::config::PostgresConfig {
+        user: std::env::var("POSTGRES_USER").unwrap_or("postgres".into()),
+        hosts: vec![format!("{host}:{port}")],
+        password: std::env::var("POSTGRES_PASSWORD").unwrap_or("postgres".into()),
+        name: std::env::var("POSTGRES_NAME").unwrap_or("postgres".into()),
+        sslmode: "".into(),
+    };
+    let db = Arc::new(new_database(&solve::config::DatabaseConfig::Postgres(config)).unwrap());
+    let _cleanup = {
+        let mut conn = db.connection(ConnectionOptions::default()).await.unwrap();
+        Defer::new(move || {
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_compiler""#)).unwrap();
+            blocking_await(conn.execute(r#"DROP TABLE IF EXISTS "solve_compiler_event""#)).unwrap();
+        })
+    };
+    let store = Arc::new(CompilerStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let mut consumer = EventConsumer::new(store.clone());
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    // First writer allocates event id but commits after second writer.
+    let mut tx = db.transaction(write_tx_options()).await.unwrap();
+    let event1 = store
+        .create(Context::new().with_tx(&mut tx), new_compiler())
+        .await
+        .unwrap();
+    let event2 = store.create(Context::new(), new_compiler()).await.unwrap();
+    assert!(event1.id() < event2.id());
+    let events = consumer.poll(Context::new(), 100).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), event2.id());
+    tx.commit().await.unwrap();
+    let events = consumer.poll(Context::new(), 100).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), event1.id());
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    // Rolled back events are never delivered.
+    let mut tx = db.transaction(write_tx_options()).await.unwrap();
+    store
+        .create(Context::new().with_tx(&mut tx), new_compiler())
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    let event4 = store.create(Context::new(), new_compiler()).await.unwrap();
+    let events = consumer.poll(Context::new(), 100).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), event4.id());
+    assert_eq!(consumer.range().unwrap().gaps().count(), 1);
+    // Gaps of running writers are kept regardless of gap window.
+    let mut consumer = consumer.with_gap_window(Duration::ZERO);
+    let mut tx = db.transaction(write_tx_options()).await.unwrap();
+    let event5 = store
+        .create(Context::new().with_tx(&mut tx), new_compiler())
+        .await
+        .unwrap();
+    let event6 = store.create(Context::new(), new_compiler()).await.unwrap();
+    let events = consumer.poll(Context::new(), 100).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), event6.id());
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    assert!(consumer.range().unwrap().gaps().any(|v| v == event5.id()));
+    tx.commit().await.unwrap();
+    let events = consumer.poll(Context::new(), 100).await.unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].id(), event5.id());
+    // Gaps of finished writers are expired.
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    assert_eq!(consumer.range().unwrap().gaps().count(), 0);
+}
+
 struct Defer<T: FnOnce()> {
     func: Option<T>,
 }
@@ -1,11 +1,21 @@
 use std::fs::{remove_dir_all, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
 use sbox::{run_as_root, BinNewIdMapper, Gid, Uid};
-use solve::core::Error;
-use solve::invoker::safeexec;
+use solve::core::{Core, Error};
+use solve::invoker::tasks::{JudgeSolutionTask, TaskProcess};
+use solve::invoker::{safeexec, Invoker};
+use solve::managers::files::MemoryFile;
+use solve::models::{
+    CompiledChecker, Compiler, CompilerConfig, Context, Event, JudgeReport,
+    JudgeSolutionTaskConfig, ObjectStore, ProblemConfig, ProblemPackage, ProblemResource,
+    ProblemResourceKind, ProblemTest, Solution, TakeOptions, Verdict,
+};
+use solve_db_types::{DurationMs, Score};
 use tar::Archive;
+use tokio_util::sync::CancellationToken;
 
 mod common;
 
@@ -62,3 +72,375 @@ async fn test_safeexec() {
     assert!(report.real_time > Duration::ZERO);
     run_as_root(&user_mapper, move || Ok(remove_dir_all(rootfs_dir)?)).unwrap();
 }
+
+/// Creates invoker with compiler image unpacked from rootfs.
+async fn new_invoker(tmpdir: &Path) -> (Arc<Core>, Arc<Invoker>, i64) {
+    let cgroup = match std::env::var("TEST_CGROUP_PATH") {
+        Ok(v) => v,
+        Err(_) => "solve-test-safeexec".into(),
+    };
+    let config: solve::config::Config = serde_json::from_value(serde_json::json!({
+        "db": {
+            "driver": "sqlite",
+            "options": {"path": tmpdir.join("db.sqlite")},
+        },
+        "storage": {
+            "driver": "local",
+            "options": {"files_dir": tmpdir.join("files")},
+        },
+        "invoker": {
+            "temp_dir": tmpdir.join("invoker"),
+            "safeexec": {"path": tmpdir.join("safeexec"), "cgroup": cgroup},
+        },
+    }))
+    .unwrap();
+    std::fs::create_dir_all(tmpdir.join("invoker")).unwrap();
+    let mut core = Core::new(&config).unwrap();
+    core.task_store().create_tables().await.unwrap();
+    core.periodic_task_store().create_tables().await.unwrap();
+    core.file_store().create_tables().await.unwrap();
+    core.problem_store().create_tables().await.unwrap();
+    core.problem_resource_store().create_tables().await.unwrap();
+    core.solution_store().create_tables().await.unwrap();
+    core.compiler_store().create_tables().await.unwrap();
+    core.init_invoker(&config).await.unwrap();
+    let core = Arc::new(core);
+    let invoker = Arc::new(Invoker::new(core.clone(), config.invoker.as_ref().unwrap()).unwrap());
+    drop(get_rootfs().unwrap());
+    let image = core
+        .file_manager()
+        .upload(
+            Context::new(),
+            MemoryFile::new(
+                std::fs::read("./tests/rootfs.tar").unwrap(),
+                Some("rootfs.tar".into()),
+            ),
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    (core, invoker, image.id)
+}
+
+async fn create_compiler(core: &Core, image_file_id: i64, compile_command: &str) -> Compiler {
+    let mut compiler = Compiler {
+        name: compile_command.into(),
+        image_file_id: Some(image_file_id),
+        ..Default::default()
+    };
+    compiler
+        .set_config(&CompilerConfig {
+            language: "Shell".into(),
+            compile_command: Some(compile_command.into()),
+            run_command: "/bin/sh solution".into(),
+            extensions: vec!["sh".into()],
+            ..Default::default()
+        })
+        .unwrap();
+    core.compiler_store()
+        .create(Context::new(), compiler)
+        .await
+        .unwrap()
+        .into_object()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_safeexec_compile() {
+    let tmpdir = common::temp_dir().unwrap();
+    let (core, invoker, image_file_id) = new_invoker(tmpdir.as_path()).await;
+    let source = tmpdir.join("source.sh");
+    std::fs::write(&source, "echo -n 'solve_test'\n").unwrap();
+    let binary = tmpdir.join("binary");
+    // Compiler copies source into binary.
+    let compiler = create_compiler(&core, image_file_id, "cp {source} {binary}").await;
+    let report = invoker.compile(&compiler, &source, &binary).await.unwrap();
+    assert!(report.success());
+    assert_eq!(
+        std::fs::read(&binary).unwrap(),
+        std::fs::read(&source).unwrap()
+    );
+    std::fs::remove_file(&binary).unwrap();
+    // Failed compilation does not produce binary.
+    let compiler = create_compiler(&core, image_file_id, "echo 'syntax error'; exit 1").await;
+    let report = invoker.compile(&compiler, &source, &binary).await.unwrap();
+    assert!(!report.success());
+    assert_eq!(report.exit_code, 1);
+    assert_eq!(report.log, "syntax error\n");
+    assert!(!binary.exists());
+    // Compilation error is saved into report of solution.
+    let problem_id = core
+        .problem_store()
+        .create(Context::new(), Default::default())
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let solution = core
+        .solution_store()
+        .create(
+            Context::new(),
+            Solution {
+                problem_id,
+                compiler_id: compiler.id,
+                content: Some("echo -n 'solve_test'\n".into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    core.task_manager()
+        .enqueue_judge_solution(
+            Context::new(),
+            JudgeSolutionTaskConfig {
+                solution_id: solution.id,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let task = core
+        .task_manager()
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    Box::new(JudgeSolutionTask::new(invoker.clone()))
+        .run(task, core.logger().clone(), CancellationToken::new())
+        .await
+        .unwrap();
+    let solution = core
+        .solution_store()
+        .get(Context::new(), solution.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let report = solution.parse_report().unwrap().unwrap();
+    assert_eq!(report.verdict, Verdict::CompilationError);
+    assert_eq!(report.compile_log.as_deref(), Some("syntax error\n"));
+}
+
+async fn upload_resource(
+    core: &Core,
+    problem_id: i64,
+    kind: ProblemResourceKind,
+    name: &str,
+    content: &str,
+) {
+    let file = core
+        .file_manager()
+        .upload(
+            Context::new(),
+            MemoryFile::new(content.as_bytes().to_vec(), None),
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let resource = ProblemResource {
+        problem_id,
+        kind,
+        name: name.into(),
+        file_id: file.id,
+        ..Default::default()
+    };
+    core.problem_resource_store()
+        .create_resource(Context::new(), resource)
+        .await
+        .unwrap();
+}
+
+/// Creates problem with package consisting of specified tests.
+///
+/// Checker is shell script that is run with specified compiler.
+async fn create_problem(
+    core: &Core,
+    tests: &[(&str, &str)],
+    checker: Option<(&Compiler, &str)>,
+) -> i64 {
+    let mut problem = core
+        .problem_store()
+        .create(Context::new(), Default::default())
+        .await
+        .unwrap()
+        .into_object();
+    let mut package = ProblemPackage {
+        revision: 1,
+        time_limit: DurationMs::from(Duration::from_secs(1)),
+        memory_limit: 256 * 1024 * 1024,
+        ..Default::default()
+    };
+    for (i, (input, answer)) in tests.iter().enumerate() {
+        let test = ProblemTest {
+            input: format!("tests/{}.in", i + 1),
+            answer: format!("tests/{}.ans", i + 1),
+        };
+        for (name, content) in [(&test.input, input), (&test.answer, answer)] {
+            upload_resource(core, problem.id, ProblemResourceKind::Test, name, content).await;
+        }
+        package.tests.push(test);
+    }
+    if let Some((compiler, content)) = checker {
+        for name in ["checker.sh", "compiled/checker"] {
+            upload_resource(
+                core,
+                problem.id,
+                ProblemResourceKind::Checker,
+                name,
+                content,
+            )
+            .await;
+        }
+        package.checker = Some("checker.sh".into());
+        package.compiled_checker = Some(CompiledChecker {
+            compiler_id: compiler.id,
+            binary: "compiled/checker".into(),
+        });
+    }
+    problem
+        .set_config(&ProblemConfig {
+            package: Some(package),
+        })
+        .unwrap();
+    core.problem_store()
+        .update(Context::new(), problem.clone())
+        .await
+        .unwrap();
+    problem.id
+}
+
+/// Judges shell script as solution of problem.
+async fn judge_solution(
+    core: &Core,
+    invoker: &Arc<Invoker>,
+    compiler: &Compiler,
+    problem_id: i64,
+    content: &str,
+    enable_points: bool,
+) -> JudgeReport {
+    let solution = core
+        .solution_store()
+        .create(
+            Context::new(),
+            Solution {
+                problem_id,
+                compiler_id: compiler.id,
+                content: Some(content.into()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    core.task_manager()
+        .enqueue_judge_solution(
+            Context::new(),
+            JudgeSolutionTaskConfig {
+                solution_id: solution.id,
+                enable_points,
+            },
+        )
+        .await
+        .unwrap();
+    let task = core
+        .task_manager()
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    Box::new(JudgeSolutionTask::new(invoker.clone()))
+        .run(task, core.logger().clone(), CancellationToken::new())
+        .await
+        .unwrap();
+    core.solution_store()
+        .get(Context::new(), solution.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .parse_report()
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_safeexec_judge_solution() {
+    let tmpdir = common::temp_dir().unwrap();
+    let (core, invoker, image_file_id) = new_invoker(tmpdir.as_path()).await;
+    let compiler = create_compiler(&core, image_file_id, "cp {source} {binary}").await;
+    let problem_id = create_problem(&core, &[("1 2\n", "3\n"), ("40 2\n", "42\n")], None).await;
+    let judge = |content: &'static str, enable_points: bool| {
+        judge_solution(
+            &core,
+            &invoker,
+            &compiler,
+            problem_id,
+            content,
+            enable_points,
+        )
+    };
+    let report = judge("read a b\necho $((a + b))\n", false).await;
+    assert_eq!(report.verdict, Verdict::Accepted);
+    assert_eq!(report.tests.len(), 2);
+    assert!(report.tests.iter().all(|v| v.verdict == Verdict::Accepted));
+    // Testing stops on first rejected test.
+    let report = judge("echo 0\n", false).await;
+    assert_eq!(report.verdict, Verdict::WrongAnswer);
+    assert_eq!(report.tests.len(), 1);
+    let report = judge("sleep 10\n", false).await;
+    assert_eq!(report.verdict, Verdict::TimeLimitExceeded);
+    assert_eq!(report.tests.len(), 1);
+    let report = judge("exit 3\n", false).await;
+    assert_eq!(report.verdict, Verdict::RuntimeError);
+    assert_eq!(report.tests.len(), 1);
+    // All tests are run when points are enabled.
+    let report = judge("read a b\n[ $a = 1 ] && echo 3 || echo 0\n", true).await;
+    assert_eq!(report.verdict, Verdict::PartiallyAccepted);
+    assert_eq!(report.points, Some(Score::from_int(1)));
+    let verdicts: Vec<_> = report.tests.iter().map(|v| v.verdict).collect();
+    assert_eq!(verdicts, [Verdict::Accepted, Verdict::WrongAnswer]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_safeexec_checker() {
+    let tmpdir = common::temp_dir().unwrap();
+    let (core, invoker, image_file_id) = new_invoker(tmpdir.as_path()).await;
+    let compiler = create_compiler(&core, image_file_id, "cp {source} {binary}").await;
+    // Checker exits with code and writes points that are printed by solution.
+    let checker = concat!(
+        "read code points <\"$2\"\n",
+        "[ -n \"$points\" ] && echo \"$points\" >points\n",
+        "echo \"checked $code\" >&2\n",
+        "exit $code\n",
+    );
+    let problem_id = create_problem(&core, &[("1\n", "1\n")], Some((&compiler, checker))).await;
+    let judge = |content: &'static str| {
+        judge_solution(&core, &invoker, &compiler, problem_id, content, true)
+    };
+    let report = judge("echo 0\n").await;
+    assert_eq!(report.verdict, Verdict::Accepted);
+    assert_eq!(report.points, Some(Score::from_int(1)));
+    assert_eq!(report.tests[0].check_log.as_deref(), Some("checked 0\n"));
+    let report = judge("echo 1\n").await;
+    assert_eq!(report.verdict, Verdict::WrongAnswer);
+    let report = judge("echo 2\n").await;
+    assert_eq!(report.verdict, Verdict::PresentationError);
+    let report = judge("echo 3\n").await;
+    assert_eq!(report.verdict, Verdict::Failed);
+    let report = judge("echo 7 0.5\n").await;
+    assert_eq!(report.verdict, Verdict::PartiallyAccepted);
+    assert_eq!(report.points, Some(Score::from_hundredths(50)));
+    // Partial verdict requires points.
+    let report = judge("echo 7\n").await;
+    assert_eq!(report.verdict, Verdict::Failed);
+    // Unknown exit code of checker is failure of checker.
+    let report = judge("echo 42\n").await;
+    assert_eq!(report.verdict, Verdict::Failed);
+    assert!(report.tests[0]
+        .check_log
+        .as_deref()
+        .unwrap()
+        .starts_with("Checker exited with code 42"));
+}
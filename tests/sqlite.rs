@@ -1,5 +1,9 @@
+use solve::db::builder::{
+    column, exists, like_escape, Aggregate, Delete, Expression, Insert, Select, SelectColumn,
+};
 use solve::db::new_database;
-use solve_db::{Database, IntoValue, Value};
+use solve_db::{Database, FromRow, IntoRow, IntoValue, Value};
+use solve_db_types::{Score, Uuid};
 
 mod common;
 
@@ -83,3 +87,309 @@ async fn test_any_sqlite() {
     let row = rows.next().await.unwrap().unwrap();
     assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(4));
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_group_by() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_tbl (a INTEGER PRIMARY KEY, b INTEGER NOT NULL)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO test_tbl (b) VALUES (1), (1), (1), (2), (3), (3)")
+        .await
+        .unwrap();
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_tbl")
+                .with_aggregate(Aggregate::Count)
+                .with_where(column("b").less(3))
+                .with_group_by(vec!["b".to_owned()])
+                .with_order_by(vec!["b".to_owned()]),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(1));
+    assert_eq!(row.get_value(1).unwrap().clone(), Value::BigInt(3));
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(2));
+    assert_eq!(row.get_value(1).unwrap().clone(), Value::BigInt(1));
+    assert!(rows.next().await.is_none());
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_tbl")
+                .with_aggregate(Aggregate::Count)
+                .with_where(true)
+                .with_group_by(vec!["b".to_owned()])
+                .with_having(Aggregate::Count.expr().greater(1))
+                .with_order_by(vec!["b".to_owned()]),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(1));
+    assert_eq!(row.get_value(1).unwrap().clone(), Value::BigInt(3));
+    let row = rows.next().await.unwrap().unwrap();
+    assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(3));
+    assert_eq!(row.get_value(1).unwrap().clone(), Value::BigInt(2));
+    assert!(rows.next().await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_subquery() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_file (id INTEGER PRIMARY KEY, size INTEGER NOT NULL)")
+        .await
+        .unwrap();
+    db.execute(
+        "CREATE TABLE test_file_event (event_id INTEGER PRIMARY KEY, file_id INTEGER NOT NULL)",
+    )
+    .await
+    .unwrap();
+    db.execute("INSERT INTO test_file (size) VALUES (10), (20), (30)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO test_file_event (file_id) VALUES (1), (2), (3), (4), (5)")
+        .await
+        .unwrap();
+    let status = db
+        .execute(
+            Delete::new().with_table("test_file_event").with_where(
+                column("event_id").greater(1).and(
+                    column("file_id").in_select(
+                        Select::new()
+                            .with_table("test_file")
+                            .with_columns(vec!["id".to_owned()])
+                            .with_where(column("size").greater_equal(20)),
+                    ),
+                ),
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status.rows_affected(), Some(2));
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_file_event")
+                .with_columns(vec!["file_id".to_owned()])
+                .with_where(exists(
+                    Select::new()
+                        .with_table("test_file")
+                        .with_columns(vec!["id".to_owned()])
+                        .with_where(column("size").equal(10)),
+                ))
+                .with_order_by(vec!["file_id".to_owned()]),
+        )
+        .await
+        .unwrap();
+    for file_id in [1, 4, 5] {
+        let row = rows.next().await.unwrap().unwrap();
+        assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(file_id));
+    }
+    assert!(rows.next().await.is_none());
+}
+
+#[derive(Debug, FromRow)]
+struct TestLengthRow {
+    a: i64,
+    b_len: i64,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_select_alias() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_tbl (a INTEGER PRIMARY KEY, b TEXT NOT NULL)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO test_tbl (b) VALUES ('a'), ('abc')")
+        .await
+        .unwrap();
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_tbl")
+                .with_select_columns(vec![
+                    SelectColumn::qualified("test_tbl", "a"),
+                    SelectColumn::expr(Expression::Raw("LENGTH(\"b\")".into()), "b_len"),
+                ])
+                .with_where(true)
+                .with_order_by(vec!["a".to_owned()]),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    let row = TestLengthRow::from_row(&row).unwrap();
+    assert_eq!(row.a, 1);
+    assert_eq!(row.b_len, 1);
+    let row = rows.next().await.unwrap().unwrap();
+    let row = TestLengthRow::from_row(&row).unwrap();
+    assert_eq!(row.a, 2);
+    assert_eq!(row.b_len, 3);
+    assert!(rows.next().await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_ilike() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_tbl (a INTEGER PRIMARY KEY, b TEXT NOT NULL)")
+        .await
+        .unwrap();
+    db.execute("INSERT INTO test_tbl (b) VALUES ('Hello'), ('HELLO_world'), ('hello%')")
+        .await
+        .unwrap();
+    let select = |pattern: String| {
+        Select::new()
+            .with_table("test_tbl")
+            .with_columns(vec!["a".to_owned()])
+            .with_where(column("b").ilike(pattern))
+            .with_order_by(vec!["a".to_owned()])
+    };
+    for (pattern, ids) in [
+        ("hello%".to_owned(), vec![1, 2, 3]),
+        (format!("{}%", like_escape("hello_")), vec![2]),
+        (like_escape("HELLO%"), vec![3]),
+    ] {
+        let mut rows = db.query(select(pattern)).await.unwrap();
+        for id in ids {
+            let row = rows.next().await.unwrap().unwrap();
+            assert_eq!(row.get_value(0).unwrap().clone(), Value::BigInt(id));
+        }
+        assert!(rows.next().await.is_none());
+    }
+}
+
+#[derive(Debug, FromRow, IntoRow)]
+struct TestUuidRow {
+    id: i64,
+    key: Uuid,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_uuid() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_tbl (id INTEGER PRIMARY KEY, key TEXT NOT NULL)")
+        .await
+        .unwrap();
+    let key = Uuid::new_v4();
+    let mut rows = db
+        .query(
+            Insert::new()
+                .with_table("test_tbl")
+                .with_row(TestUuidRow { id: 1, key })
+                .with_returning(vec!["id".to_owned(), "key".to_owned()]),
+        )
+        .await
+        .unwrap();
+    let row = TestUuidRow::from_row(&rows.next().await.unwrap().unwrap()).unwrap();
+    assert_eq!(row.key, key);
+    drop(rows);
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_tbl")
+                .with_columns(vec!["id".to_owned(), "key".to_owned()])
+                .with_where(column("key").equal(key)),
+        )
+        .await
+        .unwrap();
+    let row = TestUuidRow::from_row(&rows.next().await.unwrap().unwrap()).unwrap();
+    assert_eq!(row.id, 1);
+    assert_eq!(row.key, key);
+    assert!(rows.next().await.is_none());
+}
+
+#[derive(Debug, FromRow, IntoRow)]
+struct TestScoreRow {
+    id: i64,
+    points: Score,
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_sqlite_score() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let db: Database = new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap();
+    db.execute("CREATE TABLE test_tbl (id INTEGER PRIMARY KEY, points INTEGER NOT NULL)")
+        .await
+        .unwrap();
+    for (id, points) in [(1, "0.1"), (2, "0.2"), (3, "-1.25")] {
+        db.execute(Insert::new().with_table("test_tbl").with_row(TestScoreRow {
+            id,
+            points: points.parse().unwrap(),
+        }))
+        .await
+        .unwrap();
+    }
+    let mut rows = db
+        .query(
+            Select::new()
+                .with_table("test_tbl")
+                .with_columns(vec!["id".to_owned(), "points".to_owned()])
+                .with_order_by(vec!["id".to_owned()]),
+        )
+        .await
+        .unwrap();
+    let mut total = Score::ZERO;
+    for (id, points) in [(1, 10), (2, 20), (3, -125)] {
+        let row = TestScoreRow::from_row(&rows.next().await.unwrap().unwrap()).unwrap();
+        assert_eq!(row.id, id);
+        assert_eq!(row.points, Score::from_hundredths(points));
+        total += row.points;
+    }
+    assert!(rows.next().await.is_none());
+    assert_eq!(total, "-0.95".parse().unwrap());
+}
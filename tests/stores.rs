@@ -1,12 +1,51 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::Arc;
+use std::time::Duration;
 
+use bytes::Bytes;
+use futures_util::FutureExt;
+use sha3::Digest as _;
+use solve::config::{ChunkedUpload, FileLimits, LocalStorageConfig, StorageConfig, VerifyMode};
+use solve::core::Core;
+use solve::db::builder::{column, Column, Insert, Order, Select};
 use solve::db::new_database;
+use solve::invoker::tasks::{TaskProcess, UpdateProblemPackageTask};
+use solve::invoker::Invoker;
+use solve::managers::files::{
+    new_storage, FileInfo, FileManager, FileStorage, IntegrityError, LocalFile, MemoryFile,
+    MigrateOptions, MigrateProgress, StorageHealth, StreamFileInfo, UploadError, UploadOptions,
+    UploadPolicy, UploadResult,
+};
+use solve::managers::tasks::{
+    ParentFailurePolicy, RecoverPolicy, RecoveredTasks, TaskManager, TaskSnapshot,
+};
 use solve::models::{
-    Context, Event, EventKind, File, FileStatus, FileStore, ObjectStore, Task, TaskKind,
-    TaskStatus, TaskStore,
+    create_builtin_roles, read_tx_options, run_in_tx, write_tx_options, Account, AccountRoleStore,
+    AccountRoles, AccountStore, AsyncIter, CacheIndex, CachedStore, Compiler, CompilerConfig,
+    CompilerStore, Contest, ContestConfig, ContestParticipantKind, ContestParticipantStore,
+    ContestProblem, ContestProblemConfig, ContestProblemStore, ContestStage, ContestStore, Context,
+    Cursor, Event, EventConsumer, EventKind, File, FileEvent, FileKind, FileMeta, FileStatus,
+    FileStore, JudgeReport, JudgeSolutionTaskConfig, JudgeSolutionTaskState, MemoryStoreMetrics,
+    Object, ObjectStore, PageRequest, PeriodicTask, PeriodicTaskStore, PersistentStore,
+    ProblemResource, ProblemResourceConfig, ProblemResourceKind, ProblemResourceStore,
+    ProblemStatement, ProblemStatementConfig, ProblemStatementStore, ReadConsistency,
+    RegisterError, Role, RoleEdge, RoleEdgeStore, RoleSet, RoleStore, Schedule, SessionStore,
+    SettingStore, Solution, StatementFormat, StoreError, StoreObserver, StoreOperation,
+    TakeOptions, TakeOutcome, Task, TaskEvent, TaskKind, TaskStatus, TaskStore, TestReport,
+    TokenStore, TypeMap, UpdateProblemPackageStage, UpdateProblemPackageTaskConfig,
+    UpdateProblemPackageTaskState, UsageReport, User, UserStore, Verdict, Versioned,
+    ADMIN_GROUP_ROLE, CREATE_CONTEST_ROLE, GUEST_GROUP_ROLE, LOGOUT_ROLE, REGISTER_ROLE,
+    UPDATE_SETTINGS_ROLE, USER_GROUP_ROLE,
 };
-use solve_db::{Database, TransactionOptions, Value};
-use solve_db_types::Instant;
+use solve_db::{
+    Connection, ConnectionOptions, Database, FromRow, IntoRow, QueryBuilder, TransactionOptions,
+    Value,
+};
+use solve_db_types::{DurationMs, EventRange, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
 mod common;
 
 #[test]
@@ -31,9 +70,15 @@ fn test_event_kinds() {
 
     assert_eq!(
         Value::BigInt(4).parse::<EventKind>().unwrap(),
-        EventKind::Unknown(4)
+        EventKind::BatchUpdate
+    );
+    assert_eq!(Value::from(EventKind::BatchUpdate), Value::BigInt(4));
+
+    assert_eq!(
+        Value::BigInt(5).parse::<EventKind>().unwrap(),
+        EventKind::Unknown(5)
     );
-    assert_eq!(Value::from(EventKind::Unknown(4)), Value::BigInt(4));
+    assert_eq!(Value::from(EventKind::Unknown(5)), Value::BigInt(5));
 }
 
 #[test]
@@ -109,51 +154,22 @@ fn test_task_statuses() {
 
     assert_eq!(
         Value::BigInt(4).parse::<TaskStatus>().unwrap(),
-        TaskStatus::Unknown(4)
+        TaskStatus::Cancelled
+    );
+    assert_eq!(Value::from(TaskStatus::Cancelled), Value::BigInt(4));
+
+    assert_eq!(
+        Value::BigInt(5).parse::<TaskStatus>().unwrap(),
+        TaskStatus::Unknown(5)
     );
-    assert_eq!(Value::from(TaskStatus::Unknown(4)), Value::BigInt(4));
+    assert_eq!(Value::from(TaskStatus::Unknown(5)), Value::BigInt(5));
 }
 
 #[tokio::test(flavor = "multi_thread")]
 async fn test_file_store() {
-    let tmpdir = common::temp_dir().unwrap();
-    let config = solve::config::SQLiteConfig {
-        path: tmpdir
-            .join("db.sqlite")
-            .as_os_str()
-            .to_str()
-            .unwrap()
-            .to_string(),
-    };
-    let db: Arc<Database> =
-        Arc::new(new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap());
-    db.execute(
-        r#"CREATE TABLE "solve_file" (
-            "id" INTEGER PRIMARY KEY,
-            "status" INTEGER NOT NULL,
-            "expire_time" BIGINT,
-            "path" TEXT NOT NULL,
-            "meta" BLOB NOT NULL
-        )"#,
-    )
-    .await
-    .unwrap();
-    db.execute(
-        r#"CREATE TABLE "solve_file_event" (
-            "event_id" INTEGER PRIMARY KEY,
-            "event_time" BIGINT NOT NULL,
-            "event_kind" INTEGER NOT NULL,
-            "event_account_id" INTEGER,
-            "id" INTEGER NOT NULL,
-            "status" INTEGER NOT NULL,
-            "expire_time" BIGINT,
-            "path" TEXT NOT NULL,
-            "meta" BLOB NOT NULL
-        )"#,
-    )
-    .await
-    .unwrap();
+    let (_tmpdir, db) = common::sqlite_db();
     let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
     {
         let object = File {
             id: 123,
@@ -161,6 +177,8 @@ async fn test_file_store() {
             expire_time: None,
             path: "path".into(),
             meta: serde_json::Value::Null.into(),
+            account_id: None,
+            size: None,
         };
         let event = store.create(Context::new(), object).await.unwrap();
         assert_eq!(event.id(), 1);
@@ -189,9 +207,9 @@ async fn test_file_store() {
         assert_eq!(event.id(), 3);
         assert_eq!(event.kind(), EventKind::Delete);
         assert_eq!(event.object().id, 1);
-        assert_eq!(event.object().status, Default::default());
-        assert_eq!(event.object().expire_time, Default::default());
-        assert_eq!(event.object().path, "");
+        assert_eq!(event.object().status, FileStatus::Available);
+        assert!(event.object().expire_time.is_some());
+        assert_eq!(event.object().path, "path");
         assert_eq!(event.object().meta, serde_json::Value::Null.into());
     }
     {
@@ -209,48 +227,78 @@ async fn test_file_store() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_store_count() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    assert!(!store.exists(Context::new(), true.into()).await.unwrap());
+    for i in 0..5 {
+        let object = File {
+            status: if i % 2 == 0 {
+                FileStatus::Available
+            } else {
+                FileStatus::Pending
+            },
+            path: format!("path{i}"),
+            meta: serde_json::Value::Null.into(),
+            ..Default::default()
+        };
+        store.create(Context::new(), object).await.unwrap();
+    }
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 5);
+    assert_eq!(
+        store
+            .count(
+                Context::new(),
+                column("status").equal(FileStatus::Available)
+            )
+            .await
+            .unwrap(),
+        3
+    );
+    assert_eq!(
+        store
+            .count(Context::new(), column("path").equal("path1"))
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        store
+            .count(Context::new(), column("path").equal("unknown"))
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(store
+        .exists(Context::new(), column("path").equal("path3"))
+        .await
+        .unwrap());
+    assert!(!store
+        .exists(Context::new(), column("path").equal("unknown"))
+        .await
+        .unwrap());
+    assert!(store
+        .exists(Context::new(), column("status").equal(FileStatus::Pending))
+        .await
+        .unwrap());
+    let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+    for i in 0..5 {
+        let object = rows.next().await.unwrap().unwrap();
+        assert_eq!(object.path, format!("path{i}"));
+    }
+    assert!(rows.next().await.is_none());
+    let mut rows = store.find(Context::new(), Select::none()).await.unwrap();
+    assert!(rows.next().await.is_none());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn test_task_store() {
-    let tmpdir = common::temp_dir().unwrap();
-    let config = solve::config::SQLiteConfig {
-        path: tmpdir
-            .join("db.sqlite")
-            .as_os_str()
-            .to_str()
-            .unwrap()
-            .to_string(),
-    };
-    let db: Arc<Database> =
-        Arc::new(new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap());
-    db.execute(
-        r#"CREATE TABLE "solve_task" (
-            "id" INTEGER PRIMARY KEY,
-            "kind" INTEGER NOT NULL,
-            "config" BLOB NOT NULL,
-            "status" INTEGER NOT NULL,
-            "state" BLOB NOT NULL,
-            "expire_time" BIGINT
-        )"#,
-    )
-    .await
-    .unwrap();
-    db.execute(
-        r#"CREATE TABLE "solve_task_event" (
-            "event_id" INTEGER PRIMARY KEY,
-            "event_time" BIGINT NOT NULL,
-            "event_kind" INTEGER NOT NULL,
-            "event_account_id" INTEGER,
-            "id" INTEGER NOT NULL,
-            "kind" INTEGER NOT NULL,
-            "config" BLOB NOT NULL,
-            "status" INTEGER NOT NULL,
-            "state" BLOB NOT NULL,
-            "expire_time" BIGINT
-        )"#,
-    )
-    .await
-    .unwrap();
+    let (_tmpdir, db) = common::sqlite_db();
     let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
     {
         let object = Task {
             id: 123,
@@ -275,5 +323,5466 @@ async fn test_task_store() {
         assert_eq!(event.id(), 3);
         assert_eq!(event.kind(), EventKind::Delete);
         assert_eq!(event.object().id, 1);
+        assert!(event.object().expire_time.is_some());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_user_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let account_store = AccountStore::new(db.clone());
+    account_store.create_tables().await.unwrap();
+    let store = UserStore::new(db);
+    store.create_tables().await.unwrap();
+    let account = account_store
+        .create(Context::new(), Account::default())
+        .await
+        .unwrap()
+        .into_object();
+    let mut user = User {
+        account_id: account.id,
+        login: "test".into(),
+        create_time: Instant::now(),
+        ..Default::default()
+    };
+    user.set_password("qwerty123", "global");
+    assert!(user.password_hash.starts_with("$argon2id$"));
+    assert!(user.password_hash.contains(&user.password_salt));
+    assert!(user.check_password("qwerty123", "global"));
+    let event = store.create_user(Context::new(), user).await.unwrap();
+    assert_eq!(event.kind(), EventKind::Create);
+    assert_eq!(event.object().account_id, account.id);
+    let user = store
+        .get_by_login(Context::new(), "test")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.id, event.object().id);
+    assert!(user.check_password("qwerty123", "global"));
+    assert!(!user.check_password("qwerty124", "global"));
+    assert!(!user.check_password("qwerty123", "other"));
+    assert!(store
+        .get_by_login(Context::new(), "unknown")
+        .await
+        .unwrap()
+        .is_none());
+    // Password hashes are salted per user.
+    let mut other = user.clone();
+    other.set_password("qwerty123", "global");
+    assert_ne!(other.password_salt, user.password_salt);
+    assert_ne!(other.password_hash, user.password_hash);
+    assert!(other.check_password("qwerty123", "global"));
+    // Logins are unique.
+    let duplicate = User {
+        login: "test".into(),
+        ..Default::default()
+    };
+    let err = match store.create_user(Context::new(), duplicate.clone()).await {
+        Ok(_) => panic!("create should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_conflict(&err));
+    assert!(store.create(Context::new(), duplicate).await.is_err());
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_session_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = SessionStore::new(db);
+    store.create_tables().await.unwrap();
+    let session = store
+        .create_for_account(Context::new(), 42, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(session.account_id, 42);
+    assert!(session.expire_time > session.create_time);
+    assert!(session.secret.len() >= 32);
+    let found = store
+        .find_by_cookie(Context::new(), &session.cookie())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, session.id);
+    assert_eq!(found.secret, session.secret);
+    // Secrets are random.
+    let other = store
+        .create_for_account(Context::new(), 42, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert_ne!(other.secret, session.secret);
+    // Bad cookies are rejected.
+    for cookie in [
+        format!("{}_{}", session.id, other.secret),
+        format!("{}_", session.id),
+        format!("{}", session.id),
+        format!("{}_{}", other.id + 1, other.secret),
+        format!("abc_{}", session.secret),
+        "".to_owned(),
+    ] {
+        assert!(store
+            .find_by_cookie(Context::new(), &cookie)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    // Expired sessions are rejected.
+    let expired = store
+        .create_for_account(Context::new(), 43, Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(store
+        .find_by_cookie(Context::new(), &expired.cookie())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        store
+            .delete_expired(Context::new(), Instant::now() + Duration::from_secs(1))
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(store
+        .get(Context::new(), expired.id)
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 2);
+    assert_eq!(
+        store
+            .delete_expired(Context::new(), Instant::now() + Duration::from_secs(7200))
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    // Every deleted session has its own event.
+    let mut rows = store
+        .find_events(
+            Context::new(),
+            Select::new().with_where(column("event_kind").equal(EventKind::Delete)),
+        )
+        .await
+        .unwrap();
+    let mut deleted = Vec::new();
+    while let Some(event) = rows.next().await {
+        deleted.push(event.unwrap().object().id);
+    }
+    deleted.sort();
+    let mut expected = vec![session.id, other.id, expired.id];
+    expected.sort();
+    assert_eq!(deleted, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_compiler_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = CompilerStore::new(db);
+    store.create_tables().await.unwrap();
+    let config = CompilerConfig {
+        language: "C++".into(),
+        compile_command: Some("g++ -O2 -o solution solution.cpp".into()),
+        run_command: "./solution".into(),
+        extensions: vec!["cpp".into(), "cc".into()],
+        environ: vec!["PATH=/usr/bin".into()],
+    };
+    let mut compiler = Compiler {
+        name: "cpp17-gcc".into(),
+        image_file_id: Some(7),
+        ..Default::default()
+    };
+    compiler.set_config(&config).unwrap();
+    let event = store.create(Context::new(), compiler).await.unwrap();
+    assert_eq!(event.object().parse_config().unwrap(), config);
+    let mut python = Compiler {
+        name: "python3".into(),
+        ..Default::default()
+    };
+    python
+        .set_config(&CompilerConfig {
+            language: "Python".into(),
+            run_command: "python3 solution.py".into(),
+            ..Default::default()
+        })
+        .unwrap();
+    store.create(Context::new(), python).await.unwrap();
+    let compiler = store
+        .get_by_name(Context::new(), "cpp17-gcc")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(compiler.id, event.object().id);
+    assert_eq!(compiler.image_file_id, Some(7));
+    assert_eq!(compiler.parse_config().unwrap(), config);
+    let python = store
+        .get_by_name(Context::new(), "python3")
+        .await
+        .unwrap()
+        .unwrap();
+    let python_config = python.parse_config().unwrap();
+    assert_eq!(python_config.compile_command, None);
+    assert!(python_config.extensions.is_empty());
+    assert_eq!(python.image_file_id, None);
+    assert!(store
+        .get_by_name(Context::new(), "unknown")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(!Compiler::default().is_valid());
+    // Invalid objects are rejected without writes.
+    let err = match store.create(Context::new(), Compiler::default()).await {
+        Ok(_) => panic!("create should fail"),
+        Err(err) => err,
+    };
+    assert!(matches!(
+        StoreError::of(&err),
+        Some(StoreError::InvalidObject(_))
+    ));
+    let err = match store
+        .update(
+            Context::new(),
+            Compiler {
+                name: "".into(),
+                ..python
+            },
+        )
+        .await
+    {
+        Ok(_) => panic!("update should fail"),
+        Err(err) => err,
+    };
+    assert!(matches!(
+        StoreError::of(&err),
+        Some(StoreError::InvalidObject(_))
+    ));
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 2);
+    assert!(store
+        .get_by_name(Context::new(), "python3")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn test_contest_stage() {
+    let begin_time = Instant::from_unix(1_700_000_000);
+    let mut config = ContestConfig {
+        duration: Duration::from_secs(3600).into(),
+        ..Default::default()
+    };
+    assert_eq!(config.stage(begin_time), ContestStage::NotStarted);
+    config.begin_time = Some(begin_time);
+    let millis = Duration::from_millis(1);
+    assert_eq!(config.stage(begin_time - millis), ContestStage::NotStarted);
+    assert_eq!(config.stage(begin_time), ContestStage::Started);
+    let end_time = begin_time + Duration::from_secs(3600);
+    assert_eq!(config.stage(end_time - millis), ContestStage::Started);
+    assert_eq!(config.stage(end_time), ContestStage::Finished);
+    assert_eq!(config.stage(Instant::MAX), ContestStage::Finished);
+    config.duration = DurationMs::ZERO;
+    assert_eq!(config.stage(begin_time), ContestStage::Finished);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contest_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ContestStore::new(db);
+    store.create_tables().await.unwrap();
+    let begin_time = Instant::from_unix(1_700_000_000);
+    let config = ContestConfig {
+        begin_time: Some(begin_time),
+        duration: Duration::from_secs(5 * 3600).into(),
+        enable_registration: true,
+        enable_upsolving: false,
+        freeze_time: Some(begin_time + Duration::from_secs(4 * 3600)),
+    };
+    let mut contest = Contest {
+        owner_id: Some(1),
+        title: "Test contest".into(),
+        create_time: Instant::now(),
+        ..Default::default()
+    };
+    contest.set_config(&config).unwrap();
+    let event = store.create(Context::new(), contest).await.unwrap();
+    let contest = store
+        .get(Context::new(), event.object().id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(contest.title, "Test contest");
+    assert_eq!(contest.owner_id, Some(1));
+    assert_eq!(contest.create_time, event.object().create_time);
+    assert_eq!(contest.parse_config().unwrap(), config);
+    assert_eq!(
+        contest
+            .stage(begin_time + Duration::from_secs(3600))
+            .unwrap(),
+        ContestStage::Started
+    );
+    assert!(!Contest::default().is_valid());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contest_problem_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ContestProblemStore::new(db);
+    store.create_tables().await.unwrap();
+    for (contest_id, problem_id, code) in [(1, 10, "C"), (1, 11, "A"), (2, 12, "A"), (1, 13, "B")] {
+        let mut problem = ContestProblem {
+            contest_id,
+            problem_id,
+            code: code.into(),
+            ..Default::default()
+        };
+        problem
+            .set_config(&ContestProblemConfig {
+                points: Some("12.5".parse().unwrap()),
+                locales: vec!["en".into()],
+            })
+            .unwrap();
+        store.create_problem(Context::new(), problem).await.unwrap();
+    }
+    let problems = store.find_by_contest(Context::new(), 1).await.unwrap();
+    let codes: Vec<_> = problems.iter().map(|v| v.code.as_str()).collect();
+    assert_eq!(codes, vec!["A", "B", "C"]);
+    let problem_ids: Vec<_> = problems.iter().map(|v| v.problem_id).collect();
+    assert_eq!(problem_ids, vec![11, 13, 10]);
+    let config = problems[0].parse_config().unwrap();
+    assert_eq!(config.points, Some("12.5".parse().unwrap()));
+    assert_eq!(config.locales, vec!["en".to_owned()]);
+    // Codes are unique within contest.
+    let duplicate = ContestProblem {
+        contest_id: 1,
+        problem_id: 14,
+        code: "B".into(),
+        ..Default::default()
+    };
+    assert!(store
+        .create_problem(Context::new(), duplicate.clone())
+        .await
+        .is_err());
+    assert!(store.create(Context::new(), duplicate).await.is_err());
+    assert_eq!(
+        store
+            .find_by_contest(Context::new(), 1)
+            .await
+            .unwrap()
+            .len(),
+        3
+    );
+    // Problems are deleted together with contest.
+    assert_eq!(store.delete_by_contest(Context::new(), 1).await.unwrap(), 3);
+    assert!(store
+        .find_by_contest(Context::new(), 1)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        store
+            .find_by_contest(Context::new(), 2)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_contest_participant_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ContestParticipantStore::new(db);
+    store.create_tables().await.unwrap();
+    let new_contest = |id, begin_time, enable_registration, enable_upsolving| {
+        let mut contest = Contest {
+            id,
+            title: "Test contest".into(),
+            ..Default::default()
+        };
+        contest
+            .set_config(&ContestConfig {
+                begin_time: Some(begin_time),
+                duration: Duration::from_secs(3600).into(),
+                enable_registration,
+                enable_upsolving,
+                ..Default::default()
+            })
+            .unwrap();
+        contest
+    };
+    let register_error = |err: solve::core::Error| *err.downcast_ref::<RegisterError>().unwrap();
+    let future = Instant::now() + Duration::from_secs(600);
+    let running = Instant::now() - Duration::from_secs(600);
+    let finished = Instant::now() - Duration::from_secs(7200);
+    // Registration for not started and running contests.
+    for (id, begin_time) in [(1, future), (2, running)] {
+        let contest = new_contest(id, begin_time, true, false);
+        let participant = store
+            .register(
+                Context::new(),
+                &contest,
+                10,
+                ContestParticipantKind::Regular,
+            )
+            .await
+            .unwrap();
+        assert_eq!(participant.contest_id, id);
+        assert_eq!(participant.account_id, 10);
+        assert_eq!(participant.kind, ContestParticipantKind::Regular);
+        let err = store
+            .register(
+                Context::new(),
+                &contest,
+                10,
+                ContestParticipantKind::Regular,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(register_error(err), RegisterError::AlreadyRegistered);
+        let err = store
+            .register(
+                Context::new(),
+                &contest,
+                10,
+                ContestParticipantKind::Upsolving,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(register_error(err), RegisterError::UpsolvingDisabled);
+    }
+    // Registration is disabled.
+    let contest = new_contest(3, future, false, true);
+    let err = store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Regular,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(register_error(err), RegisterError::RegistrationDisabled);
+    let err = store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Upsolving,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(register_error(err), RegisterError::ContestNotFinished);
+    // Managers are registered regardless of contest settings.
+    store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Manager,
+        )
+        .await
+        .unwrap();
+    // Upsolving after contest is finished.
+    let contest = new_contest(4, finished, true, true);
+    let err = store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Regular,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(register_error(err), RegisterError::ContestFinished);
+    store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Upsolving,
+        )
+        .await
+        .unwrap();
+    store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Observer,
+        )
+        .await
+        .unwrap();
+    let err = store
+        .register(
+            Context::new(),
+            &contest,
+            10,
+            ContestParticipantKind::Upsolving,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(register_error(err), RegisterError::AlreadyRegistered);
+    store
+        .register(
+            Context::new(),
+            &contest,
+            11,
+            ContestParticipantKind::Upsolving,
+        )
+        .await
+        .unwrap();
+    let kinds: Vec<_> = store
+        .find_by_contest_account(Context::new(), 4, 10)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|v| v.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ContestParticipantKind::Upsolving,
+            ContestParticipantKind::Observer
+        ]
+    );
+    assert!(store
+        .find_by_contest_account(Context::new(), 4, 12)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_setting_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = SettingStore::new(db).with_cache_ttl(Duration::from_secs(3600));
+    store.create_tables().await.unwrap();
+    assert_eq!(
+        store.get_string("name", "default").await.unwrap(),
+        "default"
+    );
+    assert_eq!(store.get_i64("limit", 5).await.unwrap(), 5);
+    store.set(Context::new(), "name", "solve").await.unwrap();
+    store.set(Context::new(), "limit", "10").await.unwrap();
+    store.set(Context::new(), "enabled", "true").await.unwrap();
+    store
+        .set(Context::new(), "timeout", "1500ms")
+        .await
+        .unwrap();
+    store.set(Context::new(), "bad_limit", "ten").await.unwrap();
+    assert_eq!(store.get_string("name", "default").await.unwrap(), "solve");
+    assert_eq!(store.get_i64("limit", 5).await.unwrap(), 10);
+    assert!(store.get_bool("enabled", false).await.unwrap());
+    assert_eq!(
+        store.get_duration("timeout", Duration::ZERO).await.unwrap(),
+        Duration::from_millis(1500)
+    );
+    // Values that cannot be parsed fall back to defaults.
+    assert_eq!(store.get_i64("bad_limit", 5).await.unwrap(), 5);
+    assert!(!store.get_bool("name", false).await.unwrap());
+    assert_eq!(
+        store
+            .get_duration("name", Duration::from_secs(1))
+            .await
+            .unwrap(),
+        Duration::from_secs(1)
+    );
+    // Changes bypassing settings cache are not visible until reload.
+    let mut setting = store
+        .find(
+            Context::new(),
+            Select::new().with_where(column("key").equal("limit")),
+        )
+        .await
+        .unwrap()
+        .next()
+        .await
+        .unwrap()
+        .unwrap();
+    setting.value = "20".into();
+    store.update(Context::new(), setting).await.unwrap();
+    assert_eq!(store.get_i64("limit", 5).await.unwrap(), 10);
+    // Cache is invalidated after set.
+    store.set(Context::new(), "enabled", "0").await.unwrap();
+    assert!(!store.get_bool("enabled", true).await.unwrap());
+    assert_eq!(store.get_i64("limit", 5).await.unwrap(), 20);
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 5);
+}
+
+fn new_role_set(
+    roles: &[(i64, &str)],
+    edges: &[(i64, i64)],
+) -> Result<RoleSet, solve::core::Error> {
+    RoleSet::new(
+        roles
+            .iter()
+            .map(|(id, name)| Role {
+                id: *id,
+                name: name.to_string(),
+            })
+            .collect(),
+        edges
+            .iter()
+            .enumerate()
+            .map(|(i, (role_id, child_id))| RoleEdge {
+                id: i as i64 + 1,
+                role_id: *role_id,
+                child_id: *child_id,
+            })
+            .collect(),
+    )
+}
+
+fn sorted_names(names: std::collections::HashSet<String>) -> Vec<String> {
+    let mut names: Vec<_> = names.into_iter().collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_role_set() {
+    let roles = [(1, "a"), (2, "b"), (3, "c"), (4, "d"), (5, "e")];
+    // Diamond: a -> b -> d, a -> c -> d, d -> e.
+    let set = new_role_set(&roles, &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5)]).unwrap();
+    assert_eq!(
+        sorted_names(set.get_names(&[1])),
+        vec!["a", "b", "c", "d", "e"]
+    );
+    assert_eq!(sorted_names(set.get_names(&[2])), vec!["b", "d", "e"]);
+    assert_eq!(
+        sorted_names(set.get_names(&[2, 3])),
+        vec!["b", "c", "d", "e"]
+    );
+    assert_eq!(sorted_names(set.get_names(&[5])), vec!["e"]);
+    assert!(set.get_names(&[]).is_empty());
+    assert!(set.get_names(&[42]).is_empty());
+    // Duplicate edges are allowed.
+    let set = new_role_set(&roles, &[(1, 2), (1, 2), (2, 3)]).unwrap();
+    assert_eq!(sorted_names(set.get_names(&[1])), vec!["a", "b", "c"]);
+    // Cycles are rejected.
+    assert!(new_role_set(&roles, &[(1, 1)]).is_err());
+    assert!(new_role_set(&roles, &[(1, 2), (2, 1)]).is_err());
+    assert!(new_role_set(&roles, &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5), (5, 3)]).is_err());
+    // Unknown roles are rejected.
+    assert!(new_role_set(&roles, &[(1, 6)]).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_role_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let roles = RoleStore::new(db.clone());
+    roles.create_tables().await.unwrap();
+    let edges = RoleEdgeStore::new(db);
+    edges.create_tables().await.unwrap();
+    create_builtin_roles(Context::new(), &roles, &edges)
+        .await
+        .unwrap();
+    let role_count = roles.count(Context::new(), true.into()).await.unwrap();
+    let edge_count = edges.count(Context::new(), true.into()).await.unwrap();
+    // Bootstrap is idempotent.
+    create_builtin_roles(Context::new(), &roles, &edges)
+        .await
+        .unwrap();
+    assert_eq!(
+        roles.count(Context::new(), true.into()).await.unwrap(),
+        role_count
+    );
+    assert_eq!(
+        edges.count(Context::new(), true.into()).await.unwrap(),
+        edge_count
+    );
+    let set = RoleSet::load(&roles, &edges).await.unwrap();
+    let mut rows = roles
+        .find(
+            Context::new(),
+            Select::new().with_where(column("name").equal(ADMIN_GROUP_ROLE)),
+        )
+        .await
+        .unwrap();
+    let admin = rows.next().await.unwrap().unwrap();
+    let names = set.get_names(&[admin.id]);
+    for name in [
+        ADMIN_GROUP_ROLE,
+        USER_GROUP_ROLE,
+        LOGOUT_ROLE,
+        CREATE_CONTEST_ROLE,
+        UPDATE_SETTINGS_ROLE,
+    ] {
+        assert!(names.contains(name), "{name}");
+    }
+    assert!(!names.contains(GUEST_GROUP_ROLE));
+    assert!(!names.contains(REGISTER_ROLE));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_account_role_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let roles = Arc::new(RoleStore::new(db.clone()));
+    roles.create_tables().await.unwrap();
+    let edges = Arc::new(RoleEdgeStore::new(db.clone()));
+    edges.create_tables().await.unwrap();
+    let store = Arc::new(AccountRoleStore::new(db));
+    store.create_tables().await.unwrap();
+    create_builtin_roles(Context::new(), &roles, &edges)
+        .await
+        .unwrap();
+    let get_role = |name: &'static str| {
+        let roles = roles.clone();
+        async move {
+            let mut rows = roles
+                .find(
+                    Context::new(),
+                    Select::new().with_where(column("name").equal(name)),
+                )
+                .await
+                .unwrap();
+            rows.next().await.unwrap().unwrap()
+        }
+    };
+    let user_group = get_role(USER_GROUP_ROLE).await;
+    let create_contest = get_role(CREATE_CONTEST_ROLE).await;
+    store
+        .assign(Context::new(), 1, user_group.id)
+        .await
+        .unwrap();
+    store
+        .assign(Context::new(), 1, create_contest.id)
+        .await
+        .unwrap();
+    // Duplicate assignments are rejected.
+    assert!(store
+        .assign(Context::new(), 1, user_group.id)
+        .await
+        .is_err());
+    store
+        .assign(Context::new(), 2, user_group.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        store
+            .find_by_account(Context::new(), 1)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert_eq!(
+        store
+            .find_by_account(Context::new(), 2)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(store
+        .find_by_account(Context::new(), 3)
+        .await
+        .unwrap()
+        .is_empty());
+    let account_roles = AccountRoles::new(store, roles.clone(), edges);
+    let names = account_roles.resolve(1).await.unwrap();
+    for name in [USER_GROUP_ROLE, LOGOUT_ROLE, CREATE_CONTEST_ROLE] {
+        assert!(names.contains(name), "{name}");
+    }
+    assert!(!names.contains(UPDATE_SETTINGS_ROLE));
+    let names = account_roles.resolve(2).await.unwrap();
+    assert!(names.contains(LOGOUT_ROLE));
+    assert!(!names.contains(CREATE_CONTEST_ROLE));
+    assert!(account_roles.resolve(3).await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_token_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TokenStore::new(db);
+    store.create_tables().await.unwrap();
+    let (token, value) = store
+        .create_token(
+            Context::new(),
+            7,
+            &["solutions:*".to_owned(), "problems:read".to_owned()],
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+    assert_eq!(token.account_id, 7);
+    assert!(!token.secret_hash.is_empty());
+    assert!(!value.contains(&token.secret_hash));
+    let found = store
+        .authenticate(Context::new(), &value, "solutions:submit")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, token.id);
+    assert!(store
+        .authenticate(Context::new(), &value, "problems:read")
+        .await
+        .unwrap()
+        .is_some());
+    // Scopes are checked.
+    for scope in ["problems:write", "solutions", "contests:read", ""] {
+        assert!(store
+            .authenticate(Context::new(), &value, scope)
+            .await
+            .unwrap()
+            .is_none());
+    }
+    // Wrong secrets are rejected.
+    let (other, other_value) = store
+        .create_token(
+            Context::new(),
+            7,
+            &["*".to_owned()],
+            Duration::from_secs(3600),
+        )
+        .await
+        .unwrap();
+    let other_secret = other_value.split_once('_').unwrap().1;
+    for value in [
+        format!("{}_{}", token.id, other_secret),
+        format!("{}_", token.id),
+        format!("{}_{}", token.id, token.secret_hash),
+        format!("{}", token.id),
+        "".to_owned(),
+    ] {
+        assert!(store
+            .authenticate(Context::new(), &value, "problems:read")
+            .await
+            .unwrap()
+            .is_none());
+    }
+    // Wildcard grants everything.
+    assert_eq!(
+        store
+            .authenticate(Context::new(), &other_value, "anything")
+            .await
+            .unwrap()
+            .unwrap()
+            .id,
+        other.id
+    );
+    // Expired tokens are rejected.
+    let (_, expired_value) = store
+        .create_token(Context::new(), 7, &["*".to_owned()], Duration::ZERO)
+        .await
+        .unwrap();
+    assert!(store
+        .authenticate(Context::new(), &expired_value, "anything")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_event_range_horizon() {
+    let gaps = |range: &EventRange| range.gaps().collect::<Vec<_>>();
+    let mut range = EventRange::new(1);
+    assert!(range.add(1));
+    assert!(range.add(3));
+    assert_eq!(gaps(&range), vec![2]);
+    // Writer of gap is still running.
+    range.set_horizon(105);
+    range.expire_finished_gaps(100);
+    assert_eq!(gaps(&range), vec![2]);
+    // Later horizons do not postpone expiration of known gaps.
+    range.set_horizon(110);
+    range.expire_finished_gaps(104);
+    assert_eq!(gaps(&range), vec![2]);
+    // Late event of long running writer is still delivered.
+    assert!(range.add(2));
+    assert!(gaps(&range).is_empty());
+    // Gaps of rolled back writers expire once writers are finished.
+    assert!(range.add(6));
+    range.set_horizon(120);
+    range.expire_finished_gaps(119);
+    assert_eq!(gaps(&range), vec![4, 5]);
+    assert!(range.add(4));
+    // Horizons are kept in saved position.
+    let mut range: EventRange =
+        serde_json::from_value(serde_json::to_value(&range).unwrap()).unwrap();
+    range.expire_finished_gaps(120);
+    assert!(gaps(&range).is_empty());
+    assert!(!range.add(5));
+}
+
+async fn poll_event_ids(consumer: &mut EventConsumer<TaskStore>) -> Vec<i64> {
+    consumer
+        .poll(Context::new(), 100)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|v| v.id())
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_event_consumer() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    // Writes event as concurrent writer that allocated specified id.
+    let write_event = |id: i64| {
+        let db = db.clone();
+        async move {
+            let mut event = TaskEvent::create(Task {
+                id,
+                ..Default::default()
+            });
+            event.set_id(id);
+            db.execute(Insert::new().with_table("solve_task_event").with_row(event))
+                .await
+                .unwrap();
+        }
+    };
+    let saved = Arc::new(std::sync::Mutex::new(None));
+    let mut consumer = EventConsumer::new(store.clone()).with_save_fn({
+        let saved = saved.clone();
+        move |range: &EventRange| {
+            *saved.lock().unwrap() = Some(range.clone());
+            Ok(())
+        }
+    });
+    let mut seen = Vec::new();
+    assert!(poll_event_ids(&mut consumer).await.is_empty());
+    for id in [1, 2, 5] {
+        write_event(id).await;
+    }
+    let ids = poll_event_ids(&mut consumer).await;
+    assert_eq!(ids, vec![1, 2, 5]);
+    seen.extend(ids);
+    assert_eq!(
+        consumer.range().unwrap().gaps().collect::<Vec<_>>(),
+        vec![3, 4]
+    );
+    assert_eq!(saved.lock().unwrap().as_ref(), consumer.range());
+    // Late events are delivered once.
+    write_event(4).await;
+    write_event(7).await;
+    let ids = poll_event_ids(&mut consumer).await;
+    assert_eq!(ids, vec![4, 7]);
+    seen.extend(ids);
+    write_event(3).await;
+    write_event(6).await;
+    let ids = poll_event_ids(&mut consumer).await;
+    assert_eq!(ids, vec![3, 6]);
+    seen.extend(ids);
+    assert!(poll_event_ids(&mut consumer).await.is_empty());
+    seen.sort();
+    assert_eq!(seen, vec![1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(consumer.range().unwrap().next_begin(), 8);
+    // Events written through store are delivered as well.
+    store.create(Context::new(), Task::default()).await.unwrap();
+    let ids = poll_event_ids(&mut consumer).await;
+    assert_eq!(ids, vec![8]);
+    // Restored consumer continues from saved position.
+    let range = saved.lock().unwrap().clone().unwrap();
+    let mut consumer = EventConsumer::new(store.clone()).with_range(range);
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    // Gaps are forgotten after window expires.
+    let mut consumer = consumer.with_gap_window(Duration::ZERO);
+    write_event(10).await;
+    assert_eq!(poll_event_ids(&mut consumer).await, vec![10]);
+    assert_eq!(
+        consumer.range().unwrap().gaps().collect::<Vec<_>>(),
+        vec![9]
+    );
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert!(poll_event_ids(&mut consumer).await.is_empty());
+    assert_eq!(consumer.range().unwrap().gaps().count(), 0);
+    write_event(9).await;
+    assert!(consumer.poll(Context::new(), 100).await.unwrap().is_empty());
+    // Gaps are polled in chunks.
+    let mut consumer = EventConsumer::new(store.clone()).with_range(EventRange::new(11));
+    write_event(1200).await;
+    assert_eq!(poll_event_ids(&mut consumer).await, vec![1200]);
+    assert_eq!(
+        consumer.range().unwrap().gaps().count() as i64,
+        EventRange::MAX_GAP_SPAN
+    );
+    write_event(300).await;
+    write_event(1100).await;
+    assert_eq!(poll_event_ids(&mut consumer).await, vec![300, 1100]);
+    // New consumer starts after existing events.
+    let mut consumer = EventConsumer::new(store.clone());
+    assert!(consumer.range().is_none());
+    assert!(poll_event_ids(&mut consumer).await.is_empty());
+    assert_eq!(consumer.range().unwrap().next_begin(), 1201);
+    store.create(Context::new(), Task::default()).await.unwrap();
+    assert_eq!(poll_event_ids(&mut consumer).await, vec![1201]);
+}
+
+struct CompilerNameIndex;
+
+impl CacheIndex<Compiler> for CompilerNameIndex {
+    fn name(&self) -> &str {
+        "name"
+    }
+
+    fn key(&self, object: &Compiler) -> Option<String> {
+        Some(object.name.clone())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_cached_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = CompilerStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let gcc = store
+        .create(
+            Context::new(),
+            Compiler {
+                name: "gcc".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    let cached = CachedStore::new(Arc::new(PersistentStore::<Compiler>::new(
+        db,
+        "solve_compiler",
+        "solve_compiler_event",
+    )))
+    .with_index(CompilerNameIndex);
+    cached.init().await.unwrap();
+    assert_eq!(cached.get_cached(&gcc.id).unwrap().name, "gcc");
+    // Changes from other store are visible only after sync.
+    let clang = store
+        .create(
+            Context::new(),
+            Compiler {
+                name: "clang".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    let mut gcc_renamed = gcc.clone();
+    gcc_renamed.name = "gcc-13".into();
+    store.update(Context::new(), gcc_renamed).await.unwrap();
+    assert!(cached.get_cached(&clang.id).is_none());
+    assert_eq!(cached.find_by_index("name", "gcc").len(), 1);
+    cached.sync(Context::new()).await.unwrap();
+    assert_eq!(cached.get_cached(&clang.id).unwrap().name, "clang");
+    assert!(cached.find_by_index("name", "gcc").is_empty());
+    assert_eq!(cached.find_by_index("name", "gcc-13")[0].id, gcc.id);
+    store.delete(Context::new(), clang.id).await.unwrap();
+    cached.sync(Context::new()).await.unwrap();
+    assert!(cached.get_cached(&clang.id).is_none());
+    assert!(cached.find_by_index("name", "clang").is_empty());
+    // Writes through cached store are applied locally.
+    let python = cached
+        .create(
+            Context::new(),
+            Compiler {
+                name: "python3".into(),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    assert_eq!(cached.get_cached(&python.id).unwrap().name, "python3");
+    cached.sync(Context::new()).await.unwrap();
+    let mut names: Vec<_> = cached
+        .find_cached(|_| true)
+        .into_iter()
+        .map(|v| v.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["gcc-13".to_owned(), "python3".to_owned()]);
+    assert_eq!(cached.find_cached(|v| v.name.starts_with("py")).len(), 1);
+}
+
+async fn collect_pages(store: &FileStore, request: PageRequest) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = store
+            .find_page(Context::new(), request.clone().with_cursor(cursor))
+            .await
+            .unwrap();
+        pages.push(page.objects.into_iter().map(|v| v.path).collect());
+        match page.next_cursor {
+            Some(v) => cursor = Some(v),
+            None => return pages,
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_store_account_usage() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db);
+    store.create_tables().await.unwrap();
+    for (account_id, size, status) in [
+        (Some(1), 100, FileStatus::Available),
+        (Some(1), 200, FileStatus::Available),
+        (Some(1), 400, FileStatus::Pending),
+        (Some(2), 800, FileStatus::Available),
+        (None, 1600, FileStatus::Available),
+    ] {
+        let mut object = File {
+            status,
+            ..Default::default()
+        };
+        object
+            .set_meta(&FileMeta {
+                size: Some(size),
+                account_id,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(object.account_id, account_id);
+        assert_eq!(object.size, Some(size as i64));
+        store.create(Context::new(), object).await.unwrap();
+    }
+    let usage = |account_id| store.account_usage(Context::new(), account_id);
+    assert_eq!(usage(1).await.unwrap(), 300);
+    assert_eq!(usage(2).await.unwrap(), 800);
+    assert_eq!(usage(3).await.unwrap(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_store_page() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone()).with_cursor_key(b"secret".to_vec());
+    store.create_tables().await.unwrap();
+    for i in 0..25 {
+        let object = File {
+            // Every status is shared by several files, so id breaks ties.
+            status: if i % 3 == 0 {
+                FileStatus::Available
+            } else {
+                FileStatus::Pending
+            },
+            path: format!("path{i:02}"),
+            meta: serde_json::Value::Null.into(),
+            ..Default::default()
+        };
+        store.create(Context::new(), object).await.unwrap();
+    }
+    let paths: Vec<_> = (0..25).map(|i| format!("path{i:02}")).collect();
+    let pages = collect_pages(&store, PageRequest::new(10)).await;
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [10, 10, 5]);
+    assert_eq!(pages.concat(), paths);
+    let pages = collect_pages(
+        &store,
+        PageRequest::new(10).with_order(vec![Order::desc("path")]),
+    )
+    .await;
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [10, 10, 5]);
+    let mut expected = paths.clone();
+    expected.reverse();
+    assert_eq!(pages.concat(), expected);
+    let pages = collect_pages(
+        &store,
+        PageRequest::new(10).with_order(vec![Order::desc("status"), Order::desc("id")]),
+    )
+    .await;
+    let mut expected: Vec<_> = (0..25).filter(|i| i % 3 == 0).rev().collect();
+    expected.extend((0..25).filter(|i| i % 3 != 0).rev());
+    let expected: Vec<_> = expected.into_iter().map(|i| paths[i].clone()).collect();
+    assert_eq!(pages.concat(), expected);
+    let pages = collect_pages(
+        &store,
+        PageRequest::new(10).with_where(column("status").equal(FileStatus::Available)),
+    )
+    .await;
+    assert_eq!(pages.iter().map(Vec::len).collect::<Vec<_>>(), [9]);
+    // Tampered cursor or cursor of other order are rejected.
+    let page = store
+        .find_page(Context::new(), PageRequest::new(10))
+        .await
+        .unwrap();
+    let cursor = page.next_cursor.unwrap();
+    let mut tampered = cursor.to_string().into_bytes();
+    tampered[3] = if tampered[3] == b'A' { b'B' } else { b'A' };
+    let tampered = Cursor::from(String::from_utf8(tampered).unwrap());
+    assert!(store
+        .find_page(
+            Context::new(),
+            PageRequest::new(10).with_cursor(Some(tampered))
+        )
+        .await
+        .is_err());
+    assert!(store
+        .find_page(
+            Context::new(),
+            PageRequest::new(10).with_cursor(Some("garbage".into()))
+        )
+        .await
+        .is_err());
+    assert!(store
+        .find_page(
+            Context::new(),
+            PageRequest::new(10)
+                .with_order(vec![Order::desc("path")])
+                .with_cursor(Some(cursor.clone()))
+        )
+        .await
+        .is_err());
+    let page = store
+        .find_page(
+            Context::new(),
+            PageRequest::new(10).with_cursor(Some(cursor.clone())),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.objects[0].path, "path10");
+    // Cursors signed with other key are rejected.
+    let other = FileStore::new(db).with_cursor_key(b"other".to_vec());
+    assert!(other
+        .find_page(
+            Context::new(),
+            PageRequest::new(10).with_cursor(Some(cursor.clone()))
+        )
+        .await
+        .is_err());
+    let page = other
+        .find_page(Context::new(), PageRequest::new(10))
+        .await
+        .unwrap();
+    assert_ne!(page.next_cursor, Some(cursor));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_batch() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let new_file = |path: String| File {
+        path,
+        meta: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    let events = store
+        .create_batch(
+            Context::new(),
+            (0..5).map(|i| new_file(format!("path{i}"))).collect(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 5);
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(event.kind(), EventKind::Create);
+        assert_eq!(event.object().path, format!("path{i}"));
+        let object = store
+            .get(Context::new(), event.object().id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(object.path, event.object().path);
     }
+    assert!(events.windows(2).all(|v| v[0].id() < v[1].id()));
+    assert!(store
+        .create_batch(Context::new(), Vec::new())
+        .await
+        .unwrap()
+        .is_empty());
+    // Invalid object in the middle of batch rejects whole batch.
+    let mut objects: Vec<_> = (5..10).map(|i| new_file(format!("path{i}"))).collect();
+    objects[2].status = FileStatus::Unknown(42);
+    assert!(store.create_batch(Context::new(), objects).await.is_err());
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 5);
+    // Failure of last insert statement rolls back previous ones.
+    let account_store = AccountStore::new(db.clone());
+    account_store.create_tables().await.unwrap();
+    let user_store = UserStore::new(db.clone());
+    user_store.create_tables().await.unwrap();
+    let mut users: Vec<_> = (0..300)
+        .map(|i| User {
+            account_id: i,
+            login: format!("user{i}"),
+            ..Default::default()
+        })
+        .collect();
+    users[299].login = "user0".into();
+    assert!(user_store
+        .create_batch(Context::new(), users)
+        .await
+        .is_err());
+    assert_eq!(
+        user_store.count(Context::new(), true.into()).await.unwrap(),
+        0
+    );
+    let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+    let events = store
+        .create_batch(
+            Context::new().with_tx(&mut tx),
+            vec![new_file("path5".into())],
+        )
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    assert!(store
+        .get(Context::new(), events[0].object().id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_event_account_id() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let object = File {
+        path: "path".into(),
+        meta: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    let before = Instant::now();
+    let event = store
+        .create(Context::new().with_account_id(42), object)
+        .await
+        .unwrap();
+    assert_eq!(event.account_id(), Some(42));
+    assert!(event.time() >= before);
+    let object = event.into_object();
+    let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+    store
+        .update(
+            Context::new().with_account_id(7).with_tx(&mut tx),
+            object.clone(),
+        )
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    store.delete(Context::new(), object.id).await.unwrap();
+    let mut rows = store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    let mut events = Vec::new();
+    while let Some(event) = rows.next().await {
+        let event = event.unwrap();
+        events.push((event.kind(), event.account_id()));
+    }
+    assert_eq!(
+        events,
+        vec![
+            (EventKind::Create, Some(42)),
+            (EventKind::Update, Some(7)),
+            (EventKind::Delete, None),
+        ]
+    );
+}
+
+async fn event_ids(store: &TaskStore) -> Vec<i64> {
+    let mut rows = store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(event) = rows.next().await {
+        ids.push(event.unwrap().id());
+    }
+    ids
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_prune_events() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let now = Instant::now();
+    // Event with id i is written 26 - i days ago.
+    for id in 1..=25 {
+        let mut event = TaskEvent::create(Task {
+            id,
+            ..Default::default()
+        });
+        event.set_id(id);
+        event.set_time(now - Duration::from_secs(86400 * (26 - id) as u64));
+        db.execute(Insert::new().with_table("solve_task_event").with_row(event))
+            .await
+            .unwrap();
+    }
+    let before = now - Duration::from_secs(86400 * 10);
+    assert_eq!(
+        store.prune_events(Context::new(), before, 4).await.unwrap(),
+        15
+    );
+    assert_eq!(event_ids(&store).await, (16..=25).collect::<Vec<_>>());
+    assert_eq!(
+        store.prune_events(Context::new(), before, 4).await.unwrap(),
+        0
+    );
+    // Archive function receives every batch and can abort pruning.
+    let archived = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let store = PersistentStore::<Task>::new(db.clone(), "solve_task", "solve_task_event")
+        .with_archive_fn({
+            let archived = archived.clone();
+            move |events| {
+                let ids: Vec<_> = events.iter().map(|v| v.id()).collect();
+                if ids.contains(&20) {
+                    return Err("archive is unavailable".into());
+                }
+                archived.lock().unwrap().push(ids);
+                Ok(())
+            }
+        });
+    let before = now - Duration::from_secs(86400 * 3);
+    assert!(store.prune_events(Context::new(), before, 3).await.is_err());
+    assert_eq!(*archived.lock().unwrap(), vec![vec![16, 17, 18]]);
+    let store = TaskStore::new(db);
+    assert_eq!(event_ids(&store).await, (19..=25).collect::<Vec<_>>());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_get_many() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let object = File {
+            path: format!("path{i}"),
+            meta: serde_json::Value::Null.into(),
+            ..Default::default()
+        };
+        ids.push(
+            store
+                .create(Context::new(), object)
+                .await
+                .unwrap()
+                .object()
+                .id,
+        );
+    }
+    let objects = store
+        .get_many(Context::new(), &[ids[3], 100, ids[1], ids[3], -1, ids[4]])
+        .await
+        .unwrap();
+    assert_eq!(objects.len(), 3);
+    assert_eq!(objects[&ids[1]].path, "path1");
+    assert_eq!(objects[&ids[3]].path, "path3");
+    assert_eq!(objects[&ids[4]].path, "path4");
+    assert!(!objects.contains_key(&100));
+    assert!(store
+        .get_many(Context::new(), &[])
+        .await
+        .unwrap()
+        .is_empty());
+    let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+    store
+        .delete(Context::new().with_tx(&mut tx), ids[0])
+        .await
+        .unwrap();
+    let objects = store
+        .get_many(Context::new().with_tx(&mut tx), &ids)
+        .await
+        .unwrap();
+    assert_eq!(objects.len(), 4);
+    assert!(!objects.contains_key(&ids[0]));
+    tx.rollback().await.unwrap();
+}
+
+#[derive(Default)]
+struct RecordingObserver {
+    events: std::sync::Mutex<Vec<(EventKind, String)>>,
+}
+
+impl StoreObserver<File> for RecordingObserver {
+    fn on_event(&self, event: &FileEvent) {
+        let mut events = self.events.lock().unwrap();
+        events.push((event.kind(), event.object().path.clone()));
+    }
+}
+
+impl RecordingObserver {
+    fn take(&self) -> Vec<(EventKind, String)> {
+        std::mem::take(&mut self.events.lock().unwrap())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_observers() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let observer = Arc::new(RecordingObserver::default());
+    store.add_observer(observer.clone());
+    let new_file = |path: &str| File {
+        path: path.into(),
+        meta: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    let file = store
+        .create(Context::new(), new_file("a"))
+        .await
+        .unwrap()
+        .into_object();
+    assert_eq!(observer.take(), vec![(EventKind::Create, "a".into())]);
+    // Events of caller transaction are delivered only after commit.
+    let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+    store
+        .update(Context::new().with_tx(&mut tx), file.clone())
+        .await
+        .unwrap();
+    store
+        .create_batch(
+            Context::new().with_tx(&mut tx),
+            vec![new_file("b"), new_file("c")],
+        )
+        .await
+        .unwrap();
+    assert!(observer.take().is_empty());
+    tx.commit().await.unwrap();
+    assert_eq!(
+        observer.take(),
+        vec![
+            (EventKind::Update, "a".into()),
+            (EventKind::Create, "b".into()),
+            (EventKind::Create, "c".into()),
+        ]
+    );
+    // Rolled back and dropped transactions do not notify observers.
+    let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+    store
+        .delete(Context::new().with_tx(&mut tx), file.id)
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    {
+        let mut tx = db.transaction(TransactionOptions::default()).await.unwrap();
+        store
+            .create(Context::new().with_tx(&mut tx), new_file("d"))
+            .await
+            .unwrap();
+    }
+    assert!(observer.take().is_empty());
+    // Failed write does not notify observers.
+    assert!(store
+        .update_where(Context::new(), file.clone(), false.into())
+        .await
+        .is_err());
+    assert!(observer.take().is_empty());
+    store.delete(Context::new(), file.id).await.unwrap();
+    assert_eq!(observer.take(), vec![(EventKind::Delete, "a".into())]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_soft_delete() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ContestStore::new(db);
+    store.create_tables().await.unwrap();
+    let mut ids = Vec::new();
+    for title in ["first", "second"] {
+        let contest = Contest {
+            title: title.into(),
+            config: serde_json::Value::Null.into(),
+            ..Default::default()
+        };
+        ids.push(
+            store
+                .create(Context::new(), contest)
+                .await
+                .unwrap()
+                .object()
+                .id,
+        );
+    }
+    let event = store.soft_delete(Context::new(), ids[0]).await.unwrap();
+    assert_eq!(event.kind(), EventKind::Delete);
+    assert!(event.object().deleted_at.is_some());
+    assert!(store.soft_delete(Context::new(), ids[0]).await.is_err());
+    // Soft deleted objects are hidden from reads.
+    assert!(store.get(Context::new(), ids[0]).await.unwrap().is_none());
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 1);
+    assert!(!store
+        .exists(Context::new(), column("title").equal("first"))
+        .await
+        .unwrap());
+    let objects = store.get_many(Context::new(), &ids).await.unwrap();
+    assert_eq!(objects.len(), 1);
+    assert!(objects.contains_key(&ids[1]));
+    let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+    assert_eq!(rows.next().await.unwrap().unwrap().id, ids[1]);
+    assert!(rows.next().await.is_none());
+    drop(rows);
+    // Deleted objects are still available explicitly.
+    let mut rows = store
+        .find(
+            Context::new(),
+            Select::new()
+                .with_where(column("title").equal("first"))
+                .include_deleted(),
+        )
+        .await
+        .unwrap();
+    let deleted = rows.next().await.unwrap().unwrap();
+    assert_eq!(deleted.id, ids[0]);
+    assert!(deleted.deleted_at.is_some());
+    drop(rows);
+    // Update with empty deletion time restores object.
+    let restored = Contest {
+        deleted_at: None,
+        ..deleted
+    };
+    let event = store.update(Context::new(), restored).await.unwrap();
+    assert_eq!(event.kind(), EventKind::Update);
+    assert_eq!(
+        store
+            .get(Context::new(), ids[0])
+            .await
+            .unwrap()
+            .unwrap()
+            .title,
+        "first"
+    );
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 2);
+    // Hard delete is still available.
+    store.delete(Context::new(), ids[1]).await.unwrap();
+    let mut rows = store
+        .find(Context::new(), Select::new().include_deleted())
+        .await
+        .unwrap();
+    assert_eq!(rows.next().await.unwrap().unwrap().id, ids[0]);
+    assert!(rows.next().await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_versioned_update() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
+    let task = Task {
+        config: serde_json::Value::Null.into(),
+        state: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    let task = store
+        .create(Context::new(), task)
+        .await
+        .unwrap()
+        .into_object();
+    assert_eq!(task.version(), 0);
+    // Both writers read the same version of task.
+    let first = Task {
+        status: TaskStatus::Running,
+        ..task.clone()
+    };
+    let second = Task {
+        status: TaskStatus::Failed,
+        ..task.clone()
+    };
+    let first = store
+        .update(Context::new(), first)
+        .await
+        .unwrap()
+        .into_object();
+    assert_eq!(first.version(), 1);
+    let err = match store.update(Context::new(), second).await {
+        Ok(_) => panic!("update should fail"),
+        Err(err) => err,
+    };
+    assert!(matches!(StoreError::of(&err), Some(StoreError::Conflict)));
+    let err = match store
+        .update_where(
+            Context::new(),
+            task.clone(),
+            column("status").equal(TaskStatus::Running),
+        )
+        .await
+    {
+        Ok(_) => panic!("update should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_conflict(&err));
+    let stored = store.get(Context::new(), task.id).await.unwrap().unwrap();
+    assert_eq!(stored.status, TaskStatus::Running);
+    assert_eq!(stored.version(), 1);
+    let updated = store
+        .update(
+            Context::new(),
+            Task {
+                status: TaskStatus::Succeeded,
+                ..stored
+            },
+        )
+        .await
+        .unwrap()
+        .into_object();
+    assert_eq!(updated.version(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_write_errors() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = FileStore::new(db);
+    store.create_tables().await.unwrap();
+    let file = File {
+        status: FileStatus::Pending,
+        path: "a".into(),
+        meta: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    let file = store
+        .create(Context::new(), file)
+        .await
+        .unwrap()
+        .into_object();
+    // Update exposes previous state of object.
+    let event = store
+        .update(
+            Context::new(),
+            File {
+                path: "b".into(),
+                ..file.clone()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(event.prev_object().unwrap().path, "a");
+    assert_eq!(event.object().path, "b");
+    // Predicate mismatch of existing object is a conflict.
+    let err = match store
+        .update_where(
+            Context::new(),
+            file.clone(),
+            column("status").equal(FileStatus::Available),
+        )
+        .await
+    {
+        Ok(_) => panic!("update should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_conflict(&err));
+    let err = match store
+        .delete_where(
+            Context::new(),
+            file.id,
+            column("status").equal(FileStatus::Available),
+        )
+        .await
+    {
+        Ok(_) => panic!("delete should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_conflict(&err));
+    // Delete event contains final state of object.
+    let event = store
+        .delete_where(
+            Context::new(),
+            file.id,
+            column("status").equal(FileStatus::Pending),
+        )
+        .await
+        .unwrap();
+    assert_eq!(event.kind(), EventKind::Delete);
+    assert_eq!(event.object().path, "b");
+    // Writes of missing object are not found.
+    let err = match store.update(Context::new(), file.clone()).await {
+        Ok(_) => panic!("update should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_not_found(&err));
+    let err = match store.delete(Context::new(), file.id).await {
+        Ok(_) => panic!("delete should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_not_found(&err));
+    let err = match store
+        .delete_where(Context::new(), file.id, true.into())
+        .await
+    {
+        Ok(_) => panic!("delete should fail"),
+        Err(err) => err,
+    };
+    assert!(StoreError::is_not_found(&err));
+    // Errors of database are wrapped too.
+    let err = store
+        .update_fields_where(
+            Context::new(),
+            vec![("unknown".into(), Value::from(1i64))],
+            column("id").equal(file.id),
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        StoreError::of(&err),
+        Some(StoreError::Database(_))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_detect() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    std::fs::create_dir(&files_dir).unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir,
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone());
+    let cases = [
+        (
+            "package.zip",
+            b"PK\x03\x04\x14\x00\x00\x00".to_vec(),
+            Some("application/zip"),
+            Some(FileKind::ProblemPackage),
+        ),
+        (
+            "a.txt",
+            b"hello world\n".to_vec(),
+            Some("text/plain"),
+            Some(FileKind::Other),
+        ),
+        (
+            "main.cpp",
+            b"int main() {}\n".to_vec(),
+            Some("text/plain"),
+            Some(FileKind::SolutionSource),
+        ),
+        ("data.bin", vec![0, 1, 2, 3, 255], None, None::<FileKind>),
+        ("a.txt", vec![0; 1024], None, None),
+    ];
+    for (name, bytes, content_type, kind) in cases {
+        let pending = manager
+            .upload(
+                Context::new(),
+                MemoryFile::new(bytes.clone(), Some(name.into())),
+            )
+            .await
+            .unwrap();
+        let file = pending.confirm(Context::new()).await.unwrap();
+        let meta = file.parse_meta().unwrap();
+        assert_eq!(meta.name, name);
+        assert_eq!(meta.size, Some(bytes.len() as u64));
+        assert_eq!(meta.content_type.as_deref(), content_type);
+        assert_eq!(meta.kind, kind);
+        let loaded = manager.load(file.id).await.unwrap();
+        assert_eq!(std::fs::read(loaded.path()).unwrap(), bytes);
+    }
+    let pending = manager
+        .upload(
+            Context::new(),
+            MemoryFile::new(b"PK\x05\x06".to_vec(), Some("image.zip".into())),
+        )
+        .await
+        .unwrap();
+    let file = pending
+        .with_kind(FileKind::CompilerImage)
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let meta = file.parse_meta().unwrap();
+    assert_eq!(meta.content_type.as_deref(), Some("application/zip"));
+    assert_eq!(meta.kind, Some(FileKind::CompilerImage));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_reader() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    std::fs::create_dir(&files_dir).unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir,
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone());
+    let bytes: Vec<u8> = (0..=255).collect();
+    let file = manager
+        .upload(
+            Context::new(),
+            MemoryFile::new(bytes.clone(), Some("data.bin".into())),
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    // Local storage does not provide external URLs.
+    let url = manager
+        .download_url(file.id, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(url, None);
+    let pending = manager
+        .upload(Context::new(), MemoryFile::new(bytes.clone(), None))
+        .await
+        .unwrap();
+    let pending_id = store
+        .find(
+            Context::new(),
+            Select::new().with_where(column("status").equal(FileStatus::Pending)),
+        )
+        .await
+        .unwrap()
+        .next()
+        .await
+        .unwrap()
+        .unwrap()
+        .id;
+    assert!(manager
+        .download_url(pending_id, Duration::from_secs(60))
+        .await
+        .is_err());
+    pending.abort().await.unwrap();
+    let file = manager.load(file.id).await.unwrap();
+    assert_eq!(file.size().unwrap(), 256);
+    for (range, expected) in [
+        (None, &bytes[..]),
+        (Some(0..256), &bytes[..]),
+        (Some(10..20), &bytes[10..20]),
+        (Some(100..101), &bytes[100..101]),
+        (Some(255..256), &bytes[255..]),
+        (Some(256..256), &bytes[256..]),
+    ] {
+        let mut reader = file.reader(range).await.unwrap();
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await.unwrap();
+        assert_eq!(data, expected);
+    }
+    #[allow(clippy::reversed_empty_ranges)]
+    for range in [0..257, 200..300, 20..10, 300..300] {
+        assert!(file.reader(Some(range)).await.is_err());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_rehash() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("files"),
+        ..Default::default()
+    }))
+    .unwrap();
+    // Keys have stable length.
+    let mut keys = std::collections::HashSet::new();
+    for _ in 0..100 {
+        let key = storage.generate_key().await.unwrap();
+        assert_eq!(key.len(), 49, "{key}");
+        assert_eq!(key.find('/'), Some(4), "{key}");
+        assert!(keys.insert(key));
+    }
+    let manager = FileManager::new(storage, store.clone());
+    let file = manager
+        .upload(Context::new(), MemoryFile::new(b"abc".to_vec(), None))
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let meta = file.parse_meta().unwrap();
+    assert_eq!(
+        meta.md5.as_deref(),
+        Some("900150983cd24fb0d6963f7d28e17f72")
+    );
+    assert_eq!(
+        meta.sha3_224.as_deref(),
+        Some("e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf")
+    );
+    // Emulate hashes that were written with broken encoding.
+    let mut broken = file.clone();
+    broken
+        .set_meta(&FileMeta {
+            size: None,
+            md5: Some("9001598cd24fb0d6963f7d28e17f72".into()),
+            sha3_224: None,
+            ..meta.clone()
+        })
+        .unwrap();
+    store.update(Context::new(), broken).await.unwrap();
+    let file = manager.rehash(file.id).await.unwrap();
+    let new_meta = file.parse_meta().unwrap();
+    assert_eq!(new_meta.size, Some(3));
+    assert_eq!(new_meta.md5, meta.md5);
+    assert_eq!(new_meta.sha3_224, meta.sha3_224);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_migrate_storage() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let source_dir = tmpdir.join("source");
+    let source = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: source_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let target_dir = tmpdir.join("target");
+    let target = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: target_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(source.clone(), store.clone());
+    let mut files = Vec::new();
+    for i in 0..5 {
+        let file = manager
+            .upload(Context::new(), MemoryFile::new(vec![i; 100], None))
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        files.push(file);
+    }
+    let pending = manager
+        .upload(Context::new(), MemoryFile::new(vec![0; 10], None))
+        .await
+        .unwrap();
+    // Corrupted file cannot be migrated.
+    std::fs::write(source.load(&files[1].path).await.unwrap(), vec![9; 100]).unwrap();
+    let options = MigrateOptions {
+        batch: 2,
+        dry_run: true,
+        concurrency: 2,
+    };
+    let progress = manager
+        .migrate_storage(
+            source.clone(),
+            target.clone(),
+            Context::new(),
+            options,
+            &|_| {},
+        )
+        .await
+        .unwrap();
+    assert_eq!(progress.migrated, 5);
+    assert_eq!(progress.bytes, 500);
+    assert_eq!(count_files(&target_dir), 0);
+    let reports = std::sync::Mutex::new(Vec::new());
+    let options = MigrateOptions {
+        dry_run: false,
+        ..options
+    };
+    let progress = manager
+        .migrate_storage(
+            source.clone(),
+            target.clone(),
+            Context::new(),
+            options,
+            &|v| reports.lock().unwrap().push(v.clone()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        progress,
+        MigrateProgress {
+            migrated: 4,
+            failed: 1,
+            bytes: 400,
+        }
+    );
+    assert_eq!(reports.lock().unwrap().len(), 5);
+    assert_eq!(reports.lock().unwrap().last(), Some(&progress));
+    for (i, file) in files.iter().enumerate() {
+        let new_file = store.get(Context::new(), file.id).await.unwrap().unwrap();
+        if i == 1 {
+            assert_eq!(new_file.path, file.path);
+            assert!(source.load(&file.path).await.unwrap().exists());
+            continue;
+        }
+        assert_ne!(new_file.path, file.path);
+        assert!(!source.load(&file.path).await.unwrap().exists());
+        let path = target.load(&new_file.path).await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), vec![i as u8; 100]);
+        assert_eq!(
+            new_file.parse_meta().unwrap().md5,
+            file.parse_meta().unwrap().md5
+        );
+    }
+    // Only corrupted and pending files are left in source storage.
+    assert_eq!(count_files(&source_dir), 2);
+    assert_eq!(count_files(&target_dir), 4);
+    pending.abort().await.unwrap();
+}
+
+/// Storage that downloads objects from remote storage on load.
+struct MirrorStorage {
+    remote: Arc<dyn FileStorage>,
+    cache_dir: PathBuf,
+    loads: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl FileStorage for MirrorStorage {
+    async fn load(&self, key: &str) -> Result<PathBuf, solve::core::Error> {
+        let index = self.loads.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let path = self.cache_dir.join(index.to_string());
+        std::fs::copy(self.remote.load(key).await?, &path)?;
+        Ok(path)
+    }
+
+    async fn free(&self, _key: &str, value: PathBuf) {
+        std::fs::remove_file(value).unwrap();
+    }
+
+    async fn generate_key(&self) -> Result<String, solve::core::Error> {
+        self.remote.generate_key().await
+    }
+
+    async fn upload(
+        &self,
+        key: &str,
+        file: Pin<Box<dyn FileInfo>>,
+        cancel: &CancellationToken,
+    ) -> Result<UploadResult, solve::core::Error> {
+        self.remote.upload(key, file, cancel).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), solve::core::Error> {
+        self.remote.delete(key).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_verify() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let remote = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("remote"),
+        ..Default::default()
+    }))
+    .unwrap();
+    let cache_dir = tmpdir.join("cache");
+    std::fs::create_dir(&cache_dir).unwrap();
+    let storage = Arc::new(MirrorStorage {
+        remote: remote.clone(),
+        cache_dir,
+        loads: Default::default(),
+    });
+    let loads = || storage.loads.load(std::sync::atomic::Ordering::SeqCst);
+    let manager =
+        FileManager::new(storage.clone(), store.clone()).with_verify_mode(VerifyMode::Always);
+    let file = manager
+        .upload(Context::new(), MemoryFile::new(b"hello".to_vec(), None))
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let loaded = manager.load(file.id).await.unwrap();
+    assert_eq!(loads(), 1);
+    // Corrupted cached file is downloaded once again.
+    std::fs::write(loaded.path(), b"world").unwrap();
+    drop(loaded);
+    let loaded = manager.load(file.id).await.unwrap();
+    assert_eq!(loads(), 2);
+    assert_eq!(std::fs::read(loaded.path()).unwrap(), b"hello");
+    drop(loaded);
+    // Corruption of file with same size is not detected without hashes.
+    let size_manager =
+        FileManager::new(storage.clone(), store.clone()).with_verify_mode(VerifyMode::SizeOnly);
+    let loaded = size_manager.load(file.id).await.unwrap();
+    assert_eq!(loads(), 3);
+    std::fs::write(loaded.path(), b"world").unwrap();
+    drop(loaded);
+    let loaded = size_manager.load(file.id).await.unwrap();
+    assert_eq!(loads(), 3);
+    assert_eq!(std::fs::read(loaded.path()).unwrap(), b"world");
+    drop(loaded);
+    // Error is returned when object in storage is corrupted too.
+    let loaded = manager.load(file.id).await.unwrap();
+    std::fs::write(loaded.path(), b"hi").unwrap();
+    drop(loaded);
+    std::fs::write(remote.load(&file.path).await.unwrap(), b"hi").unwrap();
+    let err = manager.load(file.id).await.err().unwrap();
+    assert_eq!(loads(), 4);
+    assert_eq!(
+        IntegrityError::of(&err),
+        Some(&IntegrityError::SizeMismatch {
+            expected: 5,
+            actual: 2,
+        })
+    );
+    std::fs::write(remote.load(&file.path).await.unwrap(), b"hellO").unwrap();
+    let err = manager.load(file.id).await.err().unwrap();
+    assert_eq!(loads(), 5);
+    assert!(matches!(
+        IntegrityError::of(&err),
+        Some(IntegrityError::HashMismatch { .. })
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_storage_upload() {
+    let tmpdir = common::temp_dir().unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let bytes: Vec<u8> = (0..300000).map(|v| (v % 253) as u8).collect();
+    let hex = |v: &[u8]| v.iter().map(|v| format!("{v:02x}")).collect::<String>();
+    let md5 = hex(&md5::Md5::digest(&bytes));
+    let sha3_224 = hex(&sha3::Sha3_224::digest(&bytes));
+    let local_path = tmpdir.join("local.bin");
+    std::fs::write(&local_path, &bytes).unwrap();
+    let files: [Pin<Box<dyn FileInfo>>; 3] = [
+        Box::pin(LocalFile::new(local_path, None).unwrap()),
+        Box::pin(MemoryFile::new(bytes.clone(), None)),
+        Box::pin(StreamFileInfo::new(
+            std::io::Cursor::new(bytes.clone()),
+            None,
+            None,
+        )),
+    ];
+    for (i, file) in files.into_iter().enumerate() {
+        let key = format!("ab/{i}");
+        let result = storage
+            .upload(&key, file, &CancellationToken::new())
+            .await
+            .unwrap();
+        assert_eq!(result.size, bytes.len() as u64);
+        assert_eq!(result.md5, md5);
+        assert_eq!(result.sha3_224, sha3_224);
+        let path = storage.load(&key).await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), bytes);
+    }
+    // Partial file is removed when upload is cancelled.
+    let (mut writer, reader) = tokio::io::duplex(1024);
+    writer.write_all(&bytes[..1000]).await.unwrap();
+    let cancel = CancellationToken::new();
+    let task = tokio::spawn({
+        let storage = storage.clone();
+        let cancel = cancel.clone();
+        async move {
+            let file = Box::pin(StreamFileInfo::new(reader, None, None));
+            storage.upload("cd/partial", file, &cancel).await
+        }
+    });
+    let partial_path = files_dir.join("cd").join("partial");
+    while std::fs::metadata(&partial_path).map_or(0, |v| v.len()) < 1000 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    cancel.cancel();
+    assert!(task.await.unwrap().is_err());
+    assert!(!partial_path.exists());
+    drop(writer);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_upload_session() {
+    let tmpdir = common::temp_dir().unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let parts: Vec<Bytes> = (0..5u8)
+        .map(|v| Bytes::from(vec![v; 1000 + v as usize]))
+        .collect();
+    // Parts are written concurrently in reverse order.
+    let session = storage.begin_upload("ab/file").await.unwrap();
+    futures_util::future::try_join_all(
+        parts
+            .iter()
+            .enumerate()
+            .rev()
+            .map(|(i, part)| session.write_part(i as u64, part.clone())),
+    )
+    .await
+    .unwrap();
+    // Rewritten part replaces previous one.
+    session.write_part(0, parts[0].clone()).await.unwrap();
+    assert!(!files_dir.join("ab").join("file").exists());
+    session.complete().await.unwrap();
+    let path = storage.load("ab/file").await.unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), parts.concat());
+    // Upload cannot be completed with missing parts.
+    let session = storage.begin_upload("ab/missing").await.unwrap();
+    session.write_part(0, parts[0].clone()).await.unwrap();
+    session.write_part(2, parts[2].clone()).await.unwrap();
+    assert!(session.complete().await.is_err());
+    assert!(!files_dir.join("ab").join("missing").exists());
+    // Aborted upload leaves nothing in storage.
+    let session = storage.begin_upload("ab/aborted").await.unwrap();
+    session.write_part(0, parts[0].clone()).await.unwrap();
+    session.abort().await.unwrap();
+    assert!(!files_dir.join("ab").join("aborted").exists());
+    assert_eq!(count_files(&files_dir), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_chunked_upload() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("files"),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager =
+        FileManager::new(storage.clone(), store.clone()).with_chunked_upload(ChunkedUpload {
+            threshold: 1000,
+            part_size: 7000,
+            concurrency: 3,
+        });
+    let bytes: Vec<u8> = (0..300000).map(|v| (v % 251) as u8).collect();
+    let hex = |v: &[u8]| v.iter().map(|v| format!("{v:02x}")).collect::<String>();
+    for bytes in [bytes.clone(), bytes[..1000].to_vec()] {
+        let file = manager
+            .upload(Context::new(), MemoryFile::new(bytes.clone(), None))
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        let meta = file.parse_meta().unwrap();
+        assert_eq!(meta.size, Some(bytes.len() as u64));
+        assert_eq!(meta.md5, Some(hex(&md5::Md5::digest(&bytes))));
+        assert_eq!(meta.sha3_224, Some(hex(&sha3::Sha3_224::digest(&bytes))));
+        let path = storage.load(&file.path).await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), bytes);
+    }
+    // Limits are applied to chunked uploads.
+    let manager = FileManager::new(storage.clone(), store.clone())
+        .with_limits(FileLimits {
+            max_file_size: Some(5000),
+            ..Default::default()
+        })
+        .with_chunked_upload(ChunkedUpload {
+            threshold: 1000,
+            part_size: 700,
+            concurrency: 2,
+        });
+    // Declared size of file is smaller than actual.
+    let file = StreamFileInfo::new(std::io::Cursor::new(bytes), None, Some(2000));
+    let err = manager.upload(Context::new(), file).await.err().unwrap();
+    assert!(matches!(
+        UploadError::of(&err),
+        Some(UploadError::FileTooLarge { limit: 5000 })
+    ));
+    assert_eq!(count_files(&tmpdir.join("files")), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_local_storage_delete() {
+    let tmpdir = common::temp_dir().unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    for key in ["ab/first", "ab/second"] {
+        storage
+            .upload(
+                key,
+                Box::pin(MemoryFile::new(vec![1, 2, 3], None)),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        assert!(storage.load(key).await.unwrap().is_file());
+    }
+    storage.delete("ab/first").await.unwrap();
+    assert!(!files_dir.join("ab").join("first").exists());
+    // Shard directory is kept while it contains other objects.
+    assert!(files_dir.join("ab").join("second").is_file());
+    // Delete is idempotent.
+    storage.delete("ab/first").await.unwrap();
+    storage.delete("ab/second").await.unwrap();
+    assert!(!files_dir.join("ab").exists());
+    assert!(files_dir.is_dir());
+    storage.delete("ab/second").await.unwrap();
+    std::fs::write(tmpdir.join("secret"), b"secret").unwrap();
+    for key in [
+        "",
+        "../secret",
+        "ab/../../secret",
+        "/secret",
+        "ab//secret",
+        "./secret",
+    ] {
+        assert!(storage.load(key).await.is_err(), "{key}");
+        assert!(storage
+            .upload(
+                key,
+                Box::pin(MemoryFile::new(vec![1], None)),
+                &CancellationToken::new(),
+            )
+            .await
+            .is_err());
+        assert!(storage.delete(key).await.is_err());
+    }
+    assert_eq!(std::fs::read(tmpdir.join("secret")).unwrap(), b"secret");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_fresh_storage() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    // Neither root directory nor shard directories exist.
+    let files_dir = tmpdir.join("storage").join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    assert!(files_dir.is_dir());
+    let manager = FileManager::new(storage, store.clone());
+    for i in 0..3 {
+        let bytes = vec![i; 100];
+        let file = manager
+            .upload(
+                Context::new(),
+                StreamFileInfo::new(std::io::Cursor::new(bytes.clone()), None, None),
+            )
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        let loaded = manager.load(file.id).await.unwrap();
+        assert!(loaded.path().starts_with(&files_dir));
+        assert_eq!(std::fs::read(loaded.path()).unwrap(), bytes);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_stream() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    std::fs::create_dir(&files_dir).unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir,
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone());
+    let bytes: Vec<u8> = (0..100000).map(|v| (v % 251) as u8).collect();
+    let expected = manager
+        .upload(
+            Context::new(),
+            MemoryFile::new(bytes.clone(), Some("a.bin".into())),
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap()
+        .parse_meta()
+        .unwrap();
+    assert_eq!(expected.size, Some(bytes.len() as u64));
+    let chunks: Vec<_> = bytes
+        .chunks(4096)
+        .map(|v| Ok::<_, std::io::Error>(Bytes::copy_from_slice(v)))
+        .collect();
+    let files = [
+        StreamFileInfo::from_stream(
+            futures_util::stream::iter(chunks),
+            Some("b.bin".into()),
+            None,
+        ),
+        StreamFileInfo::new(
+            std::io::Cursor::new(bytes.clone()),
+            Some("c.bin".into()),
+            Some(bytes.len() as u64),
+        ),
+    ];
+    for file in files {
+        let file = manager
+            .upload(Context::new(), file)
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        let meta = file.parse_meta().unwrap();
+        assert_eq!(meta.size, expected.size);
+        assert_eq!(meta.md5, expected.md5);
+        assert_eq!(meta.sha3_224, expected.sha3_224);
+        let loaded = manager.load(file.id).await.unwrap();
+        assert_eq!(std::fs::read(loaded.path()).unwrap(), bytes);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_lease() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("files"),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage.clone(), store.clone());
+    let mut files = Vec::new();
+    for i in 0..2 {
+        let file = manager
+            .upload(Context::new(), MemoryFile::new(vec![i; 10], None))
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        files.push(file);
+    }
+    // Lease is never shortened by extension.
+    let mut lease = manager
+        .lease(files[0].id, Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert!(!lease.is_expired());
+    let expire_time = lease.expire_time();
+    lease.extend(Duration::from_secs(60));
+    assert_eq!(lease.expire_time(), expire_time);
+    lease.extend(Duration::from_secs(7200));
+    assert!(lease.expire_time() > expire_time);
+    let mut expired = manager.lease(files[0].id, Duration::ZERO).await.unwrap();
+    assert!(expired.is_expired());
+    expired.extend(Duration::from_secs(60));
+    assert!(!expired.is_expired());
+    // Lease becomes stale when file is rebuilt.
+    assert!(!lease.is_stale(Context::new()).await.unwrap());
+    let mut rebuilt = files[0].clone();
+    rebuilt.path = files[1].path.clone();
+    rebuilt.meta = files[1].meta.clone();
+    store.update(Context::new(), rebuilt).await.unwrap();
+    assert!(lease.is_stale(Context::new()).await.unwrap());
+    assert_eq!(std::fs::read(lease.path()).unwrap(), vec![0; 10]);
+    // Lease becomes stale when only hash of file is changed.
+    let lease = manager
+        .lease(files[1].id, Duration::from_secs(60))
+        .await
+        .unwrap();
+    let mut changed = files[1].clone();
+    let meta = FileMeta {
+        sha3_224: Some("00".repeat(28)),
+        ..changed.parse_meta().unwrap()
+    };
+    changed.set_meta(&meta).unwrap();
+    store.update(Context::new(), changed).await.unwrap();
+    assert!(lease.is_stale(Context::new()).await.unwrap());
+    // Lease becomes stale when file is deleted.
+    let lease = manager
+        .lease(files[0].id, Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(!lease.is_stale(Context::new()).await.unwrap());
+    store.delete(Context::new(), files[0].id).await.unwrap();
+    assert!(lease.is_stale(Context::new()).await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_upload_validated() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage.clone(), store.clone());
+    let policy = UploadPolicy {
+        allowed_extensions: vec!["png".into(), "jpg".into()],
+        max_size: Some(1000),
+        expected_kind: Some(FileKind::Other),
+    };
+    let png = |size: usize| {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        bytes.resize(size, 0);
+        bytes
+    };
+    // Name of file is sanitized.
+    let file = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(png(100), Some("../../Avatar.PNG\n".into())),
+            &policy,
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let meta = file.parse_meta().unwrap();
+    assert_eq!(meta.name, "Avatar.PNG");
+    assert_eq!(meta.content_type.as_deref(), Some("image/png"));
+    let err = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(png(100), Some("dir/".into())),
+            &policy,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(UploadError::of(&err), Some(&UploadError::InvalidName));
+    // Oversized file.
+    let err = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(png(2000), Some("avatar.png".into())),
+            &policy,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::FileTooLarge { limit: 1000 })
+    );
+    let file = StreamFileInfo::new(
+        std::io::Cursor::new(png(2000)),
+        Some("avatar.png".into()),
+        None,
+    );
+    let err = manager
+        .upload_validated(Context::new(), file, &policy)
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::FileTooLarge { limit: 1000 })
+    );
+    // Extension that is not allowed.
+    let err = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(png(100), Some("avatar.gif".into())),
+            &policy,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::ExtensionNotAllowed {
+            extension: "gif".into()
+        })
+    );
+    // Archive that is named as image.
+    let mut zip = b"PK\x03\x04".to_vec();
+    zip.resize(100, 0);
+    let err = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(zip, Some("image.png".into())),
+            &policy,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::ContentMismatch {
+            extension: "png".into(),
+            content_type: "application/zip".into(),
+        })
+    );
+    // Binary file that is named as solution source.
+    let policy = UploadPolicy {
+        allowed_extensions: vec!["cpp".into()],
+        expected_kind: Some(FileKind::SolutionSource),
+        ..Default::default()
+    };
+    let err = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(vec![0; 100], Some("main.cpp".into())),
+            &policy,
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::KindMismatch {
+            expected: FileKind::SolutionSource,
+            actual: None,
+        })
+    );
+    let file = manager
+        .upload_validated(
+            Context::new(),
+            MemoryFile::new(b"int main() {}".to_vec(), Some("main.cpp".into())),
+            &policy,
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        file.parse_meta().unwrap().kind,
+        Some(FileKind::SolutionSource)
+    );
+    // Only valid files are uploaded.
+    assert_eq!(count_files(&files_dir), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_core_storage_check() {
+    let tmpdir = common::temp_dir().unwrap();
+    let new_config = |files_dir: &Path| -> solve::config::Config {
+        serde_json::from_value(serde_json::json!({
+            "db": {
+                "driver": "sqlite",
+                "options": {"path": tmpdir.join("db.sqlite")},
+            },
+            "storage": {
+                "driver": "local",
+                "options": {"files_dir": files_dir},
+            },
+        }))
+        .unwrap()
+    };
+    let files_dir = tmpdir.join("files");
+    let config = new_config(&files_dir);
+    let mut core = Core::new(&config).unwrap();
+    core.file_store().create_tables().await.unwrap();
+    core.init_invoker(&config).await.unwrap();
+    let health = core.health().await;
+    assert!(health.is_healthy());
+    assert!(matches!(
+        health.storage,
+        Some(Ok(StorageHealth::Probed { .. }))
+    ));
+    // Probe object is deleted after check.
+    assert_eq!(count_files(&files_dir), 0);
+    drop(core);
+    // Procfs cannot be written even by root.
+    let config = new_config(Path::new("/proc"));
+    let mut core = Core::new(&config).unwrap();
+    let err = core.init_invoker(&config).await.err().unwrap();
+    assert!(err.to_string().starts_with("Storage is not available"));
+    let storage = new_storage(config.storage.as_ref().unwrap()).unwrap();
+    assert!(storage.check().await.is_err());
+    drop(core);
+    // Storage that cannot store probe object is reported as not probed.
+    let config: solve::config::Config = serde_json::from_value(serde_json::json!({
+        "db": {
+            "driver": "sqlite",
+            "options": {"path": tmpdir.join("db.sqlite")},
+        },
+        "storage": {
+            "driver": "s3",
+            "options": {"endpoint": "http://localhost:9000", "bucket": "files"},
+        },
+    }))
+    .unwrap();
+    let mut core = Core::new(&config).unwrap();
+    core.init_invoker(&config).await.unwrap();
+    let health = core.health().await;
+    assert!(health.is_healthy());
+    assert!(matches!(
+        health.storage,
+        Some(Ok(StorageHealth::NotProbed { .. }))
+    ));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_upload_progress() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let managers = [
+        FileManager::new(storage.clone(), store.clone()),
+        FileManager::new(storage.clone(), store.clone()).with_chunked_upload(ChunkedUpload {
+            threshold: 1000,
+            part_size: 100000,
+            concurrency: 2,
+        }),
+    ];
+    let bytes: Vec<u8> = (0..1000000).map(|v| (v % 251) as u8).collect();
+    let local_path = tmpdir.join("local.bin");
+    std::fs::write(&local_path, &bytes).unwrap();
+    for manager in &managers {
+        let uploads = [
+            upload_with_progress(manager, MemoryFile::new(bytes.clone(), None)).await,
+            upload_with_progress(manager, LocalFile::new(local_path.clone(), None).unwrap()).await,
+        ];
+        for (file, reports) in uploads {
+            assert!(reports.len() > 1);
+            assert!(reports.windows(2).all(|v| v[0].0 < v[1].0));
+            let size = bytes.len() as u64;
+            assert_eq!(reports.last(), Some(&(size, Some(size))));
+            let path = storage.load(&file.path).await.unwrap();
+            assert_eq!(std::fs::read(path).unwrap(), bytes);
+        }
+    }
+    // Cancelled upload is cleaned up.
+    let (mut writer, reader) = tokio::io::duplex(1024);
+    let cancel = CancellationToken::new();
+    let options = UploadOptions::new()
+        .with_cancel(cancel.clone())
+        .with_progress({
+            let cancel = cancel.clone();
+            move |uploaded, size| {
+                assert_eq!(size, None);
+                if uploaded >= 10000 {
+                    cancel.cancel();
+                }
+            }
+        });
+    let file = StreamFileInfo::new(reader, None, None);
+    let (result, ()) = tokio::join!(
+        managers[0].upload_with_options(Context::new(), file, options),
+        // Reader is dropped when upload is cancelled.
+        async { assert!(writer.write_all(&bytes).await.is_err()) },
+    );
+    assert!(result.is_err());
+    assert!(cancel.is_cancelled());
+    assert_eq!(count_files(&files_dir), 4);
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 4);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_upload_local_file() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("files"),
+        ..Default::default()
+    }))
+    .unwrap();
+    let managers = [
+        FileManager::new(storage.clone(), store.clone()),
+        FileManager::new(storage.clone(), store.clone()).with_chunked_upload(ChunkedUpload {
+            threshold: 1000,
+            part_size: 100000,
+            concurrency: 2,
+        }),
+    ];
+    let bytes: Vec<u8> = (0..1000000).map(|v| (v % 251) as u8).collect();
+    let local_path = tmpdir.join("local.bin");
+    std::fs::write(&local_path, &bytes).unwrap();
+    for manager in &managers {
+        let file = LocalFile::new(local_path.clone(), Some("local.bin".into())).unwrap();
+        let file = manager
+            .upload_with_options(Context::new(), file, UploadOptions::new())
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        let meta = file.parse_meta().unwrap();
+        assert_eq!(meta.name, "local.bin");
+        assert_eq!(meta.size, Some(bytes.len() as u64));
+        let path = storage.load(&file.path).await.unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), bytes);
+    }
+}
+
+async fn upload_with_progress<T: FileInfo + 'static>(
+    manager: &FileManager,
+    file: T,
+) -> (File, Vec<(u64, Option<u64>)>) {
+    let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let options = UploadOptions::new().with_progress({
+        let reports = reports.clone();
+        move |uploaded, size| reports.lock().unwrap().push((uploaded, size))
+    });
+    let file = manager
+        .upload_with_options(Context::new(), file, options)
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let reports = reports.lock().unwrap().clone();
+    (file, reports)
+}
+
+fn assert_send<T: Send>(value: T) -> T {
+    value
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_upload_is_send() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: tmpdir.join("files"),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = Arc::new(
+        FileManager::new(storage, store).with_chunked_upload(ChunkedUpload {
+            threshold: 10,
+            part_size: 10,
+            concurrency: 2,
+        }),
+    );
+    // Uploads can be spawned, so their futures should be sendable.
+    let upload = assert_send(manager.upload(Context::new(), MemoryFile::new(vec![1; 100], None)));
+    upload.await.unwrap().abort().await.unwrap();
+    let file = MemoryFile::new(vec![2; 100], None);
+    let upload = tokio::spawn({
+        let manager = manager.clone();
+        async move {
+            manager
+                .upload_with_options(Context::new(), file, UploadOptions::new())
+                .await
+                .unwrap()
+                .confirm(Context::new())
+                .await
+                .unwrap()
+        }
+    });
+    let file = upload.await.unwrap();
+    assert_eq!(file.parse_meta().unwrap().size, Some(100));
+}
+
+/// Storage that delays loads and tracks amount of concurrent loads.
+struct SlowStorage {
+    storage: Arc<dyn FileStorage>,
+    delay: Duration,
+    failed_key: std::sync::Mutex<Option<String>>,
+    loads: std::sync::atomic::AtomicUsize,
+    active: std::sync::atomic::AtomicUsize,
+    max_active: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl FileStorage for SlowStorage {
+    async fn load(&self, key: &str) -> Result<PathBuf, solve::core::Error> {
+        use std::sync::atomic::Ordering;
+        self.loads.fetch_add(1, Ordering::SeqCst);
+        let active = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_active.fetch_max(active, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        if self.failed_key.lock().unwrap().as_deref() == Some(key) {
+            return Err("Object is unavailable".into());
+        }
+        self.storage.load(key).await
+    }
+
+    async fn free(&self, key: &str, value: PathBuf) {
+        self.storage.free(key, value).await
+    }
+
+    async fn generate_key(&self) -> Result<String, solve::core::Error> {
+        self.storage.generate_key().await
+    }
+
+    async fn upload(
+        &self,
+        key: &str,
+        file: Pin<Box<dyn FileInfo>>,
+        cancel: &CancellationToken,
+    ) -> Result<UploadResult, solve::core::Error> {
+        self.storage.upload(key, file, cancel).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), solve::core::Error> {
+        self.storage.delete(key).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_file_manager_prefetch() {
+    use std::sync::atomic::Ordering;
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let storage = Arc::new(SlowStorage {
+        storage: new_storage(&StorageConfig::Local(LocalStorageConfig {
+            files_dir: tmpdir.join("files"),
+            ..Default::default()
+        }))
+        .unwrap(),
+        delay: Duration::from_millis(300),
+        failed_key: Default::default(),
+        loads: Default::default(),
+        active: Default::default(),
+        max_active: Default::default(),
+    });
+    let manager = FileManager::new(storage.clone(), store.clone());
+    let mut files = Vec::new();
+    for i in 0..4 {
+        let file = manager
+            .upload(Context::new(), MemoryFile::new(vec![i; 10], None))
+            .await
+            .unwrap()
+            .confirm(Context::new())
+            .await
+            .unwrap();
+        files.push(file);
+    }
+    // Prefetch does not wait for loads.
+    let start = std::time::Instant::now();
+    let guard = manager
+        .prefetch(&[files[0].id, files[1].id, 1000])
+        .await
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_millis(300));
+    let first = manager.load(files[0].id).await.unwrap();
+    let second = manager.load(files[1].id).await.unwrap();
+    guard.wait().await;
+    assert_eq!(std::fs::read(first.path()).unwrap(), vec![0; 10]);
+    assert_eq!(std::fs::read(second.path()).unwrap(), vec![1; 10]);
+    assert_eq!(storage.loads.load(Ordering::SeqCst), 2);
+    assert_eq!(storage.max_active.load(Ordering::SeqCst), 2);
+    // Object can be loaded by key without file row.
+    let path = manager.load_by_key(&files[2].path).await.unwrap();
+    assert_eq!(std::fs::read(&*path).unwrap(), vec![2; 10]);
+    // Error of prefetch is returned only by load.
+    *storage.failed_key.lock().unwrap() = Some(files[3].path.clone());
+    let guard = manager.prefetch(&[files[3].id]).await.unwrap();
+    assert!(manager.load(files[3].id).await.is_err());
+    guard.wait().await;
+}
+
+/// File of unknown size that is available only as stream.
+struct StreamFile {
+    size: u64,
+}
+
+impl FileInfo for StreamFile {
+    fn name(&self) -> Option<String> {
+        Some("data.bin".into())
+    }
+
+    fn size(&self) -> Option<u64> {
+        None
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn into_reader(self: Pin<Box<Self>>) -> Box<dyn Read + Send + Sync> {
+        Box::new(std::io::repeat(0).take(self.size))
+    }
+}
+
+fn count_files(path: &Path) -> usize {
+    let mut count = 0;
+    for entry in std::fs::read_dir(path).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            count += count_files(&entry.path());
+        } else {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_limits() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    std::fs::create_dir(&files_dir).unwrap();
+    let limits = FileLimits {
+        max_file_size: Some(1000),
+        kind_max_file_size: [(FileKind::SolutionSource, 10)].into_iter().collect(),
+        account_quota: Some(1500),
+    };
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        limits: limits.clone(),
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone()).with_limits(limits);
+    // Size of file is checked before upload.
+    let err = manager
+        .upload(
+            Context::new(),
+            MemoryFile::new(vec![0; 2000], Some("data.bin".into())),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::FileTooLarge { limit: 1000 })
+    );
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    // Size of stream is checked during upload.
+    let err = manager
+        .upload(Context::new(), StreamFile { size: 5000 })
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::FileTooLarge { limit: 1000 })
+    );
+    assert_eq!(count_files(&files_dir), 0);
+    let pending = manager
+        .upload(Context::new(), StreamFile { size: 1000 })
+        .await
+        .unwrap();
+    assert_eq!(count_files(&files_dir), 1);
+    pending.abort().await.unwrap();
+    // Limit is overridden for kind of file.
+    let err = manager
+        .upload(
+            Context::new(),
+            MemoryFile::new(
+                b"int main() { return 0; }\n".to_vec(),
+                Some("main.cpp".into()),
+            ),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::FileTooLarge { limit: 10 })
+    );
+    // Quota is computed from available files of account.
+    for _ in 0..2 {
+        manager
+            .upload(
+                Context::new().with_account_id(1),
+                MemoryFile::new(vec![0; 800], Some("data.bin".into())),
+            )
+            .await
+            .unwrap()
+            .abort()
+            .await
+            .unwrap();
+    }
+    manager
+        .upload(
+            Context::new().with_account_id(1),
+            MemoryFile::new(vec![0; 800], Some("data.bin".into())),
+        )
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    let err = manager
+        .upload(
+            Context::new().with_account_id(1),
+            MemoryFile::new(vec![0; 800], Some("data.bin".into())),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::QuotaExceeded {
+            quota: 1500,
+            usage: 800,
+        })
+    );
+    let err = manager
+        .upload(Context::new().with_account_id(1), StreamFile { size: 701 })
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(
+        UploadError::of(&err),
+        Some(&UploadError::QuotaExceeded {
+            quota: 1500,
+            usage: 800,
+        })
+    );
+    manager
+        .upload(Context::new().with_account_id(1), StreamFile { size: 700 })
+        .await
+        .unwrap()
+        .abort()
+        .await
+        .unwrap();
+    manager
+        .upload(
+            Context::new().with_account_id(2),
+            MemoryFile::new(vec![0; 800], Some("data.bin".into())),
+        )
+        .await
+        .unwrap()
+        .abort()
+        .await
+        .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_abort() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone());
+    // Row is deleted when storage upload fails.
+    let chunks = vec![
+        Ok(Bytes::from_static(b"hello")),
+        Err(std::io::Error::other("broken stream")),
+    ];
+    let err = manager
+        .upload(
+            Context::new(),
+            StreamFileInfo::from_stream(futures_util::stream::iter(chunks), None, None),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("broken stream"), "{err}");
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    assert_eq!(count_files(&files_dir), 0);
+    // Explicitly aborted file is deleted.
+    let pending = manager
+        .upload(Context::new(), MemoryFile::new(b"hello".to_vec(), None))
+        .await
+        .unwrap();
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 1);
+    assert_eq!(count_files(&files_dir), 1);
+    pending.abort().await.unwrap();
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    assert_eq!(count_files(&files_dir), 0);
+    // Dropped file is deleted in background.
+    let pending = manager
+        .upload(Context::new(), MemoryFile::new(b"hello".to_vec(), None))
+        .await
+        .unwrap();
+    drop(pending);
+    for _ in 0..100 {
+        if store.count(Context::new(), true.into()).await.unwrap() == 0
+            && count_files(&files_dir) == 0
+        {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 0);
+    assert_eq!(count_files(&files_dir), 0);
+    // Confirmed file is kept.
+    let file = manager
+        .upload(Context::new(), MemoryFile::new(b"hello".to_vec(), None))
+        .await
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(manager.load(file.id).await.is_ok());
+    assert_eq!(count_files(&files_dir), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_file_manager_cleanup_expired() {
+    let (tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(FileStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let files_dir = tmpdir.join("files");
+    std::fs::create_dir(&files_dir).unwrap();
+    let storage = new_storage(&StorageConfig::Local(LocalStorageConfig {
+        files_dir: files_dir.clone(),
+        ..Default::default()
+    }))
+    .unwrap();
+    let manager = FileManager::new(storage, store.clone());
+    let mut pending = Vec::new();
+    for i in 0..4 {
+        let file = manager
+            .upload(
+                Context::new(),
+                MemoryFile::new(vec![i; 16], Some("a.txt".into())),
+            )
+            .await
+            .unwrap();
+        pending.push(file);
+    }
+    let mut files = Vec::new();
+    let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+    while let Some(file) = rows.next().await {
+        files.push(file.unwrap());
+    }
+    drop(rows);
+    assert_eq!(files.len(), 4);
+    let confirmed = pending
+        .pop()
+        .unwrap()
+        .confirm(Context::new())
+        .await
+        .unwrap();
+    files.pop();
+    // Expire first two files and remove storage object of the second one.
+    for file in &mut files[..2] {
+        file.expire_time = Some(Instant::now() - Duration::from_secs(1));
+        *file = store
+            .update(Context::new(), file.clone())
+            .await
+            .unwrap()
+            .into_object();
+    }
+    std::fs::remove_file(files_dir.join(&files[1].path)).unwrap();
+    assert_eq!(
+        manager.cleanup_expired(Context::new(), 10).await.unwrap(),
+        2
+    );
+    for file in &files[..2] {
+        assert!(store.get(Context::new(), file.id).await.unwrap().is_none());
+        assert!(!files_dir.join(&file.path).exists());
+    }
+    let fresh = &files[2];
+    assert!(store.get(Context::new(), fresh.id).await.unwrap().is_some());
+    assert!(files_dir.join(&fresh.path).exists());
+    assert!(manager.load(confirmed.id).await.is_ok());
+    assert_eq!(
+        manager.cleanup_expired(Context::new(), 10).await.unwrap(),
+        0
+    );
+    for file in pending {
+        file.abort().await.unwrap();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_problem_resource_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ProblemResourceStore::new(db);
+    store.create_tables().await.unwrap();
+    for (problem_id, kind, name, file_id) in [
+        (1, ProblemResourceKind::Statement, "statement.pdf", 10),
+        (1, ProblemResourceKind::Image, "figure.png", 11),
+        (2, ProblemResourceKind::Attachment, "checker.cpp", 12),
+        (1, ProblemResourceKind::Attachment, "input.txt", 13),
+    ] {
+        let mut resource = ProblemResource {
+            problem_id,
+            kind,
+            name: name.into(),
+            file_id,
+            ..Default::default()
+        };
+        resource
+            .set_config(&ProblemResourceConfig {
+                locale: Some("en".into()),
+            })
+            .unwrap();
+        store
+            .create_resource(Context::new(), resource)
+            .await
+            .unwrap();
+    }
+    let resources = store.find_by_problem(Context::new(), 1).await.unwrap();
+    let names: Vec<_> = resources.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(names, vec!["figure.png", "input.txt", "statement.pdf"]);
+    let file_ids: Vec<_> = resources.iter().map(|v| v.file_id).collect();
+    assert_eq!(file_ids, vec![11, 13, 10]);
+    assert_eq!(resources[0].kind, ProblemResourceKind::Image);
+    let config = resources[0].parse_config().unwrap();
+    assert_eq!(config.locale.as_deref(), Some("en"));
+    // Names are unique within problem.
+    let duplicate = ProblemResource {
+        problem_id: 1,
+        name: "input.txt".into(),
+        file_id: 14,
+        ..Default::default()
+    };
+    assert!(store
+        .create_resource(Context::new(), duplicate.clone())
+        .await
+        .is_err());
+    assert!(store.create(Context::new(), duplicate).await.is_err());
+    let other = ProblemResource {
+        problem_id: 2,
+        name: "input.txt".into(),
+        file_id: 15,
+        ..Default::default()
+    };
+    store.create_resource(Context::new(), other).await.unwrap();
+    assert_eq!(
+        store
+            .find_by_problem(Context::new(), 2)
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+    assert!(!ProblemResource::default().is_valid());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_problem_statement_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = ProblemStatementStore::new(db)
+        .with_locales(vec!["en".into(), "ru".into(), "uz".into()])
+        .with_default_locale("en".into());
+    store.create_tables().await.unwrap();
+    let config = ProblemStatementConfig {
+        format: StatementFormat::Html,
+        legend: "<p>Sum two numbers.</p>".into(),
+        input: "<p>Two integers.</p>".into(),
+        output: "<p>One integer.</p>".into(),
+        notes: String::new(),
+    };
+    for (problem_id, locale, title) in [
+        (1, "ru", "Сумма"),
+        (1, "en", "Sum"),
+        (2, "uz", "Yig'indi"),
+        (2, "ru", "Сумма"),
+    ] {
+        let mut statement = ProblemStatement {
+            problem_id,
+            locale: locale.into(),
+            title: title.into(),
+            ..Default::default()
+        };
+        statement.set_config(&config).unwrap();
+        store
+            .create_statement(Context::new(), statement)
+            .await
+            .unwrap();
+    }
+    let statement = store
+        .find_by_problem_locale(Context::new(), 1, "ru")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(statement.title, "Сумма");
+    assert_eq!(statement.parse_config().unwrap(), config);
+    // Missing locale falls back to default one.
+    let statement = store
+        .find_by_problem_locale(Context::new(), 1, "uz")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(statement.locale, "en");
+    // Missing default locale falls back to any statement.
+    let statement = store
+        .find_by_problem_locale(Context::new(), 2, "en")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(statement.locale, "ru");
+    assert!(store
+        .find_by_problem_locale(Context::new(), 3, "en")
+        .await
+        .unwrap()
+        .is_none());
+    // Unknown locales are rejected.
+    let statement = ProblemStatement {
+        problem_id: 1,
+        locale: "xx".into(),
+        title: "Sum".into(),
+        ..Default::default()
+    };
+    assert!(store
+        .create_statement(Context::new(), statement)
+        .await
+        .is_err());
+    let mut statement = store
+        .find_by_problem_locale(Context::new(), 1, "en")
+        .await
+        .unwrap()
+        .unwrap();
+    statement.locale = "xx".into();
+    assert!(store
+        .update_statement(Context::new(), statement)
+        .await
+        .is_err());
+    let locales: Vec<_> = store
+        .find_by_problem(Context::new(), 1)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|v| v.locale)
+        .collect();
+    assert_eq!(locales, vec!["en", "ru"]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_take_task_priority() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
+    let now = Instant::now();
+    let tasks = [
+        (0, None),
+        (10, None),
+        (5, None),
+        (10, None),
+        (20, Some(now + Duration::from_secs(3600))),
+        (-1, Some(now - Duration::from_secs(60))),
+    ];
+    for (priority, not_before) in tasks {
+        let task = Task {
+            kind: TaskKind::JudgeSolution,
+            priority,
+            not_before,
+            ..Default::default()
+        };
+        store.create(Context::new(), task).await.unwrap();
+    }
+    let mut ids = Vec::new();
+    while let Some(task) = store
+        .take_task(Context::new(), Duration::from_secs(30), &Default::default())
+        .await
+        .unwrap()
+        .claimed()
+    {
+        assert_eq!(task.status, TaskStatus::Running);
+        ids.push(task.id);
+    }
+    // Deferred task is skipped, equal priorities are taken in any order.
+    assert_eq!(ids.len(), 5);
+    assert!(ids[..2] == [2, 4] || ids[..2] == [4, 2]);
+    assert_eq!(ids[2..], [3, 1, 6]);
+    let task = store.get(Context::new(), 5).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.priority, 20);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_take_task_concurrent() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    for _ in 0..50 {
+        store.create(Context::new(), Task::default()).await.unwrap();
+    }
+    let mut workers = Vec::new();
+    for _ in 0..8 {
+        let store = store.clone();
+        workers.push(tokio::spawn(async move {
+            let mut ids = Vec::new();
+            loop {
+                let outcome = store
+                    .take_task(Context::new(), Duration::from_secs(30), &Default::default())
+                    .await
+                    .unwrap();
+                match outcome {
+                    TakeOutcome::Claimed(task) => ids.push(task.id),
+                    TakeOutcome::Contended { conflicts } => assert!(conflicts > 0),
+                    TakeOutcome::Empty => {
+                        // Queue is reported empty only when all tasks are taken.
+                        let queued = store
+                            .count(Context::new(), column("status").equal(TaskStatus::Queued))
+                            .await
+                            .unwrap();
+                        assert_eq!(queued, 0);
+                        return ids;
+                    }
+                }
+            }
+        }));
+    }
+    let mut ids = Vec::new();
+    for worker in workers {
+        ids.extend(worker.await.unwrap());
+    }
+    // Every task is taken exactly once.
+    ids.sort();
+    assert_eq!(ids, (1..=50).collect::<Vec<_>>());
+    let stats = store.claim_stats();
+    assert_eq!(stats.claims, 50);
+    // Workers spread over candidates instead of fighting over the first one.
+    assert!(
+        stats.conflicts < 3 * stats.claims,
+        "too many conflicts: {}",
+        stats.conflicts
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_take_options() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let tasks = [
+        (TaskKind::JudgeSolution, 0),
+        (TaskKind::UpdateProblemPackage, 5),
+        (TaskKind::JudgeSolution, 3),
+        (TaskKind::UpdateProblemPackage, 1),
+        (TaskKind::JudgeSolution, 10),
+        (TaskKind::UpdateProblemPackage, -5),
+    ];
+    for (kind, priority) in tasks {
+        let task = Task {
+            kind,
+            priority,
+            ..Default::default()
+        };
+        store.create(Context::new(), task).await.unwrap();
+    }
+    let manager = TaskManager::new(store.clone());
+    let take_all = |options: TakeOptions| {
+        let manager = &manager;
+        async move {
+            let mut tasks = Vec::new();
+            while let Some(task) = manager.take_task(&options).await.unwrap() {
+                tasks.push((task.get_kind().await, task.get_id().await));
+            }
+            tasks
+        }
+    };
+    // Worker without kinds does not take anything.
+    assert_eq!(
+        take_all(TakeOptions::default().with_kinds(vec![])).await,
+        vec![]
+    );
+    assert_eq!(
+        take_all(
+            TakeOptions::default()
+                .with_kinds(vec![TaskKind::UpdateProblemPackage])
+                .with_min_priority(0)
+        )
+        .await,
+        vec![
+            (TaskKind::UpdateProblemPackage, 2),
+            (TaskKind::UpdateProblemPackage, 4),
+        ]
+    );
+    assert_eq!(
+        take_all(TakeOptions::default().with_kinds(vec![TaskKind::JudgeSolution])).await,
+        vec![
+            (TaskKind::JudgeSolution, 5),
+            (TaskKind::JudgeSolution, 3),
+            (TaskKind::JudgeSolution, 1),
+        ]
+    );
+    assert_eq!(
+        take_all(TakeOptions::default()).await,
+        vec![(TaskKind::UpdateProblemPackage, 6)]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_enqueue_at() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let when = Instant::now() + Duration::from_millis(300);
+    let config = JudgeSolutionTaskConfig {
+        solution_id: 42,
+        ..Default::default()
+    };
+    let task = manager
+        .enqueue_at(TaskKind::JudgeSolution, config, when)
+        .await
+        .unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.not_before, Some(when));
+    // Scheduled task is skipped until its time.
+    let options = TakeOptions::default();
+    assert!(manager.take_task(&options).await.unwrap().is_none());
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    let taken = manager.take_task(&options).await.unwrap().unwrap();
+    assert_eq!(taken.get_id().await, task.id);
+    assert_eq!(taken.get_status().await, TaskStatus::Running);
+    let config: JudgeSolutionTaskConfig = taken.parse_config().await.unwrap();
+    assert_eq!(config.solution_id, 42);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_retry() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let task = Task {
+        kind: TaskKind::JudgeSolution,
+        max_attempts: 3,
+        ..Default::default()
+    };
+    let id = store
+        .create(Context::new(), task)
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let manager = TaskManager::new(store.clone());
+    let options = TakeOptions::default();
+    // Two attempts fail and are retried.
+    for attempts in 1..=2 {
+        let task = manager.take_task(&options).await.unwrap().unwrap();
+        assert!(task.can_retry().await);
+        task.retry_later(Duration::from_millis(200)).await.unwrap();
+        assert_eq!(task.get_attempts().await, attempts);
+        // Requeued task is not owned by worker anymore.
+        let err = task.set_status(TaskStatus::Failed).await.unwrap_err();
+        assert!(StoreError::is_conflict(&err));
+        let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(stored.status, TaskStatus::Queued);
+        assert_eq!(stored.attempts, attempts);
+        assert_eq!(stored.expire_time, None);
+        // Task is taken only after backoff.
+        assert!(manager.take_task(&options).await.unwrap().is_none());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+    }
+    // Last attempt cannot be retried.
+    let task = manager.take_task(&options).await.unwrap().unwrap();
+    assert!(!task.can_retry().await);
+    task.set_status(TaskStatus::Succeeded).await.unwrap();
+    let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(stored.status, TaskStatus::Succeeded);
+    assert_eq!(stored.attempts, 2);
+    // Tasks created without limit of attempts use limit of manager.
+    store.create(Context::new(), Task::default()).await.unwrap();
+    let task = manager.take_task(&options).await.unwrap().unwrap();
+    assert!(task.can_retry().await);
+    task.set_status(TaskStatus::Succeeded).await.unwrap();
+    let manager = TaskManager::new(store.clone()).with_max_attempts(1);
+    store.create(Context::new(), Task::default()).await.unwrap();
+    let task = manager.take_task(&options).await.unwrap().unwrap();
+    assert!(!task.can_retry().await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_cancel() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone()).with_lease(Duration::from_millis(600));
+    let options = TakeOptions::default();
+    // Queued task is never taken after cancellation.
+    let id = store
+        .create(Context::new(), Task::default())
+        .await
+        .unwrap()
+        .object()
+        .id;
+    assert!(manager.cancel(id).await.unwrap());
+    assert!(manager.take_task(&options).await.unwrap().is_none());
+    assert!(!manager.cancel(id).await.unwrap());
+    assert!(!manager.cancel(1000).await.unwrap());
+    // Running task is stopped by pinger.
+    let id = store
+        .create(Context::new(), Task::default())
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let task = manager.take_task(&options).await.unwrap().unwrap();
+    let shutdown = CancellationToken::new();
+    let logger = slog::Logger::root(slog::Discard, slog::o!());
+    let pinger = task.spawn_pinger(shutdown.clone(), logger);
+    assert!(manager.cancel(id).await.unwrap());
+    let start = std::time::Instant::now();
+    tokio::time::timeout(Duration::from_secs(5), shutdown.cancelled())
+        .await
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_millis(600));
+    pinger.await.unwrap();
+    assert!(task.is_cancelled());
+    // Cancelled status cannot be overwritten.
+    let err = task.set_status(TaskStatus::Succeeded).await.unwrap_err();
+    assert!(StoreError::is_conflict(&err));
+    let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(stored.status, TaskStatus::Cancelled);
+    assert!(!manager.cancel(id).await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_enqueue() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let manager = Arc::new(TaskManager::new(store.clone()));
+    // Config of created task round-trips.
+    let config = JudgeSolutionTaskConfig {
+        solution_id: 42,
+        enable_points: true,
+    };
+    let id = manager
+        .enqueue_judge_solution(Context::new(), config.clone())
+        .await
+        .unwrap();
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(task.kind, TaskKind::JudgeSolution);
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(
+        task.parse_config::<JudgeSolutionTaskConfig>().unwrap(),
+        config
+    );
+    let config = UpdateProblemPackageTaskConfig {
+        problem_id: 1,
+        file_id: 2,
+        compile: true,
+    };
+    let id = manager
+        .enqueue_update_problem_package(Context::new(), config.clone())
+        .await
+        .unwrap();
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(task.kind, TaskKind::UpdateProblemPackage);
+    assert_eq!(
+        task.parse_config::<UpdateProblemPackageTaskConfig>()
+            .unwrap(),
+        config
+    );
+    // Rollback of outer transaction removes task.
+    let result: Result<(), _> = run_in_tx(&db, write_tx_options(), {
+        let manager = manager.clone();
+        move |ctx| {
+            async move {
+                let config = JudgeSolutionTaskConfig {
+                    solution_id: 43,
+                    ..Default::default()
+                };
+                manager.enqueue_judge_solution(ctx, config).await?;
+                Err("failed".into())
+            }
+            .boxed()
+        }
+    })
+    .await;
+    assert_eq!(result.unwrap_err().to_string(), "failed");
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 2);
+    // Commit of outer transaction keeps task.
+    let id = run_in_tx(&db, write_tx_options(), {
+        let manager = manager.clone();
+        move |ctx| {
+            async move {
+                let config = JudgeSolutionTaskConfig {
+                    solution_id: 44,
+                    ..Default::default()
+                };
+                manager.enqueue_judge_solution(ctx, config).await
+            }
+            .boxed()
+        }
+    })
+    .await
+    .unwrap();
+    assert!(store.get(Context::new(), id).await.unwrap().is_some());
+    assert_eq!(store.count(Context::new(), true.into()).await.unwrap(), 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_update_state() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let id = store
+        .create(Context::new(), Task::default())
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let task = manager
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    let stored_state = || async {
+        let task = store.get(Context::new(), id).await.unwrap().unwrap();
+        task.parse_state::<JudgeSolutionTaskState>().unwrap()
+    };
+    // First update is written immediately.
+    task.update_state(|state: &mut JudgeSolutionTaskState| state.total_tests = Some(3))
+        .await
+        .unwrap();
+    assert_eq!(stored_state().await.total_tests, Some(3));
+    // Frequent updates are coalesced.
+    for _ in 0..3 {
+        task.update_state(|state: &mut JudgeSolutionTaskState| state.test += 1)
+            .await
+            .unwrap();
+    }
+    assert_eq!(stored_state().await.test, 0);
+    let state: JudgeSolutionTaskState = task.get_state().await.parse_as().unwrap();
+    assert_eq!(state.test, 3);
+    // Concurrent updates of same task are serialized.
+    let (first, second) = tokio::join!(
+        task.update_state(|state: &mut JudgeSolutionTaskState| state.compiled = true),
+        task.update_state(|state: &mut JudgeSolutionTaskState| state.test += 1),
+    );
+    first.unwrap();
+    second.unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    task.update_state(|state: &mut JudgeSolutionTaskState| state.test += 1)
+        .await
+        .unwrap();
+    let expected = JudgeSolutionTaskState {
+        test: 5,
+        total_tests: Some(3),
+        compiled: true,
+    };
+    assert_eq!(stored_state().await, expected);
+    // Concurrent modification of task is detected.
+    let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+    store
+        .update(
+            Context::new(),
+            Task {
+                priority: 5,
+                ..stored
+            },
+        )
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let err = task
+        .update_state(|state: &mut JudgeSolutionTaskState| state.test += 1)
+        .await
+        .unwrap_err();
+    assert!(StoreError::is_conflict(&err));
+    let state: JudgeSolutionTaskState = task.get_state().await.parse_as().unwrap();
+    assert_eq!(state, expected);
+    assert_eq!(stored_state().await, expected);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_run_in_tx() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let tasks = Arc::new(TaskStore::new(db.clone()));
+    tasks.create_tables().await.unwrap();
+    let files = Arc::new(FileStore::new(db.clone()));
+    files.create_tables().await.unwrap();
+    let new_file = || File {
+        meta: serde_json::Value::Null.into(),
+        ..Default::default()
+    };
+    // Failed function rolls back writes of all stores.
+    let result: Result<(), _> = run_in_tx(&db, write_tx_options(), {
+        let (tasks, files) = (tasks.clone(), files.clone());
+        move |mut ctx| {
+            async move {
+                tasks.create(ctx.reborrow(), Task::default()).await?;
+                files.create(ctx.reborrow(), new_file()).await?;
+                Err("failed".into())
+            }
+            .boxed()
+        }
+    })
+    .await;
+    assert_eq!(result.unwrap_err().to_string(), "failed");
+    assert_eq!(tasks.count(Context::new(), true.into()).await.unwrap(), 0);
+    assert_eq!(files.count(Context::new(), true.into()).await.unwrap(), 0);
+    // Panicked function rolls back writes too.
+    let handle = tokio::spawn({
+        let (db, tasks) = (db.clone(), tasks.clone());
+        async move {
+            run_in_tx::<_, ()>(&db, write_tx_options(), move |ctx| {
+                async move {
+                    tasks.create(ctx, Task::default()).await?;
+                    panic!("failed")
+                }
+                .boxed()
+            })
+            .await
+        }
+    });
+    assert!(handle.await.unwrap_err().is_panic());
+    assert_eq!(tasks.count(Context::new(), true.into()).await.unwrap(), 0);
+    // Successful function commits writes of all stores.
+    let (task, file) = run_in_tx(&db, write_tx_options(), {
+        let (tasks, files) = (tasks.clone(), files.clone());
+        move |mut ctx| {
+            async move {
+                let task = tasks.create(ctx.reborrow(), Task::default()).await?;
+                let file = files.create(ctx.reborrow(), new_file()).await?;
+                Ok((task.into_object(), file.into_object()))
+            }
+            .boxed()
+        }
+    })
+    .await
+    .unwrap();
+    let found = run_in_tx(&db, read_tx_options(), {
+        let tasks = tasks.clone();
+        move |ctx| async move { tasks.get(ctx, task.id).await }.boxed()
+    })
+    .await
+    .unwrap();
+    assert_eq!(found.unwrap().id, task.id);
+    assert!(files.get(Context::new(), file.id).await.unwrap().is_some());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_store_metrics() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let metrics = Arc::new(MemoryStoreMetrics::new());
+    let store = TaskStore::new(db).with_metrics(metrics.clone());
+    store.create_tables().await.unwrap();
+    let task = store
+        .create(Context::new(), Task::default())
+        .await
+        .unwrap()
+        .into_object();
+    store.create(Context::new(), Task::default()).await.unwrap();
+    let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+    while let Some(v) = rows.next().await {
+        v.unwrap();
+    }
+    drop(rows);
+    store
+        .update(
+            Context::new(),
+            Task {
+                status: TaskStatus::Running,
+                ..task.clone()
+            },
+        )
+        .await
+        .unwrap();
+    // Stale version produces conflict.
+    assert!(store.update(Context::new(), task.clone()).await.is_err());
+    assert!(store.delete(Context::new(), 42).await.is_err());
+    store.delete(Context::new(), task.id).await.unwrap();
+    let stats = metrics.stats("solve_task", StoreOperation::Create);
+    assert_eq!((stats.count, stats.conflicts, stats.errors), (2, 0, 0));
+    assert_eq!(stats.latency_buckets.iter().sum::<u64>(), 2);
+    let stats = metrics.stats("solve_task", StoreOperation::Update);
+    assert_eq!((stats.count, stats.conflicts, stats.errors), (2, 1, 0));
+    let stats = metrics.stats("solve_task", StoreOperation::Delete);
+    assert_eq!((stats.count, stats.conflicts, stats.errors), (2, 0, 1));
+    let stats = metrics.stats("solve_task", StoreOperation::Find);
+    assert!(stats.count >= 1);
+    assert!(metrics.rows("solve_task") >= 2);
+    assert_eq!(metrics.stats("solve_file", StoreOperation::Find).count, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_enqueue_child() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let options = TakeOptions::default();
+    let config = JudgeSolutionTaskConfig::default();
+    // Child stays unclaimed until its parent succeeds.
+    let parent_id = manager
+        .enqueue_update_problem_package(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let child_id = manager
+        .enqueue_child(
+            Context::new(),
+            parent_id,
+            TaskKind::JudgeSolution,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+    let parent = manager.take_task(&options).await.unwrap().unwrap();
+    assert_eq!(parent.get_id().await, parent_id);
+    assert!(manager.take_task(&options).await.unwrap().is_none());
+    parent.set_status(TaskStatus::Succeeded).await.unwrap();
+    let child = manager.take_task(&options).await.unwrap().unwrap();
+    assert_eq!(child.get_id().await, child_id);
+    child.set_status(TaskStatus::Succeeded).await.unwrap();
+    // Descendants are failed when parent fails.
+    let parent_id = manager
+        .enqueue_update_problem_package(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let child_id = manager
+        .enqueue_child(
+            Context::new(),
+            parent_id,
+            TaskKind::JudgeSolution,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+    let grandchild_id = manager
+        .enqueue_child(
+            Context::new(),
+            child_id,
+            TaskKind::JudgeSolution,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+    let parent = manager.take_task(&options).await.unwrap().unwrap();
+    parent.set_status(TaskStatus::Failed).await.unwrap();
+    for id in [child_id, grandchild_id] {
+        let task = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Failed);
+        assert_eq!(task.state["error"]["kind"], "parent_failed");
+        assert!(task.finish_time.is_some());
+    }
+    // Child of already failed parent is failed immediately.
+    let id = manager
+        .enqueue_child(
+            Context::new(),
+            parent_id,
+            TaskKind::JudgeSolution,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Failed);
+    assert!(manager.take_task(&options).await.unwrap().is_none());
+    // Lenient policy keeps children queued.
+    let manager = TaskManager::new(store.clone()).with_parent_failure(ParentFailurePolicy::Keep);
+    let parent_id = manager
+        .enqueue_update_problem_package(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let child_id = manager
+        .enqueue_child(
+            Context::new(),
+            parent_id,
+            TaskKind::JudgeSolution,
+            config.clone(),
+        )
+        .await
+        .unwrap();
+    let parent = manager.take_task(&options).await.unwrap().unwrap();
+    parent.set_status(TaskStatus::Failed).await.unwrap();
+    let task = store.get(Context::new(), child_id).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert!(manager.take_task(&options).await.unwrap().is_none());
+    // Parent should exist.
+    let err = manager
+        .enqueue_child(Context::new(), 1000, TaskKind::JudgeSolution, config)
+        .await
+        .unwrap_err();
+    assert!(StoreError::is_not_found(&err));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_subscribe() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone()).with_poll_interval(Duration::from_millis(50));
+    let id = manager
+        .enqueue_judge_solution(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let mut receiver = manager.subscribe(id).await.unwrap();
+    let mut other = manager.subscribe(id).await.unwrap();
+    assert_eq!(manager.subscription_count(), 1);
+    assert_eq!(receiver.borrow_and_update().status, TaskStatus::Queued);
+    async fn changed(receiver: &mut tokio::sync::watch::Receiver<TaskSnapshot>) -> TaskSnapshot {
+        tokio::time::timeout(Duration::from_secs(5), receiver.changed())
+            .await
+            .unwrap()
+            .unwrap();
+        receiver.borrow_and_update().clone()
+    }
+    // Changes made through wrapper are observed in order.
+    let task = manager
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    let snapshot = changed(&mut receiver).await;
+    assert_eq!(snapshot.status, TaskStatus::Running);
+    assert!(snapshot.expire_time.is_some());
+    task.set_state(serde_json::json!({"test": 1}).into())
+        .await
+        .unwrap();
+    let snapshot = changed(&mut receiver).await;
+    assert_eq!(snapshot.state["test"], 1);
+    assert!(snapshot.version > 1);
+    task.set_status(TaskStatus::Succeeded).await.unwrap();
+    let snapshot = changed(&mut receiver).await;
+    assert_eq!(snapshot.status, TaskStatus::Succeeded);
+    assert_eq!(other.borrow_and_update().status, TaskStatus::Succeeded);
+    // Changes made by other processes are found by polling.
+    let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+    store
+        .update(
+            Context::new(),
+            Task {
+                status: TaskStatus::Queued,
+                ..stored
+            },
+        )
+        .await
+        .unwrap();
+    let snapshot = changed(&mut receiver).await;
+    assert_eq!(snapshot.status, TaskStatus::Queued);
+    // Dropping all receivers removes subscription.
+    drop(receiver);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(manager.subscription_count(), 1);
+    drop(other);
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while manager.subscription_count() != 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let err = manager.subscribe(1000).await.unwrap_err();
+    assert!(StoreError::is_not_found(&err));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_stats() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let judge = TakeOptions::default().with_kinds(vec![TaskKind::JudgeSolution]);
+    let update = TakeOptions::default().with_kinds(vec![TaskKind::UpdateProblemPackage]);
+    // Walk tasks through their lifecycle.
+    manager
+        .enqueue_judge_solution(Context::new(), Default::default())
+        .await
+        .unwrap();
+    manager
+        .enqueue_update_problem_package(Context::new(), Default::default())
+        .await
+        .unwrap();
+    manager
+        .enqueue_judge_solution(Context::new(), Default::default())
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let task = manager.take_task(&judge).await.unwrap().unwrap();
+    let id = task.get_id().await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    task.set_status(TaskStatus::Succeeded).await.unwrap();
+    let task = manager.take_task(&update).await.unwrap().unwrap();
+    task.set_status(TaskStatus::Failed).await.unwrap();
+    // Running task is not finished yet.
+    let running = manager.take_task(&judge).await.unwrap().unwrap();
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    let (queued, start, finish) = (
+        task.queued_time.unwrap(),
+        task.start_time.unwrap(),
+        task.finish_time.unwrap(),
+    );
+    assert!(queued < start && start < finish);
+    let stats = manager
+        .stats(Context::new(), Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(stats.len(), 2);
+    let judge_stats = &stats[&TaskKind::JudgeSolution];
+    assert_eq!(judge_stats.finished, 1);
+    assert_eq!(judge_stats.failed, 0);
+    assert!(judge_stats.queue_latency.p50 >= Duration::from_millis(20));
+    assert_eq!(judge_stats.queue_latency.p50, judge_stats.queue_latency.max);
+    assert!(judge_stats.execution_time.p99 >= Duration::from_millis(20));
+    let update_stats = &stats[&TaskKind::UpdateProblemPackage];
+    assert_eq!(update_stats.finished, 1);
+    assert_eq!(update_stats.failed, 1);
+    // Statistics include only tasks finished within window.
+    running.set_status(TaskStatus::Succeeded).await.unwrap();
+    let stats = manager
+        .stats(Context::new(), Duration::from_secs(3600))
+        .await
+        .unwrap();
+    assert_eq!(stats[&TaskKind::JudgeSolution].finished, 2);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let stats = manager
+        .stats(Context::new(), Duration::from_millis(10))
+        .await
+        .unwrap();
+    assert!(stats.is_empty());
+}
+
+/// Pings task with specified id when another task is recovered.
+struct PingingObserver {
+    store: std::sync::Weak<TaskStore>,
+    id: i64,
+}
+
+impl StoreObserver<Task> for PingingObserver {
+    fn on_event(&self, event: &TaskEvent) {
+        let store = match self.store.upgrade() {
+            Some(v) => v,
+            None => return,
+        };
+        if event.kind() != EventKind::Update || event.object().id == self.id {
+            return;
+        }
+        solve::core::blocking_await(async {
+            let task = store.get(Context::new(), self.id).await.unwrap().unwrap();
+            let task = Task {
+                expire_time: Some(Instant::now() + Duration::from_secs(30)),
+                ..task
+            };
+            store.update(Context::new(), task).await.unwrap();
+        });
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_recover_expired() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let now = Instant::now();
+    let expired = Some(now - Duration::from_secs(60));
+    let tasks = [
+        (expired, 3),
+        (expired, 1),
+        (Some(now + Duration::from_secs(3600)), 3),
+    ];
+    for (expire_time, max_attempts) in tasks {
+        let task = Task {
+            status: TaskStatus::Running,
+            state: serde_json::json!({"test": 1}).into(),
+            expire_time,
+            max_attempts,
+            ..Default::default()
+        };
+        store.create(Context::new(), task).await.unwrap();
+    }
+    let recovered = manager
+        .recover_expired(Context::new(), RecoverPolicy::Requeue)
+        .await
+        .unwrap();
+    assert_eq!(
+        recovered,
+        RecoveredTasks {
+            requeued: 1,
+            failed: 1
+        }
+    );
+    // Task with remaining attempts is requeued.
+    let task = store.get(Context::new(), 1).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.expire_time, None);
+    assert_eq!(task.attempts, 1);
+    // Task without remaining attempts fails with error merged into state.
+    let task = store.get(Context::new(), 2).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Failed);
+    assert_eq!(task.state["test"], 1);
+    assert_eq!(task.state["error"]["kind"], "abandoned");
+    // Alive task is left as is.
+    let task = store.get(Context::new(), 3).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Running);
+    assert_eq!(task.version, 0);
+    // Fail policy ignores remaining attempts.
+    let task = Task {
+        status: TaskStatus::Running,
+        expire_time: expired,
+        max_attempts: 3,
+        ..Default::default()
+    };
+    let id = store
+        .create(Context::new(), task)
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let recovered = manager
+        .recover_expired(Context::new(), RecoverPolicy::Fail)
+        .await
+        .unwrap();
+    assert_eq!(recovered.failed, 1);
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Failed);
+    // Owner that pings task after it was selected for recovery wins.
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let task = Task {
+            status: TaskStatus::Running,
+            expire_time: expired,
+            max_attempts: 3,
+            ..Default::default()
+        };
+        ids.push(
+            store
+                .create(Context::new(), task)
+                .await
+                .unwrap()
+                .object()
+                .id,
+        );
+    }
+    store.add_observer(Arc::new(PingingObserver {
+        store: Arc::downgrade(&store),
+        id: ids[1],
+    }));
+    let recovered = manager
+        .recover_expired(Context::new(), RecoverPolicy::Requeue)
+        .await
+        .unwrap();
+    assert_eq!(
+        recovered,
+        RecoveredTasks {
+            requeued: 1,
+            failed: 0
+        }
+    );
+    let task = store.get(Context::new(), ids[0]).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Queued);
+    let task = store.get(Context::new(), ids[1]).await.unwrap().unwrap();
+    assert_eq!(task.status, TaskStatus::Running);
+    assert!(task.expire_time.unwrap() > Instant::now());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_recover_expired_default_attempts() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone());
+    let expired = Some(Instant::now() - Duration::from_secs(60));
+    // Task enqueued by manager is abandoned by its worker.
+    let id = manager
+        .enqueue_judge_solution(Context::new(), Default::default())
+        .await
+        .unwrap();
+    manager
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    let task = store.get(Context::new(), id).await.unwrap().unwrap();
+    store
+        .update(
+            Context::new(),
+            Task {
+                expire_time: expired,
+                ..task
+            },
+        )
+        .await
+        .unwrap();
+    // Task created without limit of attempts uses limit of manager.
+    let task = Task {
+        status: TaskStatus::Running,
+        expire_time: expired,
+        ..Default::default()
+    };
+    let legacy_id = store
+        .create(Context::new(), task)
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let recovered = manager
+        .recover_expired(Context::new(), RecoverPolicy::Requeue)
+        .await
+        .unwrap();
+    assert_eq!(
+        recovered,
+        RecoveredTasks {
+            requeued: 2,
+            failed: 0
+        }
+    );
+    for id in [id, legacy_id] {
+        let task = store.get(Context::new(), id).await.unwrap().unwrap();
+        assert_eq!(task.status, TaskStatus::Queued);
+        assert_eq!(task.attempts, 1);
+    }
+    // Taken task uses the same limit of attempts.
+    for _ in 0..2 {
+        let task = manager
+            .take_task(&TakeOptions::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(task.can_retry().await);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_requeue_expired() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
+    let now = Instant::now();
+    let expired = Some(now - Duration::from_secs(60));
+    let tasks = [
+        (TaskStatus::Running, expired),
+        (TaskStatus::Running, expired),
+        (TaskStatus::Running, Some(now + Duration::from_secs(3600))),
+        (TaskStatus::Running, expired),
+        (TaskStatus::Queued, None),
+        (TaskStatus::Running, expired),
+        (TaskStatus::Succeeded, None),
+        (TaskStatus::Running, expired),
+    ];
+    for (status, expire_time) in tasks {
+        let task = Task {
+            status,
+            expire_time,
+            ..Default::default()
+        };
+        store.create(Context::new(), task).await.unwrap();
+    }
+    assert_eq!(store.requeue_expired(Context::new()).await.unwrap(), 5);
+    let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+    let mut tasks = Vec::new();
+    while let Some(task) = rows.next().await {
+        let task = task.unwrap();
+        tasks.push((
+            task.id,
+            task.status,
+            task.expire_time.is_some(),
+            task.version,
+        ));
+    }
+    assert_eq!(
+        tasks,
+        vec![
+            (1, TaskStatus::Queued, false, 1),
+            (2, TaskStatus::Queued, false, 1),
+            (3, TaskStatus::Running, true, 0),
+            (4, TaskStatus::Queued, false, 1),
+            (5, TaskStatus::Queued, false, 0),
+            (6, TaskStatus::Queued, false, 1),
+            (7, TaskStatus::Succeeded, false, 0),
+            (8, TaskStatus::Queued, false, 1),
+        ]
+    );
+    let mut events = store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    let mut updates = Vec::new();
+    while let Some(event) = events.next().await {
+        let event = event.unwrap();
+        if event.kind() == EventKind::Update {
+            updates.push(event.object().id);
+        }
+    }
+    assert_eq!(updates, vec![1, 2, 4, 6, 8]);
+    // Nothing to requeue anymore.
+    assert_eq!(store.requeue_expired(Context::new()).await.unwrap(), 0);
+    // Update without events of objects writes single marker event.
+    let count = store
+        .update_fields_where(
+            Context::new(),
+            vec![("priority".into(), Value::from(7i64))],
+            column("status").equal(TaskStatus::Queued),
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(count, 6);
+    let task = store.get(Context::new(), 5).await.unwrap().unwrap();
+    assert_eq!(task.priority, 7);
+    assert_eq!(task.version, 1);
+    let mut events = store
+        .find_events(
+            Context::new(),
+            Select::new().with_where(column("event_id").greater(13)),
+        )
+        .await
+        .unwrap();
+    let event = events.next().await.unwrap().unwrap();
+    assert_eq!(event.id(), 14);
+    assert_eq!(event.kind(), EventKind::BatchUpdate);
+    assert!(events.next().await.is_none());
+    drop(events);
+    // Marker event is not written if nothing is updated.
+    let count = store
+        .update_fields_where(
+            Context::new(),
+            vec![("priority".into(), Value::from(8i64))],
+            column("status").equal(TaskStatus::Cancelled),
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+    assert_eq!(store.last_event_id(Context::new()).await.unwrap(), 14);
+}
+
+#[test]
+fn test_judge_report() {
+    // Reports written before tests were introduced still parse.
+    let mut solution = Solution {
+        report: serde_json::json!({"verdict": "wrong_answer"}).into(),
+        ..Default::default()
+    };
+    let report = solution.parse_report().unwrap().unwrap();
+    assert_eq!(report.verdict, Verdict::WrongAnswer);
+    assert!(report.tests.is_empty());
+    assert!(report.usage.is_none());
+    assert_eq!(report.aggregate_verdict(), None);
+    let report = JudgeReport {
+        verdict: Verdict::WrongAnswer,
+        points: Some("12.5".parse().unwrap()),
+        tests: vec![
+            TestReport {
+                verdict: Verdict::Accepted,
+                time_ms: 15,
+                memory_bytes: 1 << 20,
+                points: Some("12.5".parse().unwrap()),
+                input_file_id: Some(1),
+                output_file_id: Some(2),
+                ..Default::default()
+            },
+            TestReport {
+                verdict: Verdict::WrongAnswer,
+                time_ms: 20,
+                memory_bytes: 2 << 20,
+                check_log: Some("expected 3, found 4".into()),
+                ..Default::default()
+            },
+        ],
+        usage: Some(UsageReport {
+            time_ms: 20,
+            memory_bytes: 2 << 20,
+        }),
+        compile_log: Some("main.cpp: warning".into()),
+    };
+    solution.set_report(Some(report.clone())).unwrap();
+    assert_eq!(solution.parse_report().unwrap(), Some(report));
+    solution.set_report(None).unwrap();
+    assert_eq!(solution.parse_report().unwrap(), None);
+}
+
+#[test]
+fn test_judge_report_aggregate_verdict() {
+    let new_report = |verdicts: &[Verdict], points: Option<&str>| JudgeReport {
+        points: points.map(|v| v.parse().unwrap()),
+        tests: verdicts
+            .iter()
+            .map(|&verdict| TestReport {
+                verdict,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let cases = [
+        (
+            vec![Verdict::Accepted, Verdict::Accepted],
+            None,
+            Verdict::Accepted,
+        ),
+        (
+            vec![
+                Verdict::Accepted,
+                Verdict::TimeLimitExceeded,
+                Verdict::WrongAnswer,
+            ],
+            None,
+            Verdict::TimeLimitExceeded,
+        ),
+        (
+            vec![Verdict::Accepted, Verdict::WrongAnswer],
+            Some("50"),
+            Verdict::PartiallyAccepted,
+        ),
+        (
+            vec![Verdict::RuntimeError, Verdict::WrongAnswer],
+            Some("0"),
+            Verdict::RuntimeError,
+        ),
+        (
+            vec![Verdict::WrongAnswer, Verdict::Failed],
+            None,
+            Verdict::Failed,
+        ),
+    ];
+    for (verdicts, points, verdict) in cases {
+        let report = new_report(&verdicts, points);
+        assert_eq!(report.aggregate_verdict(), Some(verdict));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_find_order() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
+    for priority in [3, 1, 2] {
+        let task = Task {
+            priority,
+            ..Default::default()
+        };
+        store.create(Context::new(), task).await.unwrap();
+    }
+    let find_ids = |select: Select| {
+        let store = &store;
+        async move {
+            let mut rows = store.find(Context::new(), select).await.unwrap();
+            let mut ids = Vec::new();
+            while let Some(task) = rows.next().await {
+                ids.push(task.unwrap().id);
+            }
+            ids
+        }
+    };
+    assert_eq!(find_ids(Select::new()).await, vec![1, 2, 3]);
+    assert_eq!(
+        find_ids(Select::new().with_order(vec![Order::desc("id")])).await,
+        vec![3, 2, 1]
+    );
+    assert_eq!(
+        find_ids(Select::new().with_order(vec![Order::asc("priority")])).await,
+        vec![2, 3, 1]
+    );
+}
+
+#[derive(Clone, Default, Debug, PartialEq, FromRow, IntoRow)]
+struct Flag {
+    id: String,
+    owner: String,
+    value: i64,
+}
+
+impl Object for Flag {
+    type Id = String;
+
+    const GENERATED_ID: bool = false;
+
+    fn id(&self) -> Self::Id {
+        self.id.clone()
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+
+    fn is_valid(&self) -> bool {
+        !self.id.is_empty()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_text_id_store() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store: PersistentStore<Flag> = PersistentStore::new(db, "test_flag", "test_flag_event");
+    store
+        .create_schema(Context::new(), TypeMap::new())
+        .await
+        .unwrap();
+    let flag = Flag {
+        id: "new_judge".into(),
+        owner: "judge".into(),
+        value: 1,
+    };
+    let event = store.create(Context::new(), flag.clone()).await.unwrap();
+    assert_eq!(event.kind(), EventKind::Create);
+    assert_eq!(event.object(), &flag);
+    let other = Flag {
+        id: "old_judge".into(),
+        ..Default::default()
+    };
+    store.create(Context::new(), other).await.unwrap();
+    // Ids are assigned by application, so they must be unique.
+    assert!(store.create(Context::new(), flag.clone()).await.is_err());
+    let found = store
+        .get(Context::new(), "new_judge".into())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found, flag);
+    let updated = Flag {
+        value: 2,
+        ..flag.clone()
+    };
+    let event = store
+        .update_where(
+            Context::new(),
+            updated.clone(),
+            column("owner").equal("judge"),
+        )
+        .await
+        .unwrap();
+    assert_eq!(event.object(), &updated);
+    let flags = store
+        .get_many(Context::new(), &["new_judge".into(), "missing".into()])
+        .await
+        .unwrap();
+    assert_eq!(flags.len(), 1);
+    assert_eq!(flags["new_judge"], updated);
+    let event = store
+        .delete(Context::new(), "old_judge".into())
+        .await
+        .unwrap();
+    assert_eq!(event.kind(), EventKind::Delete);
+    assert_eq!(event.object().id, "old_judge");
+    assert!(store
+        .get(Context::new(), "old_judge".into())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(store
+        .delete(Context::new(), "old_judge".into())
+        .await
+        .is_err());
+    let mut events = store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(event) = events.next().await {
+        ids.push(event.unwrap().object().id.clone());
+    }
+    assert_eq!(
+        ids,
+        vec!["new_judge", "old_judge", "new_judge", "old_judge"]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_find_chunked() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let tasks = (0..25)
+        .map(|i| Task {
+            priority: i % 2,
+            ..Default::default()
+        })
+        .collect();
+    store.create_batch(Context::new(), tasks).await.unwrap();
+    assert_eq!(db.pool_status().in_use(), 0);
+    // Plain find holds connection until iterator is dropped.
+    {
+        let mut rows = store.find(Context::new(), Select::new()).await.unwrap();
+        rows.next().await.unwrap().unwrap();
+        assert_eq!(db.pool_status().in_use(), 1);
+    }
+    assert_eq!(db.pool_status().in_use(), 0);
+    let mut rows = store
+        .find_chunked(Context::new(), Select::new(), 10)
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(task) = rows.next().await {
+        assert_eq!(db.pool_status().in_use(), 0);
+        ids.push(task.unwrap().id);
+    }
+    assert_eq!(ids, (1..=25).collect::<Vec<_>>());
+    let mut rows = store
+        .find_chunked(
+            Context::new(),
+            Select::new().with_where(column("priority").equal(1)),
+            4,
+        )
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(task) = rows.next().await {
+        ids.push(task.unwrap().id);
+    }
+    assert_eq!(ids, (1..=12).map(|i| i * 2).collect::<Vec<_>>());
+    // Chunks are read inside of transaction from context.
+    let mut tx = db
+        .transaction(TransactionOptions {
+            read_only: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let mut rows = store
+        .find_chunked(Context::new().with_tx(&mut tx), Select::new(), 7)
+        .await
+        .unwrap();
+    let mut count = 0;
+    while let Some(task) = rows.next().await {
+        task.unwrap();
+        count += 1;
+    }
+    assert_eq!(count, 25);
+    drop(rows);
+    tx.rollback().await.unwrap();
+    // Events are read in chunks as well.
+    let mut rows = store
+        .find_events_chunked(Context::new(), Select::new(), 10)
+        .await
+        .unwrap();
+    let mut ids = Vec::new();
+    while let Some(event) = rows.next().await {
+        assert_eq!(db.pool_status().in_use(), 0);
+        ids.push(event.unwrap().id());
+    }
+    assert_eq!(ids, (1..=25).collect::<Vec<_>>());
+    // Order and limit are used for continuation.
+    assert!(store
+        .find_chunked(
+            Context::new(),
+            Select::new().with_order(vec![Order::desc("id")]),
+            10
+        )
+        .await
+        .is_err());
+    assert!(store
+        .find_events_chunked(Context::new(), Select::new().with_limit(5), 10)
+        .await
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_find_events() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = TaskStore::new(db);
+    store.create_tables().await.unwrap();
+    assert_eq!(store.last_event_id(Context::new()).await.unwrap(), 0);
+    for _ in 0..3 {
+        store.create(Context::new(), Task::default()).await.unwrap();
+    }
+    let mut task = store.get(Context::new(), 2).await.unwrap().unwrap();
+    task.priority = 7;
+    store.update(Context::new(), task).await.unwrap();
+    store.delete(Context::new(), 1).await.unwrap();
+    assert_eq!(store.last_event_id(Context::new()).await.unwrap(), 5);
+    let mut rows = store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    let mut events = Vec::new();
+    while let Some(event) = rows.next().await {
+        let event = event.unwrap();
+        events.push((event.id(), event.kind(), event.object().id));
+    }
+    assert_eq!(
+        events,
+        vec![
+            (1, EventKind::Create, 1),
+            (2, EventKind::Create, 2),
+            (3, EventKind::Create, 3),
+            (4, EventKind::Update, 2),
+            (5, EventKind::Delete, 1),
+        ]
+    );
+    let mut rows = store
+        .find_events(
+            Context::new(),
+            Select::new()
+                .with_where(column("id").equal(2))
+                .with_order(vec![Order::desc("event_id")]),
+        )
+        .await
+        .unwrap();
+    let event = rows.next().await.unwrap().unwrap();
+    assert_eq!(event.kind(), EventKind::Update);
+    assert_eq!(event.object().priority, 7);
+}
+
+#[derive(Clone, Default, Debug, FromRow, IntoRow)]
+struct FlagV2 {
+    id: String,
+    owner: String,
+    value: i64,
+    expire_time: Option<Instant>,
+}
+
+impl Object for FlagV2 {
+    type Id = String;
+
+    const GENERATED_ID: bool = false;
+
+    fn id(&self) -> Self::Id {
+        self.id.clone()
+    }
+
+    fn set_id(&mut self, id: Self::Id) {
+        self.id = id;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_create_schema() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store: PersistentStore<Flag> =
+        PersistentStore::new(db.clone(), "test_flag", "test_flag_event");
+    store
+        .create_schema(Context::new(), TypeMap::new())
+        .await
+        .unwrap();
+    // Schema creation is idempotent.
+    store
+        .create_schema(Context::new(), TypeMap::new())
+        .await
+        .unwrap();
+    store.check_schema(Context::new()).await.unwrap();
+    // Added field is detected by comparing live columns.
+    let new_store: PersistentStore<FlagV2> =
+        PersistentStore::new(db.clone(), "test_flag", "test_flag_event");
+    let err = new_store.check_schema(Context::new()).await.unwrap_err();
+    assert!(err.to_string().contains("expire_time"), "{}", err);
+    // Types of nullable columns cannot be inferred.
+    let new_store: PersistentStore<FlagV2> =
+        PersistentStore::new(db.clone(), "test_flag_v2", "test_flag_v2_event");
+    assert!(new_store
+        .create_schema(Context::new(), TypeMap::new())
+        .await
+        .is_err());
+    new_store
+        .create_schema(
+            Context::new(),
+            TypeMap::new().with_column(Column::big_int("expire_time").nullable()),
+        )
+        .await
+        .unwrap();
+    new_store.check_schema(Context::new()).await.unwrap();
+    let flag = FlagV2 {
+        id: "flag".into(),
+        ..Default::default()
+    };
+    let event = new_store.create(Context::new(), flag).await.unwrap();
+    assert_eq!(event.object().expire_time, None);
+}
+
+/// Driver that records options of requested connections.
+struct RecordingDatabase {
+    inner: Database,
+    options: Arc<std::sync::Mutex<Vec<ConnectionOptions>>>,
+}
+
+#[async_trait::async_trait]
+impl solve_db::driver::Database for RecordingDatabase {
+    fn builder(&self) -> QueryBuilder {
+        self.inner.builder()
+    }
+
+    async fn connection(&self, options: ConnectionOptions) -> Result<Connection, solve_db::Error> {
+        self.options.lock().unwrap().push(options);
+        self.inner.connection(options).await
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_read_consistency() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config = solve::config::SQLiteConfig {
+        path: tmpdir
+            .join("db.sqlite")
+            .as_os_str()
+            .to_str()
+            .unwrap()
+            .to_string(),
+    };
+    let options = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let db = Arc::new(Database::new(RecordingDatabase {
+        inner: new_database(&solve::config::DatabaseConfig::SQLite(config)).unwrap(),
+        options: options.clone(),
+    }));
+    let store = FileStore::new(db.clone());
+    store.create_tables().await.unwrap();
+    let event = store
+        .create(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let id = event.object().id;
+    let strong = ConnectionOptions { read_only: false };
+    let eventual = ConnectionOptions { read_only: true };
+    // Strong consistency is used by default.
+    options.lock().unwrap().clear();
+    assert!(store.get(Context::new(), id).await.unwrap().is_some());
+    assert_eq!(*options.lock().unwrap(), vec![strong]);
+    options.lock().unwrap().clear();
+    let ctx = Context::new().with_consistency(ReadConsistency::Strong);
+    assert!(store.get(ctx, id).await.unwrap().is_some());
+    assert_eq!(*options.lock().unwrap(), vec![strong]);
+    options.lock().unwrap().clear();
+    let ctx = Context::new().with_consistency(ReadConsistency::Eventual);
+    assert!(store.get(ctx, id).await.unwrap().is_some());
+    assert_eq!(*options.lock().unwrap(), vec![eventual]);
+    options.lock().unwrap().clear();
+    let ctx = Context::new().with_consistency(ReadConsistency::Eventual);
+    let mut rows = store.find(ctx, Select::new()).await.unwrap();
+    assert!(rows.next().await.is_some());
+    drop(rows);
+    assert_eq!(*options.lock().unwrap(), vec![eventual]);
+    // Writes always use writable connections.
+    options.lock().unwrap().clear();
+    let ctx = Context::new().with_consistency(ReadConsistency::Eventual);
+    store.delete(ctx, id).await.unwrap();
+    assert!(options.lock().unwrap().iter().all(|v| !v.read_only));
+}
+
+#[test]
+fn test_schedule_next_after() {
+    let time: Instant = "2024-03-01T10:15:30Z".parse().unwrap();
+    let next = |schedule: &str| {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(time)
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(next("@every 30s"), "2024-03-01T10:16:00Z");
+    assert_eq!(next("* * * * *"), "2024-03-01T10:16:00Z");
+    assert_eq!(next("*/20 * * * *"), "2024-03-01T10:20:00Z");
+    assert_eq!(next("0 3 * * *"), "2024-03-02T03:00:00Z");
+    assert_eq!(next("30 9-17 * * 1-5"), "2024-03-01T10:30:00Z");
+    // 2024-03-01 is Friday.
+    assert_eq!(next("0 0 * * 0"), "2024-03-03T00:00:00Z");
+    assert_eq!(next("0 0 * * 7"), "2024-03-03T00:00:00Z");
+    assert_eq!(next("0 0 1,15 * *"), "2024-03-15T00:00:00Z");
+    assert_eq!(next("0 0 29 2 *"), "2028-02-29T00:00:00Z");
+    // Either day of month or day of week should match.
+    assert_eq!(next("0 0 10 * 6"), "2024-03-02T00:00:00Z");
+    assert!("0 0 31 2 *"
+        .parse::<Schedule>()
+        .unwrap()
+        .next_after(time)
+        .is_none());
+    for schedule in [
+        "",
+        "* * * *",
+        "60 * * * *",
+        "*/0 * * * *",
+        "5-1 * * * *",
+        "@every 0s",
+    ] {
+        assert!(schedule.parse::<Schedule>().is_err(), "{schedule:?}");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_schedule_periodic() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let periodic_store = Arc::new(PeriodicTaskStore::new(db));
+    periodic_store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone()).with_periodic_tasks(periodic_store.clone());
+    let now = Instant::now();
+    let mut definition = PeriodicTask {
+        kind: TaskKind::UpdateProblemPackage,
+        schedule: "@every 1m".into(),
+        last_run: Some(now - Duration::from_secs(3600)),
+        enabled: true,
+        ..Default::default()
+    };
+    definition
+        .set_config(UpdateProblemPackageTaskConfig {
+            problem_id: 1,
+            file_id: 2,
+            compile: true,
+        })
+        .unwrap();
+    let id = periodic_store
+        .create(Context::new(), definition.clone())
+        .await
+        .unwrap()
+        .into_object()
+        .id;
+    periodic_store
+        .create(
+            Context::new(),
+            PeriodicTask {
+                schedule: "@every 1s".into(),
+                enabled: false,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert!(!PeriodicTask {
+        schedule: "invalid".into(),
+        ..Default::default()
+    }
+    .is_valid());
+    // Missed runs produce only one catch-up run.
+    let ids = manager.schedule_periodic(now).await.unwrap();
+    assert_eq!(ids.len(), 1);
+    let task = store.get(Context::new(), ids[0]).await.unwrap().unwrap();
+    assert_eq!(task.kind, TaskKind::UpdateProblemPackage);
+    assert_eq!(task.status, TaskStatus::Queued);
+    assert_eq!(task.config, definition.config);
+    let definition = periodic_store
+        .get(Context::new(), id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(definition.last_run, Some(now));
+    assert!(manager.schedule_periodic(now).await.unwrap().is_empty());
+    assert!(manager
+        .schedule_periodic(now + Duration::from_secs(59))
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        manager
+            .schedule_periodic(now + Duration::from_secs(60))
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+    let definition = periodic_store
+        .get(Context::new(), id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(definition.last_run, Some(now + Duration::from_secs(60)));
+    // Manager without store of periodic tasks cannot schedule them.
+    assert!(TaskManager::new(store)
+        .schedule_periodic(now)
+        .await
+        .is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_run_scheduler() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db.clone()));
+    store.create_tables().await.unwrap();
+    let periodic_store = Arc::new(PeriodicTaskStore::new(db));
+    periodic_store.create_tables().await.unwrap();
+    let id = periodic_store
+        .create(
+            Context::new(),
+            PeriodicTask {
+                schedule: "@every 1s".into(),
+                enabled: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+        .into_object()
+        .id;
+    let shutdown = CancellationToken::new();
+    let mut schedulers = Vec::new();
+    for _ in 0..2 {
+        let manager = TaskManager::new(store.clone())
+            .with_periodic_tasks(periodic_store.clone())
+            .with_schedule_interval(Duration::from_millis(50));
+        let shutdown = shutdown.clone();
+        schedulers.push(tokio::spawn(async move {
+            manager.run_scheduler(shutdown).await
+        }));
+    }
+    tokio::time::sleep(Duration::from_millis(3500)).await;
+    shutdown.cancel();
+    for scheduler in schedulers {
+        scheduler.await.unwrap().unwrap();
+    }
+    let count = store
+        .count(Context::new(), column("id").greater(0))
+        .await
+        .unwrap();
+    assert!((3..=5).contains(&count), "{count}");
+    // Every tick moves last run by exactly one interval.
+    let mut last_runs = Vec::new();
+    let mut events = periodic_store
+        .find_events(Context::new(), Select::new())
+        .await
+        .unwrap();
+    while let Some(event) = events.next().await {
+        let definition = event.unwrap().into_object();
+        assert_eq!(definition.id, id);
+        last_runs.extend(definition.last_run);
+    }
+    assert_eq!(last_runs.len() as i64, count);
+    for pair in last_runs.windows(2) {
+        assert_eq!(pair[1], pair[0] + Duration::from_secs(1));
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_task_manager_ping() {
+    let (_tmpdir, db) = common::sqlite_db();
+    let store = Arc::new(TaskStore::new(db));
+    store.create_tables().await.unwrap();
+    let manager = TaskManager::new(store.clone()).with_lease(Duration::from_secs(2));
+    let id = manager
+        .enqueue_judge_solution(Context::new(), Default::default())
+        .await
+        .unwrap();
+    let task = manager
+        .take_task(&TakeOptions::default())
+        .await
+        .unwrap()
+        .unwrap();
+    let last_event_id = store.last_event_id(Context::new()).await.unwrap();
+    let logger = slog::Logger::root(slog::Discard, slog::o!());
+    let shutdown = CancellationToken::new();
+    let pinger = task.spawn_pinger(shutdown.clone(), logger.clone());
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    assert!(!shutdown.is_cancelled());
+    // Pings change only expiration time of task.
+    let mut events = store
+        .find_events(
+            Context::new(),
+            Select::new().with_where(column("event_id").greater(last_event_id - 1)),
+        )
+        .await
+        .unwrap();
+    let mut tasks = Vec::new();
+    while let Some(event) = events.next().await {
+        tasks.push(event.unwrap().into_object());
+    }
+    drop(events);
+    assert!(tasks.len() >= 3, "{}", tasks.len());
+    for pair in tasks.windows(2) {
+        assert!(pair[1].expire_time > pair[0].expire_time);
+        assert_eq!(pair[1].version, pair[0].version + 1);
+        let expected = Task {
+            expire_time: pair[1].expire_time,
+            version: pair[1].version,
+            ..pair[0].clone()
+        };
+        assert_eq!(format!("{:?}", pair[1]), format!("{expected:?}"));
+    }
+    // Wrapper keeps version after pings, so it can still update task.
+    task.set_state(serde_json::json!({"test": 1}).into())
+        .await
+        .unwrap();
+    // Cancelled task is not extended and pinger stops task.
+    assert!(manager.cancel(id).await.unwrap());
+    let err = task.ping(Duration::from_secs(2)).await.unwrap_err();
+    assert!(StoreError::is_conflict(&err));
+    assert!(task.is_cancelled());
+    tokio::time::timeout(Duration::from_secs(5), pinger)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(shutdown.is_cancelled());
+    let stored = store.get(Context::new(), id).await.unwrap().unwrap();
+    assert!(!store
+        .extend_expiry(
+            Context::new(),
+            id,
+            stored.expire_time.unwrap(),
+            Instant::now() + Duration::from_secs(2),
+        )
+        .await
+        .unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_update_problem_package_task() {
+    let tmpdir = common::temp_dir().unwrap();
+    let config: solve::config::Config = serde_json::from_value(serde_json::json!({
+        "db": {
+            "driver": "sqlite",
+            "options": {"path": tmpdir.join("db.sqlite")},
+        },
+        "storage": {
+            "driver": "local",
+            "options": {"files_dir": tmpdir.join("files")},
+        },
+        "invoker": {
+            "temp_dir": tmpdir.join("invoker"),
+        },
+    }))
+    .unwrap();
+    std::fs::create_dir_all(tmpdir.join("invoker")).unwrap();
+    let mut core = Core::new(&config).unwrap();
+    core.task_store().create_tables().await.unwrap();
+    core.periodic_task_store().create_tables().await.unwrap();
+    core.file_store().create_tables().await.unwrap();
+    core.problem_store().create_tables().await.unwrap();
+    core.problem_resource_store().create_tables().await.unwrap();
+    core.init_invoker(&config).await.unwrap();
+    let core = Arc::new(core);
+    let invoker = Arc::new(Invoker::new(core.clone(), config.invoker.as_ref().unwrap()).unwrap());
+    let problem_id = core
+        .problem_store()
+        .create(Context::new(), Default::default())
+        .await
+        .unwrap()
+        .object()
+        .id;
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let update_package = |name: &'static str| {
+        let core = core.clone();
+        let invoker = invoker.clone();
+        let path = fixtures.join(name);
+        async move {
+            let file = core
+                .file_manager()
+                .upload(
+                    Context::new(),
+                    MemoryFile::new(std::fs::read(path).unwrap(), Some(name.into())),
+                )
+                .await
+                .unwrap()
+                .confirm(Context::new())
+                .await
+                .unwrap();
+            let config = UpdateProblemPackageTaskConfig {
+                problem_id,
+                file_id: file.id,
+                ..Default::default()
+            };
+            core.task_manager()
+                .enqueue_update_problem_package(Context::new(), config)
+                .await
+                .unwrap();
+            let task = core
+                .task_manager()
+                .take_task(&TakeOptions::default())
+                .await
+                .unwrap()
+                .unwrap();
+            let result = Box::new(UpdateProblemPackageTask::new(invoker))
+                .run(
+                    task.clone(),
+                    core.logger().clone(),
+                    CancellationToken::new(),
+                )
+                .await;
+            let state: UpdateProblemPackageTaskState =
+                serde_json::from_value(task.get_state().await.into()).unwrap();
+            (result, state)
+        }
+    };
+    let (result, state) = update_package("problem_package.zip").await;
+    result.unwrap();
+    assert_eq!(state.stage, UpdateProblemPackageStage::Save);
+    assert_eq!(state.progress, 1.0);
+    let check_package = || async {
+        let problem = core
+            .problem_store()
+            .get(Context::new(), problem_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let package = problem.parse_config().unwrap().package.unwrap();
+        assert_eq!(package.revision, 1);
+        assert_eq!(package.time_limit, DurationMs::from_millis(1000));
+        assert_eq!(package.memory_limit, 256 << 20);
+        assert_eq!(package.checker.as_deref(), Some("package/checker.cpp"));
+        assert_eq!(package.tests.len(), 2);
+        assert_eq!(package.tests[1].input, "package/tests/2.in");
+        assert_eq!(package.tests[1].answer, "package/tests/2.ans");
+        let resources = core
+            .problem_resource_store()
+            .find_by_problem(Context::new(), problem_id)
+            .await
+            .unwrap();
+        let names: Vec<_> = resources.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "package/checker.cpp",
+                "package/tests/1.ans",
+                "package/tests/1.in",
+                "package/tests/2.ans",
+                "package/tests/2.in",
+            ]
+        );
+        assert_eq!(resources[0].kind, ProblemResourceKind::Checker);
+        assert_eq!(resources[4].kind, ProblemResourceKind::Test);
+        let file = core
+            .file_manager()
+            .load(resources[4].file_id)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(file.path()).unwrap(), b"40 2\n");
+    };
+    check_package().await;
+    // Invalid package does not change previous revision.
+    let (result, state) = update_package("problem_package_invalid.zip").await;
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("tests/missing.ans"));
+    assert_eq!(state.stage, UpdateProblemPackageStage::Validate);
+    check_package().await;
 }